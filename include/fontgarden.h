@@ -0,0 +1,47 @@
+/*
+ * The C API of fontgardener2, built with the `cdylib` feature. See
+ * src/ffi.rs for how to build the library.
+ *
+ * Strings are UTF-8 and NUL-terminated. Strings returned by the library are
+ * owned by the caller and freed with fontgarden_string_free. Functions that
+ * fail return NULL or -1 and leave a message for fontgarden_last_error.
+ */
+
+#ifndef FONTGARDEN_H
+#define FONTGARDEN_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Fontgarden Fontgarden;
+
+/* The message of the last error on this thread, or NULL. Owned by the
+ * library and valid until the next failing call. */
+const char *fontgarden_last_error(void);
+
+/* Load a fontgarden from a directory, zip archive or SQLite file. */
+Fontgarden *fontgarden_open(const char *path);
+void fontgarden_free(Fontgarden *fontgarden);
+
+void fontgarden_string_free(char *string);
+
+/* The sorted glyph names as a JSON array. */
+char *fontgarden_glyph_names(const Fontgarden *fontgarden);
+
+/* A glyph layer as the JSON of its layer file, with the layer named like the
+ * file, e.g. "Bold" or "Bold.background". */
+char *fontgarden_layer_json(const Fontgarden *fontgarden,
+                            const char *glyph_name,
+                            const char *layer_name);
+
+/* Export a UFO per source into output_dir, named after the source. Returns 0
+ * on success and -1 on failure. */
+int fontgarden_export_ufos(const Fontgarden *fontgarden,
+                           const char *output_dir);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,241 @@
+//! The `fontgarden` Python module, for reading and writing fontgardens from
+//! Python pipelines without going through the command line tool. Glyphs and
+//! layers are handed out as dicts in the shape of the layer files.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use fontgardener2::{
+    incremental,
+    structs::{codepoints_to_string, Fontgarden, Glyph, Layer, LayerName},
+    ufo::{ExportOptions, ImportOptions},
+};
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+
+create_exception!(fontgarden, FontgardenError, PyException);
+
+/// A `FontgardenError` with the messages of the whole chain of causes, like
+/// the command line tool prints them.
+fn error(error: impl std::error::Error) -> PyErr {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    FontgardenError::new_err(message)
+}
+
+/// Turn JSON data into Python objects with the `json` module.
+fn to_python(py: Python<'_>, value: &impl serde::Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(error)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn from_python<T: serde::de::DeserializeOwned>(object: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = object
+        .py()
+        .import("json")?
+        .call_method1("dumps", (object,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(error)
+}
+
+fn glyph_json(glyph: &Glyph) -> serde_json::Value {
+    let layers: BTreeMap<String, &Layer> = glyph
+        .layers
+        .iter()
+        .map(|(layer_name, layer)| (layer_name.to_string(), layer))
+        .collect();
+    serde_json::json!({
+        "codepoints": codepoints_to_string(&glyph.codepoints),
+        "set": glyph.set,
+        "opentype_category": glyph.opentype_category.to_string(),
+        "postscript_name": glyph.postscript_name,
+        "advance_link": glyph.advance_link,
+        "tags": glyph.tags,
+        "layers": layers,
+    })
+}
+
+/// A fontgarden held in memory. Layers are named like their files, e.g.
+/// `Bold` or `Bold.background`.
+#[pyclass(name = "Fontgarden")]
+#[derive(Default)]
+struct PyFontgarden {
+    fontgarden: Fontgarden,
+}
+
+#[pymethods]
+impl PyFontgarden {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a fontgarden from a directory, zip archive or SQLite file.
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        let fontgarden = Fontgarden::load(&path).map_err(error)?;
+        Ok(Self { fontgarden })
+    }
+
+    /// Save the fontgarden, replacing what is at `path`.
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        self.fontgarden.save(&path).map_err(error)
+    }
+
+    fn __len__(&self) -> usize {
+        self.fontgarden.glyphs.len()
+    }
+
+    fn __contains__(&self, glyph_name: &str) -> bool {
+        self.fontgarden.glyphs.contains_key(glyph_name)
+    }
+
+    /// The names of all glyphs, sorted.
+    fn glyph_names(&self) -> Vec<String> {
+        let mut glyph_names: Vec<String> = self
+            .fontgarden
+            .glyphs
+            .keys()
+            .map(|name| name.to_string())
+            .collect();
+        glyph_names.sort();
+        glyph_names
+    }
+
+    /// The names of all sources, sorted.
+    fn source_names(&self) -> Vec<String> {
+        self.fontgarden.source_names().into_iter().collect()
+    }
+
+    /// A glyph's metadata and layers as a dict.
+    fn glyph(&self, py: Python<'_>, glyph_name: &str) -> PyResult<PyObject> {
+        to_python(py, &glyph_json(self.glyph_ref(glyph_name)?))
+    }
+
+    /// A layer of a glyph as a dict.
+    fn layer(&self, py: Python<'_>, glyph_name: &str, layer_name: &str) -> PyResult<PyObject> {
+        let glyph = self.glyph_ref(glyph_name)?;
+        let Some(layer) = glyph.layers.get(&LayerName::from(layer_name)) else {
+            return Err(FontgardenError::new_err(format!(
+                "glyph {glyph_name} has no layer '{layer_name}'"
+            )));
+        };
+        to_python(py, layer)
+    }
+
+    /// Add or replace a layer of a glyph, adding the glyph if it is new.
+    fn set_layer(
+        &mut self,
+        glyph_name: &str,
+        layer_name: &str,
+        layer: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let layer: Layer = from_python(layer)?;
+        self.fontgarden
+            .glyphs
+            .entry(glyph_name.into())
+            .or_default()
+            .layers
+            .insert(LayerName::from(layer_name), layer);
+        Ok(())
+    }
+
+    /// Import UFO sources with the default options of `fontgarden import`.
+    fn import_ufos(&mut self, paths: Vec<PathBuf>) -> PyResult<()> {
+        self.fontgarden
+            .import_ufo_sources(&paths, &ImportOptions::default())
+            .map_err(error)
+    }
+
+    /// Export a UFO per source into `output_dir`, named after the source, and
+    /// return their paths.
+    fn export_ufos(&self, output_dir: PathBuf) -> PyResult<Vec<PathBuf>> {
+        export_ufos(&self.fontgarden, &output_dir)
+    }
+}
+
+impl PyFontgarden {
+    fn glyph_ref(&self, glyph_name: &str) -> PyResult<&Glyph> {
+        self.fontgarden.glyphs.get(glyph_name).ok_or_else(|| {
+            FontgardenError::new_err(format!("no glyph named '{glyph_name}' in the fontgarden"))
+        })
+    }
+}
+
+fn export_ufos(fontgarden: &Fontgarden, output_dir: &Path) -> PyResult<Vec<PathBuf>> {
+    let sources = fontgarden
+        .export_ufo_sources(&ExportOptions::default())
+        .map_err(error)?;
+    std::fs::create_dir_all(output_dir).map_err(error)?;
+    let mut paths = Vec::new();
+    for (source_name, source) in sources {
+        let path = output_dir.join(format!("{source_name}.ufo"));
+        incremental::write_ufo(&source, &path).map_err(error)?;
+        paths.push(path);
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[pymodule]
+fn fontgarden(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFontgarden>()?;
+    m.add("FontgardenError", m.py().get_type::<FontgardenError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_and_edit_from_python() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "fontgarden").unwrap();
+            fontgarden(&module).unwrap();
+            let dir = tempfile::tempdir().unwrap();
+            let locals = pyo3::types::PyDict::new(py);
+            locals.set_item("fontgarden", module).unwrap();
+            locals
+                .set_item("path", dir.path().join("test.fontgarden"))
+                .unwrap();
+            locals.set_item("ufo_dir", dir.path().join("ufos")).unwrap();
+            py.run(
+                cr#"
+layer = {"anchors": [], "components": [], "contours": [], "x_advance": 600}
+garden = fontgarden.Fontgarden()
+garden.set_layer("A", "Regular", {**layer, "anchors": [{"name": "top", "x": 300, "y": 700}]})
+garden.set_layer("A", "Regular.background", layer)
+garden.save(path)
+
+garden = fontgarden.Fontgarden.load(path)
+assert garden.glyph_names() == ["A"]
+assert "A" in garden and len(garden) == 1
+assert garden.source_names() == ["Regular"]
+assert garden.layer("A", "Regular")["anchors"][0]["name"] == "top"
+assert sorted(garden.glyph("A")["layers"]) == ["Regular", "Regular.background"]
+
+try:
+    garden.glyph("B")
+except fontgarden.FontgardenError as e:
+    assert "no glyph named 'B'" in str(e)
+else:
+    assert False
+
+ufos = garden.export_ufos(ufo_dir)
+assert [ufo.name for ufo in ufos] == ["Regular.ufo"]
+"#,
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
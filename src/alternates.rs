@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    errors::InterpolationError,
+    interpolate::Location,
+    structs::{Fontgarden, Glyph, LayerName},
+};
+
+/// A range on an axis, open on the sides without a bound.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AxisRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+}
+
+/// Axis tag to the range the axis must be in, for all axes at once.
+pub type ConditionSet = BTreeMap<String, AxisRange>;
+
+/// Whether the location, in axis units, fulfills all conditions. Axes missing
+/// from the location are at their default.
+pub fn conditions_hold(conditions: &ConditionSet, location: &Location, config: &Config) -> bool {
+    conditions.iter().all(|(axis_tag, range)| {
+        let default = config.axes.get(axis_tag).map(|axis| axis.default);
+        let Some(value) = location.get(axis_tag).copied().or(default) else {
+            return false;
+        };
+        !range.minimum.is_some_and(|minimum| value < minimum)
+            && !range.maximum.is_some_and(|maximum| value > maximum)
+    })
+}
+
+/// A glyph standing in for another where the conditions hold, made from the
+/// sublayers of the other glyph that carry the conditions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternate {
+    pub glyph_name: String,
+    pub alternate_name: String,
+    /// The part of the layer names after the source name, like `bracket`.
+    pub sublayer_name: String,
+    pub conditions: ConditionSet,
+}
+
+impl Fontgarden {
+    /// Find the alternates that alternate layers make for, sorted by glyph name.
+    ///
+    /// Layers with the same sublayer name in different sources make one
+    /// alternate. They should have the same conditions; if not, the ones of the
+    /// first source by name are used.
+    pub fn alternates(&self) -> Vec<Alternate> {
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+
+        let mut alternates = Vec::new();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            let mut conditions_by_sublayer: BTreeMap<&str, BTreeMap<&str, &ConditionSet>> =
+                BTreeMap::new();
+            for (layer_name, layer) in &glyph.layers {
+                let (Some(conditions), Some(sublayer_name)) =
+                    (&layer.condition, &layer_name.sublayer)
+                else {
+                    continue;
+                };
+                conditions_by_sublayer
+                    .entry(&**sublayer_name)
+                    .or_default()
+                    .insert(&*layer_name.source, conditions);
+            }
+
+            let mut number = 0;
+            for (sublayer_name, conditions_by_source) in conditions_by_sublayer {
+                let mut conditions = conditions_by_source.values();
+                let first = conditions.next().unwrap();
+                if conditions.any(|other| other != first) {
+                    eprintln!(
+                        "warning: alternate layers '{sublayer_name}' of glyph {glyph_name} have \
+                         different conditions in different sources, using the first"
+                    );
+                }
+                let alternate_name = loop {
+                    number += 1;
+                    let name = format!("{glyph_name}.varAlt{number:02}");
+                    if !self.glyphs.contains_key(name.as_str()) {
+                        break name;
+                    }
+                };
+                alternates.push(Alternate {
+                    glyph_name: glyph_name.to_string(),
+                    alternate_name,
+                    sublayer_name: sublayer_name.to_string(),
+                    conditions: (*first).clone(),
+                });
+            }
+        }
+        alternates
+    }
+
+    /// Turn alternate layers into alternate glyphs, for tools that only know
+    /// substitution rules. Sources without an alternate layer use their main
+    /// layer in the alternate glyph.
+    pub fn materialize_alternates(&mut self) -> Vec<Alternate> {
+        let alternates = self.alternates();
+        for alternate in &alternates {
+            let glyph = self.glyphs.get_mut(alternate.glyph_name.as_str()).unwrap();
+            let mut alternate_glyph = Glyph {
+                layers: Default::default(),
+                ..glyph.clone()
+            };
+            alternate_glyph.codepoints = Default::default();
+            alternate_glyph.postscript_name = None;
+
+            let main_layer_names: Vec<LayerName> = glyph
+                .layers
+                .keys()
+                .filter(|layer_name| layer_name.is_main())
+                .cloned()
+                .collect();
+            for main_layer_name in main_layer_names {
+                let layer_name = LayerName::sublayer(
+                    main_layer_name.source.clone(),
+                    alternate.sublayer_name.as_str(),
+                );
+                let mut layer = match glyph.layers.remove(&layer_name) {
+                    Some(layer) => layer,
+                    None => glyph.layers[&main_layer_name].clone(),
+                };
+                layer.condition = None;
+                alternate_glyph.layers.insert(main_layer_name, layer);
+            }
+            self.glyphs
+                .insert(alternate.alternate_name.as_str().into(), alternate_glyph);
+        }
+        // Alternate layers of sources without a main layer have nowhere to go.
+        for glyph in self.glyphs.values_mut() {
+            glyph.layers.retain(|_, layer| layer.condition.is_none());
+        }
+        alternates
+    }
+
+    /// Make a sublayer an alternate of its source's main layer where the
+    /// conditions hold, or a plain sublayer again with `None`, in the named
+    /// glyphs or all glyphs that have it. Returns the number of changed layers.
+    pub fn set_layer_condition(
+        &mut self,
+        layer_name: &LayerName,
+        glyph_names: &[String],
+        conditions: Option<ConditionSet>,
+    ) -> Result<usize, InterpolationError> {
+        if layer_name.is_main() {
+            return Err(InterpolationError::MainLayerCondition(
+                layer_name.to_string(),
+            ));
+        }
+        if let Some(conditions) = &conditions {
+            if let Some(axis_tag) = conditions
+                .keys()
+                .find(|axis_tag| !self.config.axes.contains_key(*axis_tag))
+            {
+                return Err(InterpolationError::UnknownAxis(axis_tag.clone()));
+            }
+        }
+
+        let mut changed = 0;
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            if !glyph_names.is_empty() && !glyph_names.iter().any(|n| **n == **glyph_name) {
+                continue;
+            }
+            if let Some(layer) = glyph.layers.get_mut(layer_name) {
+                layer.condition = conditions.clone();
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::Layer;
+
+    use super::*;
+
+    #[test]
+    fn materialize_bracket_layers() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config =
+            toml::from_str("axes.wght = { minimum = 100, default = 100, maximum = 900 }").unwrap();
+        let layer = |x_advance: f64| Layer {
+            x_advance: Some(x_advance),
+            ..Default::default()
+        };
+        let mut glyph = Glyph {
+            codepoints: norad::Codepoints::new(['$']),
+            ..Default::default()
+        };
+        glyph.layers.insert(LayerName::main("Thin"), layer(100.));
+        glyph.layers.insert(LayerName::main("Black"), layer(500.));
+        glyph
+            .layers
+            .insert(LayerName::sublayer("Black", "bracket"), layer(400.));
+        glyph
+            .layers
+            .insert(LayerName::sublayer("Black", "background"), layer(0.));
+        fontgarden.glyphs.insert("dollar".into(), glyph);
+        fontgarden
+            .glyphs
+            .insert("dollar.varAlt01".into(), Glyph::default());
+
+        let conditions = ConditionSet::from([(
+            "wght".to_string(),
+            AxisRange {
+                minimum: Some(600.),
+                maximum: None,
+            },
+        )]);
+        fontgarden
+            .set_layer_condition(
+                &LayerName::sublayer("Black", "bracket"),
+                &[],
+                Some(conditions.clone()),
+            )
+            .unwrap();
+
+        let alternates = fontgarden.materialize_alternates();
+
+        assert_eq!(
+            alternates,
+            vec![Alternate {
+                glyph_name: "dollar".into(),
+                alternate_name: "dollar.varAlt02".into(),
+                sublayer_name: "bracket".into(),
+                conditions: conditions.clone(),
+            }]
+        );
+        let dollar = &fontgarden.glyphs["dollar"];
+        assert!(!dollar
+            .layers
+            .contains_key(&LayerName::sublayer("Black", "bracket")));
+        assert!(dollar
+            .layers
+            .contains_key(&LayerName::sublayer("Black", "background")));
+        let alternate = &fontgarden.glyphs["dollar.varAlt02"];
+        assert!(alternate.codepoints.is_empty());
+        assert_eq!(
+            alternate.layers[&LayerName::main("Black")].x_advance,
+            Some(400.)
+        );
+        assert_eq!(
+            alternate.layers[&LayerName::main("Thin")].x_advance,
+            Some(100.)
+        );
+
+        let at = |wght: f64| Location::from([("wght".to_string(), wght)]);
+        assert!(conditions_hold(&conditions, &at(600.), &fontgarden.config));
+        assert!(!conditions_hold(&conditions, &at(500.), &fontgarden.config));
+        assert!(!conditions_hold(
+            &conditions,
+            &Location::new(),
+            &fontgarden.config
+        ));
+    }
+}
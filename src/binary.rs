@@ -0,0 +1,592 @@
+//! Compare a compiled font against the fontgarden, to catch when what ships and
+//! what is in the garden drift apart, or import one to bring back a project
+//! whose sources are lost.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::Path,
+};
+
+use kurbo::{Affine, BezPath, Point, Rect, Shape};
+use norad::Codepoints;
+use skrifa::{
+    instance::{LocationRef, Size},
+    outline::{DrawSettings, OutlinePen},
+    raw::{
+        tables::glyf::{self, Glyf},
+        tables::loca::Loca,
+        TableProvider,
+    },
+    string::StringId,
+    FontRef, GlyphId, MetadataProvider,
+};
+
+use crate::{
+    errors::BinaryError,
+    structs::{
+        Component, Contour, ContourPoint, ExtraFields, Fontgarden, Layer, LayerName, PointType,
+    },
+    ufo::{ImportOptions, ImportedGlyph},
+};
+
+/// How far, in font units, outline bounds and advances may differ before they
+/// count as changed, to allow for rounding on compilation.
+const TOLERANCE: f64 = 1.;
+
+/// How many points per side of the outline bounds to sample when comparing the
+/// filled areas of outlines.
+const SAMPLES: usize = 32;
+
+/// How a glyph in a compiled font differs from the fontgarden.
+#[derive(Debug, PartialEq)]
+pub enum Drift {
+    /// The glyph is not in the compiled font.
+    Missing,
+    AdvanceDiffers {
+        garden: f64,
+        binary: f64,
+    },
+    OutlineDiffers,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GlyphDrift {
+    pub glyph_name: String,
+    pub drift: Drift,
+}
+
+impl Display for GlyphDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.drift {
+            Drift::Missing => write!(f, "glyph {} is missing from the binary", self.glyph_name),
+            Drift::AdvanceDiffers { garden, binary } => write!(
+                f,
+                "glyph {} has advance width {garden} in the fontgarden but {binary} in the binary",
+                self.glyph_name
+            ),
+            Drift::OutlineDiffers => write!(
+                f,
+                "glyph {} has a different outline in the binary",
+                self.glyph_name
+            ),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Compare the glyphs of a source with those of a compiled font at its
+    /// default location, sorted by glyph name.
+    ///
+    /// Glyphs are matched by the names in the `post` table, or by codepoint
+    /// for fonts without glyph names there. Outlines are compared by what they
+    /// fill, so that removed overlaps, decomposed components and converted
+    /// curves don't count as changes. Glyphs with names starting with an
+    /// underscore are not exported and are skipped.
+    pub fn compare_binary(
+        &self,
+        font_path: &Path,
+        source_name: &str,
+    ) -> Result<Vec<GlyphDrift>, BinaryError> {
+        if !self.source_names().contains(source_name) {
+            return Err(BinaryError::UnknownSource(source_name.to_string()));
+        }
+        let data =
+            std::fs::read(font_path).map_err(|e| BinaryError::Io(font_path.to_path_buf(), e))?;
+        let font =
+            FontRef::new(&data).map_err(|e| BinaryError::Parse(font_path.to_path_buf(), e))?;
+        let glyph_ids = glyph_ids_by_name(&font);
+        let charmap = font.charmap();
+        let outlines = font.outline_glyphs();
+        let metrics = font.glyph_metrics(Size::unscaled(), LocationRef::default());
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+
+        let layer_name = LayerName::main(source_name);
+        let mut drifts = Vec::new();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            let Some(layer) = glyph.layers.get(&layer_name) else {
+                continue;
+            };
+            if glyph_name.starts_with('_') {
+                continue;
+            }
+            let mut drift = |drift| {
+                drifts.push(GlyphDrift {
+                    glyph_name: glyph_name.to_string(),
+                    drift,
+                })
+            };
+
+            let glyph_id = if glyph_ids.is_empty() {
+                glyph
+                    .codepoints
+                    .iter()
+                    .next()
+                    .and_then(|codepoint| charmap.map(codepoint))
+            } else {
+                glyph_ids.get(glyph_name).copied()
+            };
+            let Some(glyph_id) = glyph_id else {
+                drift(Drift::Missing);
+                continue;
+            };
+
+            let garden_advance = layer.x_advance.unwrap_or_default();
+            let binary_advance = metrics.advance_width(glyph_id).unwrap_or_default() as f64;
+            if (garden_advance - binary_advance).abs() > TOLERANCE {
+                drift(Drift::AdvanceDiffers {
+                    garden: garden_advance,
+                    binary: binary_advance,
+                });
+            }
+
+            let mut pen = BezPathPen(BezPath::new());
+            let drawn = outlines.get(glyph_id).is_some_and(|outline| {
+                outline
+                    .draw(
+                        DrawSettings::unhinted(Size::unscaled(), LocationRef::default()),
+                        &mut pen,
+                    )
+                    .is_ok()
+            });
+            let garden_path = self
+                .resolved_bez_path(glyph_name, &layer_name)
+                .unwrap_or_default();
+            if !drawn || outlines_differ(&garden_path, &pen.0) {
+                drift(Drift::OutlineDiffers);
+            }
+        }
+        Ok(drifts)
+    }
+
+    /// Import the outlines, advances, codepoints and glyph names of a compiled
+    /// font at its default location as a new source, named `source_name` or
+    /// after the style name of the font. Returns the name of the source.
+    ///
+    /// TrueType composites keep their components where these are placed by
+    /// offset; all other glyphs come out as drawn, with components decomposed.
+    /// Names from the `name` table fill in the lib where it has none. For the
+    /// options that apply, see [`Fontgarden::merge_imported_glyphs`].
+    pub fn import_binary(
+        &mut self,
+        font_path: &Path,
+        source_name: Option<&str>,
+        options: &ImportOptions,
+    ) -> Result<String, BinaryError> {
+        let data =
+            std::fs::read(font_path).map_err(|e| BinaryError::Io(font_path.to_path_buf(), e))?;
+        let font =
+            FontRef::new(&data).map_err(|e| BinaryError::Parse(font_path.to_path_buf(), e))?;
+        let name = |ids: &[StringId]| {
+            ids.iter().find_map(|id| {
+                font.localized_strings(*id)
+                    .english_or_first()
+                    .map(|name| name.to_string())
+                    .filter(|name| !name.is_empty())
+            })
+        };
+        let source_name = source_name
+            .map(String::from)
+            .or_else(|| {
+                name(&[
+                    StringId::TYPOGRAPHIC_SUBFAMILY_NAME,
+                    StringId::SUBFAMILY_NAME,
+                ])
+            })
+            .unwrap_or_else(|| "Regular".into());
+        if self.source_names().contains(&source_name) {
+            return Err(BinaryError::SourceExists(source_name));
+        }
+        let source_names = [source_name.clone()];
+        self.record_default_source(&source_names, options)?;
+        let units_per_em = font.head().map_or(1000, |head| head.units_per_em());
+        let scale_factor = self.file_scale_factor(units_per_em as f64, options)?;
+
+        let mut codepoints: HashMap<GlyphId, Vec<char>> = HashMap::new();
+        for (codepoint, glyph_id) in font.charmap().mappings() {
+            codepoints
+                .entry(glyph_id)
+                .or_default()
+                .extend(char::from_u32(codepoint));
+        }
+        let glyph_names = glyph_names(&font, &codepoints);
+        let outlines = font.outline_glyphs();
+        let metrics = font.glyph_metrics(Size::unscaled(), LocationRef::default());
+        let glyf = font.glyf().ok().zip(font.loca(None).ok());
+        let layer_name = LayerName::main(source_name.as_str());
+        let glyphs = glyph_names
+            .iter()
+            .enumerate()
+            .map(|(index, glyph_name)| {
+                let glyph_id = GlyphId::new(index as u32);
+                let mut layer = Layer {
+                    x_advance: metrics
+                        .advance_width(glyph_id)
+                        .map(|advance| advance as f64),
+                    ..Default::default()
+                };
+                match glyf
+                    .as_ref()
+                    .and_then(|(glyf, loca)| components(glyf, loca, glyph_id, &glyph_names))
+                {
+                    Some(components) => layer.components = components,
+                    None => {
+                        let mut pen = ContourPen::default();
+                        if let Some(outline) = outlines.get(glyph_id) {
+                            let settings =
+                                DrawSettings::unhinted(Size::unscaled(), LocationRef::default());
+                            // Glyphs that fail to draw come out empty.
+                            let _ = outline.draw(settings, &mut pen);
+                        }
+                        layer.contours = pen.finish();
+                    }
+                }
+                ImportedGlyph {
+                    name: glyph_name.clone(),
+                    codepoints: Codepoints::new(codepoints.remove(&glyph_id).unwrap_or_default()),
+                    set: None,
+                    opentype_category: None,
+                    postscript_name: None,
+                    layers: vec![(layer_name.clone(), layer)],
+                    ..Default::default()
+                }
+            })
+            .collect();
+        self.merge_imported_glyphs(glyphs, &source_names, scale_factor, options)?;
+
+        let lib = &mut self.lib;
+        for (field, ids) in [
+            (
+                &mut lib.family_name,
+                &[StringId::TYPOGRAPHIC_FAMILY_NAME, StringId::FAMILY_NAME][..],
+            ),
+            (&mut lib.designer, &[StringId::DESIGNER]),
+            (&mut lib.designer_url, &[StringId::DESIGNER_URL]),
+            (&mut lib.manufacturer, &[StringId::MANUFACTURER]),
+            (&mut lib.manufacturer_url, &[StringId::VENDOR_URL]),
+            (&mut lib.copyright, &[StringId::COPYRIGHT_NOTICE]),
+            (&mut lib.trademark, &[StringId::TRADEMARK]),
+        ] {
+            if field.is_none() {
+                *field = name(ids);
+            }
+        }
+        if lib.vendor_id.is_none() {
+            lib.vendor_id = font
+                .os2()
+                .ok()
+                .map(|os2| os2.ach_vend_id().to_string().trim_end().to_string())
+                .filter(|vendor_id| !vendor_id.is_empty());
+        }
+        Ok(source_name)
+    }
+}
+
+/// Name all glyphs of a font, by the names in the `post` table where there
+/// are any, or by their codepoint or glyph ID.
+fn glyph_names(font: &FontRef, codepoints: &HashMap<GlyphId, Vec<char>>) -> Vec<String> {
+    let post = font.post().ok();
+    let glyph_count = font.maxp().map_or(0, |maxp| maxp.num_glyphs());
+    let mut seen = HashSet::new();
+    (0..glyph_count)
+        .map(|index| {
+            let name = post
+                .as_ref()
+                .and_then(|post| post.glyph_name(index.into()))
+                .map(|name| name.to_string())
+                .filter(|name| !name.is_empty() && !seen.contains(name))
+                .unwrap_or_else(|| {
+                    let codepoint = codepoints
+                        .get(&GlyphId::from(index))
+                        .and_then(|codepoints| codepoints.first());
+                    match codepoint {
+                        _ if index == 0 => ".notdef".into(),
+                        Some(c) if (*c as u32) <= 0xFFFF => format!("uni{:04X}", *c as u32),
+                        Some(c) => format!("u{:05X}", *c as u32),
+                        None => format!("glyph{index:05}"),
+                    }
+                });
+            seen.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+/// The components of a TrueType composite, if all are placed by offset rather
+/// than by matching points.
+fn components(
+    glyf: &Glyf,
+    loca: &Loca,
+    glyph_id: GlyphId,
+    glyph_names: &[String],
+) -> Option<Vec<Component>> {
+    let Ok(Some(glyf::Glyph::Composite(composite))) = loca.get_glyf(glyph_id, glyf) else {
+        return None;
+    };
+    composite
+        .components()
+        .map(|component| {
+            let glyf::Anchor::Offset { x, y } = component.anchor else {
+                return None;
+            };
+            let t = component.transform;
+            let affine = Affine::new([
+                t.xx.to_f32() as f64,
+                t.yx.to_f32() as f64,
+                t.xy.to_f32() as f64,
+                t.yy.to_f32() as f64,
+                x as f64,
+                y as f64,
+            ]);
+            Some(Component {
+                name: glyph_names
+                    .get(component.glyph.to_u32() as usize)?
+                    .as_str()
+                    .into(),
+                transformation: affine.into(),
+                location: None,
+                extra: ExtraFields::new(),
+            })
+        })
+        .collect()
+}
+
+/// Map the glyph names in the `post` table to glyph IDs. Empty if the table
+/// has no names, as is usual for CFF-flavored fonts.
+fn glyph_ids_by_name(font: &FontRef) -> HashMap<String, GlyphId> {
+    let (Ok(post), Ok(maxp)) = (font.post(), font.maxp()) else {
+        return HashMap::new();
+    };
+    (0..maxp.num_glyphs())
+        .filter_map(|glyph_id| {
+            let name = post.glyph_name(glyph_id.into())?;
+            Some((name.to_string(), GlyphId::from(glyph_id)))
+        })
+        .collect()
+}
+
+/// Whether two outlines fill noticeably different areas, by their bounds and
+/// by the points of a grid over them that they fill.
+fn outlines_differ(path: &BezPath, other: &BezPath) -> bool {
+    match (path.elements().is_empty(), other.elements().is_empty()) {
+        (true, true) => return false,
+        (false, false) => (),
+        _ => return true,
+    }
+    let bounds = path.bounding_box();
+    let other_bounds = other.bounding_box();
+    let edges = |rect: Rect| [rect.x0, rect.y0, rect.x1, rect.y1];
+    if edges(bounds)
+        .iter()
+        .zip(edges(other_bounds))
+        .any(|(edge, other_edge)| (edge - other_edge).abs() > TOLERANCE)
+    {
+        return true;
+    }
+
+    let mut differing = 0;
+    for row in 0..SAMPLES {
+        for column in 0..SAMPLES {
+            let point = Point::new(
+                bounds.x0 + bounds.width() * (column as f64 + 0.5) / SAMPLES as f64,
+                bounds.y0 + bounds.height() * (row as f64 + 0.5) / SAMPLES as f64,
+            );
+            if (path.winding(point) != 0) != (other.winding(point) != 0) {
+                differing += 1;
+            }
+        }
+    }
+    // Points right at the edge can go either way after rounding.
+    differing > SAMPLES * SAMPLES / 100
+}
+
+/// Collect drawn outlines as contours. The start point of a closed contour
+/// becomes the end of its last segment, as in UFOs.
+#[derive(Default)]
+struct ContourPen {
+    contours: Vec<Contour>,
+    points: Vec<ContourPoint>,
+}
+
+impl ContourPen {
+    fn push(&mut self, x: f32, y: f32, typ: PointType) {
+        self.points.push(ContourPoint {
+            x: x as f64,
+            y: y as f64,
+            typ,
+            smooth: false,
+        });
+    }
+
+    fn finish(mut self) -> Vec<Contour> {
+        if !self.points.is_empty() {
+            self.contours.push(Contour {
+                points: std::mem::take(&mut self.points),
+                extra: ExtraFields::new(),
+            });
+        }
+        self.contours
+    }
+}
+
+impl OutlinePen for ContourPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        if !self.points.is_empty() {
+            self.contours.push(Contour {
+                points: std::mem::take(&mut self.points),
+                extra: ExtraFields::new(),
+            });
+        }
+        self.push(x, y, PointType::Move);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(x, y, PointType::Line);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.push(cx0, cy0, PointType::OffCurve);
+        self.push(x, y, PointType::QCurve);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.push(cx0, cy0, PointType::OffCurve);
+        self.push(cx1, cy1, PointType::OffCurve);
+        self.push(x, y, PointType::Curve);
+    }
+
+    fn close(&mut self) {
+        let mut points = std::mem::take(&mut self.points);
+        let Some(first) = points.first() else {
+            return;
+        };
+        let (first_x, first_y) = (first.x, first.y);
+        let closes_on_start = points.len() > 1
+            && points.last().is_some_and(|last| {
+                last.typ != PointType::OffCurve && (last.x, last.y) == (first_x, first_y)
+            });
+        points[0].typ = if closes_on_start {
+            points.pop().unwrap().typ
+        } else {
+            PointType::Line
+        };
+        self.contours.push(Contour {
+            points,
+            extra: ExtraFields::new(),
+        });
+    }
+}
+
+struct BezPathPen(BezPath);
+
+impl OutlinePen for BezPathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to((x as f64, y as f64));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to((x as f64, y as f64));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0
+            .quad_to((cx0 as f64, cy0 as f64), (x as f64, y as f64));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.curve_to(
+            (cx0 as f64, cy0 as f64),
+            (cx1 as f64, cy1 as f64),
+            (x as f64, y as f64),
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(x0: f64, y0: f64, x1: f64, y1: f64, clockwise: bool) -> BezPath {
+        let mut corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+        if clockwise {
+            corners.reverse();
+        }
+        let mut path = BezPath::new();
+        path.move_to(corners[0]);
+        for corner in &corners[1..] {
+            path.line_to(*corner);
+        }
+        path.close_path();
+        path
+    }
+
+    #[test]
+    fn compare_outlines_by_filled_area() {
+        // Two overlapping rectangles, as drawn and with the overlap removed.
+        let mut overlapping = rectangle(0., 0., 100., 50., false);
+        overlapping.extend(rectangle(0., 0., 50., 100., false));
+        let mut merged = BezPath::new();
+        merged.move_to((0., 0.));
+        for corner in [(100., 0.), (100., 50.), (50., 50.), (50., 100.), (0., 100.)] {
+            merged.line_to(corner);
+        }
+        merged.close_path();
+
+        assert!(!outlines_differ(&overlapping, &merged));
+        assert!(!outlines_differ(
+            &rectangle(0., 0., 100., 100., false),
+            &rectangle(0., 0., 100.4, 100., true)
+        ));
+        assert!(outlines_differ(
+            &rectangle(0., 0., 100., 100., false),
+            &merged
+        ));
+        assert!(outlines_differ(&BezPath::new(), &merged));
+    }
+
+    #[test]
+    fn draw_into_contours() {
+        let mut pen = ContourPen::default();
+        pen.move_to(0., 0.);
+        pen.curve_to(0., 50., 50., 100., 100., 100.);
+        pen.line_to(0., 0.);
+        pen.close();
+        pen.move_to(10., 10.);
+        pen.quad_to(20., 20., 30., 10.);
+        pen.close();
+        pen.move_to(0., -10.);
+        pen.line_to(10., -10.);
+        let contours = pen.finish();
+
+        let types = |contour: &Contour| -> Vec<PointType> {
+            contour
+                .points
+                .iter()
+                .map(|point| point.typ.clone())
+                .collect()
+        };
+        assert_eq!(contours.len(), 3);
+        assert_eq!(
+            types(&contours[0]),
+            [
+                PointType::Line,
+                PointType::OffCurve,
+                PointType::OffCurve,
+                PointType::Curve
+            ]
+        );
+        assert_eq!(
+            types(&contours[1]),
+            [PointType::Line, PointType::OffCurve, PointType::QCurve]
+        );
+        assert_eq!(types(&contours[2]), [PointType::Move, PointType::Line]);
+        assert_eq!(contours[1].points[2].x, 30.);
+    }
+}
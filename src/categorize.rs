@@ -0,0 +1,205 @@
+use glyphsinfo_rs::{Category, GlyphData, Record, Subcategory};
+use norad::Codepoints;
+
+use crate::{
+    config::SetConfig,
+    structs::{Fontgarden, OpenTypeCategory},
+};
+
+impl Fontgarden {
+    /// Guess the set of glyphs again from the glyph data, moving them between sets.
+    /// With `only_unset`, only glyphs in the Common set are considered.
+    ///
+    /// Returns the moved glyphs with their old and new set, sorted by glyph name.
+    pub fn recategorize_sets(
+        &mut self,
+        glyph_info: &GlyphData,
+        only_unset: bool,
+    ) -> Vec<(String, Option<String>, Option<String>)> {
+        let mut moved = Vec::new();
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            if only_unset && glyph.set.is_some() {
+                continue;
+            }
+            let set = set_for_glyph(glyph_name, &glyph.codepoints, glyph_info, &self.config.sets);
+            if set != glyph.set {
+                moved.push((glyph_name.to_string(), glyph.set.clone(), set.clone()));
+                glyph.set = set;
+            }
+        }
+        moved.sort();
+        moved
+    }
+
+    /// Derive an OpenType category from the glyph data for all glyphs that don't
+    /// have one yet. Returns the number of glyphs that got a category assigned.
+    pub fn assign_opentype_categories(&mut self, glyph_info: &GlyphData) -> usize {
+        let mut assigned = 0;
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            if glyph.opentype_category != OpenTypeCategory::Unassigned {
+                continue;
+            }
+            let category = opentype_category(glyph_name, &glyph.codepoints, glyph_info);
+            if category != OpenTypeCategory::Unassigned {
+                glyph.opentype_category = category;
+                assigned += 1;
+            }
+        }
+        assigned
+    }
+}
+
+/// Look up the glyph data record for a glyph, by its first codepoint, its name or
+/// the name without suffix (for variants like `a.sc`).
+pub fn lookup_record<'a>(
+    glyph_name: &str,
+    codepoints: &Codepoints,
+    glyph_info: &'a GlyphData,
+) -> Option<&'a Record> {
+    if let Some(record) = codepoints
+        .iter()
+        .next()
+        .and_then(|unicode| glyph_info.record_for_unicode(unicode))
+    {
+        return Some(record);
+    }
+    if let Some(record) = glyph_info.record_for_name(glyph_name) {
+        return Some(record);
+    }
+    let (base_name, _) = glyph_name.split_once('.')?;
+    glyph_info.record_for_name(base_name)
+}
+
+/// Guess the set a glyph belongs to from the script of its glyph data record,
+/// mapped through the set configuration. `None` means the Common set.
+pub fn set_for_glyph(
+    glyph_name: &str,
+    codepoints: &Codepoints,
+    glyph_info: &GlyphData,
+    set_config: &SetConfig,
+) -> Option<String> {
+    // FIXME: This also categorizes danda-deva.loclBENG as Devanagari because the parent
+    // is. Local variants should stay with their scripts if possible.
+    let script = lookup_record(glyph_name, codepoints, glyph_info)
+        .and_then(|record| record.script.as_ref().map(|s| format!("{s:?}")));
+
+    let set = match set_config
+        .rules
+        .iter()
+        .find(|rule| rule.matches(glyph_name, codepoints, script.as_deref()))
+    {
+        Some(rule) => rule.set.clone(),
+        None => {
+            let script = script?;
+            set_config.scripts.get(&script).cloned().unwrap_or(script)
+        }
+    };
+    (set != Fontgarden::COMMON_SET_NAME).then_some(set)
+}
+
+/// Map the Glyphs categories of a glyph to the OpenType category, the same way
+/// glyphsLib does it.
+pub fn opentype_category(
+    glyph_name: &str,
+    codepoints: &Codepoints,
+    glyph_info: &GlyphData,
+) -> OpenTypeCategory {
+    let Some(record) = lookup_record(glyph_name, codepoints, glyph_info) else {
+        return OpenTypeCategory::Unassigned;
+    };
+    match (record.category, record.subcategory) {
+        (
+            Category::Mark,
+            Some(Subcategory::Nonspacing | Subcategory::SpacingCombining | Subcategory::Enclosing),
+        ) => OpenTypeCategory::Mark,
+        (_, Some(Subcategory::Ligature)) => OpenTypeCategory::Ligature,
+        _ => OpenTypeCategory::Base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    #[test]
+    fn recategorize_after_changes() {
+        let glyph = |codepoint: char, set: &str| {
+            let glyph = GlyphBuilder::new()
+                .codepoints([codepoint])
+                .layer("Regular", LayerBuilder::new().width(500.));
+            match set {
+                Fontgarden::COMMON_SET_NAME => glyph,
+                _ => glyph.set(set),
+            }
+        };
+        let mut fontgarden = FontgardenBuilder::new()
+            .glyph("a", glyph('a', "Latin"))
+            .glyph("x", glyph('x', "Latin"))
+            .glyph("alpha", glyph('α', "Greek"))
+            .glyph("period", glyph('.', Fontgarden::COMMON_SET_NAME))
+            .build();
+        let glyph_info = GlyphData::default();
+
+        assert!(fontgarden.recategorize_sets(&glyph_info, false).is_empty());
+        assert_eq!(fontgarden.glyphs["x"].set.as_deref(), Some("Latin"));
+        assert_eq!(fontgarden.glyphs["period"].set, None);
+
+        fontgarden.glyphs.get_mut("x").unwrap().codepoints = Codepoints::new(['\u{430}']);
+        fontgarden.glyphs.get_mut("period").unwrap().set = Some("Latin".into());
+        assert!(fontgarden.recategorize_sets(&glyph_info, true).is_empty());
+        assert_eq!(
+            fontgarden.recategorize_sets(&glyph_info, false),
+            [
+                ("period".into(), Some("Latin".into()), None),
+                ("x".into(), Some("Latin".into()), Some("Cyrillic".into())),
+            ]
+        );
+        assert_eq!(fontgarden.glyphs["x"].set.as_deref(), Some("Cyrillic"));
+        assert!(fontgarden.recategorize_sets(&glyph_info, false).is_empty());
+    }
+
+    #[test]
+    fn assign_categories_from_glyph_data() {
+        let layer = || LayerBuilder::new().width(500.).rect(50., 0., 450., 500.);
+        let mut fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "acutecomb",
+                GlyphBuilder::new().codepoints(['\u{301}']).layer(
+                    "Regular",
+                    LayerBuilder::new()
+                        .rect(-50., 550., 50., 650.)
+                        .anchor("_top", 0., 500.),
+                ),
+            )
+            .glyph("f_i", GlyphBuilder::new().layer("Regular", layer()))
+            .glyph(
+                "a",
+                GlyphBuilder::new()
+                    .codepoints(['a'])
+                    .layer("Regular", layer()),
+            )
+            .glyph(
+                "a.alt",
+                GlyphBuilder::new()
+                    .category(OpenTypeCategory::Mark)
+                    .layer("Regular", layer()),
+            )
+            .glyph(
+                "unknown-glyph",
+                GlyphBuilder::new().layer("Regular", layer()),
+            )
+            .build();
+
+        let assigned = fontgarden.assign_opentype_categories(&GlyphData::default());
+
+        assert_eq!(assigned, 3);
+        let category = |glyph_name: &str| fontgarden.glyphs[glyph_name].opentype_category.clone();
+        assert_eq!(category("acutecomb"), OpenTypeCategory::Mark);
+        assert_eq!(category("f_i"), OpenTypeCategory::Ligature);
+        assert_eq!(category("a"), OpenTypeCategory::Base);
+        assert_eq!(category("a.alt"), OpenTypeCategory::Mark);
+        assert_eq!(category("unknown-glyph"), OpenTypeCategory::Unassigned);
+    }
+}
@@ -0,0 +1,443 @@
+use std::{fmt::Display, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    contours::WindingConvention,
+    errors::CheckError,
+    interpolate::{Incompatibility, Location},
+    selection::Selection,
+    structs::{Fontgarden, LayerName},
+    validate::{CodepointProblem, PathProblemKind, Severity},
+};
+
+/// The exit code of `fontgarden check` when a check found errors.
+pub const EXIT_ERRORS: i32 = 1;
+/// The exit code of `fontgarden check --ci` when the checks could not run, for
+/// example because the fontgarden or profile could not be loaded.
+pub const EXIT_FAILURE: i32 = 2;
+
+/// A check that `fontgarden check` can run, as named in profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// Glyphs that cannot be interpolated.
+    Compat,
+    /// Smooth points that kink between sources.
+    Kinks,
+    /// Anchors present in some sources but not others.
+    Anchors,
+    /// Duplicate codepoints and codepoints disagreeing with glyph names.
+    Codepoints,
+    /// Spacing glyphs without advance width.
+    Metrics,
+    /// Contour direction, open contours, stray points and missing extrema.
+    Outlines,
+    /// Mark anchors without a matching base anchor.
+    MarkAnchors,
+}
+
+impl Check {
+    pub const ALL: [Check; 7] = [
+        Check::Compat,
+        Check::Kinks,
+        Check::Anchors,
+        Check::Codepoints,
+        Check::Metrics,
+        Check::Outlines,
+        Check::MarkAnchors,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Check::Compat => "compat",
+            Check::Kinks => "kinks",
+            Check::Anchors => "anchors",
+            Check::Codepoints => "codepoints",
+            Check::Metrics => "metrics",
+            Check::Outlines => "outlines",
+            Check::MarkAnchors => "mark-anchors",
+        }
+    }
+
+    /// The checks of the built-in profiles, used unless the configuration
+    /// defines a profile of the same name.
+    fn builtin_profile(name: &str) -> Option<Vec<Check>> {
+        match name {
+            "quick" => Some(vec![Check::Codepoints, Check::Outlines]),
+            "prepublish" => Some(Check::ALL.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn run(&self, fontgarden: &Fontgarden, options: &CheckOptions) -> Vec<Finding> {
+        let finding = |severity, glyph_name: Option<&str>, message: String| Finding {
+            check: *self,
+            severity,
+            glyph_name: glyph_name.map(|name| name.to_string()),
+            layer_name: None,
+            position: None,
+            message,
+        };
+        match self {
+            Check::Compat => {
+                // Without axes, there is nothing to interpolate.
+                if fontgarden.config.axes.is_empty() {
+                    return Vec::new();
+                }
+                match fontgarden.interpolate(&Location::new()) {
+                    Ok(interpolation) => interpolation
+                        .incompatible
+                        .iter()
+                        .map(|(glyph_name, incompatibility)| {
+                            let layer_name = match incompatibility {
+                                Incompatibility::Structure { layer_name, .. } => {
+                                    Some(layer_name.clone())
+                                }
+                                Incompatibility::MissingDefault => None,
+                            };
+                            Finding {
+                                layer_name,
+                                ..finding(
+                                    Severity::Error,
+                                    Some(glyph_name),
+                                    format!("glyph {glyph_name}: {incompatibility}"),
+                                )
+                            }
+                        })
+                        .collect(),
+                    Err(error) => vec![finding(Severity::Error, None, error.to_string())],
+                }
+            }
+            Check::Kinks => {
+                if fontgarden.config.axes.is_empty() {
+                    return Vec::new();
+                }
+                // Four steps to each end of each axis catch most kinks without
+                // interpolating too often.
+                match fontgarden.interpolation_kinks(4) {
+                    Ok(kinks) => kinks
+                        .iter()
+                        .map(|kink| {
+                            finding(Severity::Warning, Some(&kink.glyph_name), kink.to_string())
+                        })
+                        .collect(),
+                    Err(error) => vec![finding(Severity::Error, None, error.to_string())],
+                }
+            }
+            Check::Anchors => fontgarden
+                .anchor_consistency()
+                .iter()
+                .flat_map(|report| {
+                    report.missing.iter().map(|(anchor_name, source_names)| {
+                        let source_names: Vec<&str> =
+                            source_names.iter().map(|n| n.as_str()).collect();
+                        finding(
+                            Severity::Error,
+                            Some(&report.glyph_name),
+                            format!(
+                                "glyph {}: anchor {anchor_name} is missing in: {}",
+                                report.glyph_name,
+                                source_names.join(", ")
+                            ),
+                        )
+                    })
+                })
+                .collect(),
+            Check::Codepoints => fontgarden
+                .codepoint_problems()
+                .iter()
+                .map(|problem| {
+                    let glyph_name = match problem {
+                        CodepointProblem::NameMismatch { glyph_name, .. } => {
+                            Some(glyph_name.as_str())
+                        }
+                        CodepointProblem::Duplicate { .. } => None,
+                    };
+                    finding(Severity::Error, glyph_name, problem.to_string())
+                })
+                .collect(),
+            Check::Metrics => fontgarden
+                .metrics_report(false)
+                .iter()
+                .flat_map(|glyph_metrics| {
+                    glyph_metrics.flags.iter().map(|flag| {
+                        finding(
+                            Severity::Warning,
+                            Some(&glyph_metrics.glyph_name),
+                            format!("glyph {}: {flag}", glyph_metrics.glyph_name),
+                        )
+                    })
+                })
+                .collect(),
+            Check::Outlines => fontgarden
+                .path_problems(options.convention)
+                .iter()
+                .map(|problem| {
+                    let point_index = match problem.kind {
+                        PathProblemKind::ZeroLengthSegment { point_index }
+                        | PathProblemKind::DuplicatePoint { point_index }
+                        | PathProblemKind::MissingExtremum { point_index, .. } => point_index,
+                        PathProblemKind::WrongDirection | PathProblemKind::OpenContour => 0,
+                    };
+                    let position = fontgarden.glyphs[problem.glyph_name.as_str()].layers
+                        [&problem.layer_name]
+                        .contours[problem.contour_index]
+                        .points
+                        .get(point_index)
+                        .map(|point| (point.x, point.y));
+                    Finding {
+                        layer_name: Some(problem.layer_name.clone()),
+                        position,
+                        ..finding(
+                            problem.severity(),
+                            Some(&problem.glyph_name),
+                            format!(
+                                "glyph {}, layer '{}', contour {}: {}",
+                                problem.glyph_name,
+                                problem.layer_name,
+                                problem.contour_index,
+                                problem.kind
+                            ),
+                        )
+                    }
+                })
+                .collect(),
+            Check::MarkAnchors => fontgarden
+                .unmatched_mark_anchors()
+                .iter()
+                .map(|unmatched| {
+                    finding(
+                        Severity::Error,
+                        Some(&unmatched.mark_name),
+                        unmatched.to_string(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Settings that individual checks depend on.
+#[derive(Debug, Default)]
+pub struct CheckOptions {
+    pub convention: WindingConvention,
+    /// Only report findings about these glyphs, and font-wide ones [default: all]
+    pub glyphs: Option<Selection>,
+}
+
+/// How `fontgarden check --ci` weighs the findings of checks, for CI jobs that
+/// should fail on some problems but only warn about others.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CiConfig {
+    /// Checks whose warnings count as errors.
+    pub error: Vec<Check>,
+    /// Checks whose errors count as warnings.
+    pub warn: Vec<Check>,
+}
+
+impl CiConfig {
+    /// Promote or demote the findings of the checks listed.
+    pub fn apply(&self, findings: &mut [Finding]) {
+        for finding in findings {
+            if self.error.contains(&finding.check) {
+                finding.severity = Severity::Error;
+            } else if self.warn.contains(&finding.check) {
+                finding.severity = Severity::Warning;
+            }
+        }
+    }
+}
+
+/// Something a check found.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub check: Check,
+    pub severity: Severity,
+    /// The glyph the finding is about, if it is about a single glyph.
+    pub glyph_name: Option<String>,
+    /// The layer of the glyph the finding is about, if it is about one.
+    pub layer_name: Option<LayerName>,
+    /// Where in the layer the problem is, if it is at a point.
+    pub position: Option<(f64, f64)>,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: [{}] {}", self.severity, self.check, self.message)
+    }
+}
+
+impl Finding {
+    /// The finding as a GitHub Actions workflow command, which shows up as an
+    /// annotation on the layer file it is about, if any.
+    pub fn github_annotation(&self, fontgarden_path: &Path) -> String {
+        let mut properties = Vec::new();
+        if let (Some(glyph_name), Some(layer_name)) = (&self.glyph_name, &self.layer_name) {
+            let path = fontgarden_path.join(Fontgarden::layer_path(glyph_name, layer_name));
+            properties.push(format!(
+                "file={}",
+                escape_annotation_property(&path.to_string_lossy())
+            ));
+        }
+        properties.push(format!("title={}", self.check));
+        format!(
+            "::{} {}::{}",
+            self.severity,
+            properties.join(","),
+            escape_annotation_data(&self.message)
+        )
+    }
+}
+
+fn escape_annotation_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_annotation_property(property: &str) -> String {
+    escape_annotation_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+impl Fontgarden {
+    /// The checks of a profile, from the configuration or the built-in
+    /// `quick` and `prepublish` profiles.
+    pub fn check_profile(&self, profile_name: &str) -> Result<Vec<Check>, CheckError> {
+        self.config
+            .check_profiles
+            .get(profile_name)
+            .cloned()
+            .or_else(|| Check::builtin_profile(profile_name))
+            .ok_or_else(|| CheckError::UnknownProfile(profile_name.to_string()))
+    }
+
+    /// Run the checks, returning their findings in the order of the checks.
+    pub fn run_checks(&self, checks: &[Check], options: &CheckOptions) -> Vec<Finding> {
+        let is_selected = |finding: &Finding| match (&options.glyphs, &finding.glyph_name) {
+            (Some(selection), Some(glyph_name)) => self
+                .glyphs
+                .get(glyph_name.as_str())
+                .is_some_and(|glyph| selection.matches(glyph_name, glyph)),
+            _ => true,
+        };
+        checks
+            .iter()
+            .flat_map(|check| check.run(self, options))
+            .filter(is_selected)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, ExtraFields, Glyph, Layer};
+
+    use super::*;
+
+    #[test]
+    fn profiles_from_config_and_builtin() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config =
+            toml::from_str("check_profiles.quick = [\"anchors\", \"mark-anchors\"]").unwrap();
+
+        assert_eq!(
+            fontgarden.check_profile("quick").unwrap(),
+            vec![Check::Anchors, Check::MarkAnchors]
+        );
+        assert_eq!(
+            fontgarden.check_profile("prepublish").unwrap(),
+            Check::ALL.to_vec()
+        );
+        assert!(matches!(
+            fontgarden.check_profile("nightly"),
+            Err(CheckError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn ci_severities_and_annotations() {
+        let finding = |check, severity, layer_name: Option<LayerName>| Finding {
+            check,
+            severity,
+            glyph_name: Some("a".into()),
+            layer_name,
+            position: None,
+            message: "100%, done\nreally".into(),
+        };
+        let mut findings = vec![
+            finding(Check::Kinks, Severity::Warning, None),
+            finding(Check::Anchors, Severity::Error, None),
+            finding(
+                Check::Outlines,
+                Severity::Error,
+                Some(LayerName::main("Bold")),
+            ),
+        ];
+        let ci_config: CiConfig =
+            toml::from_str("error = [\"kinks\"]\nwarn = [\"anchors\"]").unwrap();
+
+        ci_config.apply(&mut findings);
+
+        let severities: Vec<Severity> = findings.iter().map(|f| f.severity).collect();
+        assert_eq!(
+            severities,
+            [Severity::Error, Severity::Warning, Severity::Error]
+        );
+        assert_eq!(
+            findings[1].github_annotation(Path::new("My,Font.fontgarden")),
+            "::warning title=anchors::100%25, done%0Areally"
+        );
+        assert_eq!(
+            findings[2].github_annotation(Path::new("My,Font.fontgarden")),
+            "::error file=My%2CFont.fontgarden/glyphs/a/B_old.json,title=outlines::100%25, \
+             done%0Areally"
+        );
+    }
+
+    #[test]
+    fn run_anchor_check() {
+        let mut fontgarden = Fontgarden::new();
+        let mut glyph = Glyph::default();
+        glyph.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                anchors: vec![Anchor {
+                    name: "top".into(),
+                    x: 0.,
+                    y: 0.,
+                    extra: ExtraFields::new(),
+                }],
+                ..Default::default()
+            },
+        );
+        glyph
+            .layers
+            .insert(LayerName::main("Bold"), Layer::default());
+        fontgarden.glyphs.insert("a".into(), glyph);
+
+        let findings = fontgarden.run_checks(&[Check::Anchors], &CheckOptions::default());
+
+        assert_eq!(
+            findings,
+            vec![Finding {
+                check: Check::Anchors,
+                severity: Severity::Error,
+                glyph_name: Some("a".into()),
+                layer_name: None,
+                position: None,
+                message: "glyph a: anchor top is missing in: Bold".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use norad::{fontinfo::StyleMapStyle, Codepoints};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    check::{Check, CiConfig},
+    errors::LoadError,
+    fetch::Dependency,
+    filters::FilterStep,
+    hooks::HooksConfig,
+    interpolate::Location,
+    lib_keys::LibKeysConfig,
+    storage::Storage,
+};
+
+/// Project configuration, read from `fontgarden.toml` at the root of a fontgarden.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub sets: SetConfig,
+    /// Design axes by tag, like `wght`.
+    pub axes: BTreeMap<String, AxisConfig>,
+    /// Where the sources are in the design space, by source name.
+    pub sources: BTreeMap<String, SourceConfig>,
+    /// Named static instances to interpolate on export.
+    pub instances: BTreeMap<String, InstanceConfig>,
+    /// The source whose codepoints and font-wide glyph data win on import.
+    pub default_source: Option<String>,
+    /// The units per em of all sources, recorded on the first import.
+    pub units_per_em: Option<f64>,
+    /// Decimal places to round coordinates, transformations and metrics to
+    /// when writing layer files and exporting UFOs [default: no rounding]
+    pub precision: Option<u32>,
+    /// How to write layer files. Either style loads.
+    pub json_style: JsonStyle,
+    /// Named lists of checks for `fontgarden check`, overriding the built-in
+    /// profiles of the same name.
+    pub check_profiles: BTreeMap<String, Vec<Check>>,
+    pub ci: CiConfig,
+    /// Fontgardens to fetch sets from, by a name of choice.
+    pub dependencies: BTreeMap<String, Dependency>,
+    pub export: ExportConfig,
+    pub hooks: HooksConfig,
+    /// UFO lib keys to keep on import and write back on export.
+    pub lib_keys: LibKeysConfig,
+    /// The original text of the configuration file, written back verbatim on save
+    /// so that comments and formatting survive.
+    #[serde(skip)]
+    pub(crate) raw: Option<String>,
+}
+
+impl Config {
+    pub const FILENAME: &str = "fontgarden.toml";
+
+    /// Load the configuration of a fontgarden, falling back to the defaults if
+    /// there is no configuration file.
+    pub fn load(storage: &impl Storage) -> Result<Self, LoadError> {
+        let config_path = Path::new(Self::FILENAME);
+        if !storage.exists(config_path) {
+            return Ok(Self::default());
+        }
+        let raw = storage
+            .read_to_string(config_path)
+            .map_err(|e| LoadError::Io(storage.location(config_path), e))?;
+        let mut config: Config = toml::from_str(&raw)
+            .map_err(|e| LoadError::LoadConfig(storage.location(config_path), e))?;
+        config.raw = Some(raw);
+        Ok(config)
+    }
+
+    /// Record the location of a new source, appending it to the configuration
+    /// file's text so that the rest stays as it is.
+    pub fn add_source(&mut self, source_name: &str, location: Location) {
+        let values: Vec<String> = location
+            .iter()
+            .map(|(axis, value)| format!("{axis} = {value}"))
+            .collect();
+        let raw = self.raw.get_or_insert_with(String::new);
+        if !raw.is_empty() && !raw.ends_with('\n') {
+            raw.push('\n');
+        }
+        let is_bare_key = source_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let key = if is_bare_key {
+            source_name.to_string()
+        } else {
+            toml::Value::String(source_name.to_string()).to_string()
+        };
+        raw.push_str(&format!(
+            "\n[sources.{key}]\nlocation = {{ {} }}\n",
+            values.join(", ")
+        ));
+        self.sources.insert(
+            source_name.to_string(),
+            SourceConfig {
+                location,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// The configured naming of a source or instance.
+    pub fn style_naming(&self, name: &str) -> Option<&StyleNaming> {
+        match self.sources.get(name) {
+            Some(source) => Some(&source.naming),
+            None => self.instances.get(name).map(|instance| &instance.naming),
+        }
+    }
+
+    /// Record a new axis, appending it to the configuration file's text.
+    pub fn add_axis(&mut self, tag: &str, axis: AxisConfig) {
+        let raw = self.raw.get_or_insert_with(String::new);
+        if !raw.is_empty() && !raw.ends_with('\n') {
+            raw.push('\n');
+        }
+        raw.push_str(&format!(
+            "\n[axes.{tag}]\nminimum = {}\ndefault = {}\nmaximum = {}\n",
+            axis.minimum, axis.default, axis.maximum
+        ));
+        self.axes.insert(tag.to_string(), axis);
+    }
+
+    /// Record the default source in the configuration file's text.
+    pub fn set_default_source(&mut self, source_name: &str) {
+        self.set_top_level_value("default_source", source_name.into());
+        self.default_source = Some(source_name.to_string());
+    }
+
+    /// Record the units per em in the configuration file's text.
+    pub fn set_units_per_em(&mut self, units_per_em: f64) {
+        self.set_top_level_value("units_per_em", units_per_em.into());
+        self.units_per_em = Some(units_per_em);
+    }
+
+    /// Record how to write layer files in the configuration file's text.
+    pub fn set_json_style(&mut self, json_style: JsonStyle) {
+        let value = match json_style {
+            JsonStyle::Pretty => "pretty",
+            JsonStyle::Compact => "compact",
+        };
+        self.set_top_level_value("json_style", value.into());
+        self.json_style = json_style;
+    }
+
+    /// Set a top-level key in the configuration file's text, replacing the
+    /// existing line or adding one at the top, where top-level keys must go.
+    fn set_top_level_value(&mut self, key: &str, value: toml::Value) {
+        let line = format!("{key} = {value}");
+        let raw = self.raw.get_or_insert_with(String::new);
+        match raw
+            .lines()
+            .find(|l| l.split('=').next().map(str::trim) == Some(key))
+        {
+            Some(existing) => *raw = raw.replacen(existing, &line, 1),
+            None => raw.insert_str(0, &format!("{line}\n")),
+        }
+    }
+}
+
+/// How to write the JSON of layer files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonStyle {
+    /// Indented, one value per line, for readable diffs.
+    #[default]
+    Pretty,
+    /// On one line, for gardens so large that indentation adds up.
+    Compact,
+}
+
+/// How glyphs are sorted into sets when guessing from the glyph data.
+///
+/// Rules are tried in order and the first match wins. If none match, the script
+/// of the glyph is looked up in `scripts`, and if it isn't there either, the
+/// script name is used as the set name.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetConfig {
+    /// Script name (e.g. "Devanagari") to set name (e.g. "Indic").
+    pub scripts: HashMap<String, String>,
+    pub rules: Vec<SetRule>,
+}
+
+/// Put glyphs matching all given conditions into `set`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetRule {
+    pub set: String,
+    /// The script of the glyph according to the glyph data.
+    pub script: Option<String>,
+    /// A name suffix like `-deva` or `.loclSRB`, matched at the end of the name or
+    /// before a further `.` suffix.
+    pub suffix: Option<String>,
+    /// A hex codepoint range like `0000-024F` the first codepoint must fall into.
+    #[serde(default, deserialize_with = "deserialize_codepoint_range")]
+    pub codepoints: Option<RangeInclusive<u32>>,
+}
+
+impl SetRule {
+    pub fn matches(&self, glyph_name: &str, codepoints: &Codepoints, script: Option<&str>) -> bool {
+        if let Some(rule_script) = &self.script {
+            if script != Some(rule_script.as_str()) {
+                return false;
+            }
+        }
+        if let Some(suffix) = &self.suffix {
+            if !glyph_name.ends_with(suffix.as_str()) && !glyph_name.contains(&format!("{suffix}."))
+            {
+                return false;
+            }
+        }
+        if let Some(range) = &self.codepoints {
+            match codepoints.iter().next() {
+                Some(codepoint) if range.contains(&(codepoint as u32)) => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// The range of a design axis, in the same units as the locations.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxisConfig {
+    pub minimum: f64,
+    pub default: f64,
+    pub maximum: f64,
+}
+
+impl AxisConfig {
+    /// Map a value on the axis to -1..=1, with the default at 0.
+    pub fn normalize(&self, value: f64) -> f64 {
+        let value = value.clamp(self.minimum, self.maximum);
+        if value < self.default {
+            (value - self.default) / (self.default - self.minimum)
+        } else if value > self.default {
+            (value - self.default) / (self.maximum - self.default)
+        } else {
+            0.
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceConfig {
+    /// Axis tag to value. Axes not given are at their default.
+    pub location: Location,
+    /// Glyph name to the advance width of the glyph when made up on export,
+    /// overriding `export.default_glyph_widths`.
+    pub default_glyph_widths: BTreeMap<String, f64>,
+    pub naming: StyleNaming,
+}
+
+/// How a source or instance is named in the fontinfo of its UFO, next to the
+/// family name from `lib.json`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleNaming {
+    /// The style name [default: the source or instance name]
+    pub style_name: Option<String>,
+    /// The family name of the group of up to four styles linked as regular,
+    /// italic, bold and bold italic.
+    pub style_map_family_name: Option<String>,
+    /// `regular`, `italic`, `bold` or `bold italic`.
+    pub style_map_style_name: Option<StyleMapStyle>,
+    /// The typographic family name, name ID 16.
+    pub typographic_family_name: Option<String>,
+    /// The typographic subfamily name, name ID 17.
+    pub typographic_subfamily_name: Option<String>,
+}
+
+impl StyleNaming {
+    /// Fill in the names that are given into the fontinfo of an exported UFO.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font) {
+        let font_info = &mut ufo.font_info;
+        for (field, value) in [
+            (&mut font_info.style_name, &self.style_name),
+            (
+                &mut font_info.style_map_family_name,
+                &self.style_map_family_name,
+            ),
+            (
+                &mut font_info.open_type_name_preferred_family_name,
+                &self.typographic_family_name,
+            ),
+            (
+                &mut font_info.open_type_name_preferred_subfamily_name,
+                &self.typographic_subfamily_name,
+            ),
+        ] {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        }
+        if self.style_map_style_name.is_some() {
+            font_info
+                .style_map_style_name
+                .clone_from(&self.style_map_style_name);
+        }
+    }
+}
+
+/// The filter steps to run on export, so that e.g. static instances can be
+/// decomposed and variable sources not.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Steps for the sources and instances without a pipeline of their own.
+    pub pipeline: Vec<FilterStep>,
+    /// Source name to the steps for that source.
+    pub sources: BTreeMap<String, Vec<FilterStep>>,
+    /// Instance name to the steps for that instance.
+    pub instances: BTreeMap<String, Vec<FilterStep>>,
+    /// Glyph name to the advance width of the glyph when made up on export,
+    /// like `space = 250`, for all sources.
+    pub default_glyph_widths: BTreeMap<String, f64>,
+}
+
+impl ExportConfig {
+    pub fn source_pipeline(&self, source_name: &str) -> &[FilterStep] {
+        self.sources.get(source_name).unwrap_or(&self.pipeline)
+    }
+
+    pub fn instance_pipeline(&self, instance_name: &str) -> &[FilterStep] {
+        self.instances.get(instance_name).unwrap_or(&self.pipeline)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstanceConfig {
+    /// Axis tag to value. Axes not given are at their default.
+    pub location: Location,
+    pub naming: StyleNaming,
+}
+
+fn deserialize_codepoint_range<'de, D>(
+    deserializer: D,
+) -> Result<Option<RangeInclusive<u32>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: String = Deserialize::deserialize(deserializer)?;
+    let invalid = || serde::de::Error::custom(format!("invalid codepoint range '{value}'"));
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    let start = u32::from_str_radix(start.trim(), 16).map_err(|_| invalid())?;
+    let end = u32::from_str_radix(end.trim(), 16).map_err(|_| invalid())?;
+    Ok(Some(start..=end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_set_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [sets.scripts]
+            Devanagari = "Indic"
+            Bengali = "Indic"
+
+            [[sets.rules]]
+            suffix = ".loclSRB"
+            set = "Cyrillic"
+
+            [[sets.rules]]
+            script = "Latin"
+            codepoints = "0000-024F"
+            set = "LatinCore"
+
+            [axes.wght]
+            minimum = 100
+            default = 400
+            maximum = 900
+
+            [sources.Bold]
+            location = { wght = 900 }
+
+            [sources.Bold.naming]
+            style_map_family_name = "Garden Sans"
+            style_map_style_name = "bold"
+
+            [instances.Medium]
+            location = { wght = 500 }
+            naming = { typographic_subfamily_name = "Medium" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.sets.scripts["Bengali"], "Indic");
+        let [srb, latin_core] = &config.sets.rules[..] else {
+            panic!("expected two rules");
+        };
+        assert!(srb.matches("be-cy.loclSRB", &Codepoints::new([]), None));
+        assert!(srb.matches("be-cy.loclSRB.ss01", &Codepoints::new([]), None));
+        assert!(!srb.matches("be-cy", &Codepoints::new([]), None));
+        assert!(latin_core.matches("a", &Codepoints::new(['a']), Some("Latin")));
+        assert!(!latin_core.matches(
+            "abrevedotbelow",
+            &Codepoints::new(['\u{1EB7}']),
+            Some("Latin")
+        ));
+
+        let weight = &config.axes["wght"];
+        assert_eq!(
+            weight.normalize(config.sources["Bold"].location["wght"]),
+            1.
+        );
+        assert_eq!(weight.normalize(250.), -0.5);
+        assert_eq!(
+            weight.normalize(config.instances["Medium"].location["wght"]),
+            0.2
+        );
+
+        let mut ufo = norad::Font::new();
+        ufo.font_info.style_name = Some("Bold".into());
+        config.style_naming("Bold").unwrap().apply_to_ufo(&mut ufo);
+        config
+            .style_naming("Medium")
+            .unwrap()
+            .apply_to_ufo(&mut ufo);
+        assert_eq!(ufo.font_info.style_name.as_deref(), Some("Bold"));
+        assert_eq!(
+            ufo.font_info.style_map_style_name,
+            Some(StyleMapStyle::Bold)
+        );
+        assert_eq!(
+            ufo.font_info
+                .open_type_name_preferred_subfamily_name
+                .as_deref(),
+            Some("Medium")
+        );
+    }
+
+    #[test]
+    fn add_source_keeps_text() {
+        let mut config = Config {
+            raw: Some("# Masters\n[sources.Light]\nlocation = { wght = 100 }".into()),
+            ..Default::default()
+        };
+        config.add_source("Semi Bold", [("wght".to_string(), 600.5)].into());
+
+        let raw = config.raw.as_deref().unwrap();
+        assert!(raw.starts_with("# Masters\n"));
+        let reloaded: Config = toml::from_str(raw).unwrap();
+        assert_eq!(reloaded.sources["Semi Bold"].location["wght"], 600.5);
+        assert_eq!(reloaded.sources["Light"].location["wght"], 100.);
+    }
+
+    #[test]
+    fn set_default_source_keeps_text() {
+        let mut config = Config {
+            raw: Some("[sources.Light]\nlocation = { wght = 100 }\n".into()),
+            ..Default::default()
+        };
+        config.set_default_source("Light");
+        config.set_default_source("Extra Light");
+
+        let raw = config.raw.as_deref().unwrap();
+        assert_eq!(raw.matches("default_source").count(), 1);
+        let reloaded: Config = toml::from_str(raw).unwrap();
+        assert_eq!(reloaded.default_source.as_deref(), Some("Extra Light"));
+        assert_eq!(reloaded.sources["Light"].location["wght"], 100.);
+    }
+}
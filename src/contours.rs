@@ -0,0 +1,396 @@
+use kurbo::{BezPath, ParamCurve, Point, Rect, Shape};
+
+use crate::{
+    geometry::add_segment,
+    par::*,
+    structs::{Contour, ContourPoint, Fontgarden, Layer, PointType},
+};
+
+/// How far, in font units, a curve may bulge past its on-curve points before
+/// it counts as missing an extremum, to allow for rounding.
+const EXTREMUM_TOLERANCE: f64 = 0.5;
+
+/// Which way outer contours should wind, with counters going the other way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindingConvention {
+    /// Outer contours counter-clockwise, as for cubic (CFF) outlines.
+    #[default]
+    PostScript,
+    /// Outer contours clockwise, as for quadratic (TrueType) outlines.
+    TrueType,
+}
+
+impl Fontgarden {
+    /// Make all closed contours in all layers wind according to the convention.
+    /// Returns the number of reversed contours.
+    pub fn fix_direction(&mut self, convention: WindingConvention) -> usize {
+        self.glyphs
+            .par_iter_mut()
+            .flat_map(|(_, glyph)| glyph.layers.par_iter_mut())
+            .map(|(_, layer)| layer.fix_direction(convention))
+            .sum()
+    }
+}
+
+impl Layer {
+    /// Find the closed contours winding the wrong way for the convention, by
+    /// index. Contours nested inside an odd number of other contours are counters.
+    pub fn misdirected_contours(&self, convention: WindingConvention) -> Vec<usize> {
+        let paths: Vec<_> = self.contours.iter().map(|c| c.to_bez_path()).collect();
+
+        let mut misdirected = Vec::new();
+        for (index, contour) in self.contours.iter().enumerate() {
+            if !contour.is_closed() {
+                continue;
+            }
+            let area = paths[index].area();
+            if area == 0. {
+                continue;
+            }
+            let depth = (0..self.contours.len())
+                .filter(|&other_index| {
+                    other_index != index
+                        && self.contours[other_index].is_closed()
+                        && contains(&paths[other_index], contour)
+                })
+                .count();
+            let is_outer = depth % 2 == 0;
+            let is_counter_clockwise = area > 0.;
+            let wants_counter_clockwise = match convention {
+                WindingConvention::PostScript => is_outer,
+                WindingConvention::TrueType => !is_outer,
+            };
+            if is_counter_clockwise != wants_counter_clockwise {
+                misdirected.push(index);
+            }
+        }
+        misdirected
+    }
+
+    /// Reverse misdirected contours. Returns the number of reversed contours.
+    pub fn fix_direction(&mut self, convention: WindingConvention) -> usize {
+        let misdirected = self.misdirected_contours(convention);
+        for &index in &misdirected {
+            self.contours[index].reverse();
+        }
+        misdirected.len()
+    }
+}
+
+/// Whether the contour lies inside the path. Merely overlapping contours, as in
+/// unremoved overlaps, do not count.
+///
+/// Winding numbers are unreliable for points level with the path's own points,
+/// so most rather than all on-curve points must be inside.
+fn contains(path: &BezPath, contour: &Contour) -> bool {
+    let outer = path.bounding_box();
+    let inner = contour.to_bez_path().bounding_box();
+    if outer.union(inner) != outer {
+        return false;
+    }
+    let (inside, outside): (Vec<bool>, Vec<bool>) = contour
+        .points
+        .iter()
+        .filter(|p| p.typ != PointType::OffCurve)
+        .map(|p| path.winding(Point::new(p.x, p.y)) != 0)
+        .partition(|&inside| inside);
+    inside.len() > outside.len()
+}
+
+impl Contour {
+    pub fn is_closed(&self) -> bool {
+        !matches!(
+            self.points.first(),
+            None | Some(ContourPoint {
+                typ: PointType::Move,
+                ..
+            })
+        )
+    }
+
+    /// The segments of the contour as the indices of their start and end points.
+    /// For closed contours, the indices wrap around.
+    fn segments(&self) -> Vec<(usize, usize)> {
+        let on_curve_indices: Vec<usize> = self
+            .points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.typ != PointType::OffCurve)
+            .map(|(i, _)| i)
+            .collect();
+        let mut segments: Vec<(usize, usize)> = on_curve_indices
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        if self.is_closed() {
+            if let (Some(&first), Some(&last)) = (on_curve_indices.first(), on_curve_indices.last())
+            {
+                segments.insert(0, (last, first));
+            }
+        }
+        segments
+    }
+
+    /// The indices of the points from `start` to `end`, both included.
+    fn segment_indices(&self, start: usize, end: usize) -> impl Iterator<Item = usize> {
+        let point_count = self.points.len();
+        let length = (end + point_count - start) % point_count;
+        (start..=start + length).map(move |index| index % point_count)
+    }
+
+    /// Find segments that don't go anywhere, by the index of their end point.
+    pub fn zero_length_segments(&self) -> Vec<usize> {
+        self.segments()
+            .into_iter()
+            .filter(|&(start, end)| {
+                let start_point = &self.points[start];
+                start != end
+                    && self.segment_indices(start, end).all(|index| {
+                        self.points[index].x == start_point.x
+                            && self.points[index].y == start_point.y
+                    })
+            })
+            .map(|(_, end)| end)
+            .collect()
+    }
+
+    /// Find points at the same position as the point before them, by index.
+    /// Points in zero-length segments are left to
+    /// [`zero_length_segments`](Self::zero_length_segments).
+    pub fn duplicate_points(&self) -> Vec<usize> {
+        let point_count = self.points.len();
+        let zero_length_segments = self.zero_length_segments();
+        let mut in_zero_length_segment = vec![false; point_count];
+        for (start, end) in self.segments() {
+            if zero_length_segments.contains(&end) {
+                for index in self.segment_indices(start, end) {
+                    in_zero_length_segment[index] = true;
+                }
+            }
+        }
+
+        let first_index = if self.is_closed() { 0 } else { 1 };
+        (first_index..point_count)
+            .filter(|&index| {
+                let previous = &self.points[(index + point_count - 1) % point_count];
+                let point = &self.points[index];
+                point_count > 1
+                    && previous.x == point.x
+                    && previous.y == point.y
+                    && !in_zero_length_segment[index]
+            })
+            .collect()
+    }
+
+    /// Find curve segments that reach further horizontally or vertically than
+    /// their on-curve points, i.e. that lack points at their extrema, by the
+    /// indices of their start and end points. Implied on-curve points of quadratic splines
+    /// count as on-curve points.
+    pub fn missing_extrema(&self) -> Vec<(usize, usize)> {
+        self.segments()
+            .into_iter()
+            .filter(|&(start, end)| {
+                let mut off_curves = Vec::new();
+                let mut indices = self.segment_indices(start, end);
+                let start_point = self.points[indices.next().unwrap()].to_point();
+                for index in indices {
+                    if self.points[index].typ == PointType::OffCurve {
+                        off_curves.push(self.points[index].to_point());
+                    }
+                }
+                if start == end || off_curves.is_empty() {
+                    return false;
+                }
+                let mut path = BezPath::new();
+                path.move_to(start_point);
+                add_segment(
+                    &mut path,
+                    &self.points[end].typ,
+                    &off_curves,
+                    self.points[end].to_point(),
+                );
+                let missing_extremum = path.segments().any(|segment| {
+                    let bounds = segment.bounding_box();
+                    let on_curve_bounds = Rect::from_points(segment.start(), segment.end());
+                    on_curve_bounds.x0 - bounds.x0 > EXTREMUM_TOLERANCE
+                        || on_curve_bounds.y0 - bounds.y0 > EXTREMUM_TOLERANCE
+                        || bounds.x1 - on_curve_bounds.x1 > EXTREMUM_TOLERANCE
+                        || bounds.y1 - on_curve_bounds.y1 > EXTREMUM_TOLERANCE
+                });
+                missing_extremum
+            })
+            .collect()
+    }
+
+    /// Reverse the direction of a contour, keeping each segment's type.
+    pub fn reverse(&mut self) {
+        if !self.is_closed() {
+            // An open contour starts with a move; the segments run backwards from
+            // the last point.
+            self.points.reverse();
+            let mut previous_typ = PointType::Move;
+            for point in self
+                .points
+                .iter_mut()
+                .filter(|p| p.typ != PointType::OffCurve)
+            {
+                std::mem::swap(&mut point.typ, &mut previous_typ);
+            }
+            return;
+        }
+
+        let on_curve_indices: Vec<usize> = self
+            .points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.typ != PointType::OffCurve)
+            .map(|(i, _)| i)
+            .collect();
+        if on_curve_indices.is_empty() {
+            self.points.reverse();
+            return;
+        }
+
+        // Each segment ends on an on-curve point and carries its type. Walking the
+        // segments backwards, every segment now ends on the point it started from.
+        let point_count = self.points.len();
+        let segment_count = on_curve_indices.len();
+        let mut reversed = Vec::with_capacity(point_count);
+        let mut first_index = 0;
+        for (k, &end) in on_curve_indices.iter().enumerate().rev() {
+            let start = on_curve_indices[(k + segment_count - 1) % segment_count];
+            let mut index = end;
+            loop {
+                index = (index + point_count - 1) % point_count;
+                if index == start {
+                    break;
+                }
+                reversed.push(self.points[index].clone());
+            }
+            if start == 0 {
+                first_index = reversed.len();
+            }
+            let mut start_point = self.points[start].clone();
+            start_point.typ = self.points[end].typ.clone();
+            reversed.push(start_point);
+        }
+        // Keep the contour starting at the same point.
+        reversed.rotate_left(first_index);
+        self.points = reversed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::ExtraFields;
+
+    fn square(x: f64, y: f64, size: f64, clockwise: bool) -> Contour {
+        let mut corners = vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)];
+        if clockwise {
+            corners.reverse();
+        }
+        Contour {
+            points: corners
+                .into_iter()
+                .map(|(x, y)| ContourPoint {
+                    x,
+                    y,
+                    typ: PointType::Line,
+                    smooth: false,
+                })
+                .collect(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    #[test]
+    fn fix_outer_and_counter_direction() {
+        let mut layer = Layer {
+            contours: vec![
+                square(0., 0., 100., true),
+                square(25., 25., 50., true),
+                square(200., 0., 100., false),
+                square(250., 50., 100., false),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            layer.misdirected_contours(WindingConvention::PostScript),
+            vec![0]
+        );
+        assert_eq!(
+            layer.misdirected_contours(WindingConvention::TrueType),
+            vec![1, 2, 3]
+        );
+        assert_eq!(layer.fix_direction(WindingConvention::TrueType), 3);
+        assert!(layer
+            .misdirected_contours(WindingConvention::TrueType)
+            .is_empty());
+    }
+
+    #[test]
+    fn reverse_keeps_segment_types() {
+        let point = |x: f64, y: f64, typ: PointType| ContourPoint {
+            x,
+            y,
+            typ,
+            smooth: false,
+        };
+        let mut contour = Contour {
+            points: vec![
+                point(0., 0., PointType::Line),
+                point(100., 0., PointType::Line),
+                point(100., 50., PointType::OffCurve),
+                point(50., 100., PointType::OffCurve),
+                point(0., 100., PointType::Curve),
+            ],
+            extra: ExtraFields::new(),
+        };
+        let original_path = contour.to_bez_path();
+
+        contour.reverse();
+
+        assert_eq!(
+            contour.points,
+            vec![
+                point(0., 0., PointType::Line),
+                point(0., 100., PointType::Line),
+                point(50., 100., PointType::OffCurve),
+                point(100., 50., PointType::OffCurve),
+                point(100., 0., PointType::Curve),
+            ]
+        );
+        assert_eq!(contour.to_bez_path().area(), -original_path.area());
+
+        contour.reverse();
+        assert_eq!(contour.to_bez_path(), original_path);
+    }
+
+    #[test]
+    fn find_missing_extrema() {
+        let contour = |curve_typ: PointType| Contour {
+            points: [
+                (0., 0., PointType::Line),
+                (0., 100., PointType::OffCurve),
+                (100., 100., PointType::OffCurve),
+                (100., 0., curve_typ),
+            ]
+            .into_iter()
+            .map(|(x, y, typ)| ContourPoint {
+                x,
+                y,
+                typ,
+                smooth: false,
+            })
+            .collect(),
+            extra: ExtraFields::new(),
+        };
+
+        // The cubic bulges up to 75 units between its on-curve points.
+        assert_eq!(contour(PointType::Curve).missing_extrema(), vec![(0, 3)]);
+        // The implied on-curve point of the quadratic spline is at its top.
+        assert!(contour(PointType::QCurve).missing_extrema().is_empty());
+    }
+}
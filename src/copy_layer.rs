@@ -0,0 +1,152 @@
+//! Copying the layers of one source to another inside a fontgarden, to start a
+//! new master from an existing one.
+
+use crate::{
+    errors::CopyLayerError,
+    structs::{Fontgarden, LayerName},
+};
+
+/// How many layers a copy wrote and how many it left alone because the target
+/// source already had them.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CopiedLayers {
+    pub copied: usize,
+    pub skipped: usize,
+}
+
+impl Fontgarden {
+    /// Copy the main layers of a source to another source, in the named glyphs
+    /// or all glyphs with a layer in the source. With `sublayers`, backgrounds
+    /// and other sublayers come along, except intermediate layers, which have
+    /// a location of their own. Layers the target already has are only
+    /// replaced with `replace`.
+    pub fn copy_layers(
+        &mut self,
+        from: &str,
+        to: &str,
+        glyph_names: &[String],
+        sublayers: bool,
+        replace: bool,
+    ) -> Result<CopiedLayers, CopyLayerError> {
+        if !self.source_names().contains(from) {
+            return Err(CopyLayerError::UnknownSource(from.into()));
+        }
+        if from == to {
+            return Err(CopyLayerError::SameSource(from.into()));
+        }
+        if let Some(glyph_name) = glyph_names
+            .iter()
+            .find(|glyph_name| !self.glyphs.contains_key(glyph_name.as_str()))
+        {
+            return Err(CopyLayerError::UnknownGlyph(glyph_name.clone()));
+        }
+
+        let mut result = CopiedLayers::default();
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            if !glyph_names.is_empty() && !glyph_names.iter().any(|n| **n == **glyph_name) {
+                continue;
+            }
+            let copies: Vec<_> = glyph
+                .layers
+                .iter()
+                .filter(|(layer_name, layer)| {
+                    &*layer_name.source == from
+                        && (layer_name.is_main() || sublayers && layer.location.is_none())
+                })
+                .map(|(layer_name, layer)| {
+                    let layer_name = LayerName {
+                        source: to.into(),
+                        sublayer: layer_name.sublayer.clone(),
+                    };
+                    (layer_name, layer.clone())
+                })
+                .collect();
+            for (layer_name, layer) in copies {
+                if !replace && glyph.layers.contains_key(&layer_name) {
+                    result.skipped += 1;
+                    continue;
+                }
+                glyph.layers.insert(layer_name, layer);
+                result.copied += 1;
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Glyph, Layer};
+
+    use super::*;
+
+    #[test]
+    fn copy_layers_to_new_source() {
+        let layer = |x_advance: f64| Layer {
+            x_advance: Some(x_advance),
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: [
+                    (LayerName::main("Light"), layer(500.)),
+                    (LayerName::sublayer("Light", "background"), layer(510.)),
+                    (LayerName::main("Medium"), layer(550.)),
+                ]
+                .into(),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert(
+            "b".into(),
+            Glyph {
+                layers: [(LayerName::main("Light"), layer(520.))].into(),
+                ..Default::default()
+            },
+        );
+
+        let copied = fontgarden
+            .copy_layers("Light", "Medium", &[], true, false)
+            .unwrap();
+        assert_eq!(
+            copied,
+            CopiedLayers {
+                copied: 2,
+                skipped: 1
+            }
+        );
+        let x_advance = |fontgarden: &Fontgarden, glyph_name: &str, layer_name: LayerName| {
+            fontgarden.glyphs[glyph_name].layers[&layer_name].x_advance
+        };
+        assert_eq!(
+            x_advance(&fontgarden, "a", LayerName::main("Medium")),
+            Some(550.)
+        );
+        assert_eq!(
+            x_advance(
+                &fontgarden,
+                "a",
+                LayerName::sublayer("Medium", "background")
+            ),
+            Some(510.)
+        );
+        assert_eq!(
+            x_advance(&fontgarden, "b", LayerName::main("Medium")),
+            Some(520.)
+        );
+
+        fontgarden
+            .copy_layers("Light", "Medium", &["a".into()], false, true)
+            .unwrap();
+        assert_eq!(
+            x_advance(&fontgarden, "a", LayerName::main("Medium")),
+            Some(500.)
+        );
+        assert!(matches!(
+            fontgarden.copy_layers("Bold", "Medium", &[], false, false),
+            Err(CopyLayerError::UnknownSource(_))
+        ));
+    }
+}
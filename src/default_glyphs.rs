@@ -0,0 +1,184 @@
+//! Glyphs that every font needs, made up on export for fontgardens that lack
+//! them, so that the exported sources always compile.
+
+use norad::Codepoints;
+
+use crate::structs::{
+    Contour, ContourPoint, ExtraFields, Fontgarden, Glyph, Layer, LayerName, PointType,
+};
+
+/// A glyph every font needs: its name, codepoint and default width in ems.
+struct DefaultGlyph {
+    name: &'static str,
+    codepoint: Option<char>,
+    width: f64,
+}
+
+const DEFAULT_GLYPHS: [DefaultGlyph; 4] = [
+    DefaultGlyph {
+        name: ".notdef",
+        codepoint: None,
+        width: 0.5,
+    },
+    DefaultGlyph {
+        name: "NULL",
+        codepoint: Some('\u{0}'),
+        width: 0.,
+    },
+    DefaultGlyph {
+        name: "CR",
+        codepoint: Some('\r'),
+        width: 0.25,
+    },
+    DefaultGlyph {
+        name: "space",
+        codepoint: Some(' '),
+        width: 0.25,
+    },
+];
+
+/// The units per em to assume for fontgardens that have none recorded.
+const DEFAULT_UNITS_PER_EM: f64 = 1000.;
+
+impl Fontgarden {
+    /// Add `.notdef`, `NULL`, `CR` and `space` to every source where no glyph
+    /// has their name or codepoint, and return the names of those added.
+    ///
+    /// Widths are taken from the source's `default_glyph_widths`, then the
+    /// export configuration's, then derived from the units per em. `.notdef`
+    /// gets a box outline.
+    pub fn add_default_glyphs(&mut self) -> Vec<&'static str> {
+        let units_per_em = self.config.units_per_em.unwrap_or(DEFAULT_UNITS_PER_EM);
+        let source_names = self.source_names();
+        let mut added = Vec::new();
+        for default_glyph in &DEFAULT_GLYPHS {
+            let exists = self.glyphs.contains_key(default_glyph.name)
+                || default_glyph.codepoint.is_some_and(|codepoint| {
+                    self.glyphs
+                        .values()
+                        .any(|glyph| glyph.codepoints.contains(codepoint))
+                });
+            if exists {
+                continue;
+            }
+
+            let mut glyph = Glyph {
+                codepoints: Codepoints::new(default_glyph.codepoint),
+                ..Default::default()
+            };
+            for source_name in &source_names {
+                let width = self
+                    .config
+                    .sources
+                    .get(source_name)
+                    .and_then(|source| source.default_glyph_widths.get(default_glyph.name))
+                    .or_else(|| {
+                        self.config
+                            .export
+                            .default_glyph_widths
+                            .get(default_glyph.name)
+                    })
+                    .copied()
+                    .unwrap_or(default_glyph.width * units_per_em);
+                let contours = match default_glyph.name {
+                    ".notdef" => notdef_box(width, units_per_em),
+                    _ => Vec::new(),
+                };
+                glyph.layers.insert(
+                    LayerName::main(source_name.as_str()),
+                    Layer {
+                        contours,
+                        x_advance: Some(width),
+                        ..Default::default()
+                    },
+                );
+            }
+            self.glyphs.insert(default_glyph.name.into(), glyph);
+            added.push(default_glyph.name);
+        }
+        added
+    }
+}
+
+/// A rectangle with a rectangular counter, filling the advance width but for
+/// a margin on each side.
+fn notdef_box(width: f64, units_per_em: f64) -> Vec<Contour> {
+    let margin = 0.05 * units_per_em;
+    let stroke = 0.05 * units_per_em;
+    let height = 0.7 * units_per_em;
+    if width <= 2. * (margin + stroke) {
+        return Vec::new();
+    }
+    let rectangle = |x0: f64, y0: f64, x1: f64, y1: f64, clockwise: bool| {
+        let mut corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+        if clockwise {
+            corners.reverse();
+        }
+        Contour {
+            points: corners
+                .into_iter()
+                .map(|(x, y)| ContourPoint {
+                    x,
+                    y,
+                    typ: PointType::Line,
+                    smooth: false,
+                })
+                .collect(),
+            extra: ExtraFields::new(),
+        }
+    };
+    vec![
+        rectangle(margin, 0., width - margin, height, false),
+        rectangle(
+            margin + stroke,
+            stroke,
+            width - margin - stroke,
+            height - stroke,
+            true,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_missing_default_glyphs() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            units_per_em = 2000
+            export.default_glyph_widths = { space = 600 }
+            sources.Bold.default_glyph_widths = { space = 700 }
+            "#,
+        )
+        .unwrap();
+        for (glyph_name, codepoint) in [("A", 'A'), ("uni000D", '\r')] {
+            let mut glyph = Glyph {
+                codepoints: Codepoints::new([codepoint]),
+                ..Default::default()
+            };
+            for source_name in ["Bold", "Regular"] {
+                glyph
+                    .layers
+                    .insert(LayerName::main(source_name), Layer::default());
+            }
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        }
+
+        let added = fontgarden.add_default_glyphs();
+
+        assert_eq!(added, [".notdef", "NULL", "space"]);
+        let advance = |glyph_name: &str, source_name: &str| {
+            fontgarden.glyphs[glyph_name].layers[&LayerName::main(source_name)].x_advance
+        };
+        assert_eq!(advance("space", "Regular"), Some(600.));
+        assert_eq!(advance("space", "Bold"), Some(700.));
+        assert_eq!(advance(".notdef", "Bold"), Some(1000.));
+        let notdef = &fontgarden.glyphs[".notdef"].layers[&LayerName::main("Bold")];
+        assert_eq!(notdef.contours.len(), 2);
+        assert!(fontgarden.glyphs["space"].codepoints.contains(' '));
+        assert!(fontgarden.add_default_glyphs().is_empty());
+    }
+}
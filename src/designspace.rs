@@ -0,0 +1,295 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    alternates::ConditionSet,
+    interpolate::Location,
+    structs::{Fontgarden, LayerName},
+};
+
+impl Fontgarden {
+    /// Describe the exported sources, given by name with the names of their
+    /// UFO files, as a designspace document, along with the configured
+    /// instances, if axes are configured. Instances go in the `instances`
+    /// directory, under their names in `instance_filenames` or else
+    /// `<name>.ufo`.
+    ///
+    /// Sublayers with a location become sparse sources pointing at the layer in
+    /// their source's UFO. The location of a sublayer is taken from the first
+    /// glyph (by name) that has one there.
+    pub fn to_designspace(
+        &self,
+        exported_sources: &BTreeMap<String, String>,
+        instance_filenames: &BTreeMap<String, String>,
+    ) -> Option<String> {
+        if self.config.axes.is_empty() {
+            return None;
+        }
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        let mut intermediates: BTreeMap<&LayerName, &Location> = BTreeMap::new();
+        for glyph_name in glyph_names {
+            for (layer_name, layer) in &self.glyphs[glyph_name].layers {
+                let (Some(location), None) = (&layer.location, &layer.condition) else {
+                    continue;
+                };
+                match intermediates.get(layer_name) {
+                    Some(&existing) if existing != location => eprintln!(
+                        "warning: layer '{layer_name}' of glyph {glyph_name} is at a different \
+                         location than in other glyphs, which a designspace can't express"
+                    ),
+                    Some(_) => (),
+                    None => {
+                        intermediates.insert(layer_name, location);
+                    }
+                }
+            }
+        }
+
+        let mut xml = String::from("<?xml version='1.0' encoding='UTF-8'?>\n");
+        xml.push_str("<designspace format=\"4.1\">\n  <axes>\n");
+        for (tag, axis) in &self.config.axes {
+            let tag = escape(tag);
+            writeln!(
+                xml,
+                "    <axis tag=\"{tag}\" name=\"{tag}\" minimum=\"{}\" maximum=\"{}\" default=\"{}\"/>",
+                axis.minimum, axis.maximum, axis.default
+            )
+            .unwrap();
+        }
+        xml.push_str("  </axes>\n");
+        self.write_rules(&mut xml);
+        xml.push_str("  <sources>\n");
+        for (source_name, source) in &self.config.sources {
+            let Some(filename) = exported_sources.get(source_name) else {
+                continue;
+            };
+            let (filename, source_name) = (escape(filename), escape(source_name));
+            writeln!(
+                xml,
+                "    <source filename=\"{filename}\" name=\"{source_name}\" stylename=\"{source_name}\">"
+            )
+            .unwrap();
+            self.write_location(&mut xml, &source.location);
+            xml.push_str("    </source>\n");
+        }
+        for (layer_name, location) in intermediates {
+            let Some(sublayer_name) = &layer_name.sublayer else {
+                continue;
+            };
+            let Some(filename) = exported_sources.get(&*layer_name.source) else {
+                continue;
+            };
+            writeln!(
+                xml,
+                "    <source filename=\"{}\" name=\"{}\" layer=\"{}\">",
+                escape(filename),
+                escape(&layer_name.to_string()),
+                escape(sublayer_name)
+            )
+            .unwrap();
+            self.write_location(&mut xml, location);
+            xml.push_str("    </source>\n");
+        }
+        xml.push_str("  </sources>\n");
+        if !self.config.instances.is_empty() {
+            xml.push_str("  <instances>\n");
+            for (instance_name, instance) in &self.config.instances {
+                let filename = match instance_filenames.get(instance_name) {
+                    Some(filename) => escape(filename),
+                    None => format!("{}.ufo", escape(instance_name)),
+                };
+                let instance_name = escape(instance_name);
+                writeln!(
+                    xml,
+                    "    <instance name=\"{instance_name}\" stylename=\"{instance_name}\" filename=\"instances/{filename}\">"
+                )
+                .unwrap();
+                self.write_location(&mut xml, &instance.location);
+                xml.push_str("    </instance>\n");
+            }
+            xml.push_str("  </instances>\n");
+        }
+        xml.push_str("</designspace>\n");
+        Some(xml)
+    }
+
+    /// Write a rule per condition set, substituting the alternates of all glyphs
+    /// whose alternate layers have those conditions.
+    fn write_rules(&self, xml: &mut String) {
+        let mut substitutions_by_conditions: Vec<(ConditionSet, Vec<(String, String)>)> =
+            Vec::new();
+        for alternate in self.alternates() {
+            let substitution = (alternate.glyph_name, alternate.alternate_name);
+            match substitutions_by_conditions
+                .iter_mut()
+                .find(|(conditions, _)| *conditions == alternate.conditions)
+            {
+                Some((_, substitutions)) => substitutions.push(substitution),
+                None => {
+                    substitutions_by_conditions.push((alternate.conditions, vec![substitution]))
+                }
+            }
+        }
+        if substitutions_by_conditions.is_empty() {
+            return;
+        }
+
+        xml.push_str("  <rules processing=\"last\">\n");
+        for (number, (conditions, substitutions)) in substitutions_by_conditions.iter().enumerate()
+        {
+            writeln!(xml, "    <rule name=\"varAlt{:02}\">", number + 1).unwrap();
+            xml.push_str("      <conditionset>\n");
+            for (tag, range) in conditions {
+                write!(xml, "        <condition name=\"{}\"", escape(tag)).unwrap();
+                if let Some(minimum) = range.minimum {
+                    write!(xml, " minimum=\"{minimum}\"").unwrap();
+                }
+                if let Some(maximum) = range.maximum {
+                    write!(xml, " maximum=\"{maximum}\"").unwrap();
+                }
+                xml.push_str("/>\n");
+            }
+            xml.push_str("      </conditionset>\n");
+            for (glyph_name, alternate_name) in substitutions {
+                writeln!(
+                    xml,
+                    "      <sub name=\"{}\" with=\"{}\"/>",
+                    escape(glyph_name),
+                    escape(alternate_name)
+                )
+                .unwrap();
+            }
+            xml.push_str("    </rule>\n");
+        }
+        xml.push_str("  </rules>\n");
+    }
+
+    /// Write a location with all axes, filling in defaults for the missing ones.
+    fn write_location(&self, xml: &mut String, location: &Location) {
+        xml.push_str("      <location>\n");
+        for (tag, axis) in &self.config.axes {
+            let value = location.get(tag).copied().unwrap_or(axis.default);
+            writeln!(
+                xml,
+                "        <dimension name=\"{}\" xvalue=\"{value}\"/>",
+                escape(tag)
+            )
+            .unwrap();
+        }
+        xml.push_str("      </location>\n");
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alternates::AxisRange,
+        structs::{Glyph, Layer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn designspace_with_sparse_sources_rules_and_instances() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            axes.wght = { minimum = 100, default = 100, maximum = 900 }
+            sources.Thin.location = { wght = 100 }
+            sources.Black.location = { wght = 900 }
+            instances.Medium.location = { wght = 500 }
+            "#,
+        )
+        .unwrap();
+        let mut glyph = Glyph::default();
+        glyph
+            .layers
+            .insert(LayerName::main("Thin"), Layer::default());
+        glyph
+            .layers
+            .insert(LayerName::main("Black"), Layer::default());
+        glyph.layers.insert(
+            LayerName::sublayer("Thin", "wght600"),
+            Layer {
+                location: Some(Location::from([("wght".to_string(), 600.)])),
+                ..Default::default()
+            },
+        );
+        glyph.layers.insert(
+            LayerName::sublayer("Black", "bracket"),
+            Layer {
+                condition: Some(ConditionSet::from([(
+                    "wght".to_string(),
+                    AxisRange {
+                        minimum: Some(700.),
+                        maximum: None,
+                    },
+                )])),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("dollar".into(), glyph);
+
+        let exported_sources = BTreeMap::from([
+            ("Thin".to_string(), "Garden-Thin.ufo".to_string()),
+            ("Black".to_string(), "Garden-Black.ufo".to_string()),
+        ]);
+        let instance_filenames =
+            BTreeMap::from([("Medium".to_string(), "Garden-Medium.ufo".to_string())]);
+        let designspace = fontgarden
+            .to_designspace(&exported_sources, &instance_filenames)
+            .unwrap();
+
+        assert_eq!(
+            designspace,
+            r#"<?xml version='1.0' encoding='UTF-8'?>
+<designspace format="4.1">
+  <axes>
+    <axis tag="wght" name="wght" minimum="100" maximum="900" default="100"/>
+  </axes>
+  <rules processing="last">
+    <rule name="varAlt01">
+      <conditionset>
+        <condition name="wght" minimum="700"/>
+      </conditionset>
+      <sub name="dollar" with="dollar.varAlt01"/>
+    </rule>
+  </rules>
+  <sources>
+    <source filename="Garden-Black.ufo" name="Black" stylename="Black">
+      <location>
+        <dimension name="wght" xvalue="900"/>
+      </location>
+    </source>
+    <source filename="Garden-Thin.ufo" name="Thin" stylename="Thin">
+      <location>
+        <dimension name="wght" xvalue="100"/>
+      </location>
+    </source>
+    <source filename="Garden-Thin.ufo" name="Thin.wght600" layer="wght600">
+      <location>
+        <dimension name="wght" xvalue="600"/>
+      </location>
+    </source>
+  </sources>
+  <instances>
+    <instance name="Medium" stylename="Medium" filename="instances/Garden-Medium.ufo">
+      <location>
+        <dimension name="wght" xvalue="500"/>
+      </location>
+    </instance>
+  </instances>
+</designspace>
+"#
+        );
+    }
+}
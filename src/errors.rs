@@ -6,8 +6,30 @@ use thiserror::Error;
 pub enum SourceLoadError {
     #[error("failed to load UFO source {0}")]
     Ufo(PathBuf, #[source] norad::error::FontLoadError),
-    #[error("more than one source uses the same style name {0}, last seen in {1}")]
-    DuplicateLayerName(String, PathBuf),
+    #[error("more than one source has the name {0}, last seen in {1}")]
+    DuplicateSourceName(String, PathBuf),
+    #[error("glyph {0} has codepoints [{1}] in the fontgarden but [{2}] in the source")]
+    CodepointConflict(String, String, String),
+    #[error("the default source {0} is not among the imported sources")]
+    UnknownDefaultSource(String),
+    #[error("the fontgarden has {0} units per em, not {1}; scale it with scale-upm first")]
+    UnitsPerEmMismatch(f64, f64),
+    #[error("more than one glyph of a source would be named {0} after renaming")]
+    RenameCollision(String),
+}
+
+#[derive(Error, Debug)]
+pub enum RenameMapError {
+    #[error("failed to read the rename map {0}")]
+    Read(PathBuf, #[source] csv::Error),
+    #[error("line {1} of the rename map {0} must have a source and a fontgarden glyph name")]
+    Malformed(PathBuf, u64),
+    #[error("'{0}' is not a valid glyph name")]
+    InvalidName(String),
+    #[error("glyph {0} is renamed more than once")]
+    Duplicate(String),
+    #[error("more than one glyph is renamed to {0}")]
+    Collision(String),
 }
 
 #[derive(Error, Debug)]
@@ -22,6 +44,28 @@ pub enum LoadError {
     LoadSetData(PathBuf, #[source] csv::Error),
     #[error("failed to load JSON data from {0} for glyph {1}")]
     LoadLayerJson(PathBuf, String, #[source] serde_json::Error),
+    #[error("failed to load the configuration from {0}")]
+    LoadConfig(PathBuf, #[source] toml::de::Error),
+    #[error("failed to load the lib from {0}")]
+    LoadLib(PathBuf, #[source] serde_json::Error),
+    #[error("failed to load set metadata from {0}")]
+    LoadSetMetadata(PathBuf, #[source] toml::de::Error),
+    #[error("failed to load hinting data from {0}")]
+    LoadHinting(PathBuf, #[source] serde_json::Error),
+    #[error("failed to load lib keys from {0}")]
+    LoadSourceLib(PathBuf, #[source] serde_json::Error),
+    #[error("failed to load the modification index from {0}")]
+    LoadModificationIndex(PathBuf, #[source] serde_json::Error),
+    #[error("the fontgarden is not strictly valid:\n{}", .0.join("\n"))]
+    Strict(Vec<String>),
+    #[error("strict loading needs a directory garden, not {0}")]
+    StrictNeedsDirectory(PathBuf),
+    #[cfg(feature = "sqlite")]
+    #[error("failed to read the SQLite fontgarden {0}")]
+    Sqlite(PathBuf, #[source] rusqlite::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("failed to load the metadata of glyph {1} from {0}")]
+    LoadGlyphRecord(PathBuf, String, #[source] serde_json::Error),
 }
 
 #[derive(Error, Debug)]
@@ -32,6 +76,7 @@ pub(crate) struct InvalidCodepoints(
 );
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum SourceSaveError {
     #[error("Glyph name {0} is not alled by the UFO specification")]
     GlyphNamingError(String, #[source] norad::error::NamingError),
@@ -39,20 +84,295 @@ pub enum SourceSaveError {
     AnchorNamingError(String, #[source] norad::error::NamingError),
     #[error("Glyph named {0} has component whose name is not alled by the UFO specification")]
     ComponentNamingError(String, #[source] norad::error::NamingError),
+    #[error("failed to apply export filters")]
+    FilterError(#[from] FilterError),
+    #[error("failed to interpolate instances")]
+    InterpolationError(#[from] InterpolationError),
+    #[error("instance {0} has the same name as a source")]
+    InstanceNameClash(String),
+    #[error("layer name {0} is not allowed by the UFO specification or taken")]
+    LayerNamingError(String, #[source] norad::error::NamingError),
+}
+
+#[derive(Error, Debug)]
+pub enum InterpolationError {
+    #[error("no axes are defined in the configuration")]
+    NoAxes,
+    #[error("axis {0} is not defined in the configuration")]
+    UnknownAxis(String),
+    #[error("no source is at the default location of all axes")]
+    NoDefaultSource,
+    #[error("instance {0} is not defined in the configuration")]
+    UnknownInstance(String),
+    #[error("malformed location '{0}', expected e.g. wght:500,wdth:75")]
+    MalformedLocation(String),
+    #[error("there already is a source named {0}")]
+    SourceExists(String),
+    #[error("source {0} has no location in the configuration")]
+    UnplacedSource(String),
+    #[error("there is no glyph named {0}")]
+    UnknownGlyph(String),
+    #[error("layer {0} is the main layer of a source, whose location is set in the configuration")]
+    MainLayerLocation(String),
+    #[error("layer {0} is the main layer of a source and cannot be an alternate")]
+    MainLayerCondition(String),
+}
+
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("glyph {0} has cyclic component references: {1}")]
+    ComponentCycle(String, String),
+    #[error("glyph {0} uses component {1}, which does not exist")]
+    MissingComponent(String, String),
+    #[error("glyph {0} uses variable component {1}, which cannot be decomposed")]
+    VariableComponent(String, String),
+    #[error("more than one glyph would be renamed to {0}")]
+    RenameCollision(String),
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("failed to run hook '{0}'")]
+    Spawn(String, #[source] std::io::Error),
+    #[error("hook '{0}' failed with {1}")]
+    Failed(String, std::process::ExitStatus),
+}
+
+#[derive(Error, Debug)]
+pub enum BinaryError {
+    #[error("failed to read font binary {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to parse font binary {0}")]
+    Parse(PathBuf, #[source] skrifa::raw::ReadError),
+    #[error("there is no source named {0}")]
+    UnknownSource(String),
+    #[error("there already is a source named {0}; name the imported one with --source-name")]
+    SourceExists(String),
+    #[error(transparent)]
+    Import(#[from] SourceLoadError),
+}
+
+#[derive(Error, Debug)]
+pub enum CheckError {
+    #[error("there is no check profile named {0} in the configuration or built in")]
+    UnknownProfile(String),
+}
+
+#[derive(Error, Debug)]
+pub enum NameTemplateError {
+    #[error("a placeholder in the name template '{0}' is not closed or empty")]
+    Unclosed(String),
+    #[error("the name template '{0}' must be a file name, without directories")]
+    NotAFileName(String),
+    #[error("unknown placeholder '{{{0}}}' in the name template for {1}")]
+    UnknownPlaceholder(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum StampError {
+    #[error("invalid font version '{0}', expected one like 1.002")]
+    InvalidVersion(String),
+}
+
+#[derive(Error, Debug)]
+pub enum SelectionError {
+    #[error("the selection ends unexpectedly")]
+    UnexpectedEnd,
+    #[error("unexpected '{0}' in the selection")]
+    Unexpected(String),
+    #[error("a parenthesis in the selection is not closed")]
+    UnclosedParenthesis,
+    #[error("unknown selection term '{0}'")]
+    UnknownKey(String),
+    #[error("invalid selection term '{0}'")]
+    InvalidValue(String),
+}
+
+#[derive(Error, Debug)]
+pub enum RecipeError {
+    #[error("failed to read recipes from {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("line {0} is not a recipe like 'aacute=a+acutecomb@top': {1}")]
+    Parse(usize, String),
+    #[error("recipe for {0} uses glyph {1}, which is not in the fontgarden")]
+    UnknownGlyph(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("there is no glyph named {0}")]
+    UnknownGlyph(String),
+    #[error("glyph {0} has no components to take the advance width from")]
+    NoBaseComponent(String),
+    #[error("linking glyph {0} to {1} would make their advance widths depend on each other")]
+    Cycle(String, String),
+    #[error("glyph {0} takes its advance width from {1}; unlink it first")]
+    Linked(String, String),
+    #[error("glyph {0} has no layer in source {1}")]
+    MissingLayer(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum CopyLayerError {
+    #[error("there is no source named {0}")]
+    UnknownSource(String),
+    #[error("there is no glyph named {0}")]
+    UnknownGlyph(String),
+    #[error("cannot copy the layers of source {0} onto themselves")]
+    SameSource(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ShowError {
+    #[error("no glyph named '{0}' in the fontgarden")]
+    UnknownGlyph(String),
+}
+
+#[derive(Error, Debug)]
+pub enum TagError {
+    #[error("there is no glyph named {0}")]
+    UnknownGlyph(String),
+    #[error("tag '{0}' must not be empty or contain semicolons or whitespace")]
+    InvalidTag(String),
+}
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("neither fontgarden has a set named {0}")]
+    UnknownSet(String),
+}
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("failed to run git to clone {0}")]
+    Git(String, #[source] std::io::Error),
+    #[error("failed to clone {0}: git exited with {1}")]
+    GitFailed(String, std::process::ExitStatus),
+    #[error("failed to create a directory to clone into")]
+    TempDir(#[source] std::io::Error),
+    #[error("failed to load the fontgarden to fetch from")]
+    Load(#[source] Box<LoadError>),
+    #[error("the fontgarden to fetch from has no set named {0}")]
+    UnknownSet(String),
+    #[error("invalid git revision '{0}': revisions can't start with '-'")]
+    InvalidRevision(String),
+}
+
+impl From<LoadError> for FetchError {
+    fn from(error: LoadError) -> Self {
+        FetchError::Load(Box::new(error))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    #[error("failed to read the workspace manifest {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to parse the workspace manifest {0}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("garden {0} uses garden {1}, which is not in the workspace")]
+    UnknownGarden(String, String),
+    #[error("failed to load garden {0}")]
+    Load(String, #[source] Box<LoadError>),
+}
+
+#[derive(Error, Debug)]
+pub enum FontraError {
+    #[error("failed to read or write {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to serialize {0}")]
+    Json(PathBuf, #[source] serde_json::Error),
+    #[error("failed to parse {0}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+    #[error("malformed line in {0}: {1}")]
+    GlyphInfo(PathBuf, String),
+    #[error(transparent)]
+    Import(#[from] SourceLoadError),
+}
+
+#[derive(Error, Debug)]
+#[error("{1} on line {0}")]
+pub struct OpenStepError(pub usize, pub &'static str);
+
+#[derive(Error, Debug)]
+pub enum GlyphsError {
+    #[error("failed to read or write {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to parse {0}")]
+    Parse(PathBuf, #[source] OpenStepError),
+    #[error("{0} is not a Glyphs file: {1}")]
+    Malformed(PathBuf, &'static str),
+    #[error(transparent)]
+    Import(#[from] SourceLoadError),
+}
+
+#[derive(Error, Debug)]
+pub enum NormalizeError {
+    #[error("failed to read {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to save the normalized fontgarden for comparison")]
+    Save(#[from] SaveError),
+}
+
+#[derive(Error, Debug)]
+pub enum UfoWriteError {
+    #[error("failed to read or write {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("failed to save UFO {0}")]
+    Save(PathBuf, #[source] norad::error::FontWriteError),
 }
 
 #[derive(Error, Debug)]
 pub enum SaveError {
     #[error("failed to remove target directory before overwriting")]
     Cleanup(#[source] std::io::Error),
-    #[error("failed to create target fontgarden directory")]
+    #[error("failed to create target fontgarden directory or archive")]
     CreateDir(#[source] std::io::Error),
-    #[error("failed to create directory for glyph {0}")]
-    CreateGlyphDir(String, #[source] std::io::Error),
+    #[error("failed to finish writing the fontgarden archive")]
+    FinishArchive(#[source] std::io::Error),
     #[error("failed to save glyph {0}, layer '{1}'")]
     SaveLayer(String, String, #[source] std::io::Error),
     #[error("failed to save JSON data for glyph {0}, layer '{1}'")]
     SaveLayerJson(String, String, #[source] serde_json::Error),
     #[error("failed to save set data '{0}'")]
     SaveSetData(String, #[source] csv::Error),
+    #[error("failed to save the configuration")]
+    SaveConfig(#[source] std::io::Error),
+    #[error("failed to save the lib")]
+    SaveLib(#[source] std::io::Error),
+    #[error("failed to save the modification index")]
+    SaveModificationIndex(#[source] std::io::Error),
+    #[error("failed to save the content hashes")]
+    SaveHashIndex(#[source] std::io::Error),
+    #[error("failed to save the metadata of set '{0}'")]
+    SaveSetMetadata(String, #[source] std::io::Error),
+    #[error("failed to save the hinting data of source '{0}'")]
+    SaveHinting(String, #[source] std::io::Error),
+    #[error("failed to save the lib keys of source '{0}'")]
+    SaveSourceLib(String, #[source] std::io::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("failed to write the SQLite fontgarden {0}")]
+    Sqlite(PathBuf, #[source] rusqlite::Error),
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Error, Debug)]
+pub enum SqliteImportError {
+    #[error("failed to load the fontgarden to import from")]
+    Load(#[source] Box<LoadError>),
+    #[error(transparent)]
+    Import(#[from] SourceLoadError),
+}
+
+#[cfg(feature = "sqlite")]
+impl From<LoadError> for SqliteImportError {
+    fn from(error: LoadError) -> Self {
+        SqliteImportError::Load(Box::new(error))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ServeError {
+    #[error("failed to listen on {0}")]
+    Listen(String, #[source] Box<dyn std::error::Error + Send + Sync>),
 }
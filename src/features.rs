@@ -0,0 +1,200 @@
+//! Feature code generated from the glyphs in a fontgarden: `languagesystem`
+//! statements for the scripts it covers and a `locl` feature for its local
+//! variants, so that exported sources shape in the right scripts.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use glyphsinfo_rs::GlyphData;
+
+use crate::{
+    categorize::lookup_record,
+    structs::{Fontgarden, Glyph},
+};
+
+/// The language systems of a fontgarden: the languages besides the default one
+/// by OpenType script tag.
+pub type LanguageSystems = BTreeMap<&'static str, BTreeSet<String>>;
+
+impl Fontgarden {
+    /// Work out the scripts the fontgarden covers from the scripts of its glyphs
+    /// and the sets they are in, and the languages from suffixes like
+    /// `.loclSRB`, which count for the script of the glyph they vary.
+    pub fn languagesystems(&self, glyph_info: &GlyphData) -> LanguageSystems {
+        let mut languagesystems = LanguageSystems::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            for script_name in self.script_names(glyph_name, glyph, glyph_info) {
+                for tag in script_tags(&script_name) {
+                    let languages = languagesystems.entry(tag).or_default();
+                    if let Some(language) = locl_language(glyph_name) {
+                        languages.insert(language.into());
+                    }
+                }
+            }
+        }
+        languagesystems
+    }
+
+    /// Generate `languagesystem` statements and a `locl` feature substituting
+    /// local variants for the glyphs they vary, for the given glyph data.
+    pub fn generate_features(&self, glyph_info: &GlyphData) -> String {
+        let languagesystems = self.languagesystems(glyph_info);
+        if languagesystems.is_empty() {
+            return String::new();
+        }
+
+        let mut fea = String::from("languagesystem DFLT dflt;\n");
+        for (tag, languages) in &languagesystems {
+            writeln!(fea, "languagesystem {tag} dflt;").unwrap();
+            for language in languages {
+                writeln!(fea, "languagesystem {tag} {language};").unwrap();
+            }
+        }
+
+        let mut substitutions: BTreeMap<(&str, &str), Vec<(&str, &str)>> = BTreeMap::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            let Some(language) = locl_language(glyph_name) else {
+                continue;
+            };
+            let base_name = glyph_name.replace(&format!(".locl{language}"), "");
+            let Some((base_name, _)) = self.glyphs.get_key_value(base_name.as_str()) else {
+                continue;
+            };
+            for script_name in self.script_names(glyph_name, glyph, glyph_info) {
+                for tag in script_tags(&script_name) {
+                    substitutions
+                        .entry((tag, language))
+                        .or_default()
+                        .push((base_name, glyph_name));
+                }
+            }
+        }
+        if !substitutions.is_empty() {
+            fea.push_str("\nfeature locl {\n");
+            for ((tag, language), mut pairs) in substitutions {
+                pairs.sort();
+                writeln!(fea, "    script {tag};\n    language {language};").unwrap();
+                for (base_name, glyph_name) in pairs {
+                    writeln!(fea, "    sub {base_name} by {glyph_name};").unwrap();
+                }
+            }
+            fea.push_str("} locl;\n");
+        }
+        fea
+    }
+
+    /// The names of the scripts a glyph belongs to: that of its glyph data
+    /// record, else those its set stands for.
+    fn script_names(&self, glyph_name: &str, glyph: &Glyph, glyph_info: &GlyphData) -> Vec<String> {
+        if let Some(script) =
+            lookup_record(glyph_name, &glyph.codepoints, glyph_info).and_then(|r| r.script)
+        {
+            return vec![format!("{script:?}")];
+        }
+        let Some(set) = &glyph.set else {
+            return Vec::new();
+        };
+        let mut script_names: Vec<String> = self
+            .config
+            .sets
+            .scripts
+            .iter()
+            .filter(|(_, script_set)| *script_set == set)
+            .map(|(script_name, _)| script_name.clone())
+            .collect();
+        if script_names.is_empty() {
+            script_names.push(set.clone());
+        }
+        script_names
+    }
+}
+
+/// The OpenType script tags for a script of the glyph data, with the newer
+/// Indic tags before the older ones. Unknown scripts have none.
+fn script_tags(script_name: &str) -> &'static [&'static str] {
+    match script_name {
+        "Arabic" => &["arab"],
+        "Bengali" => &["bng2", "beng"],
+        "Cyrillic" => &["cyrl"],
+        "Devanagari" => &["dev2", "deva"],
+        "Greek" => &["grek"],
+        "Latin" => &["latn"],
+        _ => &[],
+    }
+}
+
+/// The language tag of a local variant glyph name, like `SRB` for
+/// `be-cy.loclSRB`.
+fn locl_language(glyph_name: &str) -> Option<&str> {
+    glyph_name.split('.').skip(1).find_map(|suffix| {
+        let language = suffix.strip_prefix("locl")?;
+        ((2..=4).contains(&language.len())
+            && language
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+        .then_some(language)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use norad::Codepoints;
+
+    use super::*;
+
+    #[test]
+    fn languagesystems_from_scripts_and_suffixes() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            [sets.scripts]
+            Bengali = "Indic"
+            Devanagari = "Indic"
+            "#,
+        )
+        .unwrap();
+        for (glyph_name, codepoint, set) in [
+            ("a", Some('a'), None),
+            ("period", Some('.'), None),
+            ("be-cy", None, None),
+            ("a-cy", Some('\u{430}'), None),
+            ("a-cy.loclBGR", None, None),
+            ("i.loclTRK", None, None),
+            ("ta-tamil", None, Some("Tamil")),
+            ("danda-indic", None, Some("Indic")),
+        ] {
+            let glyph = Glyph {
+                codepoints: Codepoints::new(codepoint),
+                set: set.map(String::from),
+                ..Default::default()
+            };
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        }
+
+        let features = fontgarden.generate_features(&GlyphData::default());
+
+        assert_eq!(
+            features,
+            "languagesystem DFLT dflt;\n\
+             languagesystem beng dflt;\n\
+             languagesystem bng2 dflt;\n\
+             languagesystem cyrl dflt;\n\
+             languagesystem cyrl BGR;\n\
+             languagesystem dev2 dflt;\n\
+             languagesystem deva dflt;\n\
+             languagesystem latn dflt;\n\
+             languagesystem latn TRK;\n\
+             \n\
+             feature locl {\n    \
+                 script cyrl;\n    \
+                 language BGR;\n    \
+                 sub a-cy by a-cy.loclBGR;\n\
+             } locl;\n"
+        );
+        assert!(Fontgarden::new()
+            .generate_features(&GlyphData::default())
+            .is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! Sets fetched from other fontgardens, on disk or in git repositories, so that
+//! projects can share glyph libraries.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+use crate::{errors::FetchError, hooks::GlyphChanges, structs::Fontgarden};
+
+/// A fontgarden whose sets this one shares, declared in the configuration and
+/// fetched again by `fontgarden update-deps`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Path or git URL of the fontgarden.
+    pub url: String,
+    /// The git revision to pin, like a commit hash or tag [default: the
+    /// default branch]
+    pub revision: Option<String>,
+    /// Where the fontgarden is in the repository [default: its root]
+    pub path: Option<PathBuf>,
+    pub sets: Vec<String>,
+}
+
+/// What updating a dependency changed, with glyph names sorted.
+#[derive(Debug, PartialEq)]
+pub struct DependencyUpdate {
+    pub name: String,
+    /// The commit fetched, for dependencies in git repositories.
+    pub commit: Option<String>,
+    pub changes: GlyphChanges,
+    pub dropped_sources: BTreeSet<String>,
+    /// Glyphs in the dependency's sets here that it no longer has.
+    pub missing_upstream: Vec<String>,
+}
+
+/// What fetching sets did to the fontgarden, with glyph names sorted.
+#[derive(Debug, Default, PartialEq)]
+pub struct FetchReport {
+    pub added: Vec<String>,
+    pub replaced: Vec<String>,
+    /// Glyphs that were already in the fontgarden and were left alone.
+    pub kept: Vec<String>,
+    /// Sources of the other fontgarden that this one doesn't have, whose
+    /// layers were left out.
+    pub dropped_sources: BTreeSet<String>,
+}
+
+/// Load a fontgarden from a path, or fetch it first if it is a URL of a git
+/// repository or a revision is asked for, returning the commit fetched.
+pub fn load_remote(
+    location: &str,
+    revision: Option<&str>,
+    path: Option<&Path>,
+) -> Result<(Fontgarden, Option<String>), FetchError> {
+    if !is_url(location) && revision.is_none() {
+        let fontgarden_path = Path::new(location).join(path.unwrap_or(Path::new("")));
+        return Ok((Fontgarden::load(&fontgarden_path)?, None));
+    }
+    // A revision could otherwise be taken for an option of git, like
+    // `--upload-pack=...`, and so could the location without the `--` below.
+    if let Some(revision) = revision.filter(|revision| revision.starts_with('-')) {
+        return Err(FetchError::InvalidRevision(revision.into()));
+    }
+    let clone_dir = tempfile::tempdir().map_err(FetchError::TempDir)?;
+    let git = |args: &[&str]| -> Result<String, FetchError> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(clone_dir.path())
+            .args(args)
+            .output()
+            .map_err(|e| FetchError::Git(location.into(), e))?;
+        if !output.status.success() {
+            eprint!("{}", String::from_utf8_lossy(&output.stderr));
+            return Err(FetchError::GitFailed(location.into(), output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    // Fetching a single revision works for commits as well as branches and
+    // tags, without the whole history.
+    git(&["init", "--quiet"])?;
+    git(&[
+        "fetch",
+        "--quiet",
+        "--depth",
+        "1",
+        "--",
+        location,
+        revision.unwrap_or("HEAD"),
+    ])?;
+    git(&["checkout", "--quiet", "FETCH_HEAD"])?;
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let fontgarden_path = clone_dir.path().join(path.unwrap_or(Path::new("")));
+    Ok((Fontgarden::load(&fontgarden_path)?, Some(commit)))
+}
+
+fn is_url(location: &str) -> bool {
+    location.contains("://") || location.starts_with("git@")
+}
+
+impl Fontgarden {
+    /// Copy the glyphs in the given sets of another fontgarden into this one,
+    /// with the glyphs they use as components, whatever set those are in.
+    ///
+    /// Glyphs and set metadata already here are kept unless `replace`. Only
+    /// the layers of sources this fontgarden has are copied, unless it has no
+    /// glyphs yet.
+    pub fn fetch_sets(
+        &mut self,
+        other: &Fontgarden,
+        set_names: &[String],
+        replace: bool,
+    ) -> Result<FetchReport, FetchError> {
+        if let Some(set_name) = set_names.iter().find(|set_name| {
+            set_name.as_str() != Self::COMMON_SET_NAME
+                && !other.glyphs.values().any(|glyph| glyph.is_in_set(set_name))
+        }) {
+            return Err(FetchError::UnknownSet(set_name.clone()));
+        }
+
+        let mut fetched: BTreeSet<&str> = BTreeSet::new();
+        let mut pending: Vec<&str> = other
+            .glyphs
+            .iter()
+            .filter(|(_, glyph)| set_names.iter().any(|name| glyph.is_in_set(name)))
+            .map(|(glyph_name, _)| &**glyph_name)
+            .collect();
+        while let Some(glyph_name) = pending.pop() {
+            let Some(glyph) = other.glyphs.get(glyph_name) else {
+                continue;
+            };
+            if !fetched.insert(glyph_name) {
+                continue;
+            }
+            for layer in glyph.layers.values() {
+                pending.extend(layer.components.iter().map(|c| &*c.name));
+            }
+        }
+
+        let source_names = self.source_names();
+        let mut report = FetchReport::default();
+        for glyph_name in fetched {
+            let exists = self.glyphs.contains_key(glyph_name);
+            if exists && !replace {
+                report.kept.push(glyph_name.into());
+                continue;
+            }
+            let mut glyph = other.glyphs[glyph_name].clone();
+            if !source_names.is_empty() {
+                glyph.layers.retain(|layer_name, _| {
+                    let known = source_names.contains(&*layer_name.source);
+                    if !known {
+                        report.dropped_sources.insert(layer_name.source.to_string());
+                    }
+                    known
+                });
+            }
+            self.glyphs.insert(glyph_name.into(), glyph);
+            if exists {
+                report.replaced.push(glyph_name.into());
+            } else {
+                report.added.push(glyph_name.into());
+            }
+        }
+        for set_name in set_names {
+            if let Some(metadata) = other.set_metadata.get(set_name) {
+                if replace || !self.set_metadata.contains_key(set_name) {
+                    self.set_metadata.insert(set_name.clone(), metadata.clone());
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Fetch the sets of every dependency in the configuration again, replacing
+    /// the glyphs here, and report what changed.
+    pub fn update_dependencies(&mut self) -> Result<Vec<DependencyUpdate>, FetchError> {
+        let dependencies = self.config.dependencies.clone();
+        let mut updates = Vec::new();
+        for (name, dependency) in dependencies {
+            let (other, commit) = load_remote(
+                &dependency.url,
+                dependency.revision.as_deref(),
+                dependency.path.as_deref(),
+            )?;
+            let glyphs_before = self.glyphs.clone();
+            let report = self.fetch_sets(&other, &dependency.sets, true)?;
+            let mut missing_upstream: Vec<String> = self
+                .glyphs
+                .iter()
+                .filter(|(glyph_name, glyph)| {
+                    dependency.sets.iter().any(|name| glyph.is_in_set(name))
+                        && !other.glyphs.contains_key(*glyph_name)
+                })
+                .map(|(glyph_name, _)| glyph_name.to_string())
+                .collect();
+            missing_upstream.sort();
+            updates.push(DependencyUpdate {
+                name,
+                commit,
+                changes: GlyphChanges::between(&glyphs_before, &self.glyphs),
+                dropped_sources: report.dropped_sources,
+                missing_upstream,
+            });
+        }
+        Ok(updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Component, ExtraFields, Glyph, Layer, LayerName};
+
+    use super::*;
+
+    #[test]
+    fn fetch_sets_with_components() {
+        let glyph = |set: Option<&str>, components: &[&str], sources: &[&str]| Glyph {
+            set: set.map(String::from),
+            layers: sources
+                .iter()
+                .map(|source_name| {
+                    let layer = Layer {
+                        components: components
+                            .iter()
+                            .map(|name| Component {
+                                name: (*name).into(),
+                                transformation: Default::default(),
+                                location: None,
+                                extra: ExtraFields::new(),
+                            })
+                            .collect(),
+                        ..Default::default()
+                    };
+                    (LayerName::main(*source_name), layer)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut other = Fontgarden::new();
+        let both = ["Regular", "Bold"];
+        other
+            .glyphs
+            .insert("a".into(), glyph(Some("Latin"), &[], &both));
+        other.glyphs.insert(
+            "aacute".into(),
+            glyph(Some("Latin"), &["a", "acutecomb"], &both),
+        );
+        other
+            .glyphs
+            .insert("acutecomb".into(), glyph(None, &[], &both));
+        other
+            .glyphs
+            .insert("alpha".into(), glyph(Some("Greek"), &[], &both));
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .glyphs
+            .insert("a".into(), glyph(Some("Latin"), &[], &["Regular"]));
+
+        let report = fontgarden
+            .fetch_sets(&other, &["Latin".into()], false)
+            .unwrap();
+
+        assert_eq!(
+            report,
+            FetchReport {
+                added: vec!["aacute".into(), "acutecomb".into()],
+                replaced: vec![],
+                kept: vec!["a".into()],
+                dropped_sources: BTreeSet::from(["Bold".into()]),
+            }
+        );
+        assert!(!fontgarden.glyphs.contains_key("alpha"));
+        assert_eq!(fontgarden.glyphs["acutecomb"].set, None);
+        assert_eq!(fontgarden.glyphs["aacute"].layers.len(), 1);
+        assert!(matches!(
+            fontgarden.fetch_sets(&other, &["Cyrillic".into()], false),
+            Err(FetchError::UnknownSet(_))
+        ));
+    }
+
+    #[test]
+    fn update_dependencies_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let other_path = dir.path().join("shared.fontgarden");
+        let mut other = Fontgarden::new();
+        for glyph_name in ["a", "b"] {
+            let glyph = Glyph {
+                set: Some("Latin".into()),
+                ..Default::default()
+            };
+            other.glyphs.insert(glyph_name.into(), glyph);
+        }
+        other.save(&other_path).unwrap();
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(&format!(
+            "[dependencies.shared]\nurl = '{}'\nsets = ['Latin']",
+            other_path.display()
+        ))
+        .unwrap();
+        let stale = Glyph {
+            set: Some("Latin".into()),
+            ..Default::default()
+        };
+        fontgarden.glyphs.insert("c".into(), stale);
+
+        let updates = fontgarden.update_dependencies().unwrap();
+
+        assert_eq!(
+            updates,
+            [DependencyUpdate {
+                name: "shared".into(),
+                commit: None,
+                changes: GlyphChanges {
+                    added: vec!["a".into(), "b".into()],
+                    ..Default::default()
+                },
+                dropped_sources: BTreeSet::new(),
+                missing_upstream: vec!["c".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn reject_options_as_git_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let option = format!("--upload-pack=touch {}", marker.display());
+
+        assert!(matches!(
+            load_remote("https://example.com/garden.git", Some(&option), None),
+            Err(FetchError::InvalidRevision(revision)) if revision == option
+        ));
+        assert!(load_remote(&option, Some("main"), None).is_err());
+        assert!(!marker.exists());
+    }
+}
@@ -0,0 +1,330 @@
+//! A minimal C API for hosts written in other languages, such as an editor
+//! plugin host process, declared in `include/fontgarden.h`. Built with the
+//! `cdylib` feature:
+//!
+//! ```sh
+//! cargo rustc --lib --release --features cdylib --crate-type cdylib
+//! ```
+//!
+//! Strings are UTF-8 and NUL-terminated. Strings returned by the library are
+//! owned by the caller and freed with `fontgarden_string_free`. Functions that
+//! fail return NULL or -1 and leave a message for `fontgarden_last_error`;
+//! panics inside the library are caught and reported the same way.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use crate::{incremental, structs::Fontgarden, ufo::ExportOptions};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Remember the message of an error and its causes for
+/// `fontgarden_last_error`.
+fn set_last_error(error: &dyn std::error::Error) {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    let message = CString::new(message.replace('\0', "")).expect("NULs are removed");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FfiError {
+    #[error("a required argument was NULL")]
+    Null,
+    #[error("an argument was not valid UTF-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("no glyph named '{0}' in the fontgarden")]
+    NoGlyph(String),
+    #[error("glyph {0} has no layer '{1}'")]
+    NoLayer(String, String),
+    #[error("the library panicked: {0}")]
+    Panic(String),
+}
+
+/// # Safety
+///
+/// `string` must be NULL or point to a NUL-terminated string.
+unsafe fn to_str<'a>(string: *const c_char) -> Result<&'a str, FfiError> {
+    if string.is_null() {
+        return Err(FfiError::Null);
+    }
+    Ok(CStr::from_ptr(string).to_str()?)
+}
+
+/// Hand a string over to the caller.
+fn into_c_string(string: String) -> *mut c_char {
+    CString::new(string.replace('\0', ""))
+        .expect("NULs are removed")
+        .into_raw()
+}
+
+/// Run the body of an exported function, turning errors and panics into
+/// `fallback` and a message for `fontgarden_last_error`. Unwinding across the
+/// C boundary would abort the host process.
+fn guard<T>(fallback: T, body: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_last_error(&*e);
+            fallback
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            set_last_error(&FfiError::Panic(message));
+            fallback
+        }
+    }
+}
+
+/// The message of the last error on this thread, or NULL. The string stays
+/// owned by the library and is valid until the next failing call.
+#[no_mangle]
+pub extern "C" fn fontgarden_last_error() -> *const c_char {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Load a fontgarden from a directory, zip archive or SQLite file. Returns
+/// NULL on failure.
+///
+/// # Safety
+///
+/// `path` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_open(path: *const c_char) -> *mut Fontgarden {
+    guard(ptr::null_mut(), || {
+        let fontgarden = Fontgarden::load(Path::new(to_str(path)?))?;
+        Ok(Box::into_raw(Box::new(fontgarden)))
+    })
+}
+
+/// Free a fontgarden from `fontgarden_open`.
+///
+/// # Safety
+///
+/// `fontgarden` must be NULL or come from `fontgarden_open` and not have been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_free(fontgarden: *mut Fontgarden) {
+    guard((), || {
+        if !fontgarden.is_null() {
+            drop(Box::from_raw(fontgarden));
+        }
+        Ok(())
+    })
+}
+
+/// Free a string returned by the library.
+///
+/// # Safety
+///
+/// `string` must be NULL or come from this library and not have been freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_string_free(string: *mut c_char) {
+    guard((), || {
+        if !string.is_null() {
+            drop(CString::from_raw(string));
+        }
+        Ok(())
+    })
+}
+
+/// The sorted glyph names as a JSON array. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `fontgarden` must be NULL or a live fontgarden from `fontgarden_open`.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_glyph_names(fontgarden: *const Fontgarden) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let fontgarden = fontgarden.as_ref().ok_or(FfiError::Null)?;
+        let mut glyph_names: Vec<&str> = fontgarden.glyphs.keys().map(|name| &**name).collect();
+        glyph_names.sort_unstable();
+        Ok(into_c_string(serde_json::to_string(&glyph_names)?))
+    })
+}
+
+/// A glyph layer as the JSON of its layer file, with the layer named like
+/// the file, e.g. `Bold` or `Bold.background`. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `fontgarden` must be NULL or a live fontgarden from `fontgarden_open`, and
+/// the strings must be NULL or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_layer_json(
+    fontgarden: *const Fontgarden,
+    glyph_name: *const c_char,
+    layer_name: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        Ok(into_c_string(layer_json(
+            fontgarden, glyph_name, layer_name,
+        )?))
+    })
+}
+
+unsafe fn layer_json(
+    fontgarden: *const Fontgarden,
+    glyph_name: *const c_char,
+    layer_name: *const c_char,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let fontgarden = fontgarden.as_ref().ok_or(FfiError::Null)?;
+    let glyph_name = to_str(glyph_name)?;
+    let layer_name = to_str(layer_name)?;
+    let glyph = fontgarden
+        .glyphs
+        .get(glyph_name)
+        .ok_or_else(|| FfiError::NoGlyph(glyph_name.into()))?;
+    let layer = glyph
+        .layers
+        .get(&layer_name.into())
+        .ok_or_else(|| FfiError::NoLayer(glyph_name.into(), layer_name.into()))?;
+    Ok(serde_json::to_string(layer)?)
+}
+
+/// Export a UFO per source into `output_dir`, named after the source, only
+/// rewriting files that changed. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+///
+/// `fontgarden` must be NULL or a live fontgarden from `fontgarden_open`, and
+/// `output_dir` must be NULL or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn fontgarden_export_ufos(
+    fontgarden: *const Fontgarden,
+    output_dir: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        let fontgarden = fontgarden.as_ref().ok_or(FfiError::Null)?;
+        let output_dir = PathBuf::from(to_str(output_dir)?);
+        export_ufos(fontgarden, &output_dir)?;
+        Ok(0)
+    })
+}
+
+fn export_ufos(
+    fontgarden: &Fontgarden,
+    output_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = fontgarden.export_ufo_sources(&ExportOptions::default())?;
+    std::fs::create_dir_all(output_dir)?;
+    for (source_name, source) in sources {
+        incremental::write_ufo(&source, &output_dir.join(format!("{source_name}.ufo")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    fn c(string: &str) -> CString {
+        CString::new(string).unwrap()
+    }
+
+    unsafe fn take(string: *mut c_char) -> String {
+        assert!(!string.is_null());
+        let owned = CStr::from_ptr(string).to_str().unwrap().to_string();
+        fontgarden_string_free(string);
+        owned
+    }
+
+    #[test]
+    fn open_query_and_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let garden_path = dir.path().join("test.fontgarden");
+        FontgardenBuilder::new()
+            .glyph(
+                "b",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new().width(500.)),
+            )
+            .glyph(
+                "a",
+                GlyphBuilder::new().layer(
+                    "Regular",
+                    LayerBuilder::new().width(400.).anchor("top", 200., 500.),
+                ),
+            )
+            .build()
+            .save(&garden_path)
+            .unwrap();
+
+        unsafe {
+            let missing = c(dir.path().join("missing").to_str().unwrap());
+            assert!(fontgarden_open(missing.as_ptr()).is_null());
+            assert!(!fontgarden_last_error().is_null());
+
+            let fontgarden = fontgarden_open(c(garden_path.to_str().unwrap()).as_ptr());
+            assert!(!fontgarden.is_null());
+            assert_eq!(take(fontgarden_glyph_names(fontgarden)), r#"["a","b"]"#);
+
+            let json = take(fontgarden_layer_json(
+                fontgarden,
+                c("a").as_ptr(),
+                c("Regular").as_ptr(),
+            ));
+            let layer: crate::structs::Layer = serde_json::from_str(&json).unwrap();
+            assert_eq!(layer.anchors[0].name, "top");
+
+            let missing = fontgarden_layer_json(fontgarden, c("a").as_ptr(), c("Bold").as_ptr());
+            assert!(missing.is_null());
+            let message = CStr::from_ptr(fontgarden_last_error()).to_str().unwrap();
+            assert_eq!(message, "glyph a has no layer 'Bold'");
+
+            let output_dir = dir.path().join("ufos");
+            let output = c(output_dir.to_str().unwrap());
+            assert_eq!(fontgarden_export_ufos(fontgarden, output.as_ptr()), 0);
+            assert!(output_dir.join("Regular.ufo/glyphs/a.glif").is_file());
+            assert_eq!(fontgarden_export_ufos(fontgarden, ptr::null()), -1);
+
+            fontgarden_free(fontgarden);
+        }
+    }
+
+    #[test]
+    fn catch_panics() {
+        let result = guard(ptr::null_mut::<c_char>(), || {
+            panic!("glyph {} exploded", "a")
+        });
+        assert!(result.is_null());
+        let message = unsafe { CStr::from_ptr(fontgarden_last_error()) };
+        assert_eq!(
+            message.to_str().unwrap(),
+            "the library panicked: glyph a exploded"
+        );
+
+        assert_eq!(guard(-1, || panic!("static message")), -1);
+        let message = unsafe { CStr::from_ptr(fontgarden_last_error()) };
+        assert_eq!(
+            message.to_str().unwrap(),
+            "the library panicked: static message"
+        );
+    }
+}
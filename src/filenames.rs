@@ -1,3 +1,5 @@
+use crate::structs::Fontgarden;
+
 /// Transform a name such that it can be written to case-preserving but case-insensitive
 /// filesystems without overwriting something else.
 pub fn name_to_filename(name: &str) -> String {
@@ -27,3 +29,25 @@ pub fn filename_to_name(filename: &str) -> String {
     }
     name
 }
+
+/// What stands for the separator of nested set names in filenames, as `/`
+/// cannot.
+const SET_SEPARATOR_IN_FILENAME: char = '~';
+
+/// The filename of a set's glyph list or metadata, like `set.L_atin~E_xtended.csv`
+/// for the nested set `Latin/Extended`.
+pub fn set_name_to_filename(set_name: &str, extension: &str) -> String {
+    let set_name = set_name.replace(
+        Fontgarden::SET_SEPARATOR,
+        &SET_SEPARATOR_IN_FILENAME.to_string(),
+    );
+    name_to_filename(&format!("set.{set_name}.{extension}"))
+}
+
+/// The set name of a set file's stem after the `set.` prefix.
+pub fn set_filename_to_name(filename: &str) -> String {
+    filename_to_name(filename).replace(
+        SET_SEPARATOR_IN_FILENAME,
+        &Fontgarden::SET_SEPARATOR.to_string(),
+    )
+}
@@ -0,0 +1,558 @@
+use std::collections::{BTreeMap, HashMap};
+
+use std::sync::Arc;
+
+use kurbo::{Affine, Point};
+use serde::Deserialize;
+
+use crate::{
+    errors::FilterError,
+    structs::{Anchor, Component, Contour, ExtraFields, Fontgarden, Layer, LayerName},
+};
+
+/// How far to resolve components that point to other composite glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FlattenComponents {
+    /// Replace components pointing to pure composites by the components of those,
+    /// so that no component points to a glyph made of components only.
+    SingleLevel,
+    /// Decompose all components into contours.
+    Outlines,
+}
+
+impl Fontgarden {
+    /// Flatten nested components in all layers of all glyphs.
+    pub fn flatten_components(&mut self, mode: FlattenComponents) -> Result<(), FilterError> {
+        let mut flattened: Vec<(Arc<str>, LayerName, Layer)> = Vec::new();
+
+        for (glyph_name, glyph) in &self.glyphs {
+            for (layer_name, layer) in &glyph.layers {
+                if layer.components.is_empty() {
+                    continue;
+                }
+                let mut new_layer = layer.clone();
+                let mut stack = vec![&**glyph_name];
+                match mode {
+                    FlattenComponents::SingleLevel => {
+                        new_layer.components.clear();
+                        for component in &layer.components {
+                            self.flatten_component(
+                                component,
+                                Affine::IDENTITY,
+                                layer_name,
+                                &mut stack,
+                                &mut new_layer.components,
+                            )?;
+                        }
+                    }
+                    FlattenComponents::Outlines => {
+                        new_layer.components.clear();
+                        for component in &layer.components {
+                            self.decompose_component(
+                                component,
+                                Affine::IDENTITY,
+                                layer_name,
+                                &mut stack,
+                                &mut new_layer.contours,
+                            )?;
+                        }
+                    }
+                }
+                flattened.push((glyph_name.clone(), layer_name.clone(), new_layer));
+            }
+        }
+
+        for (glyph_name, layer_name, layer) in flattened {
+            self.glyphs
+                .get_mut(&glyph_name)
+                .unwrap()
+                .layers
+                .insert(layer_name, layer);
+        }
+        Ok(())
+    }
+
+    fn flatten_component<'a>(
+        &'a self,
+        component: &'a Component,
+        transform: Affine,
+        layer_name: &LayerName,
+        stack: &mut Vec<&'a str>,
+        components: &mut Vec<Component>,
+    ) -> Result<(), FilterError> {
+        let transform = transform * Affine::from(&component.transformation);
+        let base_layer = self.component_layer(&component.name, layer_name);
+        match base_layer {
+            // The shape of a variable component depends on where in the base glyph
+            // it is taken from, so the base's components can't stand in for it.
+            Some(base_layer)
+                if base_layer.contours.is_empty()
+                    && !base_layer.components.is_empty()
+                    && component.location.is_none() =>
+            {
+                check_cycle(stack, &component.name)?;
+                stack.push(&component.name);
+                for nested in &base_layer.components {
+                    self.flatten_component(nested, transform, layer_name, stack, components)?;
+                }
+                stack.pop();
+            }
+            _ => components.push(Component {
+                name: component.name.clone(),
+                transformation: transform.into(),
+                location: component.location.clone(),
+                extra: ExtraFields::new(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn decompose_component<'a>(
+        &'a self,
+        component: &'a Component,
+        transform: Affine,
+        layer_name: &LayerName,
+        stack: &mut Vec<&'a str>,
+        contours: &mut Vec<Contour>,
+    ) -> Result<(), FilterError> {
+        check_cycle(stack, &component.name)?;
+        if component.location.is_some() {
+            return Err(FilterError::VariableComponent(
+                stack[0].to_string(),
+                component.name.to_string(),
+            ));
+        }
+        let Some(base_layer) = self.component_layer(&component.name, layer_name) else {
+            return Err(FilterError::MissingComponent(
+                stack[0].to_string(),
+                component.name.to_string(),
+            ));
+        };
+        let transform = transform * Affine::from(&component.transformation);
+
+        contours.extend(base_layer.contours.iter().map(|contour| {
+            let mut contour = contour.clone();
+            for point in contour.points.iter_mut() {
+                let transformed = transform * Point::new(point.x, point.y);
+                point.x = transformed.x;
+                point.y = transformed.y;
+            }
+            contour
+        }));
+
+        stack.push(&component.name);
+        for nested in &base_layer.components {
+            self.decompose_component(nested, transform, layer_name, stack, contours)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+}
+
+impl Fontgarden {
+    /// Give composite glyphs the anchors of their components, the way font editors
+    /// do when automatically aligning components.
+    ///
+    /// The first component is taken to be the base and contributes all its
+    /// anchors. Following mark components that attach to one of those anchors
+    /// (via their `_name` anchor) replace it with their own `name` anchor, so that
+    /// further marks stack on top. Anchors placed on the composite itself win.
+    ///
+    /// Returns the number of layers whose anchors changed.
+    pub fn propagate_anchors(&mut self) -> usize {
+        let mut propagated: Vec<(Arc<str>, LayerName, Vec<Anchor>)> = Vec::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            for (layer_name, layer) in &glyph.layers {
+                if layer.components.is_empty() {
+                    continue;
+                }
+                let mut stack = vec![&**glyph_name];
+                let anchors = self.propagated_anchors(glyph_name, layer_name, &mut stack);
+                if anchors != layer.anchors {
+                    propagated.push((glyph_name.clone(), layer_name.clone(), anchors));
+                }
+            }
+        }
+
+        let changed = propagated.len();
+        for (glyph_name, layer_name, anchors) in propagated {
+            let glyph = self.glyphs.get_mut(&glyph_name).unwrap();
+            glyph.layers.get_mut(&layer_name).unwrap().anchors = anchors;
+        }
+        changed
+    }
+
+    pub(crate) fn propagated_anchors<'a>(
+        &'a self,
+        glyph_name: &str,
+        layer_name: &LayerName,
+        stack: &mut Vec<&'a str>,
+    ) -> Vec<Anchor> {
+        let Some(layer) = self.component_layer(glyph_name, layer_name) else {
+            return Vec::new();
+        };
+        if layer.components.is_empty() {
+            return layer.anchors.clone();
+        }
+
+        let mut anchors: Vec<Anchor> = Vec::new();
+        for (index, component) in layer.components.iter().enumerate() {
+            if stack.contains(&&*component.name) {
+                continue;
+            }
+            stack.push(&component.name);
+            let transform = Affine::from(&component.transformation);
+            let component_anchors: Vec<Anchor> = self
+                .propagated_anchors(&component.name, layer_name, stack)
+                .into_iter()
+                .map(|anchor| {
+                    let position = transform * Point::new(anchor.x, anchor.y);
+                    Anchor {
+                        name: anchor.name,
+                        x: position.x,
+                        y: position.y,
+                        extra: ExtraFields::new(),
+                    }
+                })
+                .collect();
+            stack.pop();
+
+            if index == 0 {
+                anchors.extend(
+                    component_anchors
+                        .into_iter()
+                        .filter(|anchor| !anchor.name.starts_with('_')),
+                );
+                continue;
+            }
+            for mark_anchor in &component_anchors {
+                let Some(name) = mark_anchor.name.strip_prefix('_') else {
+                    continue;
+                };
+                let Some(replacement) = component_anchors.iter().find(|a| a.name == name) else {
+                    continue;
+                };
+                if let Some(anchor) = anchors.iter_mut().find(|a| a.name == name) {
+                    *anchor = replacement.clone();
+                }
+            }
+        }
+
+        for own_anchor in &layer.anchors {
+            match anchors.iter_mut().find(|a| a.name == own_anchor.name) {
+                Some(anchor) => *anchor = own_anchor.clone(),
+                None => anchors.push(own_anchor.clone()),
+            }
+        }
+        anchors
+    }
+}
+
+/// A step of an export pipeline, as configured in the `export` table of the
+/// configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilterStep {
+    /// Decompose all components into contours.
+    Decompose,
+    /// Have the compiler remove overlaps, by asking for the `removeOverlaps`
+    /// filter of ufo2ft in the exported UFO. It runs after all other steps.
+    RemoveOverlaps,
+    /// Round coordinates, offsets and advances to whole units.
+    Round,
+    /// Remove all anchors.
+    DropAnchors,
+    /// Rename glyphs from the keys to the values, also where components use
+    /// them. Renaming two glyphs to each other's names swaps them.
+    RenameMap(BTreeMap<String, String>),
+}
+
+/// The lib key under which ufo2ft looks for filters to apply on compilation.
+pub const UFO2FT_FILTERS_LIB_KEY: &str = "com.github.googlei18n.ufo2ft.filters";
+
+impl Fontgarden {
+    /// Run the steps of an export pipeline over all layers, in order. The
+    /// [`FilterStep::RemoveOverlaps`] step is left to the exporter.
+    pub fn apply_pipeline(&mut self, steps: &[FilterStep]) -> Result<(), FilterError> {
+        for step in steps {
+            match step {
+                FilterStep::Decompose => self.flatten_components(FlattenComponents::Outlines)?,
+                FilterStep::RemoveOverlaps => (),
+                FilterStep::Round => {
+                    for layer in self.glyphs.values_mut().flat_map(|g| g.layers.values_mut()) {
+                        layer.round();
+                    }
+                }
+                FilterStep::DropAnchors => {
+                    for layer in self.glyphs.values_mut().flat_map(|g| g.layers.values_mut()) {
+                        layer.anchors.clear();
+                    }
+                }
+                FilterStep::RenameMap(renames) => self.rename_glyphs(renames)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename glyphs all at once, fixing up component references, advance
+    /// links and the modification index.
+    pub(crate) fn rename_glyphs(
+        &mut self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<(), FilterError> {
+        let renames: HashMap<&str, Arc<str>> = renames
+            .iter()
+            .map(|(old_name, new_name)| (old_name.as_str(), new_name.as_str().into()))
+            .collect();
+        let new_name = |name: &Arc<str>| renames.get(&**name).unwrap_or(name).clone();
+        let mut renamed = HashMap::with_capacity(self.glyphs.len());
+        for (glyph_name, mut glyph) in std::mem::take(&mut self.glyphs) {
+            for component in glyph
+                .layers
+                .values_mut()
+                .flat_map(|layer| layer.components.iter_mut())
+            {
+                component.name = new_name(&component.name);
+            }
+            if let Some(target) = &mut glyph.advance_link {
+                if let Some(new_target) = renames.get(target.as_str()) {
+                    *target = new_target.to_string();
+                }
+            }
+            let name = new_name(&glyph_name);
+            if renamed.insert(name.clone(), glyph).is_some() {
+                return Err(FilterError::RenameCollision(name.to_string()));
+            }
+        }
+        self.glyphs = renamed;
+        self.modified.0 = std::mem::take(&mut self.modified.0)
+            .into_iter()
+            .map(
+                |(glyph_name, layers)| match renames.get(glyph_name.as_str()) {
+                    Some(new_name) => (new_name.to_string(), layers),
+                    None => (glyph_name, layers),
+                },
+            )
+            .collect();
+        Ok(())
+    }
+}
+
+impl Layer {
+    /// Round everything measured in font units to whole units.
+    pub fn round(&mut self) {
+        for point in self
+            .contours
+            .iter_mut()
+            .flat_map(|contour| contour.points.iter_mut())
+        {
+            point.x = point.x.round();
+            point.y = point.y.round();
+        }
+        for component in &mut self.components {
+            component.transformation.x_offset = component.transformation.x_offset.round();
+            component.transformation.y_offset = component.transformation.y_offset.round();
+        }
+        for anchor in &mut self.anchors {
+            anchor.x = anchor.x.round();
+            anchor.y = anchor.y.round();
+        }
+        for value in [
+            &mut self.x_advance,
+            &mut self.y_advance,
+            &mut self.vertical_origin,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *value = value.round();
+        }
+    }
+}
+
+fn check_cycle(stack: &[&str], base_name: &str) -> Result<(), FilterError> {
+    if stack.contains(&base_name) {
+        let mut chain = stack.to_vec();
+        chain.push(base_name);
+        return Err(FilterError::ComponentCycle(
+            stack[0].to_string(),
+            chain.join(" -> "),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{AffineTransformation, ContourPoint, ExtraFields, Glyph};
+
+    use super::*;
+
+    fn composite(components: &[(&str, f64)]) -> Glyph {
+        let layer = Layer {
+            components: components
+                .iter()
+                .map(|(name, x_offset)| Component {
+                    name: (*name).into(),
+                    transformation: AffineTransformation {
+                        x_offset: *x_offset,
+                        ..Default::default()
+                    },
+                    location: None,
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        Glyph {
+            layers: [(LayerName::main("Regular"), layer)].into(),
+            ..Default::default()
+        }
+    }
+
+    fn outline(x: f64) -> Glyph {
+        let layer = Layer {
+            contours: vec![Contour {
+                points: vec![ContourPoint {
+                    x,
+                    y: 0.,
+                    typ: Default::default(),
+                    smooth: false,
+                }],
+                extra: ExtraFields::new(),
+            }],
+            ..Default::default()
+        };
+        Glyph {
+            layers: [(LayerName::main("Regular"), layer)].into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flatten_nested_components() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), outline(0.));
+        fontgarden
+            .glyphs
+            .insert("b".into(), composite(&[("a", 10.)]));
+        fontgarden
+            .glyphs
+            .insert("c".into(), composite(&[("b", 100.)]));
+
+        let mut single_level = fontgarden.clone();
+        single_level
+            .flatten_components(FlattenComponents::SingleLevel)
+            .unwrap();
+        let layer = &single_level.glyphs["c"].layers[&LayerName::main("Regular")];
+        assert_eq!(layer.components.len(), 1);
+        assert_eq!(&*layer.components[0].name, "a");
+        assert_eq!(layer.components[0].transformation.x_offset, 110.);
+
+        fontgarden
+            .flatten_components(FlattenComponents::Outlines)
+            .unwrap();
+        let layer = &fontgarden.glyphs["c"].layers[&LayerName::main("Regular")];
+        assert!(layer.components.is_empty());
+        assert_eq!(layer.contours[0].points[0].x, 110.);
+    }
+
+    #[test]
+    fn propagate_stacked_mark_anchors() {
+        let anchor = |name: &str, y: f64| Anchor {
+            name: (*name).into(),
+            x: 100.,
+            y,
+            extra: ExtraFields::new(),
+        };
+        let mut fontgarden = Fontgarden::new();
+        let mut a = outline(0.);
+        a.layers
+            .get_mut(&LayerName::main("Regular"))
+            .unwrap()
+            .anchors = vec![anchor("top", 500.)];
+        let mut acute = outline(0.);
+        acute
+            .layers
+            .get_mut(&LayerName::main("Regular"))
+            .unwrap()
+            .anchors = vec![anchor("_top", 500.), anchor("top", 700.)];
+        fontgarden.glyphs.insert("a".into(), a);
+        fontgarden.glyphs.insert("acute".into(), acute);
+        fontgarden
+            .glyphs
+            .insert("aacute".into(), composite(&[("a", 0.), ("acute", 0.)]));
+        fontgarden.glyphs.insert(
+            "aacuteacute".into(),
+            composite(&[("aacute", 0.), ("acute", 0.)]),
+        );
+        // Shift the second acute up to sit on top of the first.
+        fontgarden
+            .glyphs
+            .get_mut("aacuteacute")
+            .unwrap()
+            .layers
+            .get_mut(&LayerName::main("Regular"))
+            .unwrap()
+            .components[1]
+            .transformation
+            .y_offset = 200.;
+
+        assert_eq!(fontgarden.propagate_anchors(), 2);
+        assert_eq!(
+            fontgarden.glyphs["aacute"].layers[&LayerName::main("Regular")].anchors,
+            vec![anchor("top", 700.)]
+        );
+        assert_eq!(
+            fontgarden.glyphs["aacuteacute"].layers[&LayerName::main("Regular")].anchors,
+            vec![anchor("top", 900.)]
+        );
+    }
+
+    #[test]
+    fn flatten_cyclic_components() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .glyphs
+            .insert("a".into(), composite(&[("b", 0.)]));
+        fontgarden
+            .glyphs
+            .insert("b".into(), composite(&[("a", 0.)]));
+
+        let error = fontgarden
+            .flatten_components(FlattenComponents::Outlines)
+            .unwrap_err();
+        assert!(matches!(error, FilterError::ComponentCycle(..)));
+    }
+
+    #[test]
+    fn apply_configured_pipeline() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            export.pipeline = ["decompose", "round", { rename-map = { a = "b", b = "a" } }]
+            "#,
+        )
+        .unwrap();
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), outline(10.4));
+        fontgarden
+            .glyphs
+            .insert("b".into(), composite(&[("a", 100.)]));
+
+        fontgarden
+            .apply_pipeline(config.export.source_pipeline("Regular"))
+            .unwrap();
+
+        let layer = &fontgarden.glyphs["a"].layers[&LayerName::main("Regular")];
+        assert!(layer.components.is_empty());
+        assert_eq!(layer.contours[0].points[0].x, 110.);
+        let layer = &fontgarden.glyphs["b"].layers[&LayerName::main("Regular")];
+        assert_eq!(layer.contours[0].points[0].x, 10.);
+
+        let collision = FilterStep::RenameMap([("a".to_string(), "b".to_string())].into());
+        assert!(matches!(
+            fontgarden.apply_pipeline(&[collision]),
+            Err(FilterError::RenameCollision(name)) if name == "b"
+        ));
+    }
+}
@@ -0,0 +1,960 @@
+//! Fontra packages (`.fontra`), whose per-glyph files with sources and layers
+//! map naturally onto the glyphs of a fontgarden.
+//!
+//! A package has the font-wide data in `font-data.json`, the glyph names with
+//! their codepoints in `glyph-info.csv` and a JSON file per glyph in `glyphs/`.
+//! Main layers become glyph sources at their source's location, intermediate
+//! layers sources at their own, and all other sublayers plain layers. Importing
+//! maps them back, with the font sources becoming the fontgarden's sources.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use norad::Codepoints;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    config::AxisConfig,
+    errors::FontraError,
+    geometry::DecomposedTransform,
+    interpolate::Location,
+    par::*,
+    structs::{
+        Anchor, Component, Contour, ContourPoint, ExtraFields, Fontgarden, Glyph, Layer, LayerName,
+        PointType,
+    },
+    ufo::{ImportOptions, ImportedGlyph},
+};
+
+const FONT_DATA_FILENAME: &str = "font-data.json";
+const GLYPH_INFO_FILENAME: &str = "glyph-info.csv";
+const GLYPHS_DIR: &str = "glyphs";
+
+/// Keys of the glyph custom data that keep what Fontra has no place for.
+const SET_KEY: &str = "fontgarden.set";
+const OPENTYPE_CATEGORY_KEY: &str = "fontgarden.openTypeCategory";
+const POSTSCRIPT_NAME_KEY: &str = "fontgarden.postscriptName";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FontData {
+    units_per_em: f64,
+    #[serde(default)]
+    axes: FontAxes,
+    #[serde(default)]
+    sources: BTreeMap<String, FontSource>,
+    #[serde(default)]
+    font_info: FontInfo,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FontAxes {
+    axes: Vec<FontAxis>,
+    #[serde(default)]
+    mappings: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FontAxis {
+    name: String,
+    label: String,
+    tag: String,
+    min_value: f64,
+    default_value: f64,
+    max_value: f64,
+    #[serde(default)]
+    mapping: Vec<[f64; 2]>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontSource {
+    name: String,
+    #[serde(default)]
+    location: Location,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FontInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    family_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariableGlyph {
+    name: String,
+    #[serde(default)]
+    sources: Vec<GlyphSource>,
+    #[serde(default)]
+    layers: BTreeMap<String, FontraLayer>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    custom_data: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GlyphSource {
+    name: String,
+    layer_name: String,
+    #[serde(default)]
+    location: Location,
+    /// The font source the glyph source is at, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location_base: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontraLayer {
+    glyph: StaticGlyph,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StaticGlyph {
+    #[serde(default)]
+    path: FontraPath,
+    #[serde(default)]
+    components: Vec<FontraComponent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x_advance: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y_advance: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vertical_origin: Option<f64>,
+    #[serde(default)]
+    anchors: Vec<FontraAnchor>,
+}
+
+/// The outlines of a layer, which Fontra writes either as a list of contours
+/// or packed into flat lists of coordinates and point types.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum FontraPath {
+    Unpacked { contours: Vec<FontraContour> },
+    Packed(PackedPath),
+}
+
+impl Default for FontraPath {
+    fn default() -> Self {
+        FontraPath::Unpacked {
+            contours: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackedPath {
+    coordinates: Vec<f64>,
+    point_types: Vec<u8>,
+    contour_info: Vec<PackedContourInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackedContourInfo {
+    end_point: usize,
+    is_closed: bool,
+}
+
+impl PackedPath {
+    const OFF_CURVE_QUAD: u8 = 0x01;
+    const OFF_CURVE_CUBIC: u8 = 0x02;
+    const SMOOTH_FLAG: u8 = 0x08;
+
+    /// Unpack the contours, leaving out points the coordinates are missing for.
+    fn contours(&self) -> Vec<FontraContour> {
+        let mut start = 0;
+        let mut contours = Vec::new();
+        for info in &self.contour_info {
+            let points = (start..=info.end_point)
+                .filter_map(|index| {
+                    let x = *self.coordinates.get(2 * index)?;
+                    let y = *self.coordinates.get(2 * index + 1)?;
+                    let point_type = *self.point_types.get(index)?;
+                    let typ = match point_type & !Self::SMOOTH_FLAG {
+                        Self::OFF_CURVE_QUAD => Some(OffCurveType::Quad),
+                        Self::OFF_CURVE_CUBIC => Some(OffCurveType::Cubic),
+                        _ => None,
+                    };
+                    Some(FontraPoint {
+                        x,
+                        y,
+                        typ,
+                        smooth: point_type & Self::SMOOTH_FLAG != 0,
+                    })
+                })
+                .collect();
+            contours.push(FontraContour {
+                points,
+                is_closed: info.is_closed,
+            });
+            start = info.end_point + 1;
+        }
+        contours
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FontraContour {
+    points: Vec<FontraPoint>,
+    is_closed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontraPoint {
+    x: f64,
+    y: f64,
+    /// The kind of off-curve point; on-curve points have none.
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    typ: Option<OffCurveType>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    smooth: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OffCurveType {
+    Cubic,
+    Quad,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontraComponent {
+    name: String,
+    #[serde(default)]
+    transformation: DecomposedTransform,
+    #[serde(default)]
+    location: Location,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontraAnchor {
+    name: String,
+    x: f64,
+    y: f64,
+}
+
+impl From<&Contour> for FontraContour {
+    fn from(contour: &Contour) -> Self {
+        let points = &contour.points;
+        // Off-curve points take their kind from the on-curve point ending their
+        // segment, which for closed contours may be the first.
+        let next_on_curve_type = |index: usize| {
+            points[index..]
+                .iter()
+                .chain(&points[..index])
+                .map(|point| &point.typ)
+                .find(|typ| **typ != PointType::OffCurve)
+        };
+        FontraContour {
+            points: points
+                .iter()
+                .enumerate()
+                .map(|(index, point)| FontraPoint {
+                    x: point.x,
+                    y: point.y,
+                    typ: (point.typ == PointType::OffCurve).then(|| {
+                        match next_on_curve_type(index) {
+                            Some(PointType::Curve) => OffCurveType::Cubic,
+                            _ => OffCurveType::Quad,
+                        }
+                    }),
+                    smooth: point.smooth,
+                })
+                .collect(),
+            is_closed: points.first().is_none_or(|p| p.typ != PointType::Move),
+        }
+    }
+}
+
+impl From<&FontraContour> for Contour {
+    fn from(contour: &FontraContour) -> Self {
+        let points = &contour.points;
+        // On-curve points take their kind from the off-curve point before them,
+        // which for closed contours may be the last.
+        let previous_type = |index: usize| match index {
+            0 if contour.is_closed => points.last().and_then(|point| point.typ),
+            0 => None,
+            _ => points[index - 1].typ,
+        };
+        Contour {
+            points: points
+                .iter()
+                .enumerate()
+                .map(|(index, point)| ContourPoint {
+                    x: point.x,
+                    y: point.y,
+                    typ: match point.typ {
+                        Some(_) => PointType::OffCurve,
+                        None if index == 0 && !contour.is_closed => PointType::Move,
+                        None => match previous_type(index) {
+                            Some(OffCurveType::Cubic) => PointType::Curve,
+                            Some(OffCurveType::Quad) => PointType::QCurve,
+                            None => PointType::Line,
+                        },
+                    },
+                    smooth: point.smooth,
+                })
+                .collect(),
+            extra: ExtraFields::new(),
+        }
+    }
+}
+
+impl From<&StaticGlyph> for Layer {
+    fn from(glyph: &StaticGlyph) -> Self {
+        let contours = match &glyph.path {
+            FontraPath::Unpacked { contours } => contours.iter().map(Contour::from).collect(),
+            FontraPath::Packed(packed) => packed.contours().iter().map(Contour::from).collect(),
+        };
+        Layer {
+            anchors: glyph
+                .anchors
+                .iter()
+                .map(|anchor| Anchor {
+                    name: anchor.name.clone(),
+                    x: anchor.x,
+                    y: anchor.y,
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            components: glyph
+                .components
+                .iter()
+                .map(|component| Component {
+                    name: component.name.as_str().into(),
+                    transformation: (&component.transformation).into(),
+                    location: (!component.location.is_empty()).then(|| component.location.clone()),
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            contours,
+            vertical_origin: glyph.vertical_origin,
+            x_advance: glyph.x_advance,
+            y_advance: glyph.y_advance,
+            location: None,
+            condition: None,
+            truetype_instructions: None,
+            postscript_hints: BTreeMap::new(),
+            lib: BTreeMap::new(),
+            extra: ExtraFields::new(),
+        }
+    }
+}
+
+impl From<&Layer> for StaticGlyph {
+    fn from(layer: &Layer) -> Self {
+        StaticGlyph {
+            path: FontraPath::Unpacked {
+                contours: layer.contours.iter().map(FontraContour::from).collect(),
+            },
+            components: layer
+                .components
+                .iter()
+                .map(|component: &Component| FontraComponent {
+                    name: component.name.to_string(),
+                    transformation: (&component.transformation).into(),
+                    location: component.location.clone().unwrap_or_default(),
+                })
+                .collect(),
+            x_advance: layer.x_advance,
+            y_advance: layer.y_advance,
+            vertical_origin: layer.vertical_origin,
+            anchors: layer
+                .anchors
+                .iter()
+                .map(|anchor: &Anchor| FontraAnchor {
+                    name: anchor.name.clone(),
+                    x: anchor.x,
+                    y: anchor.y,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The font sources of a package being imported, to map glyph sources and
+/// layers onto the fontgarden's layers.
+struct PackageSources {
+    /// Axis name to tag, as locations in a package use names.
+    axis_tags: HashMap<String, String>,
+    axis_defaults: Location,
+    /// Source name by identifier, with the location with all axes.
+    sources: BTreeMap<String, (String, Location)>,
+    default_source_name: String,
+}
+
+impl PackageSources {
+    fn to_tags(&self, location: &Location) -> Location {
+        location
+            .iter()
+            .map(|(name, value)| {
+                let tag = self.axis_tags.get(name).unwrap_or(name);
+                (tag.clone(), *value)
+            })
+            .collect()
+    }
+
+    fn full_location(&self, location: &Location) -> Location {
+        let mut full = self.axis_defaults.clone();
+        full.extend(self.to_tags(location));
+        full
+    }
+
+    /// The font source a glyph source is a main layer of: the one it is based
+    /// on, else one at the same location.
+    fn main_source(&self, source: &GlyphSource) -> Option<&str> {
+        if let Some((name, _)) = source
+            .location_base
+            .as_ref()
+            .and_then(|id| self.sources.get(id))
+        {
+            return Some(name);
+        }
+        let location = self.full_location(&source.location);
+        self.sources
+            .values()
+            .find(|(_, source_location)| *source_location == location)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The sublayer for a Fontra layer that isn't a main one: a `^suffix`
+    /// layer, like `Bold^background`, goes with the layer before the caret,
+    /// a `Source.sublayer` one with its source and all others with the default
+    /// source.
+    fn sublayer_name(&self, layer_name: &str, mapped: &HashMap<&str, LayerName>) -> LayerName {
+        if let Some((base, suffix)) = layer_name.split_once('^') {
+            if let Some(base) = mapped.get(base) {
+                return match &base.sublayer {
+                    None => LayerName::sublayer(base.source.clone(), suffix),
+                    Some(sublayer) => {
+                        LayerName::sublayer(base.source.clone(), format!("{sublayer}^{suffix}"))
+                    }
+                };
+            }
+        }
+        if let Some((source_name, sublayer)) = layer_name.split_once('.') {
+            if self.sources.values().any(|(name, _)| name == source_name) {
+                return LayerName::sublayer(source_name, sublayer);
+            }
+        }
+        LayerName::sublayer(self.default_source_name.as_str(), layer_name)
+    }
+
+    /// The fontgarden layers of a glyph, with intermediate sources at their
+    /// locations.
+    fn layers(&self, glyph: &VariableGlyph) -> Vec<(LayerName, Layer)> {
+        let mut mapped: HashMap<&str, LayerName> = HashMap::new();
+        let mut locations: HashMap<&str, Location> = HashMap::new();
+        let mut main_sources: HashSet<&str> = HashSet::new();
+        for source in &glyph.sources {
+            if mapped.contains_key(source.layer_name.as_str()) {
+                continue;
+            }
+            let layer_name = match self
+                .main_source(source)
+                .filter(|name| main_sources.insert(name))
+            {
+                Some(source_name) => LayerName::main(source_name),
+                None => {
+                    locations.insert(&source.layer_name, self.to_tags(&source.location));
+                    self.sublayer_name(&source.layer_name, &mapped)
+                }
+            };
+            mapped.insert(&source.layer_name, layer_name);
+        }
+        glyph
+            .layers
+            .iter()
+            .map(|(fontra_layer_name, layer)| {
+                let layer_name = match mapped.get(fontra_layer_name.as_str()) {
+                    Some(layer_name) => layer_name.clone(),
+                    None => self.sublayer_name(fontra_layer_name, &mapped),
+                };
+                let mut layer = Layer::from(&layer.glyph);
+                layer.location = locations.get(fontra_layer_name.as_str()).cloned();
+                for component in &mut layer.components {
+                    if let Some(location) = &mut component.location {
+                        *location = self.to_tags(location);
+                    }
+                }
+                (layer_name, layer)
+            })
+            .collect()
+    }
+}
+
+impl Fontgarden {
+    /// Import the glyphs of a Fontra package. Its font sources become the
+    /// fontgarden's sources, with their axes and locations added to the
+    /// configuration if missing.
+    ///
+    /// Sources are named as in the package; for the options that apply, see
+    /// [`Fontgarden::merge_imported_glyphs`].
+    pub fn import_fontra(
+        &mut self,
+        path: &Path,
+        options: &ImportOptions,
+    ) -> Result<(), FontraError> {
+        let font_data: FontData = read_json(&path.join(FONT_DATA_FILENAME))?;
+        let glyph_info = read_glyph_info(&path.join(GLYPH_INFO_FILENAME))?;
+        let glyphs_dir = path.join(GLYPHS_DIR);
+        let glyphs: Vec<(String, Codepoints, VariableGlyph)> = glyph_info
+            .into_par_iter()
+            .map(|(glyph_name, codepoints)| {
+                let glyph_path = glyphs_dir.join(format!("{}.json", fontra_filename(&glyph_name)));
+                let glyph = read_json(&glyph_path)?;
+                Ok((glyph_name, codepoints, glyph))
+            })
+            .collect::<Result<_, FontraError>>()?;
+
+        let mut axis_defaults = Location::new();
+        for axis in &font_data.axes.axes {
+            let to_design = |value| map_axis_value(&axis.mapping, value);
+            axis_defaults.insert(axis.tag.clone(), to_design(axis.default_value));
+            if !self.config.axes.contains_key(&axis.tag) {
+                let axis_config = AxisConfig {
+                    minimum: to_design(axis.min_value),
+                    default: to_design(axis.default_value),
+                    maximum: to_design(axis.max_value),
+                };
+                self.config.add_axis(&axis.tag, axis_config);
+            }
+        }
+        let mut package = PackageSources {
+            axis_tags: font_data
+                .axes
+                .axes
+                .iter()
+                .map(|axis| (axis.name.clone(), axis.tag.clone()))
+                .collect(),
+            axis_defaults,
+            sources: BTreeMap::new(),
+            default_source_name: String::new(),
+        };
+        for (id, source) in &font_data.sources {
+            let location = package.full_location(&source.location);
+            package
+                .sources
+                .insert(id.clone(), (source.name.clone(), location));
+        }
+        // Packages from before Fontra had font sources name them in each glyph.
+        if package.sources.is_empty() {
+            for (_, _, glyph) in &glyphs {
+                for source in &glyph.sources {
+                    let location = package.full_location(&source.location);
+                    package
+                        .sources
+                        .entry(source.name.clone())
+                        .or_insert_with(|| (source.name.clone(), location));
+                }
+            }
+        }
+        let source_names: Vec<String> = package
+            .sources
+            .values()
+            .map(|(name, _)| name.clone())
+            .collect();
+        for (source_name, location) in package.sources.values() {
+            if !self.config.axes.is_empty() && !self.config.sources.contains_key(source_name) {
+                self.config.add_source(source_name, location.clone());
+            }
+        }
+        self.record_default_source(&source_names, options)?;
+        package.default_source_name = if source_names.is_empty() {
+            String::from("Regular")
+        } else {
+            self.default_source_name(source_names.iter()).to_string()
+        };
+        let scale_factor = self.file_scale_factor(font_data.units_per_em, options)?;
+
+        let glyphs = glyphs
+            .into_iter()
+            .map(|(glyph_name, codepoints, glyph)| {
+                let custom_string = |key: &str| {
+                    glyph
+                        .custom_data
+                        .get(key)
+                        .and_then(|value| value.as_str())
+                        .map(String::from)
+                };
+                ImportedGlyph {
+                    codepoints,
+                    set: custom_string(SET_KEY),
+                    opentype_category: custom_string(OPENTYPE_CATEGORY_KEY)
+                        .map(|category| category.parse().unwrap_or_default()),
+                    postscript_name: custom_string(POSTSCRIPT_NAME_KEY),
+                    layers: package.layers(&glyph),
+                    name: glyph_name,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        self.merge_imported_glyphs(glyphs, &source_names, scale_factor, options)?;
+        Ok(())
+    }
+
+    /// Write the fontgarden as a Fontra package, replacing what is at `path`.
+    pub fn export_fontra(&self, path: &Path, family_name: &str) -> Result<(), FontraError> {
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |e| FontraError::Io(path, e)
+        };
+        if path.exists() {
+            std::fs::remove_dir_all(path).map_err(io_error(path))?;
+        }
+        let glyphs_dir = path.join(GLYPHS_DIR);
+        std::fs::create_dir_all(&glyphs_dir).map_err(io_error(&glyphs_dir))?;
+
+        let source_location = |source_name: &str| {
+            self.config
+                .sources
+                .get(source_name)
+                .map(|source| source.location.clone())
+                .unwrap_or_default()
+        };
+        let font_data = FontData {
+            units_per_em: self.config.units_per_em.unwrap_or(1000.),
+            axes: FontAxes {
+                axes: self
+                    .config
+                    .axes
+                    .iter()
+                    .map(|(tag, axis)| FontAxis {
+                        name: tag.clone(),
+                        label: tag.clone(),
+                        tag: tag.clone(),
+                        min_value: axis.minimum,
+                        default_value: axis.default,
+                        max_value: axis.maximum,
+                        mapping: Vec::new(),
+                    })
+                    .collect(),
+                mappings: Vec::new(),
+            },
+            sources: self
+                .source_names()
+                .into_iter()
+                .map(|source_name| {
+                    let source = FontSource {
+                        location: source_location(&source_name),
+                        name: source_name.clone(),
+                    };
+                    (source_name, source)
+                })
+                .collect(),
+            font_info: FontInfo {
+                family_name: Some(
+                    self.lib
+                        .family_name
+                        .clone()
+                        .unwrap_or_else(|| family_name.into()),
+                ),
+            },
+        };
+        write_json(&path.join(FONT_DATA_FILENAME), &font_data)?;
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        let mut glyph_info = String::from("glyph name;code points\n");
+        for glyph_name in &glyph_names {
+            let codepoints: Vec<String> = self.glyphs[*glyph_name]
+                .codepoints
+                .iter()
+                .map(|c| format!("U+{:04X}", c as u32))
+                .collect();
+            writeln!(glyph_info, "{glyph_name};{}", codepoints.join(",")).unwrap();
+        }
+        let glyph_info_path = path.join(GLYPH_INFO_FILENAME);
+        std::fs::write(&glyph_info_path, glyph_info).map_err(io_error(&glyph_info_path))?;
+
+        glyph_names.par_iter().try_for_each(|glyph_name| {
+            let glyph = to_variable_glyph(glyph_name, &self.glyphs[*glyph_name], &source_location);
+            write_json(
+                &glyphs_dir.join(format!("{}.json", fontra_filename(glyph_name))),
+                &glyph,
+            )
+        })
+    }
+}
+
+fn to_variable_glyph(
+    glyph_name: &str,
+    glyph: &Glyph,
+    source_location: &dyn Fn(&str) -> Location,
+) -> VariableGlyph {
+    let mut layer_names: Vec<&LayerName> = glyph.layers.keys().collect();
+    layer_names.sort();
+    let mut sources = Vec::new();
+    let mut layers = BTreeMap::new();
+    for layer_name in layer_names {
+        let layer = &glyph.layers[layer_name];
+        let fontra_layer_name = layer_name.to_string();
+        match (&layer_name.sublayer, &layer.location, &layer.condition) {
+            (None, _, _) => sources.push(GlyphSource {
+                name: layer_name.source.to_string(),
+                layer_name: fontra_layer_name.clone(),
+                location: source_location(&layer_name.source),
+                location_base: Some(layer_name.source.to_string()),
+            }),
+            (Some(_), Some(location), None) => sources.push(GlyphSource {
+                name: fontra_layer_name.clone(),
+                layer_name: fontra_layer_name.clone(),
+                location: location.clone(),
+                location_base: None,
+            }),
+            _ => (),
+        }
+        layers.insert(
+            fontra_layer_name,
+            FontraLayer {
+                glyph: layer.into(),
+            },
+        );
+    }
+
+    let mut custom_data = BTreeMap::new();
+    if let Some(set) = &glyph.set {
+        custom_data.insert(SET_KEY.into(), set.clone().into());
+    }
+    if let Ok(serde_json::Value::String(category)) = serde_json::to_value(&glyph.opentype_category)
+    {
+        if category != "unassigned" {
+            custom_data.insert(OPENTYPE_CATEGORY_KEY.into(), category.into());
+        }
+    }
+    if let Some(postscript_name) = &glyph.postscript_name {
+        custom_data.insert(POSTSCRIPT_NAME_KEY.into(), postscript_name.clone().into());
+    }
+    VariableGlyph {
+        name: glyph_name.into(),
+        sources,
+        layers,
+        custom_data,
+    }
+}
+
+/// Map a user-space axis value to design space, interpolating linearly
+/// between the points of the mapping and keeping the value if there are none.
+fn map_axis_value(mapping: &[[f64; 2]], value: f64) -> f64 {
+    let (Some(first), Some(last)) = (mapping.first(), mapping.last()) else {
+        return value;
+    };
+    if value <= first[0] {
+        return first[1];
+    }
+    for pair in mapping.windows(2) {
+        let ([from_user, from_design], [to_user, to_design]) = (pair[0], pair[1]);
+        if value <= to_user {
+            let t = (value - from_user) / (to_user - from_user);
+            return from_design + t * (to_design - from_design);
+        }
+    }
+    last[1]
+}
+
+/// Read the glyph names with their codepoints, like `Aacute;U+00C1`.
+fn read_glyph_info(path: &Path) -> Result<Vec<(String, Codepoints)>, FontraError> {
+    let text = std::fs::read_to_string(path).map_err(|e| FontraError::Io(path.into(), e))?;
+    let mut glyph_info = Vec::new();
+    for line in text.lines().skip(1).filter(|line| !line.trim().is_empty()) {
+        let malformed = || FontraError::GlyphInfo(path.into(), line.into());
+        let (glyph_name, codepoints) = line.split_once(';').ok_or_else(malformed)?;
+        let codepoints = codepoints
+            .split(',')
+            .map(str::trim)
+            .filter(|codepoint| !codepoint.is_empty())
+            .map(|codepoint| {
+                let hex = codepoint.strip_prefix("U+")?;
+                char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+            })
+            .collect::<Option<Vec<char>>>()
+            .ok_or_else(malformed)?;
+        glyph_info.push((glyph_name.to_string(), Codepoints::new(codepoints)));
+    }
+    Ok(glyph_info)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, FontraError> {
+    let file = std::fs::File::open(path).map_err(|e| FontraError::Io(path.into(), e))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| FontraError::Parse(path.into(), e))
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), FontraError> {
+    let file = std::fs::File::create(path).map_err(|e| FontraError::Io(path.into(), e))?;
+    serde_json::to_writer_pretty(file, value).map_err(|e| FontraError::Json(path.into(), e))
+}
+
+/// The file name Fontra stores a glyph under: the name with characters that
+/// are unsafe in file names escaped, followed by the positions of uppercase
+/// letters, five to a base-32 digit, so that names differing only in case
+/// don't clash on case-insensitive file systems.
+fn fontra_filename(glyph_name: &str) -> String {
+    const BASE32_DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+    let chars: Vec<char> = glyph_name.chars().collect();
+    let mut case_digits = String::new();
+    for chunk in chars.chunks(5) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_uppercase())
+            .fold(0, |bits, (index, _)| bits | 1 << index);
+        case_digits.push(BASE32_DIGITS[bits] as char);
+    }
+    let case_digits = case_digits.trim_end_matches('0');
+
+    let mut filename = String::new();
+    for (index, c) in chars.iter().enumerate() {
+        if c.is_control() || "\"%*/:<>?\\|".contains(*c) || (index == 0 && *c == '.') {
+            write!(filename, "%{:02X}", *c as u32).unwrap();
+        } else {
+            filename.push(*c);
+        }
+    }
+    if !case_digits.is_empty() {
+        write!(filename, "^{case_digits}").unwrap();
+    }
+    filename
+}
+
+/// The path of the Fontra package to export a fontgarden named `family_name`
+/// to, in `output_dir`.
+pub fn package_path(output_dir: &Path, family_name: &str) -> PathBuf {
+    output_dir.join(family_name).with_extension("fontra")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames() {
+        assert_eq!(fontra_filename("a"), "a");
+        assert_eq!(fontra_filename("A"), "A^1");
+        assert_eq!(fontra_filename("Aacute"), "Aacute^1");
+        assert_eq!(fontra_filename("aaaaaB"), "aaaaaB^01");
+        assert_eq!(fontra_filename(".notdef"), "%2Enotdef");
+    }
+
+    #[test]
+    fn packed_path() {
+        let glyph: StaticGlyph = serde_json::from_str(
+            r#"{"path": {
+                "coordinates": [0, 0, 0, 50, 50, 100, 100, 100, 100, 0, 200, 0],
+                "pointTypes": [8, 2, 2, 0, 0, 0],
+                "contourInfo": [{"endPoint": 3, "isClosed": false}, {"endPoint": 5, "isClosed": true}]
+            }}"#,
+        )
+        .unwrap();
+        let layer = Layer::from(&glyph);
+        let types: Vec<Vec<PointType>> = layer
+            .contours
+            .iter()
+            .map(|contour| contour.points.iter().map(|p| p.typ.clone()).collect())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                vec![
+                    PointType::Move,
+                    PointType::OffCurve,
+                    PointType::OffCurve,
+                    PointType::Curve
+                ],
+                vec![PointType::Line, PointType::Line],
+            ]
+        );
+        assert!(layer.contours[0].points[0].smooth);
+    }
+
+    #[test]
+    fn export_and_import_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let point = |x, y, typ| ContourPoint {
+            x,
+            y,
+            typ,
+            smooth: false,
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            "axes.wght = { minimum = 100, default = 400, maximum = 900 }\n\
+             sources.Bold.location = { wght = 900 }",
+        )
+        .unwrap();
+        let layer = Layer {
+            contours: vec![Contour {
+                points: vec![
+                    point(0., 0., PointType::Curve),
+                    point(0., 100., PointType::OffCurve),
+                    point(100., 100., PointType::OffCurve),
+                    point(100., 0., PointType::Curve),
+                    point(50., -50., PointType::OffCurve),
+                ],
+                extra: ExtraFields::new(),
+            }],
+            x_advance: Some(500.),
+            ..Default::default()
+        };
+        let mut glyph = Glyph {
+            set: Some("Latin".into()),
+            codepoints: norad::Codepoints::new(['A']),
+            ..Default::default()
+        };
+        glyph.layers.insert(LayerName::main("Bold"), layer.clone());
+        glyph.layers.insert(
+            LayerName::sublayer("Bold", "Semibold"),
+            Layer {
+                location: Some(Location::from([("wght".into(), 600.)])),
+                ..layer.clone()
+            },
+        );
+        glyph
+            .layers
+            .insert(LayerName::sublayer("Bold", "background"), layer);
+        fontgarden.glyphs.insert("A".into(), glyph);
+
+        let path = package_path(dir.path(), "Test");
+        fontgarden.export_fontra(&path, "Test").unwrap();
+
+        let glyph_info = std::fs::read_to_string(path.join(GLYPH_INFO_FILENAME)).unwrap();
+        assert_eq!(glyph_info, "glyph name;code points\nA;U+0041\n");
+        let font_data: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path.join(FONT_DATA_FILENAME)).unwrap()).unwrap();
+        assert_eq!(font_data["axes"]["axes"][0]["maxValue"], 900.);
+        assert_eq!(font_data["fontInfo"]["familyName"], "Test");
+        let glyph: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path.join("glyphs/A^1.json")).unwrap()).unwrap();
+        assert_eq!(
+            glyph["sources"],
+            serde_json::json!([
+                {"name": "Bold", "layerName": "Bold", "location": {"wght": 900.0}, "locationBase": "Bold"},
+                {"name": "Bold.Semibold", "layerName": "Bold.Semibold", "location": {"wght": 600.0}},
+            ])
+        );
+        let layers = glyph["layers"].as_object().unwrap();
+        assert_eq!(layers.len(), 3);
+        let points = &layers["Bold"]["glyph"]["path"]["contours"][0]["points"];
+        assert_eq!(points[1]["type"], "cubic");
+        assert_eq!(points[4]["type"], "cubic");
+        assert_eq!(points[0].get("type"), None);
+        assert_eq!(glyph["customData"][SET_KEY], "Latin");
+
+        let mut imported = Fontgarden::new();
+        imported
+            .import_fontra(&path, &ImportOptions::default())
+            .unwrap();
+        assert_eq!(imported.glyphs, fontgarden.glyphs);
+        assert_eq!(imported.config.units_per_em, Some(1000.));
+        assert_eq!(imported.config.axes, fontgarden.config.axes);
+        assert_eq!(imported.config.sources, fontgarden.config.sources);
+    }
+}
@@ -0,0 +1,149 @@
+//! Project metadata in `lib.json` at the root of a fontgarden: the font-wide
+//! names and IDs that go into the fontinfo of exported sources, and any other
+//! keys for tools, which go into their lib.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::LoadError, storage::Storage};
+
+/// The contents of `lib.json`. Known keys are typed, all others are kept as
+/// they are in `other`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GardenLib {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub designer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub designer_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer_url: Option<String>,
+    /// The four-character OS/2 vendor ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trademark: Option<String>,
+    /// Keys for other tools, like `com.example.tool.setting`, exported into
+    /// the lib of every source.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_json::Value>,
+}
+
+impl GardenLib {
+    pub const FILENAME: &str = "lib.json";
+
+    /// Load `lib.json` from a fontgarden, or an empty lib if there is none.
+    pub fn load(storage: &impl Storage) -> Result<Self, LoadError> {
+        let path = Path::new(Self::FILENAME);
+        if !storage.exists(path) {
+            return Ok(Self::default());
+        }
+        let json = storage
+            .read(path)
+            .map_err(|e| LoadError::Io(storage.location(path), e))?;
+        serde_json::from_slice(&json).map_err(|e| LoadError::LoadLib(storage.location(path), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Look up a key, typed or not.
+    pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(mut map)) => map.remove(key),
+            _ => None,
+        }
+    }
+
+    /// Set a key, typed or not, or remove it by setting it to `null`. Values
+    /// of the wrong type for typed keys are rejected.
+    pub fn set(&mut self, key: &str, value: serde_json::Value) -> Result<(), serde_json::Error> {
+        let mut map = match serde_json::to_value(&*self)? {
+            serde_json::Value::Object(map) => map,
+            _ => unreachable!("the lib serializes to an object"),
+        };
+        if value.is_null() {
+            map.remove(key);
+        } else {
+            map.insert(key.into(), value);
+        }
+        *self = serde_json::from_value(serde_json::Value::Object(map))?;
+        Ok(())
+    }
+
+    /// Fill in the fontinfo of an exported source and add the other keys to
+    /// its lib. Keys with values that a UFO lib can't hold are skipped.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font) {
+        let font_info = &mut ufo.font_info;
+        for (field, value) in [
+            (&mut font_info.family_name, &self.family_name),
+            (&mut font_info.open_type_name_designer, &self.designer),
+            (
+                &mut font_info.open_type_name_designer_url,
+                &self.designer_url,
+            ),
+            (
+                &mut font_info.open_type_name_manufacturer,
+                &self.manufacturer,
+            ),
+            (
+                &mut font_info.open_type_name_manufacturer_url,
+                &self.manufacturer_url,
+            ),
+            (&mut font_info.open_type_os2_vendor_id, &self.vendor_id),
+            (&mut font_info.copyright, &self.copyright),
+            (&mut font_info.trademark, &self.trademark),
+        ] {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        }
+        for (key, value) in &self.other {
+            match serde_json::from_value::<plist::Value>(value.clone()) {
+                Ok(value) => {
+                    ufo.lib.insert(key.clone(), value);
+                }
+                Err(e) => eprintln!("warning: not exporting lib key {key}: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_and_other_keys() {
+        let mut lib: GardenLib = serde_json::from_str(
+            r#"{"family_name": "Garden Sans", "com.example.tool": {"level": 3}}"#,
+        )
+        .unwrap();
+        lib.set("vendor_id", "GRDN".into()).unwrap();
+        lib.set("com.example.flag", true.into()).unwrap();
+        assert!(lib.set("designer", 3.into()).is_err());
+        lib.set("family_name", serde_json::Value::Null).unwrap();
+
+        assert_eq!(lib.family_name, None);
+        assert_eq!(lib.vendor_id.as_deref(), Some("GRDN"));
+        assert_eq!(lib.get("com.example.flag"), Some(true.into()));
+
+        let mut ufo = norad::Font::new();
+        lib.apply_to_ufo(&mut ufo);
+        assert_eq!(
+            ufo.font_info.open_type_os2_vendor_id.as_deref(),
+            Some("GRDN")
+        );
+        assert_eq!(ufo.lib["com.example.flag"], plist::Value::Boolean(true));
+        let tool = ufo.lib["com.example.tool"].as_dictionary().unwrap();
+        assert_eq!(tool["level"].as_signed_integer(), Some(3));
+        assert!(!ufo.lib.contains_key("vendor_id"));
+    }
+}
@@ -0,0 +1,460 @@
+//! Outlines as kurbo paths, with components resolved, and their bounds.
+
+use kurbo::{Affine, BezPath, Point, Rect, Shape};
+use serde::{Deserialize, Serialize};
+
+use crate::structs::{
+    AffineTransformation, Contour, ContourPoint, Fontgarden, Glyph, Layer, LayerName, PointType,
+};
+
+impl Fontgarden {
+    /// Build the outline of a glyph's layer, with components resolved into
+    /// plain contours.
+    ///
+    /// Components are looked up with [`Fontgarden::component_layer`].
+    pub fn resolved_bez_path(&self, glyph_name: &str, layer_name: &LayerName) -> Option<BezPath> {
+        let mut path = BezPath::new();
+        let mut stack = vec![glyph_name];
+        self.add_resolved_layer(
+            glyph_name,
+            layer_name,
+            Affine::IDENTITY,
+            &mut stack,
+            &mut path,
+        )
+        .then_some(path)
+    }
+
+    pub(crate) fn add_resolved_layer<'a>(
+        &'a self,
+        glyph_name: &str,
+        layer_name: &LayerName,
+        transform: Affine,
+        stack: &mut Vec<&'a str>,
+        path: &mut BezPath,
+    ) -> bool {
+        let Some(layer) = self.component_layer(glyph_name, layer_name) else {
+            return false;
+        };
+
+        for contour in &layer.contours {
+            let mut contour_path = contour.to_bez_path();
+            contour_path.apply_affine(transform);
+            path.extend(contour_path);
+        }
+        for component in &layer.components {
+            // Skip components that would lead us in circles.
+            if stack.contains(&&*component.name) {
+                continue;
+            }
+            stack.push(&component.name);
+            let component_transform = transform * Affine::from(&component.transformation);
+            self.add_resolved_layer(
+                &component.name,
+                layer_name,
+                component_transform,
+                stack,
+                path,
+            );
+            stack.pop();
+        }
+        true
+    }
+}
+
+impl Glyph {
+    /// The bounds of the glyph's layer, with components resolved through the
+    /// fontgarden, or `None` if the layer is missing or has no outline.
+    pub fn bounds(&self, layer_name: &LayerName, fontgarden: &Fontgarden) -> Option<Rect> {
+        let layer = self.layers.get(layer_name)?;
+        let mut path = BezPath::new();
+        for component in &layer.components {
+            let mut stack = vec![&*component.name];
+            fontgarden.add_resolved_layer(
+                &component.name,
+                layer_name,
+                Affine::from(&component.transformation),
+                &mut stack,
+                &mut path,
+            );
+        }
+        let component_bounds = (!path.elements().is_empty()).then(|| path.bounding_box());
+        match (layer.bounds(), component_bounds) {
+            (Some(bounds), Some(other)) => Some(bounds.union(other)),
+            (bounds, other) => bounds.or(other),
+        }
+    }
+}
+
+impl Layer {
+    /// The contours of the layer as paths, one per contour. Components are
+    /// left out; see [`Fontgarden::resolved_bez_path`] for those.
+    pub fn to_bez_paths(&self) -> Vec<BezPath> {
+        self.contours.iter().map(Contour::to_bez_path).collect()
+    }
+
+    /// The bounds of the layer's contours, or `None` if it has none. Components
+    /// are left out; see [`Glyph::bounds`] for those.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.to_bez_paths()
+            .iter()
+            .filter(|path| !path.elements().is_empty())
+            .map(|path| path.bounding_box())
+            .reduce(|bounds, other| bounds.union(other))
+    }
+}
+
+impl Contour {
+    /// Convert UFO-style point lists into a path, following the segment rules
+    /// of the UFO specification.
+    pub fn to_bez_path(&self) -> BezPath {
+        let mut path = BezPath::new();
+        let points = &self.points;
+        let is_closed = !matches!(
+            points.first(),
+            Some(ContourPoint {
+                typ: PointType::Move,
+                ..
+            })
+        );
+
+        // A closed contour starts at its last on-curve point, so that leading
+        // off-curve points wrap around correctly.
+        let start = match points.iter().rposition(|p| p.typ != PointType::OffCurve) {
+            Some(index) if is_closed => index,
+            Some(_) => 0,
+            None => return quadratic_only_contour(points),
+        };
+
+        let mut ordered: Vec<&ContourPoint> = points[start..].iter().collect();
+        if is_closed {
+            ordered.extend(&points[..start]);
+        }
+
+        let mut off_curves: Vec<Point> = vec![];
+        path.move_to(ordered[0].to_point());
+        for point in &ordered[1..] {
+            if point.typ == PointType::OffCurve {
+                off_curves.push(point.to_point());
+            } else {
+                add_segment(&mut path, &point.typ, &off_curves, point.to_point());
+                off_curves.clear();
+            }
+        }
+        if is_closed {
+            add_segment(
+                &mut path,
+                &ordered[0].typ,
+                &off_curves,
+                ordered[0].to_point(),
+            );
+            path.close_path();
+        }
+
+        path
+    }
+}
+
+impl ContourPoint {
+    pub(crate) fn to_point(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+}
+
+impl From<&AffineTransformation> for Affine {
+    fn from(t: &AffineTransformation) -> Self {
+        Affine::new([
+            t.x_scale, t.xy_scale, t.yx_scale, t.y_scale, t.x_offset, t.y_offset,
+        ])
+    }
+}
+
+impl From<Affine> for AffineTransformation {
+    fn from(affine: Affine) -> Self {
+        let [x_scale, xy_scale, yx_scale, y_scale, x_offset, y_offset] = affine.as_coeffs();
+        Self {
+            x_scale,
+            xy_scale,
+            yx_scale,
+            y_scale,
+            x_offset,
+            y_offset,
+        }
+    }
+}
+
+/// A transformation as fontTools, Fontra and Glyphs describe it, in the order of
+/// application: skew, scale, rotation (in degrees) and translation, all around
+/// the center.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct DecomposedTransform {
+    pub(crate) translate_x: f64,
+    pub(crate) translate_y: f64,
+    pub(crate) rotation: f64,
+    pub(crate) scale_x: f64,
+    pub(crate) scale_y: f64,
+    pub(crate) skew_x: f64,
+    pub(crate) skew_y: f64,
+    pub(crate) t_center_x: f64,
+    pub(crate) t_center_y: f64,
+}
+
+impl Default for DecomposedTransform {
+    fn default() -> Self {
+        Self {
+            translate_x: 0.,
+            translate_y: 0.,
+            rotation: 0.,
+            scale_x: 1.,
+            scale_y: 1.,
+            skew_x: 0.,
+            skew_y: 0.,
+            t_center_x: 0.,
+            t_center_y: 0.,
+        }
+    }
+}
+
+/// Compose the affine transformation the same way fontTools does.
+impl From<&DecomposedTransform> for AffineTransformation {
+    fn from(t: &DecomposedTransform) -> Self {
+        let affine =
+            Affine::translate((t.translate_x + t.t_center_x, t.translate_y + t.t_center_y))
+                * Affine::rotate(t.rotation.to_radians())
+                * Affine::scale_non_uniform(t.scale_x, t.scale_y)
+                * Affine::new([
+                    1.,
+                    t.skew_y.to_radians().tan(),
+                    t.skew_x.to_radians().tan(),
+                    1.,
+                    0.,
+                    0.,
+                ])
+                * Affine::translate((-t.t_center_x, -t.t_center_y));
+        affine.into()
+    }
+}
+
+/// Decompose an affine transformation the same way fontTools does.
+impl From<&AffineTransformation> for DecomposedTransform {
+    fn from(t: &AffineTransformation) -> Self {
+        let (mut a, mut b, c, d) = (t.x_scale, t.xy_scale, t.yx_scale, t.y_scale);
+        let sign_x = if a < 0. { -1. } else { 1. };
+        a *= sign_x;
+        b *= sign_x;
+        let delta = a * d - b * c;
+        let (rotation, scale_x, scale_y, skew_x, skew_y) = if a != 0. || b != 0. {
+            let r = a.hypot(b);
+            let rotation = if b >= 0. {
+                (a / r).acos()
+            } else {
+                -(a / r).acos()
+            };
+            (
+                rotation,
+                r,
+                delta / r,
+                ((a * c + b * d) / (r * r)).atan(),
+                0.,
+            )
+        } else if c != 0. || d != 0. {
+            let s = c.hypot(d);
+            let rotation = std::f64::consts::FRAC_PI_2
+                - if d >= 0. {
+                    (-c / s).acos()
+                } else {
+                    -(c / s).acos()
+                };
+            (
+                rotation,
+                delta / s,
+                s,
+                0.,
+                ((a * c + b * d) / (s * s)).atan(),
+            )
+        } else {
+            (0., 0., 0., 0., 0.)
+        };
+        Self {
+            translate_x: t.x_offset,
+            translate_y: t.y_offset,
+            rotation: rotation.to_degrees(),
+            scale_x: scale_x * sign_x,
+            scale_y,
+            skew_x: skew_x.to_degrees() * sign_x,
+            skew_y: skew_y.to_degrees(),
+            t_center_x: 0.,
+            t_center_y: 0.,
+        }
+    }
+}
+
+pub(crate) fn add_segment(path: &mut BezPath, typ: &PointType, off_curves: &[Point], end: Point) {
+    match (typ, off_curves) {
+        (_, []) => path.line_to(end),
+        (PointType::Curve, [c0, c1]) => path.curve_to(*c0, *c1, end),
+        (PointType::Curve, [c0]) => path.quad_to(*c0, end),
+        (PointType::Curve, _) => {
+            for (c0, c1, on) in cubic_super_segments(off_curves, end) {
+                path.curve_to(c0, c1, on);
+            }
+        }
+        (PointType::QCurve, _) => {
+            // Quadratic splines with implied on-curve points between consecutive
+            // off-curve points.
+            for pair in off_curves.windows(2) {
+                path.quad_to(pair[0], pair[0].midpoint(pair[1]));
+            }
+            path.quad_to(*off_curves.last().unwrap(), end);
+        }
+        // Line, Move and OffCurve should not have off-curve points before them.
+        _ => path.line_to(end),
+    }
+}
+
+/// Split a cubic segment with more than two off-curve points into plain
+/// cubic segments, the way fontTools' `decomposeSuperBezierSegment` does.
+fn cubic_super_segments(off_curves: &[Point], end: Point) -> Vec<(Point, Point, Point)> {
+    let n = off_curves.len();
+    let mut segments = vec![];
+    let mut c0 = off_curves[0];
+    let mut c1 = None;
+    for i in 2..=n {
+        // Points in between the off-curve points, as many as fit.
+        let divisions = i.min(3).min(n + 2 - i);
+        for j in 1..divisions {
+            let point = off_curves[i - 2].lerp(off_curves[i - 1], j as f64 / divisions as f64);
+            match c1.take() {
+                None => c1 = Some(point),
+                Some(previous) => {
+                    segments.push((c0, previous, previous.midpoint(point)));
+                    c0 = point;
+                }
+            }
+        }
+    }
+    segments.push((c0, off_curves[n - 1], end));
+    segments
+}
+
+/// Handle the TrueType special case of a closed contour made up of off-curve
+/// points only.
+fn quadratic_only_contour(points: &[ContourPoint]) -> BezPath {
+    let mut path = BezPath::new();
+    let Some(last) = points.last() else {
+        return path;
+    };
+    let start = last.to_point().midpoint(points[0].to_point());
+    path.move_to(start);
+    for pair in points.windows(2) {
+        path.quad_to(
+            pair[0].to_point(),
+            pair[0].to_point().midpoint(pair[1].to_point()),
+        );
+    }
+    path.quad_to(last.to_point(), start);
+    path.close_path();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Component, ExtraFields};
+
+    use super::*;
+
+    #[test]
+    fn decompose_and_compose_transforms() {
+        let transform = DecomposedTransform::from(&AffineTransformation {
+            x_scale: 0.,
+            xy_scale: 2.,
+            yx_scale: -2.,
+            y_scale: 0.,
+            x_offset: 10.,
+            y_offset: 20.,
+        });
+        assert_eq!(transform.translate_x, 10.);
+        assert!((transform.rotation - 90.).abs() < 1e-9);
+        assert!((transform.scale_x - 2.).abs() < 1e-9);
+        assert!((transform.scale_y - 2.).abs() < 1e-9);
+
+        let skewed = AffineTransformation {
+            x_scale: -1.5,
+            xy_scale: 0.25,
+            yx_scale: 0.5,
+            y_scale: 0.75,
+            x_offset: -30.,
+            y_offset: 40.,
+        };
+        let composed = AffineTransformation::from(&DecomposedTransform::from(&skewed));
+        let coefficients = |t: &AffineTransformation| Affine::from(t).as_coeffs();
+        for (a, b) in coefficients(&composed).iter().zip(coefficients(&skewed)) {
+            assert!((a - b).abs() < 1e-9, "{composed:?} != {skewed:?}");
+        }
+    }
+
+    #[test]
+    fn bounds_with_components() {
+        // A curve bulging out to x = 75 between on-curve points at x = 0.
+        let contour = Contour {
+            points: [
+                (0., 0., PointType::Line),
+                (100., 0., PointType::OffCurve),
+                (100., 100., PointType::OffCurve),
+                (0., 100., PointType::Curve),
+            ]
+            .into_iter()
+            .map(|(x, y, typ)| ContourPoint {
+                x,
+                y,
+                typ,
+                smooth: false,
+            })
+            .collect(),
+            extra: ExtraFields::new(),
+        };
+        let layer_name = LayerName::main("Regular");
+        let mut fontgarden = Fontgarden::new();
+        let mut base = Glyph::default();
+        base.layers.insert(
+            layer_name.clone(),
+            Layer {
+                contours: vec![contour.clone()],
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("base".into(), base);
+        let mut composite = Glyph::default();
+        composite.layers.insert(
+            layer_name.clone(),
+            Layer {
+                contours: vec![contour],
+                components: vec![Component {
+                    name: "base".into(),
+                    transformation: AffineTransformation {
+                        x_offset: 200.,
+                        y_scale: 2.,
+                        ..Default::default()
+                    },
+                    location: None,
+                    extra: ExtraFields::new(),
+                }],
+                ..Default::default()
+            },
+        );
+
+        let layer = &composite.layers[&layer_name];
+        assert_eq!(layer.to_bez_paths().len(), 1);
+        assert_eq!(layer.bounds(), Some(Rect::new(0., 0., 75., 100.)));
+        assert_eq!(
+            composite.bounds(&layer_name, &fontgarden),
+            Some(Rect::new(0., 0., 275., 200.))
+        );
+        assert_eq!(Layer::default().bounds(), None);
+        assert_eq!(
+            composite.bounds(&LayerName::main("Bold"), &fontgarden),
+            None
+        );
+    }
+}
@@ -0,0 +1,1200 @@
+//! Glyphs files (`.glyphs`) and packages (`.glyphspackage`), from Glyphs 2 or 3.
+//!
+//! Masters become sources, brace layers intermediate layers at their location
+//! and bracket layers alternates with their axis ranges as conditions. The
+//! script of a glyph picks its set the way the guessed ones are picked, and its
+//! category the OpenType category.
+//!
+//! Exporting writes Glyphs 3 files the same way around, with a master per
+//! source. Sets and OpenType categories also go into the user data of glyphs,
+//! which importing prefers, so that they survive a round trip.
+
+use std::{collections::HashMap, path::Path};
+
+use kurbo::Affine;
+use norad::Codepoints;
+use plist::{Dictionary, Value};
+
+use crate::{
+    alternates::{AxisRange, ConditionSet},
+    config::AxisConfig,
+    errors::{GlyphsError, SourceLoadError},
+    geometry::DecomposedTransform,
+    interpolate::Location,
+    openstep::{self, number},
+    structs::{
+        Anchor, Component, Contour, ContourPoint, ExtraFields, Fontgarden, Glyph, Layer, LayerName,
+        OpenTypeCategory, PointType,
+    },
+    ufo::{ImportOptions, ImportedGlyph},
+};
+
+/// Where Glyphs 2 keeps the axis values of masters, for the axes in order.
+const GLYPHS2_VALUE_KEYS: [&str; 6] = [
+    "weightValue",
+    "widthValue",
+    "customValue",
+    "customValue1",
+    "customValue2",
+    "customValue3",
+];
+
+struct Axis {
+    tag: String,
+    /// The key of the axis values in Glyphs 2 masters.
+    value_key: Option<&'static str>,
+}
+
+struct Master {
+    id: String,
+    name: String,
+    location: Location,
+}
+
+/// Where glyph data without a place in Glyphs goes in the user data of glyphs.
+const SET_KEY: &str = "fontgarden.set";
+const OPENTYPE_CATEGORY_KEY: &str = "fontgarden.openTypeCategory";
+
+/// The font-wide parts of a Glyphs file that glyphs are read with.
+struct GlyphsFont {
+    is_glyphs3: bool,
+    axes: Vec<Axis>,
+    masters: Vec<Master>,
+    /// Script name, like "Latin", to set name, from the configuration.
+    script_sets: HashMap<String, String>,
+}
+
+impl Fontgarden {
+    /// Import the glyphs of a Glyphs file or package. Its masters become the
+    /// fontgarden's sources, with their axes and locations added to the
+    /// configuration if missing.
+    ///
+    /// Sources are named after the masters; for the options that apply, see
+    /// [`Fontgarden::merge_imported_glyphs`].
+    pub fn import_glyphs(
+        &mut self,
+        path: &Path,
+        options: &ImportOptions,
+    ) -> Result<(), GlyphsError> {
+        let font = load(path)?;
+        let is_glyphs3 = font.get(".formatVersion").and_then(number) == Some(3.);
+        let master_dicts: Vec<&Dictionary> = font
+            .get("fontMaster")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_dictionary)
+            .collect();
+        if master_dicts.is_empty() {
+            return Err(GlyphsError::Malformed(path.into(), "there are no masters"));
+        }
+        let axes = axes(&font, &master_dicts, is_glyphs3);
+        let mut masters: Vec<Master> = Vec::new();
+        for master in &master_dicts {
+            let name = master_name(master, is_glyphs3);
+            if masters.iter().any(|other| other.name == name) {
+                return Err(SourceLoadError::DuplicateSourceName(name, path.into()).into());
+            }
+            masters.push(Master {
+                id: string(master, "id").unwrap_or_default().to_string(),
+                location: master_location(master, &axes),
+                name,
+            });
+        }
+
+        let origin = custom_parameter(&font, "Variable Font Origin")
+            .and_then(Value::as_string)
+            .and_then(|origin| {
+                masters
+                    .iter()
+                    .find(|master| master.id == origin || master.name == origin)
+            })
+            .unwrap_or(&masters[0]);
+        for axis in &axes {
+            if self.config.axes.contains_key(&axis.tag) {
+                continue;
+            }
+            let values = masters.iter().map(|master| master.location[&axis.tag]);
+            let axis_config = AxisConfig {
+                minimum: values.clone().fold(f64::INFINITY, f64::min),
+                default: origin.location[&axis.tag],
+                maximum: values.fold(f64::NEG_INFINITY, f64::max),
+            };
+            self.config.add_axis(&axis.tag, axis_config);
+        }
+        for master in &masters {
+            if !axes.is_empty() && !self.config.sources.contains_key(&master.name) {
+                self.config
+                    .add_source(&master.name, master.location.clone());
+            }
+        }
+        let source_names: Vec<String> = masters.iter().map(|master| master.name.clone()).collect();
+        self.record_default_source(&source_names, options)?;
+        let units_per_em = font.get("unitsPerEm").and_then(number).unwrap_or(1000.);
+        let scale_factor = self.file_scale_factor(units_per_em, options)?;
+
+        let glyphs_font = GlyphsFont {
+            is_glyphs3,
+            axes,
+            masters,
+            script_sets: self.config.sets.scripts.clone(),
+        };
+        let glyphs = font
+            .get("glyphs")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_dictionary)
+            .filter_map(|glyph| glyphs_font.glyph(glyph))
+            .collect();
+        self.merge_imported_glyphs(glyphs, &source_names, scale_factor, options)?;
+        Ok(())
+    }
+
+    /// Write all glyphs to a Glyphs 3 file, with a master per source and the
+    /// configured axes. The family name of the lib wins over `family_name`.
+    pub fn export_glyphs(&self, path: &Path, family_name: &str) -> Result<(), GlyphsError> {
+        let source_names: Vec<String> = self.source_names().into_iter().collect();
+        let writer = GlyphsWriter {
+            axes: self
+                .config
+                .axes
+                .iter()
+                .map(|(tag, axis)| (tag.as_str(), axis.default))
+                .collect(),
+            master_ids: source_names
+                .iter()
+                .enumerate()
+                .map(|(index, source_name)| (source_name.as_str(), format!("m{:02}", index + 1)))
+                .collect(),
+        };
+
+        let mut font = Dictionary::new();
+        font.insert(".appVersion".into(), "3219".into());
+        font.insert(".formatVersion".into(), 3.into());
+        if !writer.axes.is_empty() {
+            let axes = writer
+                .axes
+                .iter()
+                .map(|(tag, _)| {
+                    let mut axis = Dictionary::new();
+                    axis.insert("name".into(), axis_name(tag).into());
+                    axis.insert("tag".into(), (*tag).into());
+                    axis.into()
+                })
+                .collect::<Vec<Value>>();
+            font.insert("axes".into(), axes.into());
+            if !source_names.is_empty() {
+                let origin = self.default_source_name(source_names.iter());
+                let mut parameter = Dictionary::new();
+                parameter.insert("name".into(), "Variable Font Origin".into());
+                parameter.insert("value".into(), writer.master_ids[origin].clone().into());
+                font.insert(
+                    "customParameters".into(),
+                    vec![Value::from(parameter)].into(),
+                );
+            }
+        }
+        let family_name = self.lib.family_name.as_deref().unwrap_or(family_name);
+        font.insert("familyName".into(), family_name.into());
+        let masters = source_names
+            .iter()
+            .map(|source_name| {
+                let location = self
+                    .config
+                    .sources
+                    .get(source_name)
+                    .map(|source| &source.location);
+                let mut master = Dictionary::new();
+                if !writer.axes.is_empty() {
+                    master.insert("axesValues".into(), writer.coordinates(location).into());
+                }
+                master.insert(
+                    "id".into(),
+                    writer.master_ids[source_name.as_str()].clone().into(),
+                );
+                master.insert("name".into(), source_name.clone().into());
+                master.into()
+            })
+            .collect::<Vec<Value>>();
+        font.insert("fontMaster".into(), masters.into());
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        let glyphs = glyph_names
+            .into_iter()
+            .map(|glyph_name| writer.glyph(glyph_name, &self.glyphs[glyph_name]).into())
+            .collect::<Vec<Value>>();
+        font.insert("glyphs".into(), glyphs.into());
+        font.insert(
+            "unitsPerEm".into(),
+            self.config.units_per_em.unwrap_or(1000.).into(),
+        );
+        font.insert("versionMajor".into(), 1.into());
+        font.insert("versionMinor".into(), 0.into());
+
+        std::fs::write(path, openstep::to_string(&font.into()))
+            .map_err(|e| GlyphsError::Io(path.into(), e))
+    }
+}
+
+/// The font-wide parts of a fontgarden that glyphs are written with.
+struct GlyphsWriter<'a> {
+    /// Axis tags and defaults, in the order of the axes in the file.
+    axes: Vec<(&'a str, f64)>,
+    master_ids: HashMap<&'a str, String>,
+}
+
+impl GlyphsWriter<'_> {
+    /// The values of a location on all axes, with missing ones at the default.
+    fn coordinates(&self, location: Option<&Location>) -> Vec<Value> {
+        self.axes
+            .iter()
+            .map(|(tag, default)| {
+                location
+                    .and_then(|location| location.get(*tag))
+                    .copied()
+                    .unwrap_or(*default)
+                    .into()
+            })
+            .collect()
+    }
+
+    fn glyph(&self, glyph_name: &str, glyph: &Glyph) -> Dictionary {
+        let mut dictionary = Dictionary::new();
+        dictionary.insert("glyphname".into(), glyph_name.into());
+        let category = match glyph.opentype_category {
+            OpenTypeCategory::Mark => Some(("Mark", "Nonspacing")),
+            OpenTypeCategory::Ligature => Some(("Letter", "Ligature")),
+            _ => None,
+        };
+        if let Some((category, _)) = category {
+            dictionary.insert("category".into(), category.into());
+        }
+        dictionary.insert("layers".into(), self.layers(glyph).into());
+        if let Some(postscript_name) = &glyph.postscript_name {
+            dictionary.insert("production".into(), postscript_name.clone().into());
+        }
+        if let Some((_, sub_category)) = category {
+            dictionary.insert("subCategory".into(), sub_category.into());
+        }
+        let codepoints: Vec<Value> = glyph.codepoints.iter().map(|c| (c as u32).into()).collect();
+        match <[Value; 1]>::try_from(codepoints) {
+            Ok([codepoint]) => {
+                dictionary.insert("unicode".into(), codepoint);
+            }
+            Err(codepoints) if !codepoints.is_empty() => {
+                dictionary.insert("unicode".into(), codepoints.into());
+            }
+            Err(_) => (),
+        }
+        let mut user_data = Dictionary::new();
+        if let Some(set) = &glyph.set {
+            user_data.insert(SET_KEY.into(), set.clone().into());
+        }
+        if glyph.opentype_category != OpenTypeCategory::Unassigned {
+            user_data.insert(
+                OPENTYPE_CATEGORY_KEY.into(),
+                glyph.opentype_category.to_string().into(),
+            );
+        }
+        if !user_data.is_empty() {
+            dictionary.insert("userData".into(), user_data.into());
+        }
+        dictionary
+    }
+
+    /// The layers of the sources that there are masters for, with background
+    /// sublayers put into the layer they are the background of.
+    fn layers(&self, glyph: &Glyph) -> Vec<Value> {
+        let background_name = |layer_name: &LayerName| match &layer_name.sublayer {
+            None => LayerName::sublayer(layer_name.source.clone(), "background"),
+            Some(sublayer) => {
+                LayerName::sublayer(layer_name.source.clone(), format!("{sublayer}.background"))
+            }
+        };
+        let is_nested_background = |layer_name: &LayerName| {
+            let owner = match layer_name.sublayer.as_deref() {
+                Some("background") => layer_name.main_layer(),
+                Some(sublayer) => match sublayer.strip_suffix(".background") {
+                    Some(owner) => LayerName::sublayer(layer_name.source.clone(), owner),
+                    None => return false,
+                },
+                None => return false,
+            };
+            glyph.layers.contains_key(&owner)
+        };
+        let mut layer_names: Vec<&LayerName> = glyph
+            .layers
+            .keys()
+            .filter(|layer_name| self.master_ids.contains_key(&*layer_name.source))
+            .filter(|layer_name| !is_nested_background(layer_name))
+            .collect();
+        layer_names.sort();
+        layer_names
+            .into_iter()
+            .map(|layer_name| {
+                let background = glyph.layers.get(&background_name(layer_name));
+                self.layer(layer_name, &glyph.layers[layer_name], background)
+                    .into()
+            })
+            .collect()
+    }
+
+    fn layer(
+        &self,
+        layer_name: &LayerName,
+        layer: &Layer,
+        background: Option<&Layer>,
+    ) -> Dictionary {
+        let master_id = &self.master_ids[&*layer_name.source];
+        let mut dictionary = Dictionary::new();
+        insert_anchors(&mut dictionary, layer);
+        if layer_name.sublayer.is_some() {
+            dictionary.insert("associatedMasterId".into(), master_id.clone().into());
+            let mut attributes = Dictionary::new();
+            if let Some(location) = &layer.location {
+                attributes.insert(
+                    "coordinates".into(),
+                    self.coordinates(Some(location)).into(),
+                );
+            }
+            if let Some(conditions) = &layer.condition {
+                let rules = self
+                    .axes
+                    .iter()
+                    .map(|(tag, _)| {
+                        let mut rule = Dictionary::new();
+                        let range = conditions.get(*tag).cloned().unwrap_or_default();
+                        if let Some(minimum) = range.minimum {
+                            rule.insert("min".into(), minimum.into());
+                        }
+                        if let Some(maximum) = range.maximum {
+                            rule.insert("max".into(), maximum.into());
+                        }
+                        rule.into()
+                    })
+                    .collect::<Vec<Value>>();
+                attributes.insert("axisRules".into(), rules.into());
+            }
+            if !attributes.is_empty() {
+                dictionary.insert("attr".into(), attributes.into());
+            }
+        }
+        if let Some(background) = background {
+            let mut background_dictionary = Dictionary::new();
+            insert_anchors(&mut background_dictionary, background);
+            insert_shapes(&mut background_dictionary, background);
+            dictionary.insert("background".into(), background_dictionary.into());
+        }
+        match &layer_name.sublayer {
+            None => {
+                dictionary.insert("layerId".into(), master_id.clone().into());
+            }
+            Some(sublayer) => {
+                dictionary.insert("layerId".into(), format!("{master_id}.{sublayer}").into());
+                dictionary.insert("name".into(), sublayer.to_string().into());
+            }
+        }
+        insert_shapes(&mut dictionary, layer);
+        if let Some(y_advance) = layer.y_advance {
+            dictionary.insert("vertWidth".into(), y_advance.into());
+        }
+        dictionary.insert("width".into(), layer.x_advance.unwrap_or_default().into());
+        dictionary
+    }
+}
+
+/// The name Glyphs gives registered axes, and the tag for all others.
+fn axis_name(tag: &str) -> &str {
+    match tag {
+        "wght" => "Weight",
+        "wdth" => "Width",
+        "ital" => "Italic",
+        "slnt" => "Slant",
+        "opsz" => "Optical Size",
+        _ => tag,
+    }
+}
+
+fn insert_anchors(dictionary: &mut Dictionary, layer: &Layer) {
+    if layer.anchors.is_empty() {
+        return;
+    }
+    let anchors = layer
+        .anchors
+        .iter()
+        .map(|anchor| {
+            let mut dictionary = Dictionary::new();
+            dictionary.insert("name".into(), anchor.name.clone().into());
+            dictionary.insert("pos".into(), vec![anchor.x.into(), anchor.y.into()].into());
+            dictionary.into()
+        })
+        .collect::<Vec<Value>>();
+    dictionary.insert("anchors".into(), anchors.into());
+}
+
+/// Paths and then components, as Glyphs 3 keeps them together.
+fn insert_shapes(dictionary: &mut Dictionary, layer: &Layer) {
+    let shapes: Vec<Value> = layer
+        .contours
+        .iter()
+        .map(path)
+        .chain(layer.components.iter().map(component_shape))
+        .map(Value::from)
+        .collect();
+    if !shapes.is_empty() {
+        dictionary.insert("shapes".into(), shapes.into());
+    }
+}
+
+/// A path, with the start point of closed ones moved to the end, where
+/// Glyphs has it.
+fn path(contour: &Contour) -> Dictionary {
+    let is_closed = contour
+        .points
+        .first()
+        .is_none_or(|point| point.typ != PointType::Move);
+    let mut nodes: Vec<Value> = contour
+        .points
+        .iter()
+        .map(|point| {
+            let typ = match point.typ {
+                PointType::Move | PointType::Line => "l",
+                PointType::Curve => "c",
+                PointType::QCurve => "q",
+                PointType::OffCurve => "o",
+            };
+            let typ = if point.smooth && point.typ != PointType::OffCurve {
+                format!("{typ}s")
+            } else {
+                typ.to_string()
+            };
+            vec![point.x.into(), point.y.into(), typ.into()].into()
+        })
+        .collect();
+    if is_closed {
+        nodes.rotate_left(1);
+    }
+    let mut dictionary = Dictionary::new();
+    dictionary.insert("closed".into(), (is_closed as i64).into());
+    dictionary.insert("nodes".into(), nodes.into());
+    dictionary
+}
+
+/// A component, with its transformation decomposed into what Glyphs shows.
+fn component_shape(component: &Component) -> Dictionary {
+    // Decomposing leaves rounding errors like 89.99999999999999 degrees.
+    let round = |value: f64| (value * 1e9).round() / 1e9;
+    let transform = DecomposedTransform::from(&component.transformation);
+    let mut dictionary = Dictionary::new();
+    if round(transform.rotation) != 0. {
+        dictionary.insert("angle".into(), round(transform.rotation).into());
+    }
+    if let Some(location) = &component.location {
+        let piece: Dictionary = location
+            .iter()
+            .map(|(axis, value)| (axis.clone(), Value::from(*value)))
+            .collect();
+        dictionary.insert("piece".into(), piece.into());
+    }
+    if transform.translate_x != 0. || transform.translate_y != 0. {
+        let position = vec![
+            round(transform.translate_x).into(),
+            round(transform.translate_y).into(),
+        ];
+        dictionary.insert("pos".into(), position.into());
+    }
+    dictionary.insert("ref".into(), component.name.to_string().into());
+    if round(transform.scale_x) != 1. || round(transform.scale_y) != 1. {
+        let scale = vec![
+            round(transform.scale_x).into(),
+            round(transform.scale_y).into(),
+        ];
+        dictionary.insert("scale".into(), scale.into());
+    }
+    if round(transform.skew_x) != 0. || round(transform.skew_y) != 0. {
+        let slant = vec![
+            round(transform.skew_x).into(),
+            round(transform.skew_y).into(),
+        ];
+        dictionary.insert("slant".into(), slant.into());
+    }
+    dictionary
+}
+
+impl GlyphsFont {
+    fn master(&self, id: &str) -> Option<&Master> {
+        self.masters.iter().find(|master| master.id == id)
+    }
+
+    fn glyph(&self, glyph: &Dictionary) -> Option<ImportedGlyph> {
+        let name = string(glyph, "glyphname")?.to_string();
+        let codepoints = match glyph.get("unicode") {
+            // Glyphs 3 writes decimal numbers, a list of them for several.
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter_map(|value| char::from_u32(number(value)? as u32))
+                .collect(),
+            Some(value) if self.is_glyphs3 => number(value)
+                .and_then(|value| char::from_u32(value as u32))
+                .into_iter()
+                .collect(),
+            // Glyphs 2 writes hex values, separated by commas for several.
+            Some(Value::String(hex)) => hex
+                .split(',')
+                .filter_map(|hex| char::from_u32(u32::from_str_radix(hex.trim(), 16).ok()?))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let user_data = glyph.get("userData").and_then(Value::as_dictionary);
+        let user_data_string = |key| user_data.and_then(|user_data| string(user_data, key));
+        let set = user_data_string(SET_KEY).map(String::from).or_else(|| {
+            let script = string(glyph, "script")?;
+            let mut chars = script.chars();
+            let script: String = chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+            Some(self.script_sets.get(&script).cloned().unwrap_or(script))
+        });
+        let opentype_category = user_data_string(OPENTYPE_CATEGORY_KEY)
+            .and_then(|category| category.parse().ok())
+            .or_else(|| {
+                let category = string(glyph, "category")?;
+                Some(match (category, string(glyph, "subCategory")) {
+                    (_, Some("Ligature")) => OpenTypeCategory::Ligature,
+                    ("Mark", Some("Spacing")) => OpenTypeCategory::Base,
+                    ("Mark", _) => OpenTypeCategory::Mark,
+                    _ => OpenTypeCategory::Base,
+                })
+            });
+
+        let mut layers = Vec::new();
+        for layer in glyph
+            .get("layers")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_dictionary)
+        {
+            let layer_id = string(layer, "layerId").unwrap_or_default();
+            let mut fontgarden_layer = self.layer(layer);
+            let layer_name = match self.master(layer_id) {
+                Some(master) => LayerName::main(master.name.as_str()),
+                None => {
+                    let Some(master) = string(layer, "associatedMasterId")
+                        .and_then(|master_id| self.master(master_id))
+                    else {
+                        continue;
+                    };
+                    let name = string(layer, "name").unwrap_or(layer_id);
+                    let attributes = layer.get("attr").and_then(Value::as_dictionary);
+                    // Glyphs 3 names layers after their attributes, but they
+                    // can be renamed, which the name is kept for.
+                    let given_name = Some(name)
+                        .filter(|name| attributes.is_some() && !name.contains(['{', '[', ']']));
+                    if let Some(coordinates) = self.brace_coordinates(name, attributes) {
+                        let values: Vec<String> =
+                            coordinates.iter().map(|value| value.to_string()).collect();
+                        fontgarden_layer.location = Some(
+                            self.axes
+                                .iter()
+                                .zip(coordinates)
+                                .map(|(axis, value)| (axis.tag.clone(), value))
+                                .collect(),
+                        );
+                        let sublayer = given_name
+                            .map(String::from)
+                            .unwrap_or_else(|| format!("{{{}}}", values.join(", ")));
+                        LayerName::sublayer(master.name.as_str(), sublayer)
+                    } else if let Some(conditions) = self.bracket_conditions(name, attributes) {
+                        let ranges: Vec<String> = conditions
+                            .iter()
+                            .map(|(tag, range)| {
+                                let bound = |bound: Option<f64>| {
+                                    bound.map(|value| value.to_string()).unwrap_or_default()
+                                };
+                                format!("{tag}={}-{}", bound(range.minimum), bound(range.maximum))
+                            })
+                            .collect();
+                        fontgarden_layer.condition = Some(conditions);
+                        let sublayer = given_name
+                            .map(String::from)
+                            .unwrap_or_else(|| format!("[{}]", ranges.join(",")));
+                        LayerName::sublayer(master.name.as_str(), sublayer)
+                    } else {
+                        LayerName::sublayer(master.name.as_str(), name)
+                    }
+                }
+            };
+            if let Some(background) = layer.get("background").and_then(Value::as_dictionary) {
+                let background = self.layer(background);
+                if !background.is_empty() {
+                    let background_name = match &layer_name.sublayer {
+                        None => LayerName::sublayer(layer_name.source.clone(), "background"),
+                        Some(sublayer) => LayerName::sublayer(
+                            layer_name.source.clone(),
+                            format!("{sublayer}.background"),
+                        ),
+                    };
+                    layers.push((background_name, background));
+                }
+            }
+            layers.push((layer_name, fontgarden_layer));
+        }
+
+        Some(ImportedGlyph {
+            name,
+            codepoints: Codepoints::new(codepoints),
+            set,
+            opentype_category,
+            postscript_name: string(glyph, "production").map(String::from),
+            layers,
+            ..Default::default()
+        })
+    }
+
+    /// The location of a brace layer, from its attributes in Glyphs 3 or its
+    /// name, like "Light {250}".
+    fn brace_coordinates(&self, name: &str, attributes: Option<&Dictionary>) -> Option<Vec<f64>> {
+        if let Some(coordinates) = attributes
+            .and_then(|attributes| attributes.get("coordinates"))
+            .and_then(Value::as_array)
+        {
+            return Some(coordinates.iter().filter_map(number).collect());
+        }
+        let start = name.find('{')?;
+        let end = start + name[start..].find('}')?;
+        name[start + 1..end]
+            .split(',')
+            .map(|value| value.trim().parse().ok())
+            .collect()
+    }
+
+    /// The axis ranges of a bracket layer, from its attributes in Glyphs 3 or
+    /// its name, like "Bold [600]" for a minimum or "Bold ]600]" for a maximum
+    /// on the first axis.
+    fn bracket_conditions(
+        &self,
+        name: &str,
+        attributes: Option<&Dictionary>,
+    ) -> Option<ConditionSet> {
+        if let Some(rules) = attributes
+            .and_then(|attributes| attributes.get("axisRules"))
+            .and_then(Value::as_array)
+        {
+            let conditions: ConditionSet = self
+                .axes
+                .iter()
+                .zip(rules)
+                .filter_map(|(axis, rule)| {
+                    let rule = rule.as_dictionary()?;
+                    let range = AxisRange {
+                        minimum: rule.get("min").and_then(number),
+                        maximum: rule.get("max").and_then(number),
+                    };
+                    (range != AxisRange::default()).then(|| (axis.tag.clone(), range))
+                })
+                .collect();
+            return (!conditions.is_empty()).then_some(conditions);
+        }
+        let axis = self.axes.first()?;
+        let start = name.find(['[', ']'])?;
+        let end = start + 1 + name[start + 1..].find(']')?;
+        let value = name[start + 1..end].trim().parse().ok()?;
+        let range = if name[start..].starts_with('[') {
+            AxisRange {
+                minimum: Some(value),
+                maximum: None,
+            }
+        } else {
+            AxisRange {
+                minimum: None,
+                maximum: Some(value),
+            }
+        };
+        Some(ConditionSet::from([(axis.tag.clone(), range)]))
+    }
+
+    /// The outlines, components, anchors and advances of a layer or background.
+    fn layer(&self, layer: &Dictionary) -> Layer {
+        // Glyphs 3 has paths and components together in `shapes`.
+        let shapes: Vec<&Dictionary> = ["shapes", "paths", "components"]
+            .iter()
+            .filter_map(|key| layer.get(key).and_then(Value::as_array))
+            .flatten()
+            .filter_map(Value::as_dictionary)
+            .collect();
+        Layer {
+            anchors: layer
+                .get("anchors")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_dictionary)
+                .filter_map(|anchor| {
+                    let position = anchor.get("pos").or_else(|| anchor.get("position"));
+                    let [x, y] = position.map_or([0., 0.], point);
+                    Some(Anchor {
+                        name: string(anchor, "name")?.to_string(),
+                        x,
+                        y,
+                        extra: ExtraFields::new(),
+                    })
+                })
+                .collect(),
+            components: shapes.iter().filter_map(|shape| component(shape)).collect(),
+            contours: shapes
+                .iter()
+                .filter(|shape| shape.contains_key("nodes"))
+                .map(|path| contour(path))
+                .collect(),
+            x_advance: layer.get("width").and_then(number),
+            y_advance: layer.get("vertWidth").and_then(number),
+            ..Default::default()
+        }
+    }
+}
+
+/// Read a Glyphs file, or the files of a Glyphs package put together into one.
+fn load(path: &Path) -> Result<Dictionary, GlyphsError> {
+    let read = |path: &Path| -> Result<Dictionary, GlyphsError> {
+        let text = std::fs::read_to_string(path).map_err(|e| GlyphsError::Io(path.into(), e))?;
+        openstep::parse(&text)
+            .map_err(|e| GlyphsError::Parse(path.into(), e))?
+            .into_dictionary()
+            .ok_or_else(|| GlyphsError::Malformed(path.into(), "expected a dictionary"))
+    };
+    if !path.is_dir() {
+        return read(path);
+    }
+    let mut font = read(&path.join("fontinfo.plist"))?;
+    let glyphs_dir = path.join("glyphs");
+    let io_error = |e| GlyphsError::Io(glyphs_dir.clone(), e);
+    let mut glyphs = Vec::new();
+    for entry in std::fs::read_dir(&glyphs_dir).map_err(io_error)? {
+        let glyph_path = entry.map_err(io_error)?.path();
+        if glyph_path.extension().is_some_and(|ext| ext == "glyph") {
+            glyphs.push(Value::Dictionary(read(&glyph_path)?));
+        }
+    }
+    font.insert("glyphs".into(), Value::Array(glyphs));
+    Ok(font)
+}
+
+fn string<'a>(dictionary: &'a Dictionary, key: &str) -> Option<&'a str> {
+    dictionary.get(key).and_then(Value::as_string)
+}
+
+fn custom_parameter<'a>(dictionary: &'a Dictionary, name: &str) -> Option<&'a Value> {
+    dictionary
+        .get("customParameters")?
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_dictionary)
+        .find(|parameter| string(parameter, "name") == Some(name))?
+        .get("value")
+}
+
+/// Read numbers written as a list, or as a string like "{1, 2}" in Glyphs 2.
+fn numbers(value: &Value) -> Vec<f64> {
+    match value {
+        Value::Array(values) => values.iter().filter_map(number).collect(),
+        Value::String(s) => s
+            .trim_matches(|c| c == '{' || c == '}')
+            .split(',')
+            .filter_map(|value| value.trim().parse().ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn point(value: &Value) -> [f64; 2] {
+    match numbers(value)[..] {
+        [x, y, ..] => [x, y],
+        _ => [0., 0.],
+    }
+}
+
+fn axes(font: &Dictionary, masters: &[&Dictionary], is_glyphs3: bool) -> Vec<Axis> {
+    let axis = |axis: &Value, tag_key: &str, value_key| {
+        Some(Axis {
+            tag: string(axis.as_dictionary()?, tag_key)?.to_string(),
+            value_key,
+        })
+    };
+    if is_glyphs3 {
+        return font
+            .get("axes")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| axis(value, "tag", None))
+            .collect();
+    }
+    if let Some(axes) = custom_parameter(font, "Axes").and_then(Value::as_array) {
+        return axes
+            .iter()
+            .zip(GLYPHS2_VALUE_KEYS)
+            .filter_map(|(value, value_key)| axis(value, "Tag", Some(value_key)))
+            .collect();
+    }
+    // Without the parameter, there is a weight axis and whichever of the
+    // others the masters have values for.
+    ["wght", "wdth", "XXXX"]
+        .into_iter()
+        .zip(GLYPHS2_VALUE_KEYS)
+        .enumerate()
+        .filter(|(index, (_, value_key))| {
+            *index == 0 || masters.iter().any(|master| master.contains_key(value_key))
+        })
+        .map(|(_, (tag, value_key))| Axis {
+            tag: tag.into(),
+            value_key: Some(value_key),
+        })
+        .collect()
+}
+
+fn master_name(master: &Dictionary, is_glyphs3: bool) -> String {
+    if let Some(name) = string(master, "name").filter(|_| is_glyphs3) {
+        return name.to_string();
+    }
+    if let Some(name) = custom_parameter(master, "Master Name").and_then(Value::as_string) {
+        return name.to_string();
+    }
+    let parts: Vec<&str> = ["weight", "width", "custom"]
+        .iter()
+        .filter_map(|key| string(master, key))
+        .filter(|part| !part.is_empty() && *part != "Regular")
+        .collect();
+    if parts.is_empty() {
+        "Regular".into()
+    } else {
+        parts.join(" ")
+    }
+}
+
+fn master_location(master: &Dictionary, axes: &[Axis]) -> Location {
+    let values = master.get("axesValues").map(numbers).unwrap_or_default();
+    axes.iter()
+        .enumerate()
+        .map(|(index, axis)| {
+            let value = match axis.value_key {
+                Some(value_key) => master.get(value_key).and_then(number).unwrap_or(
+                    // Glyphs 2 leaves out values at their defaults.
+                    match value_key {
+                        "weightValue" | "widthValue" => 100.,
+                        _ => 0.,
+                    },
+                ),
+                None => values.get(index).copied().unwrap_or_default(),
+            };
+            (axis.tag.clone(), value)
+        })
+        .collect()
+}
+
+/// A path, with the start point moved to the front as in UFOs: Glyphs writes
+/// it last in closed paths.
+fn contour(path: &Dictionary) -> Contour {
+    let mut points: Vec<ContourPoint> = path
+        .get("nodes")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|node| {
+            let (x, y, typ) = match node {
+                // Glyphs 3: (x, y, type), with types like "l" or "cs".
+                Value::Array(values) => (
+                    number(values.first()?)?,
+                    number(values.get(1)?)?,
+                    values.get(2)?.as_string()?.to_string(),
+                ),
+                // Glyphs 2: "x y TYPE [SMOOTH]".
+                Value::String(node) => {
+                    let mut parts = node.split_whitespace();
+                    let x = parts.next()?.parse().ok()?;
+                    let y = parts.next()?.parse().ok()?;
+                    (x, y, parts.collect::<Vec<_>>().join(" "))
+                }
+                _ => return None,
+            };
+            let (typ, smooth) = match typ.as_str() {
+                "l" | "LINE" => (PointType::Line, false),
+                "ls" | "LINE SMOOTH" => (PointType::Line, true),
+                "c" | "CURVE" => (PointType::Curve, false),
+                "cs" | "CURVE SMOOTH" => (PointType::Curve, true),
+                "q" | "QCURVE" => (PointType::QCurve, false),
+                "qs" | "QCURVE SMOOTH" => (PointType::QCurve, true),
+                _ => (PointType::OffCurve, false),
+            };
+            Some(ContourPoint { x, y, typ, smooth })
+        })
+        .collect();
+    let is_closed = path.get("closed").and_then(number) == Some(1.);
+    if is_closed {
+        points.rotate_right(1);
+    } else if let Some(first) = points.first_mut() {
+        first.typ = PointType::Move;
+    }
+    Contour {
+        points,
+        extra: ExtraFields::new(),
+    }
+}
+
+/// A component, with the transformation given by position, scale, angle and
+/// slant in Glyphs 3 or as a matrix in Glyphs 2.
+fn component(shape: &Dictionary) -> Option<Component> {
+    let name = string(shape, "ref").or_else(|| string(shape, "name"))?;
+    if shape.contains_key("nodes") {
+        return None;
+    }
+    let affine = match shape.get("transform").map(numbers).as_deref() {
+        Some(&[a, b, c, d, e, f]) => Affine::new([a, b, c, d, e, f]),
+        _ => {
+            let [x, y] = shape.get("pos").map_or([0., 0.], point);
+            let [scale_x, scale_y] = shape.get("scale").map_or([1., 1.], point);
+            let [slant_x, slant_y] = shape.get("slant").map_or([0., 0.], point);
+            let angle = shape.get("angle").and_then(number).unwrap_or_default();
+            Affine::translate((x, y))
+                * Affine::rotate(angle.to_radians())
+                * Affine::scale_non_uniform(scale_x, scale_y)
+                * Affine::new([
+                    1.,
+                    slant_y.to_radians().tan(),
+                    slant_x.to_radians().tan(),
+                    1.,
+                    0.,
+                    0.,
+                ])
+        }
+    };
+    let location: Location = shape
+        .get("piece")
+        .and_then(Value::as_dictionary)
+        .into_iter()
+        .flatten()
+        .filter_map(|(axis, value)| Some((axis.clone(), number(value)?)))
+        .collect();
+    Some(Component {
+        name: name.into(),
+        transformation: affine.into(),
+        location: (!location.is_empty()).then_some(location),
+        extra: ExtraFields::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_glyphs3_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Test.glyphs");
+        std::fs::write(
+            &path,
+            r#"{
+.formatVersion = 3;
+axes = ({name = Weight; tag = wght;});
+fontMaster = (
+{id = m01; name = Light; axesValues = (300);},
+{id = m02; name = Bold; axesValues = (700);}
+);
+glyphs = (
+{
+glyphname = A;
+unicode = 65;
+script = latin;
+production = uni0041;
+layers = (
+{layerId = m01; width = 500; shapes = (
+{closed = 1; nodes = ((100,0,l),(200,0,l),(150,100,l));}
+); anchors = ({name = top; pos = (150,100);});
+background = {shapes = ({closed = 1; nodes = ((0,0,l),(10,0,l),(5,5,l));});};
+},
+{layerId = m02; width = 600; shapes = ({ref = B; pos = (10,20); angle = 90;});},
+{layerId = L1; associatedMasterId = m01; name = "{500}"; attr = {coordinates = (500);}; width = 550;},
+{layerId = L2; associatedMasterId = m01; name = "Light [600]"; attr = {axisRules = ({min = 600;});}; width = 510;}
+);
+},
+{glyphname = acutecomb; category = Mark; unicode = (769, 770); layers = ({layerId = m01; width = 0;});}
+);
+unitsPerEm = 1000;
+}"#,
+        )
+        .unwrap();
+
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_glyphs(&path, &ImportOptions::default())
+            .unwrap();
+
+        assert_eq!(fontgarden.config.axes["wght"].minimum, 300.);
+        assert_eq!(fontgarden.config.axes["wght"].default, 300.);
+        assert_eq!(fontgarden.config.sources["Bold"].location["wght"], 700.);
+        let glyph = &fontgarden.glyphs["A"];
+        assert_eq!(glyph.set.as_deref(), Some("Latin"));
+        assert_eq!(glyph.postscript_name.as_deref(), Some("uni0041"));
+        let mut layer_names: Vec<String> = glyph.layers.keys().map(|n| n.to_string()).collect();
+        layer_names.sort();
+        assert_eq!(
+            layer_names,
+            [
+                "Bold",
+                "Light",
+                "Light.[wght=600-]",
+                "Light.background",
+                "Light.{500}"
+            ]
+        );
+        let light = &glyph.layers[&LayerName::main("Light")];
+        let points = &light.contours[0].points;
+        assert_eq!(
+            (points[0].x, points[0].typ.clone()),
+            (150., PointType::Line)
+        );
+        assert_eq!(light.anchors[0].x, 150.);
+        let bold = &glyph.layers[&LayerName::main("Bold")];
+        let transformation = &bold.components[0].transformation;
+        assert!((transformation.xy_scale - 1.).abs() < 1e-9);
+        assert_eq!(transformation.x_offset, 10.);
+        let brace = &glyph.layers[&LayerName::sublayer("Light", "{500}")];
+        assert_eq!(
+            brace.location,
+            Some(Location::from([("wght".into(), 500.)]))
+        );
+        let bracket = &glyph.layers[&LayerName::sublayer("Light", "[wght=600-]")];
+        assert_eq!(
+            bracket.condition.as_ref().unwrap()["wght"].minimum,
+            Some(600.)
+        );
+        let acutecomb = &fontgarden.glyphs["acutecomb"];
+        assert_eq!(acutecomb.opentype_category, OpenTypeCategory::Mark);
+        assert_eq!(acutecomb.codepoints.len(), 2);
+    }
+
+    #[test]
+    fn export_and_import_glyphs3_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let point = |x, y, typ, smooth| ContourPoint { x, y, typ, smooth };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            "axes.wght = { minimum = 400, default = 400, maximum = 900 }\n\
+             sources.Regular.location = { wght = 400 }\n\
+             sources.Bold.location = { wght = 900 }",
+        )
+        .unwrap();
+        let layer = Layer {
+            anchors: vec![Anchor {
+                name: "top".into(),
+                x: 50.,
+                y: 100.,
+                extra: ExtraFields::new(),
+            }],
+            components: vec![Component {
+                name: "acutecomb".into(),
+                transformation: Affine::new([2., 0., 0., 2., 10., 20.]).into(),
+                location: None,
+                extra: ExtraFields::new(),
+            }],
+            contours: vec![
+                Contour {
+                    points: vec![
+                        point(0., 0., PointType::Curve, true),
+                        point(0., 100., PointType::OffCurve, false),
+                        point(100., 100., PointType::OffCurve, false),
+                        point(100., 0., PointType::Curve, false),
+                    ],
+                    extra: ExtraFields::new(),
+                },
+                Contour {
+                    points: vec![
+                        point(0., 0., PointType::Move, false),
+                        point(10.5, 0., PointType::Line, false),
+                    ],
+                    extra: ExtraFields::new(),
+                },
+            ],
+            x_advance: Some(500.),
+            ..Default::default()
+        };
+        let mut glyph = Glyph {
+            set: Some("Latin".into()),
+            codepoints: Codepoints::new(['A']),
+            opentype_category: OpenTypeCategory::Base,
+            postscript_name: Some("uni0041".into()),
+            ..Default::default()
+        };
+        glyph
+            .layers
+            .insert(LayerName::main("Regular"), layer.clone());
+        glyph.layers.insert(LayerName::main("Bold"), layer.clone());
+        glyph.layers.insert(
+            LayerName::sublayer("Bold", "Semibold"),
+            Layer {
+                location: Some(Location::from([("wght".into(), 600.)])),
+                ..layer.clone()
+            },
+        );
+        glyph.layers.insert(
+            LayerName::sublayer("Regular", "alt"),
+            Layer {
+                condition: Some(ConditionSet::from([(
+                    "wght".into(),
+                    AxisRange {
+                        minimum: Some(600.),
+                        maximum: None,
+                    },
+                )])),
+                ..layer.clone()
+            },
+        );
+        glyph.layers.insert(
+            LayerName::sublayer("Regular", "background"),
+            Layer {
+                anchors: Vec::new(),
+                x_advance: None,
+                ..layer.clone()
+            },
+        );
+        fontgarden.glyphs.insert("A".into(), glyph);
+        let mut acutecomb = Glyph {
+            codepoints: Codepoints::new(['\u{301}', '\u{302}']),
+            opentype_category: OpenTypeCategory::Mark,
+            ..Default::default()
+        };
+        acutecomb.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                x_advance: Some(0.),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("acutecomb".into(), acutecomb);
+
+        let path = dir.path().join("Test.glyphs");
+        fontgarden.export_glyphs(&path, "Test").unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let font = openstep::parse(&text).unwrap().into_dictionary().unwrap();
+        assert_eq!(string(&font, "familyName"), Some("Test"));
+        assert_eq!(
+            custom_parameter(&font, "Variable Font Origin").and_then(Value::as_string),
+            Some("m02")
+        );
+        assert!(text.contains("nodes = (\n(0,100,o),\n(100,100,o),\n(100,0,c),\n(0,0,cs)\n);"));
+        assert!(text.contains("{\npos = (10,20);\nref = acutecomb;\nscale = (2,2);\n}"));
+        assert!(text.contains("category = Mark;"));
+        assert!(text.contains("unicode = (769,770);"));
+
+        let mut imported = Fontgarden::new();
+        imported
+            .import_glyphs(&path, &ImportOptions::default())
+            .unwrap();
+        assert_eq!(imported.glyphs, fontgarden.glyphs);
+        assert_eq!(imported.config.axes, fontgarden.config.axes);
+        assert_eq!(imported.config.sources, fontgarden.config.sources);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::structs::{Fontgarden, LayerName};
+
+/// Output formats for the component dependency graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT, with edges pointing from composites to their components.
+    #[default]
+    Dot,
+    /// A JSON object mapping composites to the glyphs they use as components.
+    Json,
+}
+
+impl Fontgarden {
+    /// Map every composite glyph to the glyphs it uses as components in any layer.
+    ///
+    /// With `set_name`, only composites in that set and those nested in it are
+    /// included; their components
+    /// may belong to other sets.
+    pub fn component_graph(&self, set_name: Option<&str>) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut graph: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            if let Some(set_name) = set_name {
+                if !glyph.is_in_set(set_name) {
+                    continue;
+                }
+            }
+            for layer in glyph.layers.values() {
+                for component in &layer.components {
+                    graph
+                        .entry(&**glyph_name)
+                        .or_default()
+                        .insert(&*component.name);
+                }
+            }
+        }
+        graph
+    }
+}
+
+impl Fontgarden {
+    /// Find the glyphs using `glyph_name` as a component, with the layers they do
+    /// so in. With `recursive`, glyphs using those glyphs are included as well.
+    pub fn used_by(
+        &self,
+        glyph_name: &str,
+        recursive: bool,
+    ) -> BTreeMap<&str, BTreeSet<&LayerName>> {
+        let mut users: BTreeMap<&str, BTreeSet<&LayerName>> = BTreeMap::new();
+        let mut stack = vec![glyph_name];
+        while let Some(base_name) = stack.pop() {
+            for (user_name, glyph) in &self.glyphs {
+                for (layer_name, layer) in &glyph.layers {
+                    if !layer.components.iter().any(|c| &*c.name == base_name) {
+                        continue;
+                    }
+                    let is_new = !users.contains_key(&**user_name);
+                    users.entry(&**user_name).or_default().insert(layer_name);
+                    if recursive && is_new && &**user_name != glyph_name {
+                        stack.push(user_name);
+                    }
+                }
+            }
+        }
+        users
+    }
+}
+
+/// Format the component graph as Graphviz DOT.
+pub fn graph_to_dot(graph: &BTreeMap<&str, BTreeSet<&str>>) -> String {
+    let mut dot = String::from("digraph components {\n");
+    for (composite, components) in graph {
+        for component in components {
+            dot.push_str(&format!(
+                "  {} -> {};\n",
+                dot_id(composite),
+                dot_id(component)
+            ));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Quote a glyph name as a DOT identifier. Only quotes and backslashes need
+/// escaping; anything else, like non-ASCII letters, goes in as it is.
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    #[test]
+    fn find_users() {
+        let composite = || LayerBuilder::new().component("a", 0., 0.);
+        let fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "a",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new()),
+            )
+            .glyph(
+                "aacute",
+                GlyphBuilder::new()
+                    .layer("Regular", composite())
+                    .layer("Bold", composite()),
+            )
+            .glyph(
+                "aacute.sc",
+                GlyphBuilder::new()
+                    .layer("Regular", LayerBuilder::new().component("aacute", 0., 0.)),
+            )
+            .glyph(
+                "b",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new().width(500.)),
+            )
+            .build();
+        let regular = LayerName::main("Regular");
+        let bold = LayerName::main("Bold");
+
+        assert_eq!(
+            fontgarden.used_by("a", false),
+            BTreeMap::from([("aacute", BTreeSet::from([&bold, &regular]))])
+        );
+        assert_eq!(
+            fontgarden.used_by("a", true),
+            BTreeMap::from([
+                ("aacute", BTreeSet::from([&bold, &regular])),
+                ("aacute.sc", BTreeSet::from([&regular])),
+            ])
+        );
+        assert!(fontgarden.used_by("b", true).is_empty());
+        assert!(fontgarden.used_by("nonexistent", true).is_empty());
+    }
+
+    #[test]
+    fn format_dot() {
+        let graph = BTreeMap::from([
+            ("aacute", BTreeSet::from(["a", "acutecomb"])),
+            ("a.sc-cy", BTreeSet::from(["a.sc"])),
+            (r#"quote"d"#, BTreeSet::from([r"back\slash", "é"])),
+        ]);
+        assert_eq!(
+            graph_to_dot(&graph),
+            r#"digraph components {
+  "a.sc-cy" -> "a.sc";
+  "aacute" -> "a";
+  "aacute" -> "acutecomb";
+  "quote\"d" -> "back\\slash";
+  "quote\"d" -> "é";
+}
+"#
+        );
+        assert_eq!(graph_to_dot(&BTreeMap::new()), "digraph components {\n}\n");
+    }
+}
@@ -0,0 +1,172 @@
+//! Content hashes of glyphs, kept in `hashes.json` at the root of a fontgarden
+//! so that other tools can tell which glyphs changed between two revisions
+//! without loading and comparing their layers.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::structs::{Fontgarden, Glyph, Layer, OpenTypeCategory};
+
+/// The contents of `hashes.json`: glyph name to content hash, written anew on
+/// every save.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct HashIndex(pub BTreeMap<String, String>);
+
+impl HashIndex {
+    pub const FILENAME: &str = "hashes.json";
+}
+
+/// What goes into the hash of a glyph, in a fixed order independent of how
+/// the glyph was loaded.
+#[derive(Serialize)]
+struct HashedGlyph<'a> {
+    codepoints: Vec<u32>,
+    opentype_category: &'a OpenTypeCategory,
+    postscript_name: &'a Option<String>,
+    set: &'a Option<String>,
+    advance_link: &'a Option<String>,
+    tags: &'a BTreeSet<String>,
+    layers: BTreeMap<String, Cow<'a, Layer>>,
+}
+
+impl Glyph {
+    /// The SHA-256 of the glyph's metadata and layers as a hex string, the same
+    /// for the same glyph data on every platform and run.
+    pub fn content_hash(&self) -> String {
+        self.saved_content_hash(None)
+    }
+
+    /// The content hash of the glyph as saving writes it with the configured
+    /// precision: with the layers rounded and empty layers, which get no
+    /// file, left out. Loading the saved glyph gives the same hash.
+    pub fn saved_content_hash(&self, precision: Option<u32>) -> String {
+        let mut codepoints: Vec<u32> = self.codepoints.iter().map(u32::from).collect();
+        codepoints.sort();
+        let hashed = HashedGlyph {
+            codepoints,
+            opentype_category: &self.opentype_category,
+            postscript_name: &self.postscript_name,
+            set: &self.set,
+            advance_link: &self.advance_link,
+            tags: &self.tags,
+            layers: self
+                .layers
+                .iter()
+                .filter(|(_, layer)| !layer.is_empty())
+                .map(|(layer_name, layer)| {
+                    let layer = match precision {
+                        Some(decimals) => Cow::Owned(layer.with_precision(decimals)),
+                        None => Cow::Borrowed(layer),
+                    };
+                    (layer_name.to_string(), layer)
+                })
+                .collect(),
+        };
+        let json = serde_json::to_vec(&hashed).expect("glyph data is valid JSON");
+        Sha256::digest(json)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+impl Fontgarden {
+    /// The content hashes of all glyphs, as saved.
+    pub fn content_hashes(&self) -> HashIndex {
+        HashIndex(
+            self.glyphs
+                .iter()
+                .map(|(glyph_name, glyph)| {
+                    let hash = glyph.saved_content_hash(self.config.precision);
+                    (glyph_name.to_string(), hash)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        structs::LayerName,
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn hash_glyph_content() {
+        let layer = |x_advance: f64| Layer {
+            x_advance: Some(x_advance),
+            ..Default::default()
+        };
+        let glyph = Glyph {
+            codepoints: norad::Codepoints::new(['a', 'A']),
+            layers: HashMap::from([
+                (LayerName::main("Light"), layer(500.)),
+                (LayerName::sublayer("Light", "background"), layer(510.)),
+                (LayerName::main("Bold"), layer(600.)),
+            ]),
+            ..Default::default()
+        };
+        let hash = glyph.content_hash();
+        assert_eq!(hash.len(), 64);
+
+        let mut reordered = Glyph {
+            codepoints: norad::Codepoints::new(['A', 'a']),
+            ..glyph.clone()
+        };
+        let mut layers: Vec<(LayerName, Layer)> = glyph.layers.clone().into_iter().collect();
+        layers.reverse();
+        reordered.layers = layers.into_iter().collect();
+        assert_eq!(reordered.content_hash(), hash);
+
+        let mut tagged = glyph.clone();
+        tagged.tags.insert("review".into());
+        assert_ne!(tagged.content_hash(), hash);
+
+        let mut moved = glyph.clone();
+        moved.layers.insert(LayerName::main("Bold"), layer(610.));
+        assert_ne!(moved.content_hash(), hash);
+    }
+
+    #[test]
+    fn hash_glyphs_as_saved_with_precision() {
+        let fontgarden = FontgardenBuilder::new()
+            .config("precision = 0\n")
+            .glyph(
+                "J",
+                GlyphBuilder::new()
+                    .layer(
+                        "Regular",
+                        LayerBuilder::new().width(500.4).polygon(&[
+                            (10.2, 0.),
+                            (250.6, 700.3),
+                            (480.5, 0.),
+                        ]),
+                    )
+                    .layer("Regular.empty", LayerBuilder::new()),
+            )
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+
+        let loaded = Fontgarden::load(&path).unwrap();
+        assert!(loaded.normalization_changes(&path).unwrap().is_empty());
+        let saved: BTreeMap<String, String> =
+            serde_json::from_slice(&std::fs::read(path.join(HashIndex::FILENAME)).unwrap())
+                .unwrap();
+        assert_eq!(saved, loaded.content_hashes().0);
+        assert_eq!(saved["J"], loaded.glyphs["J"].content_hash());
+        assert_ne!(saved["J"], fontgarden.glyphs["J"].content_hash());
+    }
+}
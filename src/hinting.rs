@@ -0,0 +1,327 @@
+//! Hinting data that the garden keeps but doesn't use, so that importing and
+//! exporting UFOs doesn't strip hinting work: the TrueType instructions and
+//! PostScript hints of glyph layers, and the font-wide data of sources in
+//! `hinting.<source>.json`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::LoadError, filenames::name_to_filename, storage::Storage};
+
+/// The glyph and font lib key of TrueType instructions. Font-wide, it holds
+/// the control values and the font and pre-programs.
+pub const TRUETYPE_LIB_KEY: &str = "public.truetype.instructions";
+
+/// Glyph lib keys of PostScript hints, as written by psautohint and as in the
+/// UFO specification.
+pub const POSTSCRIPT_HINT_LIB_KEYS: [&str; 2] =
+    ["com.adobe.type.autohint", "public.postscript.hints"];
+
+/// The TrueType instructions of a glyph, from the glyph lib of UFOs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TrueTypeInstructions {
+    pub format_version: String,
+    /// The hash of the outline the instructions were made for, so that
+    /// compilers can drop them when the outline changed.
+    pub id: String,
+    /// The instructions in fontTools assembly.
+    pub assembly: String,
+}
+
+impl TrueTypeInstructions {
+    pub fn from_lib(lib: &plist::Dictionary) -> Option<Self> {
+        let instructions = lib.get(TRUETYPE_LIB_KEY)?.as_dictionary()?;
+        let string = |key: &str| {
+            instructions
+                .get(key)
+                .and_then(|value| value.as_string())
+                .map(String::from)
+        };
+        Some(Self {
+            format_version: string("formatVersion")?,
+            id: string("id").unwrap_or_default(),
+            assembly: string("assembly").unwrap_or_default(),
+        })
+    }
+
+    pub fn to_lib(&self) -> plist::Value {
+        let mut instructions = plist::Dictionary::new();
+        instructions.insert("formatVersion".into(), self.format_version.clone().into());
+        instructions.insert("id".into(), self.id.clone().into());
+        instructions.insert("assembly".into(), self.assembly.clone().into());
+        instructions.into()
+    }
+}
+
+/// Take the PostScript hints from a glyph lib, by lib key.
+pub fn postscript_hints_from_lib(lib: &plist::Dictionary) -> BTreeMap<String, serde_json::Value> {
+    POSTSCRIPT_HINT_LIB_KEYS
+        .iter()
+        .filter_map(|key| {
+            let value = serde_json::to_value(lib.get(key)?).ok()?;
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Put PostScript hints back into a glyph lib.
+pub fn postscript_hints_to_lib(
+    hints: &BTreeMap<String, serde_json::Value>,
+    lib: &mut plist::Dictionary,
+) {
+    for (key, value) in hints {
+        match serde_json::from_value::<plist::Value>(value.clone()) {
+            Ok(value) => {
+                lib.insert(key.clone(), value);
+            }
+            Err(e) => eprintln!("warning: not exporting PostScript hints {key}: {e}"),
+        }
+    }
+}
+
+/// The PostScript hinting parameters of a source, from the fontinfo of UFOs.
+/// Values left out there are left out here, too.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostScriptHinting {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blue_values: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_blues: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family_blues: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family_other_blues: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stem_snap_h: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stem_snap_v: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blue_fuzz: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blue_shift: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blue_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_bold: Option<bool>,
+}
+
+impl PostScriptHinting {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn from_font_info(font_info: &norad::FontInfo) -> Self {
+        Self {
+            blue_values: font_info.postscript_blue_values.clone(),
+            other_blues: font_info.postscript_other_blues.clone(),
+            family_blues: font_info.postscript_family_blues.clone(),
+            family_other_blues: font_info.postscript_family_other_blues.clone(),
+            stem_snap_h: font_info.postscript_stem_snap_h.clone(),
+            stem_snap_v: font_info.postscript_stem_snap_v.clone(),
+            blue_fuzz: font_info.postscript_blue_fuzz,
+            blue_shift: font_info.postscript_blue_shift,
+            blue_scale: font_info.postscript_blue_scale,
+            force_bold: font_info.postscript_force_bold,
+        }
+    }
+
+    fn apply_to_font_info(&self, font_info: &mut norad::FontInfo) {
+        for (field, value) in [
+            (&mut font_info.postscript_blue_values, &self.blue_values),
+            (&mut font_info.postscript_other_blues, &self.other_blues),
+            (&mut font_info.postscript_family_blues, &self.family_blues),
+            (
+                &mut font_info.postscript_family_other_blues,
+                &self.family_other_blues,
+            ),
+            (&mut font_info.postscript_stem_snap_h, &self.stem_snap_h),
+            (&mut font_info.postscript_stem_snap_v, &self.stem_snap_v),
+        ] {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        }
+        for (field, value) in [
+            (&mut font_info.postscript_blue_fuzz, self.blue_fuzz),
+            (&mut font_info.postscript_blue_shift, self.blue_shift),
+            (&mut font_info.postscript_blue_scale, self.blue_scale),
+        ] {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+        if self.force_bold.is_some() {
+            font_info.postscript_force_bold = self.force_bold;
+        }
+    }
+
+    /// Scale the zones and stems, as when changing the units per em. The blue
+    /// scale relates to pixel sizes and stays as it is.
+    pub fn scale(&mut self, factor: f64) {
+        for values in [
+            &mut self.blue_values,
+            &mut self.other_blues,
+            &mut self.family_blues,
+            &mut self.family_other_blues,
+            &mut self.stem_snap_h,
+            &mut self.stem_snap_v,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            values.iter_mut().for_each(|value| *value *= factor);
+        }
+        for value in [&mut self.blue_fuzz, &mut self.blue_shift]
+            .into_iter()
+            .flatten()
+        {
+            *value *= factor;
+        }
+    }
+}
+
+/// The font-wide hinting data of a source.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceHinting {
+    /// The value of the `public.truetype.instructions` font lib key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truetype: Option<serde_json::Value>,
+    /// ttfautohint control files in the data directory of the UFO, by their
+    /// path in it.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ttfautohint_data: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "PostScriptHinting::is_empty")]
+    pub postscript: PostScriptHinting,
+}
+
+impl SourceHinting {
+    /// The name of the file of a source's hinting data in a fontgarden.
+    pub fn filename(source_name: &str) -> String {
+        name_to_filename(&format!("hinting.{source_name}.json"))
+    }
+
+    pub fn load(storage: &impl Storage, path: &Path) -> Result<Self, LoadError> {
+        let json = storage
+            .read(path)
+            .map_err(|e| LoadError::Io(storage.location(path), e))?;
+        serde_json::from_slice(&json).map_err(|e| LoadError::LoadHinting(storage.location(path), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take the hinting data from the fontinfo, lib and data directory of a
+    /// UFO.
+    pub fn from_ufo(ufo: &norad::Font) -> Self {
+        let truetype = ufo
+            .lib
+            .get(TRUETYPE_LIB_KEY)
+            .and_then(|value| serde_json::to_value(value).ok());
+        let mut ttfautohint_data = BTreeMap::new();
+        for (path, data) in ufo.data.iter() {
+            let key = path.to_string_lossy();
+            if !key.to_lowercase().contains("ttfautohint") {
+                continue;
+            }
+            match data.map(|data| String::from_utf8(data.to_vec())) {
+                Ok(Ok(text)) => {
+                    ttfautohint_data.insert(key.into_owned(), text);
+                }
+                _ => eprintln!("warning: not keeping data file {key}, which is not text"),
+            }
+        }
+        Self {
+            truetype,
+            ttfautohint_data,
+            postscript: PostScriptHinting::from_font_info(&ufo.font_info),
+        }
+    }
+
+    /// Put the hinting data back into the fontinfo, lib and data directory of
+    /// a UFO.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font) {
+        self.postscript.apply_to_font_info(&mut ufo.font_info);
+        if let Some(truetype) = &self.truetype {
+            match serde_json::from_value::<plist::Value>(truetype.clone()) {
+                Ok(value) => {
+                    ufo.lib.insert(TRUETYPE_LIB_KEY.into(), value);
+                }
+                Err(e) => eprintln!("warning: not exporting TrueType hinting data: {e}"),
+            }
+        }
+        for (path, text) in &self.ttfautohint_data {
+            if let Err(e) = ufo.data.insert(path.into(), text.clone().into_bytes()) {
+                eprintln!("warning: not exporting data file {path}: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_hinting_of_ufos() {
+        let mut ufo = norad::Font::new();
+        let mut truetype = plist::Dictionary::new();
+        truetype.insert("formatVersion".into(), "1".into());
+        truetype.insert(
+            "controlValue".into(),
+            plist::Value::Array(vec![10.into(), (-20).into()]),
+        );
+        ufo.lib.insert(TRUETYPE_LIB_KEY.into(), truetype.into());
+        ufo.data
+            .insert("com.google.ttfautohint".into(), b"a 1 x 10\n".to_vec())
+            .unwrap();
+        ufo.data
+            .insert("com.example.tool".into(), b"other".to_vec())
+            .unwrap();
+
+        let hinting = SourceHinting::from_ufo(&ufo);
+        assert_eq!(
+            hinting.truetype,
+            Some(serde_json::json!({"formatVersion": "1", "controlValue": [10, -20]}))
+        );
+        assert_eq!(
+            hinting.ttfautohint_data,
+            BTreeMap::from([("com.google.ttfautohint".into(), "a 1 x 10\n".into())])
+        );
+
+        let mut exported = norad::Font::new();
+        hinting.apply_to_ufo(&mut exported);
+        assert_eq!(exported.lib, ufo.lib);
+        assert!(exported
+            .data
+            .contains_key(Path::new("com.google.ttfautohint")));
+        assert!(!exported.data.contains_key(Path::new("com.example.tool")));
+    }
+
+    #[test]
+    fn keep_and_scale_postscript_hinting() {
+        let mut ufo = norad::Font::new();
+        ufo.font_info.postscript_blue_values = Some(vec![-10., 0., 500., 510.]);
+        ufo.font_info.postscript_stem_snap_h = Some(vec![80.]);
+        ufo.font_info.postscript_blue_scale = Some(0.04);
+        ufo.font_info.postscript_force_bold = Some(false);
+
+        let mut hinting = SourceHinting::from_ufo(&ufo);
+        let mut exported = norad::Font::new();
+        hinting.apply_to_ufo(&mut exported);
+        assert_eq!(exported.font_info, ufo.font_info);
+
+        hinting.postscript.scale(2.);
+        assert_eq!(
+            hinting.postscript.blue_values,
+            Some(vec![-20., 0., 1000., 1020.])
+        );
+        assert_eq!(hinting.postscript.stem_snap_h, Some(vec![160.]));
+        assert_eq!(hinting.postscript.blue_scale, Some(0.04));
+        assert_eq!(hinting.postscript.other_blues, None);
+    }
+}
@@ -0,0 +1,248 @@
+//! When each glyph layer last changed on import and what was imported, kept in
+//! `modified.json` at the root of a fontgarden so that reviewers can find
+//! recent changes without diffing layer files.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::LoadError,
+    selection::Selection,
+    storage::Storage,
+    structs::{Fontgarden, Glyph},
+};
+
+/// The last import that changed a layer.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LayerModification {
+    /// When, in UTC, like `2024-05-01T12:30:00Z`.
+    pub modified: String,
+    /// The imported file or files.
+    pub source: String,
+}
+
+/// The contents of `modified.json`: glyph name to layer name to the last
+/// import that changed the layer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ModificationIndex(pub BTreeMap<String, BTreeMap<String, LayerModification>>);
+
+impl ModificationIndex {
+    pub const FILENAME: &str = "modified.json";
+
+    /// Load `modified.json` from a fontgarden, or an empty index if there is
+    /// none.
+    pub fn load(storage: &impl Storage) -> Result<Self, LoadError> {
+        let path = Path::new(Self::FILENAME);
+        if !storage.exists(path) {
+            return Ok(Self::default());
+        }
+        let json = storage
+            .read(path)
+            .map_err(|e| LoadError::Io(storage.location(path), e))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| LoadError::LoadModificationIndex(storage.location(path), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The most recent modification of any layer of a glyph.
+    pub fn latest(&self, glyph_name: &str) -> Option<&LayerModification> {
+        self.0.get(glyph_name)?.values().max()
+    }
+
+    /// The imports that last changed some layers, newest first, with the
+    /// layers they changed by glyph name.
+    pub fn log(&self) -> Vec<(&LayerModification, BTreeMap<&str, Vec<&str>>)> {
+        let mut imports: BTreeMap<&LayerModification, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+        for (glyph_name, layers) in &self.0 {
+            for (layer_name, modification) in layers {
+                imports
+                    .entry(modification)
+                    .or_default()
+                    .entry(glyph_name)
+                    .or_default()
+                    .push(layer_name);
+            }
+        }
+        imports.into_iter().rev().collect()
+    }
+}
+
+/// How to order listed glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListOrder {
+    Name,
+    /// The most recently changed first, then those with no recorded change.
+    Modified,
+}
+
+impl Fontgarden {
+    /// Stamp the layers that an import added or changed with the time and what
+    /// was imported, and forget those it removed.
+    pub fn record_modifications(
+        &mut self,
+        glyphs_before: &HashMap<Arc<str>, Glyph>,
+        source: &str,
+        now: SystemTime,
+    ) {
+        let modification = LayerModification {
+            modified: format_timestamp(now),
+            source: source.into(),
+        };
+        let index = &mut self.modified.0;
+        index.retain(|glyph_name, _| self.glyphs.contains_key(glyph_name.as_str()));
+        for (glyph_name, glyph) in &self.glyphs {
+            let before = glyphs_before.get(glyph_name);
+            let layers = index.entry(glyph_name.to_string()).or_default();
+            layers.retain(|layer_name, _| glyph.layers.contains_key(&layer_name.as_str().into()));
+            for (layer_name, layer) in &glyph.layers {
+                if before.and_then(|glyph| glyph.layers.get(layer_name)) != Some(layer) {
+                    layers.insert(layer_name.to_string(), modification.clone());
+                }
+            }
+        }
+        index.retain(|_, layers| !layers.is_empty());
+    }
+
+    /// Glyph names with the latest change to any of their layers.
+    pub fn list_glyphs(
+        &self,
+        selection: Option<&Selection>,
+        order: ListOrder,
+    ) -> Vec<(&str, Option<&LayerModification>)> {
+        let mut glyphs: Vec<(&str, Option<&LayerModification>)> = self
+            .glyphs
+            .iter()
+            .filter(|(glyph_name, glyph)| {
+                selection.is_none_or(|selection| selection.matches(glyph_name, glyph))
+            })
+            .map(|(glyph_name, _)| (&**glyph_name, self.modified.latest(glyph_name)))
+            .collect();
+        match order {
+            ListOrder::Name => glyphs.sort(),
+            ListOrder::Modified => glyphs.sort_by(|(a_name, a), (b_name, b)| {
+                let b_modified = b.map(|m| &m.modified);
+                b_modified
+                    .cmp(&a.map(|m| &m.modified))
+                    .then(a_name.cmp(b_name))
+            }),
+        }
+        glyphs
+    }
+}
+
+/// Format a time as UTC in RFC 3339, to the second.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+    // From days since the epoch to the civil date, after Howard Hinnant's
+    // `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::structs::Layer;
+
+    use super::*;
+
+    #[test]
+    fn record_modified_layers() {
+        assert_eq!(
+            format_timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "2000-02-29T12:34:56Z"
+        );
+
+        let glyph = |x_advance: f64| Glyph {
+            layers: [
+                ("Regular".into(), Layer::default()),
+                (
+                    "Bold".into(),
+                    Layer {
+                        x_advance: Some(x_advance),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), glyph(500.));
+        fontgarden.record_modifications(&HashMap::new(), "first.ufo", SystemTime::UNIX_EPOCH);
+
+        let glyphs_before = fontgarden.glyphs.clone();
+        fontgarden.glyphs.insert("a".into(), glyph(600.));
+        fontgarden.glyphs.insert("b".into(), glyph(600.));
+        fontgarden.record_modifications(
+            &glyphs_before,
+            "second.ufo",
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+        );
+
+        let sources = |glyph_name: &str| -> Vec<&str> {
+            fontgarden.modified.0[glyph_name]
+                .values()
+                .map(|modification| modification.source.as_str())
+                .collect()
+        };
+        assert_eq!(sources("a"), ["second.ufo", "first.ufo"]);
+        assert_eq!(sources("b"), ["second.ufo", "second.ufo"]);
+        assert_eq!(
+            fontgarden.modified.latest("a").unwrap().modified,
+            "1970-01-01T00:01:00Z"
+        );
+
+        let listed: Vec<&str> = fontgarden
+            .list_glyphs(None, ListOrder::Modified)
+            .into_iter()
+            .map(|(glyph_name, _)| glyph_name)
+            .collect();
+        assert_eq!(listed, ["a", "b"]);
+        let log = fontgarden.modified.log();
+        assert_eq!(log[0].0.source, "second.ufo");
+        assert_eq!(log[0].1["a"], ["Bold"]);
+        assert_eq!(log[1].1["a"], ["Regular"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        assert_eq!(
+            Fontgarden::load(&path).unwrap().modified,
+            fontgarden.modified
+        );
+    }
+}
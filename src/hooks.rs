@@ -0,0 +1,210 @@
+//! External commands run before and after changes to a fontgarden, to trigger
+//! builds, linters or notifications.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::HookError, structs::Glyph};
+
+/// Shell commands to run per event. Each gets the fontgarden path as its first
+/// argument and in `FONTGARDEN_PATH`, and a JSON summary on stdin.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    /// Run before importing; a failing command stops the import.
+    pub pre_import: Vec<String>,
+    pub post_import: Vec<String>,
+    pub post_export: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    PreImport,
+    PostImport,
+    PostExport,
+}
+
+impl HooksConfig {
+    fn commands(&self, event: HookEvent) -> &[String] {
+        match event {
+            HookEvent::PreImport => &self.pre_import,
+            HookEvent::PostImport => &self.post_import,
+            HookEvent::PostExport => &self.post_export,
+        }
+    }
+
+    pub fn has(&self, event: HookEvent) -> bool {
+        !self.commands(event).is_empty()
+    }
+
+    /// Run the commands for the event in order, stopping at the first that fails.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        fontgarden_path: &Path,
+        summary: &ChangeSummary,
+    ) -> Result<(), HookError> {
+        let input = serde_json::to_vec(&HookInput { event, summary }).unwrap();
+        for command in self.commands(event) {
+            let mut child = shell(command)
+                .arg(fontgarden_path)
+                .env("FONTGARDEN_PATH", fontgarden_path)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| HookError::Spawn(command.clone(), e))?;
+            // Commands that don't read their input may close stdin early.
+            let _ = child.stdin.take().unwrap().write_all(&input);
+            let status = child
+                .wait()
+                .map_err(|e| HookError::Spawn(command.clone(), e))?;
+            if !status.success() {
+                return Err(HookError::Failed(command.clone(), status));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    // The name after the command becomes `$0`, so that the path is `$1`.
+    shell.args(["-c", command, "fontgarden-hook"]);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+/// What an import or export did or is about to do, as handed to hooks.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ChangeSummary {
+    /// The sources imported or exported, by path for imports and by name for
+    /// exports.
+    pub sources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<GlyphChanges>,
+}
+
+#[derive(Serialize)]
+struct HookInput<'a> {
+    event: HookEvent,
+    #[serde(flatten)]
+    summary: &'a ChangeSummary,
+}
+
+/// Which glyphs appeared, changed or disappeared, sorted by name.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct GlyphChanges {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl GlyphChanges {
+    pub fn between(before: &HashMap<Arc<str>, Glyph>, after: &HashMap<Arc<str>, Glyph>) -> Self {
+        let mut changes = Self::default();
+        for (glyph_name, glyph) in after {
+            match before.get(glyph_name) {
+                None => changes.added.push(glyph_name.to_string()),
+                Some(old_glyph) if old_glyph != glyph => {
+                    changes.changed.push(glyph_name.to_string())
+                }
+                Some(_) => (),
+            }
+        }
+        changes.removed = before
+            .keys()
+            .filter(|glyph_name| !after.contains_key(*glyph_name))
+            .map(|glyph_name| glyph_name.to_string())
+            .collect();
+        changes.added.sort();
+        changes.changed.sort();
+        changes.removed.sort();
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Layer, LayerName};
+
+    use super::*;
+
+    #[test]
+    fn glyph_changes_between() {
+        let glyph = |x_advance: f64| Glyph {
+            layers: [(
+                LayerName::main("Regular"),
+                Layer {
+                    x_advance: Some(x_advance),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let before = HashMap::from([
+            ("a".into(), glyph(500.)),
+            ("b".into(), glyph(500.)),
+            ("c".into(), glyph(500.)),
+        ]);
+        let after = HashMap::from([
+            ("a".into(), glyph(500.)),
+            ("b".into(), glyph(600.)),
+            ("d".into(), glyph(500.)),
+        ]);
+
+        assert_eq!(
+            GlyphChanges::between(&before, &after),
+            GlyphChanges {
+                added: vec!["d".into()],
+                changed: vec!["b".into()],
+                removed: vec!["c".into()],
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_hooks_with_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("hook-output");
+        let hooks = HooksConfig {
+            post_export: vec![format!("{{ echo \"$1\"; cat; }} > '{}'", output.display())],
+            pre_import: vec!["exit 3".into()],
+            ..Default::default()
+        };
+        let summary = ChangeSummary {
+            sources: vec!["Regular".into()],
+            ..Default::default()
+        };
+
+        hooks
+            .run(HookEvent::PostExport, Path::new("/some/garden"), &summary)
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "/some/garden\n{\"event\":\"post-export\",\"sources\":[\"Regular\"]}"
+        );
+
+        assert!(matches!(
+            hooks.run(HookEvent::PreImport, Path::new("/some/garden"), &summary),
+            Err(HookError::Failed(..))
+        ));
+    }
+}
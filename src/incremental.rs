@@ -0,0 +1,191 @@
+//! Writing UFOs over previously exported ones. norad saves a UFO by deleting
+//! and rewriting the whole directory, which makes every file look changed to
+//! build tools that go by modification times. Instead, the UFO is saved to a
+//! staging directory next to the target and only the files whose contents
+//! hash differently are moved over.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{errors::UfoWriteError, par::*};
+
+/// How many files writing a UFO touched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UfoWriteSummary {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl std::ops::AddAssign for UfoWriteSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.written += other.written;
+        self.unchanged += other.unchanged;
+        self.removed += other.removed;
+    }
+}
+
+/// Save `font` to `path`, leaving files of a UFO already there alone if their
+/// contents stay the same and removing those the new UFO doesn't have. Hidden
+/// files in an existing UFO are kept.
+pub fn write_ufo(font: &norad::Font, path: &Path) -> Result<UfoWriteSummary, UfoWriteError> {
+    if !path.is_dir() {
+        font.save(path)
+            .map_err(|e| UfoWriteError::Save(path.into(), e))?;
+        return Ok(UfoWriteSummary {
+            written: file_hashes(path)?.len(),
+            ..Default::default()
+        });
+    }
+
+    // Stage next to the target so that files can be renamed into place.
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let staging = tempfile::tempdir_in(parent).map_err(|e| UfoWriteError::Io(parent.into(), e))?;
+    let staged_path = staging.path().join("staged.ufo");
+    font.save(&staged_path)
+        .map_err(|e| UfoWriteError::Save(path.into(), e))?;
+
+    let staged = file_hashes(&staged_path)?;
+    let existing = file_hashes(path)?;
+
+    let mut summary = UfoWriteSummary::default();
+    for (file, hash) in &staged {
+        if existing.get(file) == Some(hash) {
+            summary.unchanged += 1;
+            continue;
+        }
+        let target = path.join(file);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).map_err(|e| UfoWriteError::Io(dir.into(), e))?;
+        }
+        fs::rename(staged_path.join(file), &target)
+            .map_err(|e| UfoWriteError::Io(target.clone(), e))?;
+        summary.written += 1;
+    }
+    for file in existing.keys().filter(|file| !staged.contains_key(*file)) {
+        let target = path.join(file);
+        fs::remove_file(&target).map_err(|e| UfoWriteError::Io(target.clone(), e))?;
+        summary.removed += 1;
+    }
+    remove_empty_dirs(path)?;
+
+    Ok(summary)
+}
+
+/// The SHA-256 of every file below `root` that isn't hidden, by its path
+/// relative to `root`.
+fn file_hashes(root: &Path) -> Result<BTreeMap<PathBuf, Vec<u8>>, UfoWriteError> {
+    let mut files = Vec::new();
+    collect_files(root, Path::new(""), &mut files)?;
+    files
+        .into_par_iter()
+        .map(|file| {
+            let full_path = root.join(&file);
+            let contents =
+                fs::read(&full_path).map_err(|e| UfoWriteError::Io(full_path.clone(), e))?;
+            Ok((file, Sha256::digest(contents).to_vec()))
+        })
+        .collect()
+}
+
+fn collect_files(
+    root: &Path,
+    relative: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), UfoWriteError> {
+    let dir = root.join(relative);
+    let entries = fs::read_dir(&dir).map_err(|e| UfoWriteError::Io(dir.clone(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| UfoWriteError::Io(dir.clone(), e))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let relative = relative.join(entry.file_name());
+        if entry.path().is_dir() {
+            collect_files(root, &relative, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Remove the directories below `dir` left empty by removed files, such as
+/// the glyph directory of a dropped layer.
+fn remove_empty_dirs(dir: &Path) -> Result<(), UfoWriteError> {
+    let entries = fs::read_dir(dir).map_err(|e| UfoWriteError::Io(dir.into(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| UfoWriteError::Io(dir.into(), e))?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        remove_empty_dirs(&path)?;
+        let is_empty = fs::read_dir(&path)
+            .map_err(|e| UfoWriteError::Io(path.clone(), e))?
+            .next()
+            .is_none();
+        if is_empty {
+            fs::remove_dir(&path).map_err(|e| UfoWriteError::Io(path.clone(), e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font(glyphs: &[(&str, f64)]) -> norad::Font {
+        let mut font = norad::Font::new();
+        for (name, width) in glyphs {
+            let mut glyph = norad::Glyph::new(name);
+            glyph.width = *width;
+            font.default_layer_mut().insert_glyph(glyph);
+        }
+        font
+    }
+
+    #[test]
+    fn rewrite_only_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Test.ufo");
+
+        let first = write_ufo(&font(&[("A", 500.), ("B", 600.)]), &path).unwrap();
+        assert_eq!(first.unchanged, 0);
+        assert!(first.written > 2);
+
+        let again = write_ufo(&font(&[("A", 500.), ("B", 600.)]), &path).unwrap();
+        assert_eq!(
+            again,
+            UfoWriteSummary {
+                written: 0,
+                unchanged: first.written,
+                removed: 0,
+            }
+        );
+
+        let changed = write_ufo(&font(&[("A", 510.), ("B", 600.)]), &path).unwrap();
+        assert_eq!(changed.written, 1);
+        assert_eq!(changed.removed, 0);
+        let glif = fs::read_to_string(path.join("glyphs/A_.glif")).unwrap();
+        assert!(glif.contains("510"));
+
+        fs::write(path.join(".keep"), "").unwrap();
+        let dropped = write_ufo(&font(&[("A", 510.)]), &path).unwrap();
+        assert_eq!(dropped.written, 1, "contents.plist");
+        assert_eq!(dropped.removed, 1);
+        assert!(!path.join("glyphs/B_.glif").exists());
+        assert!(path.join(".keep").exists());
+
+        let entries: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1, "the staging directory is cleaned up");
+    }
+}
@@ -0,0 +1,883 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    config::Config,
+    errors::InterpolationError,
+    structs::{Fontgarden, Layer, LayerName},
+};
+
+/// A position in the design space, as axis tag to value.
+pub type Location = BTreeMap<String, f64>;
+
+/// A static instance to interpolate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub location: Location,
+}
+
+impl Instance {
+    /// Parse an instance from `Name=wght:500,wdth:75`, or look up a plain `Name`
+    /// among the instances in the configuration.
+    pub fn parse(spec: &str, config: &Config) -> Result<Self, InterpolationError> {
+        let Some((name, location)) = spec.split_once('=') else {
+            return match config.instances.get(spec) {
+                Some(instance) => Ok(Instance {
+                    name: spec.to_string(),
+                    location: instance.location.clone(),
+                }),
+                None => Err(InterpolationError::UnknownInstance(spec.to_string())),
+            };
+        };
+        Ok(Instance {
+            name: name.to_string(),
+            location: parse_location(location)?,
+        })
+    }
+}
+
+/// Parse a location like `wght:500,wdth:75`.
+pub fn parse_location(spec: &str) -> Result<Location, InterpolationError> {
+    let malformed = || InterpolationError::MalformedLocation(spec.to_string());
+    spec.split(',')
+        .map(|pair| {
+            let (axis, value) = pair.split_once(':').ok_or_else(malformed)?;
+            let value: f64 = value.trim().parse().map_err(|_| malformed())?;
+            Ok((axis.trim().to_string(), value))
+        })
+        .collect()
+}
+
+/// Why a glyph cannot be interpolated.
+#[derive(Debug, PartialEq)]
+pub enum Incompatibility {
+    /// The glyph has no layer in the source at the default location.
+    MissingDefault,
+    /// The glyph's layer in a source, or an intermediate layer, is built
+    /// differently from the one in the default source.
+    Structure {
+        layer_name: LayerName,
+        difference: &'static str,
+    },
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Incompatibility::MissingDefault => write!(f, "no layer in the default source"),
+            Incompatibility::Structure {
+                layer_name,
+                difference,
+            } => write!(
+                f,
+                "layer {layer_name} has different {difference} than the layer in the default source"
+            ),
+        }
+    }
+}
+
+/// The outcome of interpolating all glyphs at a location.
+#[derive(Debug, Default)]
+pub struct Interpolation {
+    /// Interpolated layers by glyph name.
+    pub layers: BTreeMap<String, Layer>,
+    /// Glyphs that could not be interpolated, by name.
+    pub incompatible: BTreeMap<String, Incompatibility>,
+}
+
+impl Fontgarden {
+    /// Normalize a location given in axis units to -1..=1 per axis, leaving out
+    /// axes at their default.
+    pub fn normalize_location(&self, location: &Location) -> Result<Location, InterpolationError> {
+        if self.config.axes.is_empty() {
+            return Err(InterpolationError::NoAxes);
+        }
+        let mut normalized = Location::new();
+        for (axis_tag, value) in location {
+            let Some(axis) = self.config.axes.get(axis_tag) else {
+                return Err(InterpolationError::UnknownAxis(axis_tag.clone()));
+            };
+            let value = axis.normalize(*value);
+            if value != 0. {
+                normalized.insert(axis_tag.clone(), value);
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Interpolate the main layers of all glyphs at the location, from the sources
+    /// with a location in the configuration.
+    ///
+    /// Glyphs missing from some sources are interpolated from the sources they
+    /// are in, as long as the default source is one of them. Other layers with a
+    /// location take part as intermediate masters of their glyph.
+    pub fn interpolate(&self, location: &Location) -> Result<Interpolation, InterpolationError> {
+        self.interpolate_glyphs(location, &[])
+    }
+
+    /// Interpolate the main layers of the named glyphs, or all if none are
+    /// named, like [`Fontgarden::interpolate`] does.
+    fn interpolate_glyphs(
+        &self,
+        location: &Location,
+        glyph_names: &[String],
+    ) -> Result<Interpolation, InterpolationError> {
+        let location = self.normalize_location(location)?;
+        let mut source_locations: BTreeMap<&str, Location> = BTreeMap::new();
+        for (source_name, source) in &self.config.sources {
+            source_locations.insert(source_name, self.normalize_location(&source.location)?);
+        }
+        let Some(default_source) = source_locations
+            .iter()
+            .find(|(_, location)| location.is_empty())
+            .map(|(name, _)| *name)
+        else {
+            return Err(InterpolationError::NoDefaultSource);
+        };
+
+        let glyph_names: HashSet<&str> = glyph_names.iter().map(String::as_str).collect();
+        let mut models: HashMap<BTreeSet<&str>, VariationModel> = HashMap::new();
+        let mut interpolation = Interpolation::default();
+        for (glyph_name, glyph) in &self.glyphs {
+            if !glyph_names.is_empty() && !glyph_names.contains(&**glyph_name) {
+                continue;
+            }
+            let Some(default_layer) = glyph.layers.get(&LayerName::main(default_source)) else {
+                interpolation
+                    .incompatible
+                    .insert(glyph_name.to_string(), Incompatibility::MissingDefault);
+                continue;
+            };
+            let sources: BTreeSet<&str> = source_locations
+                .keys()
+                .copied()
+                .filter(|source_name| glyph.layers.contains_key(&LayerName::main(*source_name)))
+                .collect();
+            let mut intermediates: Vec<(&LayerName, Location)> = Vec::new();
+            for (layer_name, layer) in &glyph.layers {
+                if let Some(layer_location) = &layer.location {
+                    if !layer_name.is_main() {
+                        intermediates.push((layer_name, self.normalize_location(layer_location)?));
+                    }
+                }
+            }
+            intermediates.sort_by_key(|(layer_name, _)| *layer_name);
+            let master_names: Vec<LayerName> = sources
+                .iter()
+                .map(|source_name| LayerName::main(*source_name))
+                .chain(
+                    intermediates
+                        .iter()
+                        .map(|(layer_name, _)| (*layer_name).clone()),
+                )
+                .collect();
+
+            let incompatibility = master_names.iter().find_map(|layer_name| {
+                default_layer
+                    .structure_difference(&glyph.layers[layer_name])
+                    .map(|difference| Incompatibility::Structure {
+                        layer_name: layer_name.clone(),
+                        difference,
+                    })
+            });
+            if let Some(incompatibility) = incompatibility {
+                interpolation
+                    .incompatible
+                    .insert(glyph_name.to_string(), incompatibility);
+                continue;
+            }
+
+            let source_model;
+            let intermediate_model;
+            let model = if intermediates.is_empty() {
+                source_model = models.entry(sources.clone()).or_insert_with(|| {
+                    let locations: Vec<Location> = sources
+                        .iter()
+                        .map(|source_name| source_locations[source_name].clone())
+                        .collect();
+                    VariationModel::new(&locations)
+                });
+                &*source_model
+            } else {
+                // Intermediate layers are placed per glyph, so models including
+                // them aren't shared.
+                let locations: Vec<Location> = sources
+                    .iter()
+                    .map(|source_name| source_locations[source_name].clone())
+                    .chain(intermediates.into_iter().map(|(_, location)| location))
+                    .collect();
+                intermediate_model = VariationModel::new(&locations);
+                &intermediate_model
+            };
+            let weights = model.master_weights(&location);
+            let layers: Vec<(&Layer, f64)> = master_names
+                .iter()
+                .map(|layer_name| &glyph.layers[layer_name])
+                .zip(weights)
+                .collect();
+            interpolation
+                .layers
+                .insert(glyph_name.to_string(), Layer::weighted_sum(&layers));
+        }
+        Ok(interpolation)
+    }
+}
+
+impl Fontgarden {
+    /// Add a source at the location, with a layer interpolated from the other
+    /// sources for every glyph that allows it. Returns the glyphs left out.
+    pub fn add_interpolated_source(
+        &mut self,
+        source_name: &str,
+        location: Location,
+    ) -> Result<BTreeMap<String, Incompatibility>, InterpolationError> {
+        if self.source_names().contains(source_name) {
+            return Err(InterpolationError::SourceExists(source_name.to_string()));
+        }
+
+        let interpolation = self.interpolate(&location)?;
+        for (glyph_name, layer) in interpolation.layers {
+            self.glyphs
+                .get_mut(glyph_name.as_str())
+                .unwrap()
+                .layers
+                .insert(LayerName::main(source_name), layer);
+        }
+        self.config.add_source(source_name, location);
+        Ok(interpolation.incompatible)
+    }
+
+    /// Interpolate the missing layers of a source in the named glyphs, or in
+    /// all glyphs that lack one, from the other sources, to fill in a sparse
+    /// master. Glyphs that already have a layer in the source are left alone.
+    pub fn fill_layers(
+        &mut self,
+        source_name: &str,
+        glyph_names: &[String],
+    ) -> Result<Interpolation, InterpolationError> {
+        let Some(source) = self.config.sources.get(source_name) else {
+            return Err(InterpolationError::UnplacedSource(source_name.into()));
+        };
+        if let Some(glyph_name) = glyph_names
+            .iter()
+            .find(|glyph_name| !self.glyphs.contains_key(glyph_name.as_str()))
+        {
+            return Err(InterpolationError::UnknownGlyph(glyph_name.clone()));
+        }
+        let layer_name = LayerName::main(source_name);
+        let missing: Vec<String> = self
+            .glyphs
+            .iter()
+            .filter(|&(glyph_name, glyph)| {
+                (glyph_names.is_empty() || glyph_names.iter().any(|n| **n == **glyph_name))
+                    && !glyph.layers.contains_key(&layer_name)
+            })
+            .map(|(glyph_name, _)| glyph_name.to_string())
+            .collect();
+        if missing.is_empty() {
+            return Ok(Interpolation::default());
+        }
+
+        let interpolation = self.interpolate_glyphs(&source.location.clone(), &missing)?;
+        for (glyph_name, layer) in &interpolation.layers {
+            self.glyphs
+                .get_mut(glyph_name.as_str())
+                .unwrap()
+                .layers
+                .insert(layer_name.clone(), layer.clone());
+        }
+        Ok(interpolation)
+    }
+}
+
+impl Fontgarden {
+    /// Place a sublayer at a location in the design space, or take it out with
+    /// `None`, in the named glyphs or all glyphs that have it. Returns the number
+    /// of changed layers.
+    pub fn set_layer_location(
+        &mut self,
+        layer_name: &LayerName,
+        glyph_names: &[String],
+        location: Option<Location>,
+    ) -> Result<usize, InterpolationError> {
+        if layer_name.is_main() {
+            return Err(InterpolationError::MainLayerLocation(
+                layer_name.to_string(),
+            ));
+        }
+        if let Some(location) = &location {
+            self.normalize_location(location)?;
+        }
+
+        let mut changed = 0;
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            if !glyph_names.is_empty() && !glyph_names.iter().any(|n| **n == **glyph_name) {
+                continue;
+            }
+            if let Some(layer) = glyph.layers.get_mut(layer_name) {
+                layer.location = location.clone();
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl Layer {
+    /// Describe what keeps the layers from being interpolated, if anything.
+    pub fn structure_difference(&self, other: &Layer) -> Option<&'static str> {
+        if self.contours.len() != other.contours.len() {
+            return Some("numbers of contours");
+        }
+        for (contour, other_contour) in self.contours.iter().zip(&other.contours) {
+            if contour.points.len() != other_contour.points.len() {
+                return Some("numbers of points");
+            }
+            if contour
+                .points
+                .iter()
+                .zip(&other_contour.points)
+                .any(|(point, other_point)| point.typ != other_point.typ)
+            {
+                return Some("point types");
+            }
+        }
+        if self.components.len() != other.components.len()
+            || self
+                .components
+                .iter()
+                .zip(&other.components)
+                .any(|(component, other_component)| {
+                    component.name != other_component.name
+                        || !same_axes(&component.location, &other_component.location)
+                })
+        {
+            return Some("components");
+        }
+        let anchor_names: BTreeSet<&str> = self.anchors.iter().map(|a| a.name.as_str()).collect();
+        let other_anchor_names: BTreeSet<&str> =
+            other.anchors.iter().map(|a| a.name.as_str()).collect();
+        if anchor_names != other_anchor_names {
+            return Some("anchors");
+        }
+        None
+    }
+
+    /// Add up compatible layers multiplied by their weights. The first layer
+    /// provides everything that isn't a number.
+    pub fn weighted_sum(layers: &[(&Layer, f64)]) -> Layer {
+        let (template, _) = layers[0];
+        let sum = |value: &dyn Fn(&Layer) -> f64| -> f64 {
+            layers
+                .iter()
+                .map(|(layer, weight)| value(layer) * weight)
+                .sum()
+        };
+
+        let mut result = template.clone();
+        for (c, contour) in result.contours.iter_mut().enumerate() {
+            for (p, point) in contour.points.iter_mut().enumerate() {
+                point.x = sum(&|layer| layer.contours[c].points[p].x);
+                point.y = sum(&|layer| layer.contours[c].points[p].y);
+            }
+        }
+        for (c, component) in result.components.iter_mut().enumerate() {
+            let transformation = &mut component.transformation;
+            transformation.x_scale = sum(&|layer| layer.components[c].transformation.x_scale);
+            transformation.xy_scale = sum(&|layer| layer.components[c].transformation.xy_scale);
+            transformation.yx_scale = sum(&|layer| layer.components[c].transformation.yx_scale);
+            transformation.y_scale = sum(&|layer| layer.components[c].transformation.y_scale);
+            transformation.x_offset = sum(&|layer| layer.components[c].transformation.x_offset);
+            transformation.y_offset = sum(&|layer| layer.components[c].transformation.y_offset);
+            for (axis, value) in component.location.iter_mut().flatten() {
+                *value = sum(&|layer| layer.components[c].location.as_ref().unwrap()[axis]);
+            }
+        }
+        for anchor in result.anchors.iter_mut() {
+            let matching = |layer: &Layer| {
+                layer
+                    .anchors
+                    .iter()
+                    .find(|a| a.name == anchor.name)
+                    .map(|a| (a.x, a.y))
+                    .unwrap_or_default()
+            };
+            let (x, y) = (
+                sum(&|layer| matching(layer).0),
+                sum(&|layer| matching(layer).1),
+            );
+            anchor.x = x;
+            anchor.y = y;
+        }
+        if template.x_advance.is_some() {
+            result.x_advance = Some(sum(&|layer| layer.x_advance.unwrap_or_default()));
+        }
+        if template.y_advance.is_some() {
+            result.y_advance = Some(sum(&|layer| layer.y_advance.unwrap_or_default()));
+        }
+        if template.vertical_origin.is_some() {
+            result.vertical_origin = Some(sum(&|layer| layer.vertical_origin.unwrap_or_default()));
+        }
+        result
+    }
+}
+
+/// Whether two component locations can be interpolated, i.e. name the same axes.
+fn same_axes(location: &Option<Location>, other: &Option<Location>) -> bool {
+    match (location, other) {
+        (Some(location), Some(other)) => location.keys().eq(other.keys()),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// A region of influence per axis, as lower bound, peak and upper bound.
+type Support = BTreeMap<String, (f64, f64, f64)>;
+
+/// Weighs masters at normalized locations for interpolation, like the
+/// `VariationModel` of fontTools, so that results match what a font compiler
+/// makes of the same masters.
+#[derive(Debug)]
+pub struct VariationModel {
+    /// The masters in the order in which their deltas are stacked.
+    order: Vec<usize>,
+    supports: Vec<Support>,
+    /// For each delta in stacking order, the masters it is made of with factors.
+    delta_coefficients: Vec<Vec<f64>>,
+}
+
+impl VariationModel {
+    /// Build a model from normalized master locations, one of which must be at the
+    /// default location.
+    pub fn new(locations: &[Location]) -> Self {
+        let locations: Vec<Location> = locations
+            .iter()
+            .map(|location| {
+                location
+                    .iter()
+                    .filter(|(_, value)| **value != 0.)
+                    .map(|(axis, value)| (axis.clone(), *value))
+                    .collect()
+            })
+            .collect();
+
+        // Values of masters that are on a single axis.
+        let mut axis_points: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        for location in &locations {
+            if let [(axis, value)] = location.iter().collect::<Vec<_>>()[..] {
+                axis_points
+                    .entry(axis)
+                    .or_insert_with(|| vec![0.])
+                    .push(*value);
+            }
+        }
+        let on_point_axes = |location: &Location| {
+            location
+                .iter()
+                .filter(|(axis, value)| {
+                    axis_points
+                        .get(axis.as_str())
+                        .is_some_and(|values| values.contains(value))
+                })
+                .count()
+        };
+        let mut order: Vec<usize> = (0..locations.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&locations[a], &locations[b]);
+            a.len()
+                .cmp(&b.len())
+                .then_with(|| on_point_axes(b).cmp(&on_point_axes(a)))
+                .then_with(|| a.keys().cmp(b.keys()))
+                .then_with(|| {
+                    let signs = |location: &Location| -> Vec<i8> {
+                        location.values().map(|v| v.signum() as i8).collect()
+                    };
+                    signs(a).cmp(&signs(b))
+                })
+                .then_with(|| {
+                    a.values()
+                        .zip(b.values())
+                        .map(|(a, b)| a.abs().total_cmp(&b.abs()))
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                })
+        });
+        let sorted: Vec<&Location> = order.iter().map(|&index| &locations[index]).collect();
+
+        let supports = compute_supports(&sorted);
+
+        let master_count = sorted.len();
+        let mut delta_coefficients: Vec<Vec<f64>> = Vec::with_capacity(master_count);
+        for (index, location) in sorted.iter().enumerate() {
+            let mut coefficients = vec![0.; master_count];
+            coefficients[index] = 1.;
+            for (previous, support) in supports[..index].iter().enumerate() {
+                let scalar = support_scalar(location, support);
+                if scalar != 0. {
+                    for (coefficient, previous_coefficient) in
+                        coefficients.iter_mut().zip(&delta_coefficients[previous])
+                    {
+                        *coefficient -= scalar * previous_coefficient;
+                    }
+                }
+            }
+            delta_coefficients.push(coefficients);
+        }
+
+        VariationModel {
+            order,
+            supports,
+            delta_coefficients,
+        }
+    }
+
+    /// The factor of each master, in the order given on construction, for the
+    /// value at a normalized location.
+    pub fn master_weights(&self, location: &Location) -> Vec<f64> {
+        let mut weights = vec![0.; self.order.len()];
+        for (support, coefficients) in self.supports.iter().zip(&self.delta_coefficients) {
+            let scalar = support_scalar(location, support);
+            if scalar == 0. {
+                continue;
+            }
+            for (sorted_index, coefficient) in coefficients.iter().enumerate() {
+                weights[self.order[sorted_index]] += scalar * coefficient;
+            }
+        }
+        weights
+    }
+}
+
+/// Compute the region each master influences, splitting boxes where earlier
+/// masters lie inside them.
+fn compute_supports(locations: &[&Location]) -> Vec<Support> {
+    let mut minimum: BTreeMap<&str, f64> = BTreeMap::new();
+    let mut maximum: BTreeMap<&str, f64> = BTreeMap::new();
+    for location in locations {
+        for (axis, &value) in location.iter() {
+            let min = minimum.entry(axis).or_insert(value);
+            *min = min.min(value);
+            let max = maximum.entry(axis).or_insert(value);
+            *max = max.max(value);
+        }
+    }
+    let mut regions: Vec<Support> = locations
+        .iter()
+        .map(|location| {
+            location
+                .iter()
+                .map(|(axis, &value)| {
+                    let bounds = if value > 0. {
+                        (0., value, maximum[axis.as_str()])
+                    } else {
+                        (minimum[axis.as_str()], value, 0.)
+                    };
+                    (axis.clone(), bounds)
+                })
+                .collect()
+        })
+        .collect();
+
+    for index in 0..regions.len() {
+        let (previous_regions, rest) = regions.split_at_mut(index);
+        let region = &mut rest[0];
+        for previous in previous_regions.iter() {
+            if !previous.keys().eq(region.keys()) {
+                continue;
+            }
+            let is_inside = region.iter().all(|(axis, &(lower, peak, upper))| {
+                let value = previous[axis].1;
+                value == peak || (lower < value && value < upper)
+            });
+            if !is_inside {
+                continue;
+            }
+
+            // Split the box in the direction with the largest range ratio.
+            let mut best_axes: BTreeMap<String, (f64, f64, f64)> = BTreeMap::new();
+            let mut best_ratio = -1.;
+            for (axis, &(_, value, _)) in previous.iter() {
+                let (lower, peak, upper) = region[axis];
+                let (bounds, ratio) = match value.partial_cmp(&peak) {
+                    Some(Ordering::Less) => ((value, peak, upper), (value - peak) / (lower - peak)),
+                    Some(Ordering::Greater) => {
+                        ((lower, peak, value), (value - peak) / (upper - peak))
+                    }
+                    _ => continue,
+                };
+                if ratio > best_ratio {
+                    best_axes.clear();
+                    best_ratio = ratio;
+                }
+                if ratio == best_ratio {
+                    best_axes.insert(axis.clone(), bounds);
+                }
+            }
+            region.extend(best_axes);
+        }
+    }
+    regions
+}
+
+/// How much a region contributes at a location.
+fn support_scalar(location: &Location, support: &Support) -> f64 {
+    let mut scalar = 1.;
+    for (axis, &(lower, peak, upper)) in support {
+        if peak == 0. || lower > peak || peak > upper || (lower < 0. && upper > 0.) {
+            continue;
+        }
+        let value = location.get(axis).copied().unwrap_or_default();
+        if value == peak {
+            continue;
+        }
+        if value <= lower || upper <= value {
+            return 0.;
+        }
+        scalar *= if value < peak {
+            (value - lower) / (peak - lower)
+        } else {
+            (value - upper) / (peak - upper)
+        };
+    }
+    scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, ExtraFields, Glyph};
+
+    use super::*;
+
+    fn location(values: &[(&str, f64)]) -> Location {
+        values
+            .iter()
+            .map(|(axis, value)| (axis.to_string(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn weights_of_corner_masters() {
+        let model = VariationModel::new(&[
+            location(&[("wdth", 1.), ("wght", 1.)]),
+            location(&[]),
+            location(&[("wght", 1.)]),
+            location(&[("wdth", 1.)]),
+        ]);
+
+        assert_eq!(
+            model.master_weights(&location(&[("wdth", 0.5), ("wght", 0.5)])),
+            vec![0.25, 0.25, 0.25, 0.25]
+        );
+        assert_eq!(
+            model.master_weights(&location(&[("wght", 1.)])),
+            vec![0., 0., 1., 0.]
+        );
+    }
+
+    #[test]
+    fn weights_with_intermediate_master() {
+        let model = VariationModel::new(&[
+            location(&[]),
+            location(&[("wght", 1.)]),
+            location(&[("wght", 0.5)]),
+        ]);
+
+        assert_eq!(
+            model.master_weights(&location(&[("wght", 0.25)])),
+            vec![0.5, 0., 0.5]
+        );
+        assert_eq!(
+            model.master_weights(&location(&[("wght", 0.75)])),
+            vec![0., 0.5, 0.5]
+        );
+    }
+
+    #[test]
+    fn add_source_between_masters() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            axes.wght = { minimum = 100, default = 100, maximum = 900 }
+            sources.Thin.location = { wght = 100 }
+            sources.Black.location = { wght = 900 }
+            "#,
+        )
+        .unwrap();
+        let layer = |x_advance: f64, anchor_names: &[&str]| Layer {
+            x_advance: Some(x_advance),
+            anchors: anchor_names
+                .iter()
+                .map(|name| Anchor {
+                    name: name.to_string(),
+                    x: x_advance / 2.,
+                    y: 0.,
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let glyph = |layers: [Layer; 2]| Glyph {
+            layers: [LayerName::main("Thin"), LayerName::main("Black")]
+                .into_iter()
+                .zip(layers)
+                .collect(),
+            ..Default::default()
+        };
+        fontgarden.glyphs.insert(
+            "a".into(),
+            glyph([layer(100., &["top"]), layer(500., &["top"])]),
+        );
+        fontgarden
+            .glyphs
+            .insert("b".into(), glyph([layer(100., &["top"]), layer(500., &[])]));
+
+        let incompatible = fontgarden
+            .add_interpolated_source("Regular", location(&[("wght", 400.)]))
+            .unwrap();
+
+        assert_eq!(
+            incompatible,
+            BTreeMap::from([(
+                "b".to_string(),
+                Incompatibility::Structure {
+                    layer_name: "Black".into(),
+                    difference: "anchors"
+                }
+            )])
+        );
+        let regular = &fontgarden.glyphs["a"].layers[&LayerName::main("Regular")];
+        assert_eq!(regular.x_advance, Some(250.));
+        assert_eq!(regular.anchors[0].x, 125.);
+        assert!(!fontgarden.glyphs["b"]
+            .layers
+            .contains_key(&LayerName::main("Regular")));
+        assert_eq!(
+            fontgarden.config.sources["Regular"].location,
+            location(&[("wght", 400.)])
+        );
+        assert!(matches!(
+            fontgarden.add_interpolated_source("Regular", location(&[("wght", 500.)])),
+            Err(InterpolationError::SourceExists(_))
+        ));
+    }
+
+    #[test]
+    fn fill_missing_layers() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            axes.wght = { minimum = 100, default = 100, maximum = 900 }
+            sources.Thin.location = { wght = 100 }
+            sources.Medium.location = { wght = 500 }
+            sources.Black.location = { wght = 900 }
+            "#,
+        )
+        .unwrap();
+        let layer = |x_advance: f64| Layer {
+            x_advance: Some(x_advance),
+            ..Default::default()
+        };
+        for glyph_name in ["a", "b"] {
+            fontgarden.glyphs.insert(
+                glyph_name.into(),
+                Glyph {
+                    layers: [
+                        (LayerName::main("Thin"), layer(400.)),
+                        (LayerName::main("Black"), layer(600.)),
+                    ]
+                    .into(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let filled = fontgarden.fill_layers("Medium", &["a".into()]).unwrap();
+        assert_eq!(filled.layers.len(), 1);
+        assert_eq!(
+            fontgarden.glyphs["a"].layers[&LayerName::main("Medium")].x_advance,
+            Some(500.)
+        );
+        assert!(!fontgarden.glyphs["b"]
+            .layers
+            .contains_key(&LayerName::main("Medium")));
+        assert!(matches!(
+            fontgarden.fill_layers("Medium", &["c".into()]),
+            Err(InterpolationError::UnknownGlyph(_))
+        ));
+        assert!(matches!(
+            fontgarden.fill_layers("Bold", &[]),
+            Err(InterpolationError::UnplacedSource(_))
+        ));
+    }
+
+    #[test]
+    fn interpolate_with_intermediate_layer() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            axes.wght = { minimum = 100, default = 100, maximum = 900 }
+            sources.Thin.location = { wght = 100 }
+            sources.Black.location = { wght = 900 }
+            "#,
+        )
+        .unwrap();
+        let layer = |x_advance: f64| Layer {
+            x_advance: Some(x_advance),
+            ..Default::default()
+        };
+        let mut glyph = Glyph::default();
+        glyph.layers.insert(LayerName::main("Thin"), layer(100.));
+        glyph.layers.insert(LayerName::main("Black"), layer(500.));
+        glyph
+            .layers
+            .insert(LayerName::sublayer("Thin", "brace"), layer(400.));
+        fontgarden.glyphs.insert("a".into(), glyph);
+
+        let advance_at = |fontgarden: &Fontgarden, wght: f64| {
+            fontgarden
+                .interpolate(&location(&[("wght", wght)]))
+                .unwrap()
+                .layers["a"]
+                .x_advance
+                .unwrap()
+        };
+        assert_eq!(advance_at(&fontgarden, 500.), 300.);
+
+        let changed = fontgarden
+            .set_layer_location(
+                &LayerName::sublayer("Thin", "brace"),
+                &[],
+                Some(location(&[("wght", 500.)])),
+            )
+            .unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(advance_at(&fontgarden, 500.), 400.);
+        assert_eq!(advance_at(&fontgarden, 300.), 250.);
+        assert!(matches!(
+            fontgarden.set_layer_location(&LayerName::main("Thin"), &[], None),
+            Err(InterpolationError::MainLayerLocation(_))
+        ));
+    }
+
+    #[test]
+    fn parse_instance_locations() {
+        let instance = Instance::parse("Medium=wght:500, wdth:75", &Config::default()).unwrap();
+        assert_eq!(instance.name, "Medium");
+        assert_eq!(
+            instance.location,
+            location(&[("wght", 500.), ("wdth", 75.)])
+        );
+        assert!(matches!(
+            Instance::parse("Medium", &Config::default()),
+            Err(InterpolationError::UnknownInstance(_))
+        ));
+        assert!(matches!(
+            Instance::parse("Medium=wght500", &Config::default()),
+            Err(InterpolationError::MalformedLocation(_))
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    errors::InterpolationError,
+    interpolate::Location,
+    structs::{Contour, Fontgarden, LayerName, PointType},
+};
+
+/// How many degrees a smooth point may bend before it counts as kinked, to
+/// allow for rounded coordinates.
+const KINK_TOLERANCE: f64 = 1.;
+
+/// A point by glyph name, contour index and point index.
+type PointKey = (String, usize, usize);
+
+/// A point that is smooth in all sources but kinks somewhere in between.
+#[derive(Debug, PartialEq)]
+pub struct Kink {
+    pub glyph_name: String,
+    pub contour_index: usize,
+    pub point_index: usize,
+    /// The largest bend found, in degrees.
+    pub angle: f64,
+    /// Axis tag to the smallest and largest value at which the point kinks, in
+    /// axis units. Axes along which the point kinks everywhere are left out.
+    pub region: BTreeMap<String, (f64, f64)>,
+}
+
+impl Display for Kink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "glyph {}, contour {}, point {}: smooth point kinks by up to {:.1}°",
+            self.glyph_name, self.contour_index, self.point_index, self.angle
+        )?;
+        if !self.region.is_empty() {
+            let ranges: Vec<String> = self
+                .region
+                .iter()
+                .map(|(axis_tag, (minimum, maximum))| format!("{axis_tag} {minimum}–{maximum}"))
+                .collect();
+            write!(f, " at {}", ranges.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Fontgarden {
+    /// Interpolate at `steps` locations from the default to the minimum and to
+    /// the maximum of each axis, and every combination of these across axes, and
+    /// find smooth points that kink there, sorted by glyph name.
+    ///
+    /// Glyphs that cannot be interpolated are skipped.
+    pub fn interpolation_kinks(&self, steps: usize) -> Result<Vec<Kink>, InterpolationError> {
+        let mut locations = vec![Location::new()];
+        for (axis_tag, axis) in &self.config.axes {
+            let mut values = vec![axis.default];
+            for step in 1..=steps {
+                let t = step as f64 / steps as f64;
+                values.push(axis.default + (axis.minimum - axis.default) * t);
+                values.push(axis.default + (axis.maximum - axis.default) * t);
+            }
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            locations = locations
+                .into_iter()
+                .flat_map(|location| {
+                    values.iter().map(move |value| {
+                        let mut location = location.clone();
+                        location.insert(axis_tag.clone(), *value);
+                        location
+                    })
+                })
+                .collect();
+        }
+
+        let masters = |glyph_name: &str| {
+            let glyph = &self.glyphs[glyph_name];
+            self.config
+                .sources
+                .keys()
+                .filter_map(|source_name| glyph.layers.get(&LayerName::main(source_name.as_str())))
+                .collect::<Vec<_>>()
+        };
+
+        // Points to the locations where they kink, with the angle.
+        let mut kinked: BTreeMap<PointKey, Vec<(&Location, f64)>> = BTreeMap::new();
+        for location in &locations {
+            let interpolation = self.interpolate(location)?;
+            for (glyph_name, layer) in &interpolation.layers {
+                let masters = masters(glyph_name);
+                for (contour_index, contour) in layer.contours.iter().enumerate() {
+                    for point_index in 0..contour.points.len() {
+                        let Some(angle) = contour.bend(point_index) else {
+                            continue;
+                        };
+                        if angle <= KINK_TOLERANCE {
+                            continue;
+                        }
+                        let smooth_in_masters = masters.iter().all(|master| {
+                            let contour = &master.contours[contour_index];
+                            contour.points[point_index].smooth
+                                && contour
+                                    .bend(point_index)
+                                    .is_some_and(|angle| angle <= KINK_TOLERANCE)
+                        });
+                        if smooth_in_masters {
+                            kinked
+                                .entry((glyph_name.clone(), contour_index, point_index))
+                                .or_default()
+                                .push((location, angle));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(kinked
+            .into_iter()
+            .map(|((glyph_name, contour_index, point_index), found)| {
+                let angle = found.iter().map(|(_, angle)| *angle).fold(0., f64::max);
+                let mut region = BTreeMap::new();
+                for (axis_tag, axis) in &self.config.axes {
+                    let values = found.iter().map(|(location, _)| location[axis_tag]);
+                    let minimum = values.clone().fold(f64::INFINITY, f64::min);
+                    let maximum = values.fold(f64::NEG_INFINITY, f64::max);
+                    if (minimum, maximum) != (axis.minimum, axis.maximum) {
+                        region.insert(axis_tag.clone(), (minimum, maximum));
+                    }
+                }
+                Kink {
+                    glyph_name,
+                    contour_index,
+                    point_index,
+                    angle,
+                    region,
+                }
+            })
+            .collect())
+    }
+}
+
+impl Contour {
+    /// How many degrees the outline turns at an on-curve point between its
+    /// neighbouring points, or `None` if it isn't an on-curve point between two
+    /// others or a neighbour is at the same position.
+    fn bend(&self, index: usize) -> Option<f64> {
+        let point_count = self.points.len();
+        let point = &self.points[index];
+        if point.typ == PointType::OffCurve || point_count < 3 {
+            return None;
+        }
+        if !self.is_closed() && (index == 0 || index == point_count - 1) {
+            return None;
+        }
+        let previous = &self.points[(index + point_count - 1) % point_count];
+        let next = &self.points[(index + 1) % point_count];
+        let incoming = (point.x - previous.x, point.y - previous.y);
+        let outgoing = (next.x - point.x, next.y - point.y);
+        if incoming == (0., 0.) || outgoing == (0., 0.) {
+            return None;
+        }
+        let cross = incoming.0 * outgoing.1 - incoming.1 * outgoing.0;
+        let dot = incoming.0 * outgoing.0 + incoming.1 * outgoing.1;
+        Some(cross.atan2(dot).abs().to_degrees())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{ContourPoint, ExtraFields, Glyph, Layer};
+
+    use super::*;
+
+    #[test]
+    fn find_kink_between_sources() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.config = toml::from_str(
+            r#"
+            axes.wght = { minimum = 100, default = 100, maximum = 900 }
+            sources.Thin.location = { wght = 100 }
+            sources.Black.location = { wght = 900 }
+            "#,
+        )
+        .unwrap();
+        // A smooth point at (100, 100) with handles that are collinear in both
+        // sources but turn the other way around it in between.
+        let layer = |handle_in: (f64, f64), handle_out: (f64, f64)| Layer {
+            contours: vec![Contour {
+                points: [
+                    (0., 0., PointType::Line, false),
+                    (handle_in.0, handle_in.1, PointType::OffCurve, false),
+                    (100., 100., PointType::Curve, true),
+                    (handle_out.0, handle_out.1, PointType::OffCurve, false),
+                    (200., 0., PointType::Curve, false),
+                ]
+                .into_iter()
+                .map(|(x, y, typ, smooth)| ContourPoint { x, y, typ, smooth })
+                .collect(),
+                extra: ExtraFields::new(),
+            }],
+            ..Default::default()
+        };
+        let mut glyph = Glyph::default();
+        glyph
+            .layers
+            .insert(LayerName::main("Thin"), layer((50., 100.), (150., 100.)));
+        glyph
+            .layers
+            .insert(LayerName::main("Black"), layer((100., 50.), (100., 200.)));
+        fontgarden.glyphs.insert("a".into(), glyph);
+
+        let kinks = fontgarden.interpolation_kinks(4).unwrap();
+
+        assert_eq!(kinks.len(), 1);
+        let kink = &kinks[0];
+        assert_eq!((kink.contour_index, kink.point_index), (0, 2));
+        assert_eq!(kink.region, BTreeMap::from([("wght".into(), (300., 700.))]));
+    }
+}
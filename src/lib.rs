@@ -0,0 +1,71 @@
+//! Fontgardens: font sources stored as one file per glyph layer, sorted into
+//! sets, for projects that share glyphs between families. The command line
+//! tool lives in `main.rs`; this library is what it and bindings to other
+//! languages are built on.
+
+pub mod alternates;
+pub mod binary;
+pub mod categorize;
+pub mod check;
+pub mod config;
+pub mod contours;
+pub mod copy_layer;
+pub mod default_glyphs;
+pub mod designspace;
+pub mod errors;
+pub mod features;
+pub mod fetch;
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+pub mod filenames;
+pub mod filters;
+pub mod fontra;
+pub mod garden_lib;
+pub mod geometry;
+pub mod glyphs_file;
+pub mod graph;
+pub mod hashing;
+pub mod hinting;
+pub mod history;
+pub mod hooks;
+pub mod incremental;
+pub mod interpolate;
+pub mod kinks;
+pub mod lib_keys;
+pub mod metrics;
+pub mod name_template;
+pub mod naming_scheme;
+pub mod normalize;
+pub mod openstep;
+pub mod par;
+pub mod precision;
+pub mod project;
+pub mod query;
+pub mod recipes;
+pub mod rename_map;
+pub mod render;
+pub mod report;
+pub mod scale;
+pub mod schema;
+pub mod selection;
+pub mod serve;
+pub mod set_metadata;
+pub mod show;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stamp;
+pub mod storage;
+pub mod strict;
+pub mod structs;
+pub mod sync;
+pub mod tags;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod ufo;
+pub mod validate;
+pub mod variable_components;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod workspace;
@@ -0,0 +1,206 @@
+//! UFO lib keys that the garden keeps for other tools, as configured in the
+//! `[lib_keys]` section: those of glyph libs in the layer files, and those of
+//! font and layer libs in `lib.<source>.json`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::LoadError,
+    filenames::name_to_filename,
+    filters::UFO2FT_FILTERS_LIB_KEY,
+    hinting::{POSTSCRIPT_HINT_LIB_KEYS, TRUETYPE_LIB_KEY},
+    selection::glob_matches,
+    stamp::BUILD_LIB_KEY,
+    storage::Storage,
+    variable_components::VARIABLE_COMPONENTS_LIB_KEY,
+};
+
+/// Lib keys that the garden reads and writes itself.
+const MANAGED_LIB_KEYS: [&str; 10] = [
+    "public.glyphOrder",
+    "public.openTypeCategories",
+    "public.postscriptNames",
+    "public.verticalOrigin",
+    TRUETYPE_LIB_KEY,
+    POSTSCRIPT_HINT_LIB_KEYS[0],
+    POSTSCRIPT_HINT_LIB_KEYS[1],
+    UFO2FT_FILTERS_LIB_KEY,
+    VARIABLE_COMPONENTS_LIB_KEY,
+    BUILD_LIB_KEY,
+];
+
+/// The name under which the lib of a source's main layer is kept.
+const MAIN_LAYER_KEY: &str = "public.default";
+
+/// Which lib keys to keep, by patterns with `*` and `?` wildcards, like
+/// `com.example.*`. Denied keys are not kept even if allowed, and neither
+/// are keys the garden handles itself.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LibKeysConfig {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl LibKeysConfig {
+    pub fn keeps(&self, key: &str) -> bool {
+        !MANAGED_LIB_KEYS.contains(&key)
+            && self.allow.iter().any(|pattern| glob_matches(pattern, key))
+            && !self.deny.iter().any(|pattern| glob_matches(pattern, key))
+    }
+
+    /// The kept keys of a lib.
+    pub fn take(&self, lib: &plist::Dictionary) -> BTreeMap<String, serde_json::Value> {
+        lib.iter()
+            .filter(|(key, _)| self.keeps(key))
+            .filter_map(|(key, value)| Some((key.clone(), serde_json::to_value(value).ok()?)))
+            .collect()
+    }
+
+    /// Put kept keys back into a lib, leaving out those no longer kept.
+    pub fn restore(&self, kept: &BTreeMap<String, serde_json::Value>, lib: &mut plist::Dictionary) {
+        insert_lib_keys(kept.iter().filter(|(key, _)| self.keeps(key)), lib);
+    }
+}
+
+/// Insert kept keys into a lib, skipping values plists cannot hold.
+pub fn insert_lib_keys<'a>(
+    kept: impl IntoIterator<Item = (&'a String, &'a serde_json::Value)>,
+    lib: &mut plist::Dictionary,
+) {
+    for (key, value) in kept {
+        match serde_json::from_value::<plist::Value>(value.clone()) {
+            Ok(value) => {
+                lib.insert(key.clone(), value);
+            }
+            Err(e) => eprintln!("warning: not exporting lib key {key}: {e}"),
+        }
+    }
+}
+
+/// The kept font and layer lib keys of a source.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceLib {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub font: BTreeMap<String, serde_json::Value>,
+    /// Layer lib keys by sublayer name, and `public.default` for the main
+    /// layer.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub layers: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+}
+
+impl SourceLib {
+    /// The name of the file of a source's lib keys in a fontgarden.
+    pub fn filename(source_name: &str) -> String {
+        name_to_filename(&format!("lib.{source_name}.json"))
+    }
+
+    pub fn load(storage: &impl Storage, path: &Path) -> Result<Self, LoadError> {
+        let json = storage
+            .read(path)
+            .map_err(|e| LoadError::Io(storage.location(path), e))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| LoadError::LoadSourceLib(storage.location(path), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take the kept keys of the font and layer libs of a UFO, with layers
+    /// named the way importing names sublayers.
+    pub fn from_ufo(ufo: &norad::Font, config: &LibKeysConfig) -> Self {
+        let default_layer = ufo.layers.default_layer();
+        let layers = ufo
+            .iter_layers()
+            .filter_map(|layer| {
+                let name = if std::ptr::eq(layer, default_layer) {
+                    MAIN_LAYER_KEY
+                } else if layer.name() == &"public.background" {
+                    "background"
+                } else {
+                    layer.name().as_str()
+                };
+                let kept = config.take(&layer.lib);
+                (!kept.is_empty()).then(|| (name.to_string(), kept))
+            })
+            .collect();
+        Self {
+            font: config.take(&ufo.lib),
+            layers,
+        }
+    }
+
+    /// Put the kept keys back into the font and layer libs of an exported UFO.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font, config: &LibKeysConfig) {
+        config.restore(&self.font, &mut ufo.lib);
+        for (layer_name, kept) in &self.layers {
+            let layer = match layer_name.as_str() {
+                MAIN_LAYER_KEY => Some(ufo.layers.default_layer_mut()),
+                _ => ufo.layers.get_mut(layer_name),
+            };
+            if let Some(layer) = layer {
+                config.restore(kept, &mut layer.lib);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_allowed_lib_keys() {
+        let config: LibKeysConfig = toml::from_str(
+            r#"
+            allow = ["com.example.*", "public.glyphOrder"]
+            deny = ["com.example.secret"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.keeps("com.example.tool"));
+        assert!(!config.keeps("com.example.secret"));
+        assert!(!config.keeps("com.other.tool"));
+        assert!(!config.keeps("public.glyphOrder"));
+
+        let mut ufo = norad::Font::new();
+        ufo.lib.insert("com.example.tool".into(), 1.into());
+        ufo.lib.insert("com.other.tool".into(), 2.into());
+        ufo.layers
+            .default_layer_mut()
+            .lib
+            .insert("com.example.layer".into(), "x".into());
+        ufo.layers
+            .get_or_create_layer("public.background")
+            .unwrap()
+            .lib
+            .insert("com.example.layer".into(), "y".into());
+
+        let source_lib = SourceLib::from_ufo(&ufo, &config);
+        assert_eq!(
+            source_lib.font,
+            BTreeMap::from([("com.example.tool".into(), 1.into())])
+        );
+        assert_eq!(
+            source_lib.layers.keys().collect::<Vec<_>>(),
+            ["background", MAIN_LAYER_KEY]
+        );
+
+        let mut exported = norad::Font::new();
+        exported.layers.get_or_create_layer("background").unwrap();
+        source_lib.apply_to_ufo(&mut exported, &config);
+        assert_eq!(exported.lib.len(), 1);
+        assert_eq!(
+            exported.layers.get("background").unwrap().lib["com.example.layer"],
+            plist::Value::String("y".into())
+        );
+        assert_eq!(
+            exported.layers.default_layer().lib["com.example.layer"],
+            plist::Value::String("x".into())
+        );
+    }
+}
@@ -1,21 +1,54 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
 };
 
 use clap::{CommandFactory, Parser, Subcommand};
 use rayon::prelude::*;
 
-use structs::Fontgarden;
-
-mod errors;
-mod filenames;
-mod structs;
-mod ufo;
+#[cfg(feature = "sqlite")]
+use fontgardener2::sqlite;
+use fontgardener2::{
+    alternates::ConditionSet,
+    check::{self, Check, CheckOptions, CiConfig},
+    config::JsonStyle,
+    contours::WindingConvention,
+    fetch, filenames,
+    filters::{FilterStep, FlattenComponents},
+    fontra, graph,
+    history::ListOrder,
+    hooks::{ChangeSummary, GlyphChanges, HookEvent},
+    incremental::{self, UfoWriteSummary},
+    interpolate::{Instance, Location},
+    metrics::SpacingRow,
+    name_template::NameTemplate,
+    naming_scheme::NamingScheme,
+    project, query, recipes,
+    rename_map::RenameMap,
+    report,
+    schema::SchemaKind,
+    selection::Selection,
+    stamp::{BuildStamp, FontVersion},
+    structs::{self, Fontgarden, LayerName},
+    sync::SyncDirection,
+    ufo::{
+        self, CodepointPolicy, CollisionPolicy, DeletePolicy, ExportFormat, ExportOptions,
+        ImportChannel, ImportOptions, SourceNaming,
+    },
+    validate,
+    workspace::Workspace,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// How many threads to load, save and export with [default: one per CPU]
+    #[arg(long, global = true, env = "FONTGARDEN_THREADS", value_name = "N")]
+    threads: Option<NonZeroUsize>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -26,9 +59,211 @@ enum Commands {
         /// Fontgarden package path to export from.
         fontgarden_path: PathBuf,
 
-        /// Sources to import.
+        /// UFO sources, or a single Fontra package, Glyphs file or SQLite fontgarden,
+        /// to import.
+        #[arg(required = true)]
+        sources: Vec<PathBuf>,
+
+        /// What to do when glyphs already in the fontgarden have different codepoints in the sources.
+        #[arg(long, value_enum, default_value_t)]
+        codepoints: CodepointPolicy,
+
+        /// Derive missing OpenType categories of imported glyphs from the glyph data.
+        #[arg(long)]
+        assign_opentype_categories: bool,
+
+        /// Only import glyphs belonging to this set or one nested in it [default:
+        /// all]
+        #[arg(long = "set", value_name = "SET_NAME")]
+        set_names: Vec<String>,
+
+        /// Also import glyphs from other sets that imported glyphs use as components.
+        #[arg(long)]
+        follow_components: bool,
+
+        /// What to remove from the fontgarden when the sources don't have it.
+        #[arg(long, value_enum, default_value_t)]
+        delete: DeletePolicy,
+
+        /// How to name sources: by style name, family and style name, or file name.
+        /// Sources without a style name are named after their file.
+        #[arg(long, value_enum, default_value_t)]
+        source_naming: SourceNaming,
+
+        /// Name a source explicitly, as e.g. "Sans-Bd.ufo=Bold".
+        #[arg(long = "source-name", value_name = "SOURCE=NAME", value_parser = parse_source_name)]
+        source_names: Vec<(String, String)>,
+
+        /// What to do when two sources end up with the same name.
+        #[arg(long, value_enum, default_value_t)]
+        on_collision: CollisionPolicy,
+
+        /// The source whose codepoints and glyph data win, remembered for later
+        /// imports [default: the remembered one, the one at the default location,
+        /// "Regular", or the first by name]
+        #[arg(long, value_name = "SOURCE_NAME")]
+        default_source: Option<String>,
+
+        /// Scale sources with different units per em to these, e.g. 1000.
+        #[arg(long, value_name = "UPM")]
+        scale_to_upm: Option<f64>,
+
+        /// Only import these layers, like "default,background", by the names
+        /// they get in the fontgarden, with "default" for the main layer
+        /// [default: all]
+        #[arg(long, value_delimiter = ',', value_name = "LAYER")]
+        layers: Vec<String>,
+
+        /// Only take these parts of the glyphs, like "metrics" for a spacing
+        /// pass, leaving the rest as it is in the fontgarden. Glyphs and
+        /// layers new to the fontgarden are left out, and nothing is deleted
+        /// [default: all]
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "PART")]
+        only: Vec<ImportChannel>,
+
+        /// A CSV file of source glyph names and the names to give the glyphs
+        /// in the fontgarden instead, one pair per line, with components
+        /// renamed along.
+        #[arg(long, value_name = "CSV_PATH")]
+        rename_map: Option<PathBuf>,
+
+        /// Change or remove glyphs of locked sets anyway.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import a compiled font as a new source, like to bring back a project
+    /// whose sources are lost.
+    ImportBinary {
+        /// Fontgarden package path to import into.
+        fontgarden_path: PathBuf,
+
+        /// The compiled font, a TTF or OTF file.
+        font_path: PathBuf,
+
+        /// Name of the new source [default: the style name of the font]
+        #[arg(long, value_name = "NAME")]
+        source_name: Option<String>,
+
+        /// What to do when glyphs already in the fontgarden have different codepoints in the font.
+        #[arg(long, value_enum, default_value_t)]
+        codepoints: CodepointPolicy,
+
+        /// Derive missing OpenType categories of imported glyphs from the glyph data.
+        #[arg(long)]
+        assign_opentype_categories: bool,
+
+        /// Only import glyphs belonging to this set or one nested in it [default:
+        /// all]
+        #[arg(long = "set", value_name = "SET_NAME")]
+        set_names: Vec<String>,
+
+        /// Scale a font with different units per em to these, e.g. 1000.
+        #[arg(long, value_name = "UPM")]
+        scale_to_upm: Option<f64>,
+
+        /// Change or remove glyphs of locked sets anyway.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Take the anchors of the glyph layers the fontgarden already has from
+    /// UFO sources, leaving everything else alone, like after a pass over the
+    /// mark positioning of a family.
+    SyncAnchors {
+        /// Fontgarden package path to update.
+        fontgarden_path: PathBuf,
+
+        /// UFO sources to take the anchors from.
         #[arg(required = true)]
         sources: Vec<PathBuf>,
+
+        /// How to name sources: by style name, family and style name, or file name.
+        #[arg(long, value_enum, default_value_t)]
+        source_naming: SourceNaming,
+
+        /// Name a source explicitly, as e.g. "Sans-Bd.ufo=Bold".
+        #[arg(long = "source-name", value_name = "SOURCE=NAME", value_parser = parse_source_name)]
+        source_names: Vec<(String, String)>,
+
+        /// Change anchors of glyphs of locked sets anyway.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Copy sets from another fontgarden, given by path or as the URL of a git
+    /// repository, with the glyphs they use as components.
+    Fetch {
+        /// Fontgarden package path to fetch into.
+        fontgarden_path: PathBuf,
+
+        /// Path or git URL of the fontgarden to fetch from.
+        location: String,
+
+        /// Sets to fetch, with the sets nested in them.
+        #[arg(long = "set", value_name = "SET_NAME", required = true)]
+        set_names: Vec<String>,
+
+        /// Replace glyphs that are already in the fontgarden.
+        #[arg(long)]
+        replace: bool,
+
+        /// The git revision to fetch, like a commit hash or tag [default: the
+        /// default branch]
+        #[arg(long)]
+        revision: Option<String>,
+
+        /// Where the fontgarden is in the repository [default: its root]
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+    /// Compare sets that two fontgardens share, and optionally make them
+    /// identical in either direction.
+    Sync {
+        /// Fontgarden package path to compare.
+        fontgarden_path: PathBuf,
+
+        /// The other fontgarden package path.
+        #[arg(long = "with", value_name = "PATH")]
+        other_path: PathBuf,
+
+        /// Sets to compare, with the sets nested in them.
+        #[arg(long, value_delimiter = ',', required = true)]
+        sets: Vec<String>,
+
+        /// Apply the differences, pulling from the other fontgarden or pushing
+        /// to it [default: only report them].
+        #[arg(long, value_name = "DIRECTION")]
+        apply: Option<SyncDirection>,
+
+        /// Apply even to glyphs of locked sets.
+        #[arg(long, requires = "apply")]
+        force: bool,
+    },
+    /// Write a new fontgarden with only some sets or glyphs and the glyphs they
+    /// use as components, like to hand a part of the project to a contractor.
+    Subset {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Sets to keep, with the sets nested in them.
+        #[arg(long, value_delimiter = ',', required_unless_present = "select")]
+        sets: Vec<String>,
+
+        /// Also keep the glyphs in this selection, like "name:*.sc".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Path of the new fontgarden, which must not exist yet.
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Fetch the sets of the dependencies in the configuration again and
+    /// report what changed.
+    UpdateDeps {
+        /// Fontgarden package path to update.
+        fontgarden_path: PathBuf,
+
+        /// Only report what would change.
+        #[arg(long)]
+        dry_run: bool,
     },
     Export {
         /// Fontgarden package path to export from.
@@ -40,60 +275,2412 @@ enum Commands {
         /// Sources to export glyphs for [default: all]
         #[arg(long = "source-name", value_name = "SOURCE_NAME")]
         source_names: Vec<String>,
+
+        /// Give composites the anchors of their components.
+        #[arg(long)]
+        propagate_anchors: bool,
+
+        /// Resolve components pointing to other composites, either to a single level of components or fully to outlines.
+        #[arg(long, value_enum)]
+        flatten_components: Option<FlattenComponents>,
+
+        /// Also interpolate a static instance, given as e.g. "Medium=wght:500" or by the name of an instance in the configuration.
+        #[arg(long = "instance", value_name = "INSTANCE")]
+        instances: Vec<String>,
+
+        /// Only export the glyphs in this selection, like "set:Latin and not
+        /// name:*.sc", and the glyphs they use as components.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Make up .notdef, space and other glyphs every font needs where
+        /// missing, with widths from the configuration or the units per em.
+        #[arg(long)]
+        add_default_glyphs: bool,
+
+        /// Write languagesystem statements and a locl feature inferred from
+        /// the scripts of the glyphs and their .loclXXX suffixes.
+        #[arg(long)]
+        generate_features: bool,
+
+        /// Write a project to build the fonts from: the sources with generated
+        /// features in a sources/ directory and a justfile with fontmake and
+        /// fontc recipes.
+        #[arg(long, conflicts_with = "layered")]
+        project: bool,
+
+        /// Write a single UFO named after the fontgarden, with the default
+        /// source in the default layer and the other sources as layers.
+        #[arg(long)]
+        layered: bool,
+
+        /// Format to export to. Fontra packages and Glyphs files take the
+        /// selection and source names but none of the UFO-specific options.
+        #[arg(long, value_enum, default_value_t, conflicts_with_all = ["project", "layered"])]
+        format: ExportFormat,
+
+        /// Name the UFOs like "{family}-{style}.ufo", with the family name,
+        /// the source or instance name and axis values like {wght} of its
+        /// location [default: {style}.ufo]
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["layered", "format"])]
+        name_template: Option<NameTemplate>,
+
+        /// Stamp this version, like 1.002, into the sources along with the
+        /// export date and the git revision of the fontgarden, to trace fonts
+        /// back to it.
+        #[arg(long, value_name = "VERSION", conflicts_with = "format")]
+        stamp_version: Option<FontVersion>,
+
+        /// Leave out backgrounds, sketches and other sublayers, except those
+        /// with a location that are sparse sources of the design space.
+        #[arg(long, conflicts_with = "format")]
+        skip_sublayers: bool,
+    },
+    Show {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Name of the glyph to show.
+        glyph_name: String,
+
+        /// Also dump the raw JSON data of each layer.
+        #[arg(long)]
+        json: bool,
+    },
+    Render {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Source layer to render glyphs from.
+        #[arg(long = "source")]
+        source_name: String,
+
+        /// Glyphs to render [default: all]
+        #[arg(long, value_delimiter = ',')]
+        glyphs: Vec<String>,
+
+        /// Render the glyphs in this selection, like "set:Latin and not name:*.sc".
+        #[arg(long, value_name = "EXPRESSION", conflicts_with = "glyphs")]
+        select: Option<Selection>,
+
+        /// Directory to write SVG files into [default: current dir].
+        #[arg(long = "out")]
+        output_dir: Option<PathBuf>,
+    },
+    CheckAnchors {
+        /// Fontgarden package path to check.
+        fontgarden_path: PathBuf,
+
+        /// Only list glyphs whose anchors differ between sources.
+        #[arg(long)]
+        only_problems: bool,
+    },
+    Metrics {
+        #[command(subcommand)]
+        command: MetricsCommands,
+    },
+    /// Lock sets, so that imports leave their glyphs and those of the sets
+    /// nested in them alone unless forced.
+    Lock {
+        /// Fontgarden package path to lock sets in.
+        fontgarden_path: PathBuf,
+
+        /// Sets to lock.
+        #[arg(required = true)]
+        set_names: Vec<String>,
+    },
+    /// Unlock sets again.
+    Unlock {
+        /// Fontgarden package path to unlock sets in.
+        fontgarden_path: PathBuf,
+
+        /// Sets to unlock.
+        #[arg(required = true)]
+        set_names: Vec<String>,
+    },
+    /// Mark glyphs with freeform tags, like `needs-kerning` or `review`, to
+    /// select them by with `tag:TAG`.
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+    /// Work on several gardens at once, as listed in a workspace manifest
+    /// (`fontgarden-workspace.toml`), taking components that a garden lacks
+    /// from the gardens it uses.
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
+    },
+    CheckCodepoints {
+        /// Fontgarden package path to check.
+        fontgarden_path: PathBuf,
+    },
+    CheckPaths {
+        /// Fontgarden package path to check.
+        fontgarden_path: PathBuf,
+
+        /// Expect outer contours to wind clockwise, for quadratic outlines.
+        #[arg(long)]
+        truetype: bool,
+    },
+    CheckMarkAnchors {
+        /// Fontgarden package path to check.
+        fontgarden_path: PathBuf,
+    },
+    /// Compare a compiled font with a source, to find where they drifted apart.
+    CompareBinary {
+        /// Fontgarden package path to compare with.
+        fontgarden_path: PathBuf,
+
+        /// The compiled font, a TTF or OTF file.
+        font_path: PathBuf,
+
+        /// The source to compare with [default: the default source].
+        #[arg(long = "source")]
+        source_name: Option<String>,
+    },
+    /// Run the checks of a profile and report all findings.
+    Check {
+        /// Fontgarden package path to check.
+        fontgarden_path: PathBuf,
+
+        /// The profile to run, from the configuration or built in (`quick`,
+        /// `prepublish`).
+        #[arg(long, default_value = "prepublish")]
+        profile: String,
+
+        /// Run these checks instead of a profile.
+        #[arg(long, value_delimiter = ',', conflicts_with = "profile")]
+        only: Vec<Check>,
+
+        /// Expect outer contours to wind clockwise, for quadratic outlines.
+        #[arg(long)]
+        truetype: bool,
+
+        /// Also write the findings as an HTML report with glyph thumbnails.
+        #[arg(long, value_name = "PATH")]
+        html: Option<PathBuf>,
+
+        /// Only report findings about the glyphs in this selection, like
+        /// "set:Latin and not name:*.sc", besides font-wide ones.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Print the findings as GitHub Actions annotations grouped by check,
+        /// with severities adjusted by the `ci` configuration and --error and
+        /// --warn. Exits with 0 without errors, 1 with errors and 2 if the
+        /// checks could not run.
+        #[arg(long)]
+        ci: bool,
+
+        /// Count the warnings of these checks as errors.
+        #[arg(long, value_delimiter = ',', requires = "ci")]
+        error: Vec<Check>,
+
+        /// Count the errors of these checks as warnings.
+        #[arg(long, value_delimiter = ',', requires = "ci")]
+        warn: Vec<Check>,
+    },
+    Categorize {
+        /// Fontgarden package path to categorize glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// Derive OpenType categories for glyphs that don't have one yet.
+        #[arg(long, required = true)]
+        opentype: bool,
+    },
+    Query {
+        /// Fontgarden package path to search in.
+        fontgarden_path: PathBuf,
+
+        /// Only match glyphs in this set or those nested in it.
+        #[arg(long)]
+        set: Option<String>,
+
+        /// Only look at this layer for the layer conditions [default: any layer]
+        #[arg(long = "in", value_name = "LAYER_NAME")]
+        layer: Option<LayerName>,
+
+        /// Match glyphs using this glyph as a component.
+        #[arg(long)]
+        uses_component: Option<String>,
+
+        /// Match glyphs having an anchor of this name.
+        #[arg(long)]
+        has_anchor: Option<String>,
+
+        /// Match glyphs lacking an anchor of this name.
+        #[arg(long)]
+        missing_anchor: Option<String>,
+
+        /// Match glyphs with more than this many points.
+        #[arg(long, value_name = "N")]
+        more_points_than: Option<usize>,
+
+        /// Match glyphs in this selection, like "set:Latin and not name:*.sc".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Print the matching glyphs as JSON with their set and codepoints.
+        #[arg(long)]
+        json: bool,
+    },
+    Graph {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Output format.
+        #[arg(long, value_enum, default_value_t)]
+        format: graph::GraphFormat,
+
+        /// Only include composites in this set or those nested in it.
+        #[arg(long)]
+        set: Option<String>,
+    },
+    UsedBy {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Name of the glyph to find users of.
+        glyph_name: String,
+
+        /// Also list glyphs using the users, and so on.
+        #[arg(long)]
+        recursive: bool,
+    },
+    PropagateAnchors {
+        /// Fontgarden package path to propagate anchors in.
+        fontgarden_path: PathBuf,
+    },
+    Recategorize {
+        /// Fontgarden package path to recategorize glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// Only move glyphs that are in the Common set.
+        #[arg(long)]
+        only_unset: bool,
+    },
+    Interpolate {
+        /// Fontgarden package path to add the source to.
+        fontgarden_path: PathBuf,
+
+        /// Where to put the new source, as e.g. "wght=600". Axes not given are at their default.
+        #[arg(long = "at", value_name = "AXIS=VALUE", value_parser = parse_axis_value, required = true)]
+        location: Vec<(String, f64)>,
+
+        /// Name of the new source.
+        #[arg(long)]
+        name: String,
+    },
+    SetLayerLocation {
+        /// Fontgarden package path to change.
+        fontgarden_path: PathBuf,
+
+        /// Sublayer to place, like "Bold.support".
+        layer_name: LayerName,
+
+        /// Only change the layer in this glyph [default: all glyphs with the layer]
+        #[arg(long = "glyph", value_name = "GLYPH_NAME")]
+        glyph_names: Vec<String>,
+
+        /// Where the layer is, as e.g. "wght=700". Axes not given are at their default.
+        #[arg(long = "at", value_name = "AXIS=VALUE", value_parser = parse_axis_value, required_unless_present = "clear")]
+        location: Vec<(String, f64)>,
+
+        /// Remove the location, so that the layer no longer takes part in interpolation.
+        #[arg(long, conflicts_with = "location")]
+        clear: bool,
+    },
+    /// Make a sublayer an alternate (bracket) layer, used instead of the main
+    /// layer where the axes are in the given ranges.
+    SetLayerCondition {
+        /// Fontgarden package path to change.
+        fontgarden_path: PathBuf,
+
+        /// Sublayer to make an alternate, like "Bold.bracket".
+        layer_name: LayerName,
+
+        /// Only change the layer in this glyph [default: all glyphs with the layer]
+        #[arg(long = "glyph", value_name = "GLYPH_NAME")]
+        glyph_names: Vec<String>,
+
+        /// The alternate is used from this axis value upwards, as e.g. "wght=600".
+        #[arg(long = "from", value_name = "AXIS=VALUE", value_parser = parse_axis_value, required_unless_present_any = ["maximums", "clear"])]
+        minimums: Vec<(String, f64)>,
+
+        /// The alternate is used up to this axis value, as e.g. "wdth=80".
+        #[arg(long = "to", value_name = "AXIS=VALUE", value_parser = parse_axis_value)]
+        maximums: Vec<(String, f64)>,
+
+        /// Remove the condition, so that the layer is a plain sublayer again.
+        #[arg(long, conflicts_with_all = ["minimums", "maximums"])]
+        clear: bool,
+    },
+    /// Interpolate the layers a source lacks in some glyphs from the other
+    /// sources, to fill in a sparse master.
+    FillLayer {
+        /// Fontgarden package path to fill layers in.
+        fontgarden_path: PathBuf,
+
+        /// The source to fill in, which must have a location in the configuration.
+        #[arg(long = "source", value_name = "SOURCE_NAME")]
+        source_name: String,
+
+        /// Glyphs to fill in [default: all glyphs without a layer in the source]
+        #[arg(long, value_delimiter = ',')]
+        glyphs: Vec<String>,
+
+        /// Fill in the glyphs in this selection, like "set:Latin".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+    /// Rewrite all files of a fontgarden in the canonical form that saving
+    /// writes, for pre-commit hooks.
+    Normalize {
+        /// Fontgarden package path to normalize.
+        fontgarden_path: PathBuf,
+
+        /// Only list the files that would change and fail if there are any.
+        #[arg(long)]
+        check: bool,
+
+        /// Switch to writing layer files in this style from now on.
+        #[arg(long, value_enum, conflicts_with = "check")]
+        json_style: Option<JsonStyle>,
+    },
+    /// Print stable content hashes of glyphs, over their metadata and all
+    /// layers, as also kept in `hashes.json`.
+    Hash {
+        /// Fontgarden package path to hash glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// Glyphs to hash [default: all]
+        glyph_names: Vec<String>,
+
+        /// Hash the glyphs in this selection.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+    /// Rename all glyphs to human-readable or production names using the glyph
+    /// data, fixing up components and PostScript names.
+    ConvertNames {
+        /// Fontgarden package path to rename glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// The naming scheme to convert to.
+        #[arg(long, value_enum)]
+        to: NamingScheme,
+
+        /// Write the old and new glyph names to this CSV file, to import
+        /// sources with the old names using --rename-map.
+        #[arg(long, value_name = "CSV_PATH")]
+        write_map: Option<PathBuf>,
+
+        /// Rename glyphs of locked sets anyway.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Copy the layers of one source to another, e.g. to start a new master
+    /// from an existing one.
+    CopyLayer {
+        /// Fontgarden package path to copy layers in.
+        fontgarden_path: PathBuf,
+
+        /// The source to copy the layers of.
+        #[arg(long, value_name = "SOURCE_NAME")]
+        from: String,
+
+        /// The source to copy the layers to, which may be a new one.
+        #[arg(long, value_name = "SOURCE_NAME")]
+        to: String,
+
+        /// Glyphs to copy [default: all glyphs with a layer in the source]
+        #[arg(long, value_delimiter = ',')]
+        glyphs: Vec<String>,
+
+        /// Copy the glyphs in this selection, like "set:Latin".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Also copy backgrounds and other sublayers without a location.
+        #[arg(long)]
+        sublayers: bool,
+
+        /// Replace layers the target source already has.
+        #[arg(long)]
+        replace: bool,
+
+        /// Where to put the target source if it is a new one, as e.g.
+        /// "wght=600". Axes not given are at their default.
+        #[arg(long = "at", value_name = "AXIS=VALUE", value_parser = parse_axis_value)]
+        location: Vec<(String, f64)>,
+    },
+    /// Scale all layers to different units per em, e.g. from 1000 to 2048.
+    ScaleUpm {
+        /// Fontgarden package path to scale.
+        fontgarden_path: PathBuf,
+
+        /// The units per em to scale to.
+        units_per_em: f64,
+
+        /// The current units per em, if the fontgarden has none recorded.
+        #[arg(long, value_name = "UPM")]
+        from: Option<f64>,
+    },
+    FixDirection {
+        /// Fontgarden package path to fix contour directions in.
+        fontgarden_path: PathBuf,
+
+        /// Wind outer contours counter-clockwise, for cubic outlines (default).
+        #[arg(long, conflicts_with = "truetype")]
+        postscript: bool,
+
+        /// Wind outer contours clockwise, for quadratic outlines.
+        #[arg(long)]
+        truetype: bool,
+    },
+    /// Build composite glyphs from recipes like "aacute=a+acutecomb@top", one
+    /// per line, in every source.
+    BuildComposites {
+        /// Fontgarden package path to build composites in.
+        fontgarden_path: PathBuf,
+
+        /// The file with the recipes.
+        #[arg(required_unless_present = "from_unicode")]
+        recipes_path: Option<PathBuf>,
+
+        /// Also build encoded glyphs without outlines from the glyphs for the
+        /// parts of their canonical Unicode decomposition.
+        #[arg(long)]
+        from_unicode: bool,
+
+        /// Replace the layers of glyphs that already exist.
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Print the feature code that export generates: languagesystem statements
+    /// for the scripts of the glyphs and a locl feature for local variants.
+    Features {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+    },
+    /// Show or change the project metadata in lib.json, like the family name or
+    /// keys for other tools.
+    Lib {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// The key to show or set [default: show all]
+        key: Option<String>,
+
+        /// The JSON value to set the key to, or a plain string. `null` removes
+        /// the key.
+        value: Option<String>,
+    },
+    /// List glyphs with when an import last changed any of their layers and
+    /// what was imported.
+    List {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Only list the glyphs in this selection, like "set:Latin".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// What to order the glyphs by.
+        #[arg(long, value_enum, default_value = "name")]
+        sort: ListOrder,
+    },
+    /// Show the imports that last changed glyph layers, newest first.
+    Log {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// How many imports to show.
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: usize,
+    },
+    /// Load a fontgarden to check that it is readable.
+    Load {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+        /// Also fail on unexpected files, glyph directories of glyphs in no
+        /// set, unknown set columns and layer files for unknown sources.
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Print the JSON Schema of a kind of fontgarden file, for editors and
+    /// external tools to validate it with.
+    Schema {
+        /// The kind of file to describe.
+        #[arg(value_enum, default_value = "layer")]
+        kind: SchemaKind,
+    },
+    /// Serve a read-only JSON API over a fontgarden: sets, glyphs, their
+    /// layers rendered to SVG and coverage statistics.
+    Serve {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8000")]
+        address: String,
+    },
+    /// Browse the sets, glyphs and layers of a fontgarden in the terminal,
+    /// with glyph search and outline previews.
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Directory to write SVG files of layers into [default: current dir].
+        #[arg(long = "out")]
+        output_dir: Option<PathBuf>,
     },
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+#[derive(Debug, Subcommand)]
+enum MetricsCommands {
+    /// Show advance widths per glyph and source and flag suspicious ones.
+    Report {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
 
-    match cli.command {
-        Commands::Import {
+        /// Expect all glyphs except marks to share the same width per source.
+        #[arg(long)]
+        monospace: bool,
+
+        /// Write the report as CSV to this path instead of printing it.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Make glyphs take their advance width from another glyph, on export and
+    /// right away.
+    Link {
+        /// Fontgarden package path to link advance widths in.
+        fontgarden_path: PathBuf,
+
+        /// Glyphs to link.
+        #[arg(required_unless_present = "select")]
+        glyph_names: Vec<String>,
+
+        /// Link the glyphs in this selection, like "has:component=a".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// The glyph to take the advance width from [default: the base component]
+        #[arg(long, value_name = "GLYPH_NAME")]
+        to: Option<String>,
+    },
+    /// Set sidebearings, measured from the outline bounds with components
+    /// resolved, by moving outlines and changing advance widths.
+    Set {
+        /// Fontgarden package path to change sidebearings in.
+        fontgarden_path: PathBuf,
+
+        /// Glyphs to change.
+        #[arg(long, value_delimiter = ',', required_unless_present = "select")]
+        glyphs: Vec<String>,
+
+        /// Change the glyphs in this selection, like "set:Latin and not name:*.sc".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Sources to change glyphs in [default: all]
+        #[arg(long = "source", value_name = "SOURCE_NAME")]
+        source_names: Vec<String>,
+
+        /// The left sidebearing to set.
+        #[arg(long, required_unless_present = "rsb", allow_negative_numbers = true)]
+        lsb: Option<f64>,
+
+        /// The right sidebearing to set.
+        #[arg(long, allow_negative_numbers = true)]
+        rsb: Option<f64>,
+    },
+    /// Write the advance widths and sidebearings of each source to a CSV file
+    /// named after it, for reviewing spacing in a spreadsheet.
+    Export {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// Directory to write the CSV files to.
+        #[arg(long = "out")]
+        output_dir: PathBuf,
+
+        /// Sources to write [default: all]
+        #[arg(long = "source", value_name = "SOURCE_NAME")]
+        source_names: Vec<String>,
+
+        /// Only write the glyphs in this selection.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+    /// Apply advance widths and sidebearings edited in CSV files written by
+    /// `metrics export`, each to the source it is named after.
+    Import {
+        /// Fontgarden package path to change spacing in.
+        fontgarden_path: PathBuf,
+
+        /// CSV files to read, like "Regular.csv".
+        #[arg(required = true)]
+        csv_paths: Vec<PathBuf>,
+    },
+    /// Remove advance links, keeping the current advance widths.
+    Unlink {
+        /// Fontgarden package path to unlink advance widths in.
+        fontgarden_path: PathBuf,
+
+        /// Glyphs to unlink.
+        #[arg(required_unless_present = "select")]
+        glyph_names: Vec<String>,
+
+        /// Unlink the glyphs in this selection.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TagCommands {
+    /// Add a tag to glyphs.
+    Add {
+        /// Fontgarden package path to tag glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// The tag to add.
+        tag: String,
+
+        /// Glyphs to tag.
+        #[arg(required_unless_present = "select")]
+        glyph_names: Vec<String>,
+
+        /// Tag the glyphs in this selection, like "set:Latin and has:anchor=top".
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+    /// Remove a tag from glyphs.
+    Remove {
+        /// Fontgarden package path to untag glyphs in.
+        fontgarden_path: PathBuf,
+
+        /// The tag to remove.
+        tag: String,
+
+        /// Glyphs to untag.
+        #[arg(required_unless_present_any = ["select", "all"])]
+        glyph_names: Vec<String>,
+
+        /// Untag the glyphs in this selection.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+
+        /// Untag every glyph.
+        #[arg(long)]
+        all: bool,
+    },
+    /// List the tags in use with their glyph counts, or the glyphs with a tag.
+    List {
+        /// Fontgarden package path to read from.
+        fontgarden_path: PathBuf,
+
+        /// The tag to list the glyphs of [default: list all tags]
+        tag: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum WorkspaceCommands {
+    /// List the glyphs of every garden, with those taken from other gardens.
+    List {
+        /// Workspace manifest, or the directory it is in.
+        manifest_path: PathBuf,
+
+        /// Only list the glyphs in this selection.
+        #[arg(long, value_name = "EXPRESSION")]
+        select: Option<Selection>,
+    },
+    /// Count the glyphs and codepoints of every garden, and list the
+    /// codepoints other gardens have that it lacks.
+    Coverage {
+        /// Workspace manifest, or the directory it is in.
+        manifest_path: PathBuf,
+    },
+    /// Export every garden to UFOs and a designspace file, in a directory per
+    /// garden.
+    Export {
+        /// Workspace manifest, or the directory it is in.
+        manifest_path: PathBuf,
+
+        /// Directory to write the garden directories into [default: current dir].
+        #[arg(long = "out")]
+        output_dir: Option<PathBuf>,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build_global()?;
+    }
+
+    match cli.command {
+        Commands::Import {
+            fontgarden_path,
+            sources,
+            codepoints,
+            assign_opentype_categories,
+            set_names,
+            follow_components,
+            delete,
+            source_naming,
+            source_names,
+            on_collision,
+            default_source,
+            scale_to_upm,
+            layers,
+            only,
+            rename_map,
+            force,
+        } => {
+            if sources.is_empty() {
+                error_and_exit(
+                    clap::error::ErrorKind::WrongNumberOfValues,
+                    "must give at least one source to import",
+                )
+            }
+            let mut fontgarden = if fontgarden_path.exists() {
+                Fontgarden::load(&fontgarden_path)?
+            } else {
+                Fontgarden::new()
+            };
+            let options = ImportOptions {
+                codepoint_policy: codepoints,
+                assign_opentype_categories,
+                sets: set_names.into_iter().collect(),
+                follow_components,
+                delete_policy: delete,
+                source_naming,
+                source_names: source_names.into_iter().collect(),
+                collision_policy: on_collision,
+                default_source,
+                scale_to_upm,
+                layers: layers.into_iter().collect(),
+                only: only.into_iter().collect(),
+                rename_map: match rename_map {
+                    Some(path) => RenameMap::load(&path)?,
+                    None => RenameMap::default(),
+                },
+            };
+            let hooks = fontgarden.config.hooks.clone();
+            let mut summary = ChangeSummary {
+                sources: sources
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+                ..Default::default()
+            };
+            hooks.run(HookEvent::PreImport, &fontgarden_path, &summary)?;
+            let glyphs_before = fontgarden.glyphs.clone();
+            let has_extension = |path: &PathBuf, extensions: &[&str]| {
+                path.extension()
+                    .is_some_and(|ext| extensions.iter().any(|e| ext == *e))
+            };
+            let is_fontra = |path: &PathBuf| has_extension(path, &["fontra"]);
+            let is_glyphs = |path: &PathBuf| has_extension(path, &["glyphs", "glyphspackage"]);
+            match sources.as_slice() {
+                [package] if is_fontra(package) => fontgarden.import_fontra(package, &options)?,
+                [file] if is_glyphs(file) => fontgarden.import_glyphs(file, &options)?,
+                #[cfg(feature = "sqlite")]
+                [file] if has_extension(file, &[sqlite::EXTENSION]) => {
+                    fontgarden.import_sqlite(file, &options)?
+                }
+                _ if sources
+                    .iter()
+                    .any(|path| is_fontra(path) || is_glyphs(path)) =>
+                {
+                    anyhow::bail!("a Fontra package or Glyphs file must be imported on its own")
+                }
+                _ => fontgarden.import_ufo_sources(&sources, &options)?,
+            }
+            check_locked_sets(&fontgarden, &glyphs_before, force)?;
+            fontgarden.record_modifications(
+                &glyphs_before,
+                &summary.sources.join(", "),
+                SystemTime::now(),
+            );
+            fontgarden.save(&fontgarden_path)?;
+            if hooks.has(HookEvent::PostImport) {
+                summary.glyphs = Some(GlyphChanges::between(&glyphs_before, &fontgarden.glyphs));
+                hooks.run(HookEvent::PostImport, &fontgarden_path, &summary)?;
+            }
+        }
+        Commands::ImportBinary {
+            fontgarden_path,
+            font_path,
+            source_name,
+            codepoints,
+            assign_opentype_categories,
+            set_names,
+            scale_to_upm,
+            force,
+        } => {
+            let mut fontgarden = if fontgarden_path.exists() {
+                Fontgarden::load(&fontgarden_path)?
+            } else {
+                Fontgarden::new()
+            };
+            let options = ImportOptions {
+                codepoint_policy: codepoints,
+                assign_opentype_categories,
+                sets: set_names.into_iter().collect(),
+                scale_to_upm,
+                ..Default::default()
+            };
+            let hooks = fontgarden.config.hooks.clone();
+            let mut summary = ChangeSummary {
+                sources: vec![font_path.to_string_lossy().into_owned()],
+                ..Default::default()
+            };
+            hooks.run(HookEvent::PreImport, &fontgarden_path, &summary)?;
+            let glyphs_before = fontgarden.glyphs.clone();
+            let source_name =
+                fontgarden.import_binary(&font_path, source_name.as_deref(), &options)?;
+            check_locked_sets(&fontgarden, &glyphs_before, force)?;
+            fontgarden.record_modifications(
+                &glyphs_before,
+                &font_path.to_string_lossy(),
+                SystemTime::now(),
+            );
+            fontgarden.save(&fontgarden_path)?;
+            println!("imported {} as source {source_name}", font_path.display());
+            if hooks.has(HookEvent::PostImport) {
+                summary.glyphs = Some(GlyphChanges::between(&glyphs_before, &fontgarden.glyphs));
+                hooks.run(HookEvent::PostImport, &fontgarden_path, &summary)?;
+            }
+        }
+        Commands::SyncAnchors {
+            fontgarden_path,
+            sources,
+            source_naming,
+            source_names,
+            force,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let hooks = fontgarden.config.hooks.clone();
+            let mut summary = ChangeSummary {
+                sources: sources
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+                ..Default::default()
+            };
+            hooks.run(HookEvent::PreImport, &fontgarden_path, &summary)?;
+            let glyphs_before = fontgarden.glyphs.clone();
+            let changed_layers = fontgarden.sync_anchors(
+                &sources,
+                source_naming,
+                source_names.into_iter().collect(),
+            )?;
+            check_locked_sets(&fontgarden, &glyphs_before, force)?;
+            println!("updated the anchors of {changed_layers} glyph layers");
+            fontgarden.record_modifications(
+                &glyphs_before,
+                &summary.sources.join(", "),
+                SystemTime::now(),
+            );
+            fontgarden.save(&fontgarden_path)?;
+            if hooks.has(HookEvent::PostImport) {
+                summary.glyphs = Some(GlyphChanges::between(&glyphs_before, &fontgarden.glyphs));
+                hooks.run(HookEvent::PostImport, &fontgarden_path, &summary)?;
+            }
+        }
+        Commands::Fetch {
+            fontgarden_path,
+            location,
+            set_names,
+            replace,
+            revision,
+            path,
+        } => {
+            let mut fontgarden = if fontgarden_path.exists() {
+                Fontgarden::load(&fontgarden_path)?
+            } else {
+                Fontgarden::new()
+            };
+            let hooks = fontgarden.config.hooks.clone();
+            let mut summary = ChangeSummary {
+                sources: vec![location.clone()],
+                ..Default::default()
+            };
+            hooks.run(HookEvent::PreImport, &fontgarden_path, &summary)?;
+            let glyphs_before = fontgarden.glyphs.clone();
+            let (other, commit) =
+                fetch::load_remote(&location, revision.as_deref(), path.as_deref())?;
+            if let Some(commit) = commit {
+                println!("fetched {location} at {commit}");
+            }
+            let report = fontgarden.fetch_sets(&other, &set_names, replace)?;
+            for glyph_name in &report.kept {
+                eprintln!("keeping glyph {glyph_name}, which is already in the fontgarden");
+            }
+            if !report.dropped_sources.is_empty() {
+                let source_names: Vec<&str> =
+                    report.dropped_sources.iter().map(|n| n.as_str()).collect();
+                eprintln!(
+                    "warning: leaving out the layers of sources the fontgarden doesn't have: {}",
+                    source_names.join(", ")
+                );
+            }
+            println!(
+                "added {} glyphs, replaced {}",
+                report.added.len(),
+                report.replaced.len()
+            );
+            fontgarden.record_modifications(&glyphs_before, &location, SystemTime::now());
+            fontgarden.save(&fontgarden_path)?;
+            if hooks.has(HookEvent::PostImport) {
+                summary.glyphs = Some(GlyphChanges::between(&glyphs_before, &fontgarden.glyphs));
+                hooks.run(HookEvent::PostImport, &fontgarden_path, &summary)?;
+            }
+        }
+        Commands::Sync {
+            fontgarden_path,
+            other_path,
+            sets,
+            apply,
+            force,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let mut other = Fontgarden::load(&other_path)?;
+            let (target, target_path, origin, origin_path) = match apply {
+                Some(SyncDirection::Push) => {
+                    (&mut other, &other_path, &fontgarden, &fontgarden_path)
+                }
+                _ => (&mut fontgarden, &fontgarden_path, &other, &other_path),
+            };
+            let glyphs_before = target.glyphs.clone();
+            let report = match apply {
+                Some(_) => target.take_sets(origin, &sets)?,
+                None => target.compare_sets(origin, &sets)?,
+            };
+            for (label, glyph_names) in [
+                ("only here", &report.only_here),
+                ("only there", &report.only_there),
+                ("differing", &report.differing),
+            ] {
+                if !glyph_names.is_empty() {
+                    println!("{label}: {}", glyph_names.join(", "));
+                }
+            }
+            if report.is_empty() {
+                println!("sets are identical");
+            } else if apply.is_some() {
+                check_locked_sets(target, &glyphs_before, force)?;
+                target.record_modifications(
+                    &glyphs_before,
+                    &origin_path.display().to_string(),
+                    SystemTime::now(),
+                );
+                target.save(target_path)?;
+                println!("synced {}", target_path.display());
+            }
+        }
+        Commands::Subset {
+            fontgarden_path,
+            sets,
+            select,
+            output,
+        } => {
+            if output.exists() {
+                anyhow::bail!("{} exists already", output.display());
+            }
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let subset = fontgarden.subset(&sets, select.as_ref());
+            if subset.glyphs.is_empty() {
+                anyhow::bail!("no glyphs in the given sets or selection");
+            }
+            subset.save(&output)?;
+            println!(
+                "wrote {} of {} glyphs to {}",
+                subset.glyphs.len(),
+                fontgarden.glyphs.len(),
+                output.display()
+            );
+        }
+        Commands::UpdateDeps {
+            fontgarden_path,
+            dry_run,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let hooks = fontgarden.config.hooks.clone();
+            let mut summary = ChangeSummary {
+                sources: fontgarden
+                    .config
+                    .dependencies
+                    .values()
+                    .map(|dependency| dependency.url.clone())
+                    .collect(),
+                ..Default::default()
+            };
+            if !dry_run {
+                hooks.run(HookEvent::PreImport, &fontgarden_path, &summary)?;
+            }
+            let glyphs_before = fontgarden.glyphs.clone();
+            for update in fontgarden.update_dependencies()? {
+                match &update.commit {
+                    Some(commit) => println!("{} at {commit}", update.name),
+                    None => println!("{}", update.name),
+                }
+                for (label, glyph_names) in [
+                    ("added", &update.changes.added),
+                    ("changed", &update.changes.changed),
+                    ("no longer in the dependency", &update.missing_upstream),
+                ] {
+                    if !glyph_names.is_empty() {
+                        println!("  {label}: {}", glyph_names.join(", "));
+                    }
+                }
+                if !update.dropped_sources.is_empty() {
+                    let source_names: Vec<&str> =
+                        update.dropped_sources.iter().map(|n| n.as_str()).collect();
+                    println!(
+                        "  left out layers of unknown sources: {}",
+                        source_names.join(", ")
+                    );
+                }
+            }
+            if !dry_run {
+                fontgarden.record_modifications(
+                    &glyphs_before,
+                    &summary.sources.join(", "),
+                    SystemTime::now(),
+                );
+                fontgarden.save(&fontgarden_path)?;
+                if hooks.has(HookEvent::PostImport) {
+                    summary.glyphs =
+                        Some(GlyphChanges::between(&glyphs_before, &fontgarden.glyphs));
+                    hooks.run(HookEvent::PostImport, &fontgarden_path, &summary)?;
+                }
+            }
+        }
+        Commands::Export {
+            fontgarden_path,
+            source_names,
+            output_dir,
+            propagate_anchors,
+            flatten_components,
+            instances,
+            select,
+            add_default_glyphs,
+            generate_features,
+            project,
+            layered,
+            format,
+            name_template,
+            stamp_version,
+            skip_sublayers,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let instances = instances
+                .iter()
+                .map(|spec| Instance::parse(spec, &fontgarden.config))
+                .collect::<Result<_, _>>()?;
+            let options = ExportOptions {
+                source_names: source_names.into_iter().collect(),
+                propagate_anchors,
+                flatten_components,
+                instances,
+                glyphs: select,
+                add_default_glyphs,
+                generate_features: generate_features || project,
+                name_template,
+                stamp: stamp_version.map(|version| BuildStamp::new(version, &fontgarden_path)),
+                skip_sublayers,
+            };
+            let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
+            let family_name = fontgarden_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "fontgarden".into());
+            let exported = if format != ExportFormat::Ufo {
+                command_export_single_file(
+                    &fontgarden,
+                    &options,
+                    &output_dir,
+                    &family_name,
+                    format,
+                )?
+            } else if project {
+                let sources_dir = output_dir.join(project::SOURCES_DIR);
+                let exported =
+                    command_export(&fontgarden, &options, &sources_dir, &family_name, false)?;
+                anyhow::ensure!(!exported.is_empty(), "there are no sources to export");
+                let has_designspace = sources_dir
+                    .join(&family_name)
+                    .with_extension("designspace")
+                    .exists();
+                let filenames = ufo_filenames(&fontgarden, &options, &family_name, &exported)?;
+                let filenames: Vec<String> = filenames.into_values().collect();
+                project::write_justfile(&output_dir, &family_name, &filenames, has_designspace)?;
+                exported
+            } else {
+                command_export(&fontgarden, &options, &output_dir, &family_name, layered)?
+            };
+            let summary = ChangeSummary {
+                sources: exported,
+                output_dir: Some(output_dir.to_string_lossy().into_owned()),
+                glyphs: None,
+            };
+            fontgarden
+                .config
+                .hooks
+                .run(HookEvent::PostExport, &fontgarden_path, &summary)?;
+        }
+        Commands::Show {
+            fontgarden_path,
+            glyph_name,
+            json,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            print!("{}", fontgarden.describe_glyph(&glyph_name, json)?);
+        }
+        Commands::Render {
+            fontgarden_path,
+            source_name,
+            glyphs,
+            select,
+            output_dir,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
+            let glyphs = match select {
+                Some(selection) => {
+                    let selected: Vec<String> = fontgarden
+                        .select(&selection)
+                        .into_iter()
+                        .map(String::from)
+                        .collect();
+                    if selected.is_empty() {
+                        anyhow::bail!("no glyphs in the selection");
+                    }
+                    selected
+                }
+                None => glyphs,
+            };
+            command_render(&fontgarden, &source_name, &glyphs, &output_dir)?;
+        }
+        Commands::CheckAnchors {
+            fontgarden_path,
+            only_problems,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            if !command_check_anchors(&fontgarden, only_problems) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Report {
+                    fontgarden_path,
+                    monospace,
+                    csv,
+                },
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            command_metrics_report(&fontgarden, monospace, csv.as_deref())?;
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Link {
+                    fontgarden_path,
+                    glyph_names,
+                    select,
+                    to,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for glyph_name in selected_glyph_names(&fontgarden, glyph_names, select.as_ref()) {
+                fontgarden.link_advance(&glyph_name, to.as_deref())?;
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Set {
+                    fontgarden_path,
+                    glyphs,
+                    select,
+                    source_names,
+                    lsb,
+                    rsb,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_names = selected_glyph_names(&fontgarden, glyphs, select.as_ref());
+            let source_names = source_names.into_iter().collect();
+            let changed = fontgarden.set_sidebearings(&glyph_names, &source_names, lsb, rsb)?;
+            println!("changed {changed} layers");
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Export {
+                    fontgarden_path,
+                    output_dir,
+                    source_names,
+                    select,
+                },
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let known_sources = fontgarden.source_names();
+            if let Some(source_name) = source_names.iter().find(|s| !known_sources.contains(*s)) {
+                anyhow::bail!("no source named '{source_name}' in the fontgarden");
+            }
+            let selected: Option<HashSet<&str>> = select
+                .as_ref()
+                .map(|selection| fontgarden.select(selection).into_iter().collect());
+            std::fs::create_dir_all(&output_dir)?;
+            for source_name in &known_sources {
+                if !source_names.is_empty() && !source_names.contains(source_name) {
+                    continue;
+                }
+                let csv_path = output_dir.join(format!("{source_name}.csv"));
+                let mut writer = csv::Writer::from_path(&csv_path)?;
+                for row in fontgarden.spacing_table(source_name) {
+                    if selected
+                        .as_ref()
+                        .is_none_or(|selected| selected.contains(row.glyph.as_str()))
+                    {
+                        writer.serialize(row)?;
+                    }
+                }
+                writer.flush()?;
+                println!("wrote {}", csv_path.display());
+            }
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Import {
+                    fontgarden_path,
+                    csv_paths,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let known_sources = fontgarden.source_names();
+            for csv_path in &csv_paths {
+                let Some(source_name) = csv_path.file_stem().and_then(|stem| stem.to_str()) else {
+                    anyhow::bail!("cannot tell the source of {}", csv_path.display());
+                };
+                if !known_sources.contains(source_name) {
+                    anyhow::bail!(
+                        "no source named '{source_name}' in the fontgarden for {}",
+                        csv_path.display()
+                    );
+                }
+                let rows: Vec<SpacingRow> = csv::Reader::from_path(csv_path)?
+                    .deserialize()
+                    .collect::<Result<_, _>>()?;
+                let changed = fontgarden.apply_spacing_table(source_name, &rows)?;
+                println!("changed {changed} layers in {source_name}");
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Metrics {
+            command:
+                MetricsCommands::Unlink {
+                    fontgarden_path,
+                    glyph_names,
+                    select,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for glyph_name in selected_glyph_names(&fontgarden, glyph_names, select.as_ref()) {
+                let Some(glyph) = fontgarden.glyphs.get_mut(glyph_name.as_str()) else {
+                    anyhow::bail!("no glyph named '{glyph_name}' in the fontgarden");
+                };
+                glyph.advance_link = None;
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::CheckCodepoints { fontgarden_path } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let problems = fontgarden.codepoint_problems();
+            for problem in &problems {
+                println!("{problem}");
+            }
+            if !problems.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::CheckPaths {
+            fontgarden_path,
+            truetype,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let convention = if truetype {
+                WindingConvention::TrueType
+            } else {
+                WindingConvention::PostScript
+            };
+            let problems = fontgarden.path_problems(convention);
+            for problem in &problems {
+                println!("{problem}");
+            }
+            if problems
+                .iter()
+                .any(|p| p.severity() == validate::Severity::Error)
+            {
+                std::process::exit(1);
+            }
+        }
+        Commands::CheckMarkAnchors { fontgarden_path } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let unmatched = fontgarden.unmatched_mark_anchors();
+            for unmatched_anchor in &unmatched {
+                println!("{unmatched_anchor}");
+            }
+            if !unmatched.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::CompareBinary {
+            fontgarden_path,
+            font_path,
+            source_name,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let source_names = fontgarden.source_names();
+            if source_names.is_empty() {
+                anyhow::bail!("the fontgarden has no sources");
+            }
+            let source_name = source_name
+                .unwrap_or_else(|| fontgarden.default_source_name(source_names.iter()).into());
+            let drifts = fontgarden.compare_binary(&font_path, &source_name)?;
+            for drift in &drifts {
+                println!("{drift}");
+            }
+            if !drifts.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Check {
+            fontgarden_path,
+            profile,
+            only,
+            truetype,
+            html,
+            select,
+            ci,
+            error,
+            warn,
+        } => {
+            let options = CheckOptions {
+                convention: if truetype {
+                    WindingConvention::TrueType
+                } else {
+                    WindingConvention::PostScript
+                },
+                glyphs: select,
+            };
+            let ci_overrides = ci.then_some(CiConfig { error, warn });
+            match command_check(
+                &fontgarden_path,
+                &profile,
+                only,
+                &options,
+                html.as_deref(),
+                ci_overrides.as_ref(),
+            ) {
+                Ok(false) => (),
+                Ok(true) => std::process::exit(check::EXIT_ERRORS),
+                Err(error) if ci => {
+                    eprintln!("error: {error:#}");
+                    std::process::exit(check::EXIT_FAILURE);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Commands::Categorize {
+            fontgarden_path,
+            opentype,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            if opentype {
+                let glyph_info = glyphsinfo_rs::GlyphData::default();
+                let assigned = fontgarden.assign_opentype_categories(&glyph_info);
+                println!("assigned OpenType categories to {assigned} glyphs");
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Query {
+            fontgarden_path,
+            set,
+            layer,
+            uses_component,
+            has_anchor,
+            missing_anchor,
+            more_points_than,
+            select,
+            json,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let query = query::Query {
+                set,
+                layer,
+                uses_component,
+                has_anchor,
+                missing_anchor,
+                more_points_than,
+                select,
+            };
+            command_query(&fontgarden, &query, json)?;
+        }
+        Commands::Graph {
+            fontgarden_path,
+            format,
+            set,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let graph = fontgarden.component_graph(set.as_deref());
+            match format {
+                graph::GraphFormat::Dot => print!("{}", graph::graph_to_dot(&graph)),
+                graph::GraphFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&graph)?)
+                }
+            }
+        }
+        Commands::UsedBy {
+            fontgarden_path,
+            glyph_name,
+            recursive,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            if !fontgarden.glyphs.contains_key(glyph_name.as_str()) {
+                anyhow::bail!("no glyph named '{glyph_name}' in the fontgarden");
+            }
+            for (user_name, layer_names) in fontgarden.used_by(&glyph_name, recursive) {
+                let layer_names: Vec<String> = layer_names.iter().map(|n| n.to_string()).collect();
+                println!("{user_name}: {}", layer_names.join(", "));
+            }
+        }
+        Commands::PropagateAnchors { fontgarden_path } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let changed = fontgarden.propagate_anchors();
+            println!("updated anchors in {changed} layers");
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Recategorize {
+            fontgarden_path,
+            only_unset,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_info = glyphsinfo_rs::GlyphData::default();
+            for (glyph_name, old_set, new_set) in
+                fontgarden.recategorize_sets(&glyph_info, only_unset)
+            {
+                println!(
+                    "{glyph_name}: {} -> {}",
+                    old_set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME),
+                    new_set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME),
+                );
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Interpolate {
+            fontgarden_path,
+            location,
+            name,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let incompatible =
+                fontgarden.add_interpolated_source(&name, location.into_iter().collect())?;
+            for (glyph_name, incompatibility) in &incompatible {
+                eprintln!("warning: leaving out glyph {glyph_name}: {incompatibility}");
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::SetLayerLocation {
+            fontgarden_path,
+            layer_name,
+            glyph_names,
+            location,
+            clear,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let location = (!clear).then(|| location.into_iter().collect());
+            let changed = fontgarden.set_layer_location(&layer_name, &glyph_names, location)?;
+            println!("updated {changed} layers");
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::SetLayerCondition {
+            fontgarden_path,
+            layer_name,
+            glyph_names,
+            minimums,
+            maximums,
+            clear,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let mut conditions = ConditionSet::new();
+            for (axis_tag, minimum) in minimums {
+                conditions.entry(axis_tag).or_default().minimum = Some(minimum);
+            }
+            for (axis_tag, maximum) in maximums {
+                conditions.entry(axis_tag).or_default().maximum = Some(maximum);
+            }
+            let conditions = (!clear).then_some(conditions);
+            let changed = fontgarden.set_layer_condition(&layer_name, &glyph_names, conditions)?;
+            println!("updated {changed} layers");
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Normalize {
+            fontgarden_path,
+            check,
+            json_style,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            if let Some(json_style) = json_style {
+                fontgarden.config.set_json_style(json_style);
+            }
+            let changes = fontgarden.normalization_changes(&fontgarden_path)?;
+            if check {
+                for file in &changes {
+                    println!("not normalized: {}", file.display());
+                }
+                if !changes.is_empty() {
+                    std::process::exit(1);
+                }
+            } else if !changes.is_empty() {
+                fontgarden.save(&fontgarden_path)?;
+                println!("normalized {} files", changes.len());
+            }
+        }
+        Commands::Hash {
+            fontgarden_path,
+            glyph_names,
+            select,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let mut glyph_names = if glyph_names.is_empty() && select.is_none() {
+                fontgarden.glyphs.keys().map(|n| n.to_string()).collect()
+            } else {
+                selected_glyph_names(&fontgarden, glyph_names, select.as_ref())
+            };
+            glyph_names.sort();
+            glyph_names.dedup();
+            for glyph_name in glyph_names {
+                let Some(glyph) = fontgarden.glyphs.get(glyph_name.as_str()) else {
+                    anyhow::bail!("no glyph named '{glyph_name}' in the fontgarden");
+                };
+                let hash = glyph.saved_content_hash(fontgarden.config.precision);
+                println!("{hash}  {glyph_name}");
+            }
+        }
+        Commands::ConvertNames {
+            fontgarden_path,
+            to,
+            write_map,
+            force,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyphs_before = fontgarden.glyphs.clone();
+            let glyph_info = glyphsinfo_rs::GlyphData::default();
+            let renames = fontgarden.convert_naming_scheme(to, &glyph_info)?;
+            check_locked_sets(&fontgarden, &glyphs_before, force)?;
+            let export = &fontgarden.config.export;
+            let mut stale: BTreeSet<&str> = export
+                .default_glyph_widths
+                .keys()
+                .filter(|glyph_name| renames.contains_key(*glyph_name))
+                .map(String::as_str)
+                .collect();
+            for step in export
+                .pipeline
+                .iter()
+                .chain(export.sources.values().flatten())
+                .chain(export.instances.values().flatten())
+            {
+                if let FilterStep::RenameMap(export_renames) = step {
+                    stale.extend(
+                        export_renames
+                            .keys()
+                            .filter(|glyph_name| renames.contains_key(*glyph_name))
+                            .map(String::as_str),
+                    );
+                }
+            }
+            if !stale.is_empty() {
+                eprintln!(
+                    "warning: the export configuration still uses the old names of: {}",
+                    stale.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+            if let Some(path) = write_map {
+                let mut writer = csv::Writer::from_path(&path)?;
+                for (old_name, new_name) in &renames {
+                    writer.write_record([old_name, new_name])?;
+                }
+                writer.flush()?;
+            }
+            println!("renamed {} glyphs", renames.len());
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::FillLayer {
+            fontgarden_path,
+            source_name,
+            glyphs,
+            select,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_names = selected_glyph_names(&fontgarden, glyphs, select.as_ref());
+            if select.is_some() && glyph_names.is_empty() {
+                anyhow::bail!("the selection matches no glyphs");
+            }
+            let filled = fontgarden.fill_layers(&source_name, &glyph_names)?;
+            for (glyph_name, incompatibility) in &filled.incompatible {
+                eprintln!("warning: leaving out glyph {glyph_name}: {incompatibility}");
+            }
+            println!("filled {} layers of {source_name}", filled.layers.len());
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::CopyLayer {
+            fontgarden_path,
+            from,
+            to,
+            glyphs,
+            select,
+            sublayers,
+            replace,
+            location,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let location: Location = location.into_iter().collect();
+            if !location.is_empty() {
+                if fontgarden.source_names().contains(&to) {
+                    anyhow::bail!("source {to} already exists; --at is only for new sources");
+                }
+                fontgarden.normalize_location(&location)?;
+            }
+            let glyph_names = selected_glyph_names(&fontgarden, glyphs, select.as_ref());
+            if select.is_some() && glyph_names.is_empty() {
+                anyhow::bail!("the selection matches no glyphs");
+            }
+            let copied = fontgarden.copy_layers(&from, &to, &glyph_names, sublayers, replace)?;
+            println!("copied {} layers from {from} to {to}", copied.copied);
+            if copied.skipped > 0 {
+                println!(
+                    "skipped {} layers that {to} already has; use --replace to overwrite them",
+                    copied.skipped
+                );
+            }
+            if !location.is_empty() {
+                fontgarden.config.add_source(&to, location);
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::ScaleUpm {
+            fontgarden_path,
+            units_per_em,
+            from,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let Some(from) = from.or(fontgarden.config.units_per_em) else {
+                anyhow::bail!("the fontgarden has no units per em recorded, pass --from");
+            };
+            fontgarden.scale_units_per_em(from, units_per_em);
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::FixDirection {
+            fontgarden_path,
+            postscript: _,
+            truetype,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let convention = if truetype {
+                WindingConvention::TrueType
+            } else {
+                WindingConvention::PostScript
+            };
+            let reversed = fontgarden.fix_direction(convention);
+            println!("reversed {reversed} contours");
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::BuildComposites {
+            fontgarden_path,
+            recipes_path,
+            from_unicode,
+            replace,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let mut recipes = match recipes_path {
+                Some(recipes_path) => recipes::load_recipes(&recipes_path)?,
+                None => Vec::new(),
+            };
+            if from_unicode {
+                recipes.extend(fontgarden.precomposed_recipes());
+            }
+            let problems = fontgarden.build_composites(&recipes, replace)?;
+            for problem in &problems {
+                eprintln!("warning: {problem}");
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Features { fontgarden_path } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_info = glyphsinfo_rs::GlyphData::default();
+            print!("{}", fontgarden.generate_features(&glyph_info));
+        }
+        Commands::Lib {
+            fontgarden_path,
+            key,
+            value,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            match (key, value) {
+                (None, _) => println!("{}", serde_json::to_string_pretty(&fontgarden.lib)?),
+                (Some(key), None) => match fontgarden.lib.get(&key) {
+                    Some(value) => println!("{}", serde_json::to_string_pretty(&value)?),
+                    None => anyhow::bail!("the lib has no key {key}"),
+                },
+                (Some(key), Some(value)) => {
+                    let value =
+                        serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+                    if let Err(error) = fontgarden.lib.set(&key, value) {
+                        anyhow::bail!("cannot set {key}: {error}");
+                    }
+                    fontgarden.save(&fontgarden_path)?;
+                }
+            }
+        }
+        Commands::List {
+            fontgarden_path,
+            select,
+            sort,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for (glyph_name, modification) in fontgarden.list_glyphs(select.as_ref(), sort) {
+                match modification {
+                    Some(modification) => println!(
+                        "{glyph_name}\t{}\t{}",
+                        modification.modified, modification.source
+                    ),
+                    None => println!("{glyph_name}"),
+                }
+            }
+        }
+        Commands::Log {
+            fontgarden_path,
+            limit,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for (modification, glyphs) in fontgarden.modified.log().into_iter().take(limit) {
+                println!("{}  {}", modification.modified, modification.source);
+                for (glyph_name, layer_names) in glyphs {
+                    println!("  {glyph_name}: {}", layer_names.join(", "));
+                }
+            }
+        }
+        Commands::Load {
+            fontgarden_path,
+            strict,
+        } => {
+            let fontgarden = if strict {
+                Fontgarden::load_strict(&fontgarden_path)?
+            } else {
+                Fontgarden::load(&fontgarden_path)?
+            };
+            println!(
+                "{} glyphs in {} sources",
+                fontgarden.glyphs.len(),
+                fontgarden.source_names().len()
+            );
+        }
+        Commands::Lock {
             fontgarden_path,
-            sources,
+            set_names,
         } => {
-            if sources.is_empty() {
-                error_and_exit(
-                    clap::error::ErrorKind::WrongNumberOfValues,
-                    "must give at least one source to import",
-                )
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for set_name in &set_names {
+                fontgarden.set_locked(set_name, true);
             }
-            let mut fontgarden = if fontgarden_path.exists() {
-                Fontgarden::load(&fontgarden_path)?
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Unlock {
+            fontgarden_path,
+            set_names,
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            for set_name in &set_names {
+                fontgarden.set_locked(set_name, false);
+            }
+            fontgarden.save(&fontgarden_path)?;
+        }
+        Commands::Tag {
+            command:
+                TagCommands::Add {
+                    fontgarden_path,
+                    tag,
+                    glyph_names,
+                    select,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_names = selected_glyph_names(&fontgarden, glyph_names, select.as_ref());
+            let tagged = fontgarden.tag_glyphs(&glyph_names, &tag)?;
+            fontgarden.save(&fontgarden_path)?;
+            println!("tagged {} glyphs with {tag}", tagged.len());
+        }
+        Commands::Tag {
+            command:
+                TagCommands::Remove {
+                    fontgarden_path,
+                    tag,
+                    glyph_names,
+                    select,
+                    all,
+                },
+        } => {
+            let mut fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let glyph_names = if all {
+                fontgarden.glyphs.keys().map(|n| n.to_string()).collect()
             } else {
-                Fontgarden::new()
+                selected_glyph_names(&fontgarden, glyph_names, select.as_ref())
             };
-            fontgarden.import_ufo_sources(&sources)?;
+            let untagged = fontgarden.untag_glyphs(&glyph_names, &tag)?;
             fontgarden.save(&fontgarden_path)?;
+            println!("removed {tag} from {} glyphs", untagged.len());
         }
-        Commands::Export {
+        Commands::Tag {
+            command:
+                TagCommands::List {
+                    fontgarden_path,
+                    tag,
+                },
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            let tags = fontgarden.tags();
+            match tag {
+                Some(tag) => {
+                    for glyph_name in tags.get(tag.as_str()).into_iter().flatten() {
+                        println!("{glyph_name}");
+                    }
+                }
+                None => {
+                    for (tag, glyph_names) in tags {
+                        println!("{tag}\t{}", glyph_names.len());
+                    }
+                }
+            }
+        }
+        Commands::Workspace {
+            command:
+                WorkspaceCommands::List {
+                    manifest_path,
+                    select,
+                },
+        } => {
+            let workspace = Workspace::load(&manifest_path)?;
+            let gardens = workspace.load_gardens()?;
+            for garden_name in gardens.keys() {
+                let resolved = workspace.resolve(&gardens, garden_name);
+                let mut glyph_names: Vec<&Arc<str>> = resolved
+                    .fontgarden
+                    .glyphs
+                    .iter()
+                    .filter(|(glyph_name, glyph)| {
+                        select
+                            .as_ref()
+                            .is_none_or(|select| select.matches(glyph_name, glyph))
+                    })
+                    .map(|(glyph_name, _)| glyph_name)
+                    .collect();
+                glyph_names.sort();
+                for glyph_name in glyph_names {
+                    match resolved.borrowed.get(&**glyph_name) {
+                        Some(from) => println!("{garden_name}\t{glyph_name}\t(from {from})"),
+                        None => println!("{garden_name}\t{glyph_name}"),
+                    }
+                }
+            }
+        }
+        Commands::Workspace {
+            command: WorkspaceCommands::Coverage { manifest_path },
+        } => {
+            let workspace = Workspace::load(&manifest_path)?;
+            let gardens = workspace.load_gardens()?;
+            let codepoints: BTreeMap<&str, BTreeSet<char>> = gardens
+                .iter()
+                .map(|(garden_name, fontgarden)| {
+                    let codepoints = fontgarden
+                        .glyphs
+                        .values()
+                        .flat_map(|glyph| glyph.codepoints.iter())
+                        .collect();
+                    (garden_name.as_str(), codepoints)
+                })
+                .collect();
+            let all_codepoints: BTreeSet<char> = codepoints.values().flatten().copied().collect();
+            for (garden_name, fontgarden) in &gardens {
+                let garden_codepoints = &codepoints[garden_name.as_str()];
+                println!(
+                    "{garden_name}\t{} glyphs\t{} codepoints",
+                    fontgarden.glyphs.len(),
+                    garden_codepoints.len()
+                );
+                let missing: Vec<String> = all_codepoints
+                    .difference(garden_codepoints)
+                    .map(|c| format!("U+{:04X}", *c as u32))
+                    .collect();
+                if !missing.is_empty() {
+                    println!("  missing: {}", missing.join(" "));
+                }
+            }
+        }
+        Commands::Workspace {
+            command:
+                WorkspaceCommands::Export {
+                    manifest_path,
+                    output_dir,
+                },
+        } => {
+            let workspace = Workspace::load(&manifest_path)?;
+            let gardens = workspace.load_gardens()?;
+            let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
+            for garden_name in gardens.keys() {
+                let resolved = workspace.resolve(&gardens, garden_name);
+                let exported = command_export(
+                    &resolved.fontgarden,
+                    &ExportOptions::default(),
+                    &output_dir.join(garden_name),
+                    garden_name,
+                    false,
+                )?;
+                println!("{garden_name}: exported {}", exported.join(", "));
+            }
+        }
+        Commands::Schema { kind } => {
+            println!("{}", serde_json::to_string_pretty(&kind.schema())?);
+        }
+        Commands::Serve {
+            fontgarden_path,
+            address,
+        } => {
+            let fontgarden = Fontgarden::load(&fontgarden_path)?;
+            println!("serving {} at http://{address}", fontgarden_path.display());
+            fontgarden.serve(&address)?;
+        }
+        #[cfg(feature = "tui")]
+        Commands::Tui {
             fontgarden_path,
-            source_names,
             output_dir,
         } => {
             let fontgarden = Fontgarden::load(&fontgarden_path)?;
-            let source_names: HashSet<&str> = source_names.iter().map(|s| s.as_str()).collect();
             let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
-            command_export(&fontgarden, &source_names, &output_dir)?;
+            fontgardener2::tui::browse(&fontgarden, &output_dir)?;
         }
     }
 
     Ok(())
 }
 
+/// Fail if an import changed or removed glyphs of locked sets, unless forced.
+fn check_locked_sets(
+    fontgarden: &Fontgarden,
+    glyphs_before: &HashMap<Arc<str>, structs::Glyph>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let changed = fontgarden.locked_glyph_changes(glyphs_before);
+    if !changed.is_empty() && !force {
+        anyhow::bail!(
+            "refusing to change glyphs of locked sets without --force: {}",
+            changed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The glyphs given by name, followed by those in the selection.
+fn selected_glyph_names(
+    fontgarden: &Fontgarden,
+    mut glyph_names: Vec<String>,
+    selection: Option<&Selection>,
+) -> Vec<String> {
+    if let Some(selection) = selection {
+        glyph_names.extend(fontgarden.select(selection).into_iter().map(String::from));
+    }
+    glyph_names
+}
+
+fn parse_axis_value(value: &str) -> Result<(String, f64), String> {
+    let (axis, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected AXIS=VALUE, got '{value}'"))?;
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a number"))?;
+    Ok((axis.to_string(), value))
+}
+
+fn parse_source_name(value: &str) -> Result<(String, String), String> {
+    let (source, name) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected SOURCE=NAME, got '{value}'"))?;
+    Ok((source.to_string(), name.to_string()))
+}
+
 fn command_export(
     fontgarden: &Fontgarden,
-    source_names: &HashSet<&str>,
+    options: &ExportOptions,
+    output_dir: &Path,
+    family_name: &str,
+    layered: bool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let sources: HashMap<String, norad::Font> = fontgarden.export_ufo_sources(options)?;
+
+    std::fs::create_dir_all(output_dir)?;
+    let source_names: HashSet<String> = sources.keys().cloned().collect();
+    let exported_glyph_names: HashSet<&str> = sources
+        .values()
+        .flat_map(|source| source.default_layer().iter().map(|g| g.name().as_str()))
+        .collect();
+    if let Some(credits) = fontgarden.credits(exported_glyph_names) {
+        std::fs::write(output_dir.join("CREDITS.txt"), credits)?;
+    }
+    let mut summary = UfoWriteSummary::default();
+    if layered {
+        if !sources.is_empty() {
+            let default_source_name = fontgarden.default_source_name(source_names.iter());
+            let ufo = ufo::layered_ufo(sources, default_source_name)?;
+            let ufo_path = output_dir.join(family_name).with_extension("ufo");
+            summary = incremental::write_ufo(&ufo, &ufo_path)?;
+        }
+    } else {
+        let filenames = ufo_filenames(fontgarden, options, family_name, source_names.iter())?;
+        let instance_filenames = ufo_filenames(
+            fontgarden,
+            options,
+            family_name,
+            fontgarden.config.instances.keys(),
+        )?;
+        if let Some(designspace) = fontgarden.to_designspace(&filenames, &instance_filenames) {
+            std::fs::write(
+                output_dir.join(family_name).with_extension("designspace"),
+                designspace,
+            )?;
+        }
+        let summaries: Vec<UfoWriteSummary> = sources
+            .into_par_iter()
+            .map(|(source_name, source)| {
+                incremental::write_ufo(&source, &output_dir.join(&filenames[&source_name]))
+            })
+            .collect::<Result<_, _>>()?;
+        for source_summary in summaries {
+            summary += source_summary;
+        }
+    }
+    if summary.unchanged > 0 || summary.removed > 0 {
+        println!(
+            "wrote {} files, kept {} unchanged and removed {}",
+            summary.written, summary.unchanged, summary.removed
+        );
+    }
+
+    let mut source_names: Vec<String> = source_names.into_iter().collect();
+    source_names.sort();
+    Ok(source_names)
+}
+
+/// The names of the UFOs of exported sources and instances, by source or
+/// instance name.
+fn ufo_filenames<'a>(
+    fontgarden: &Fontgarden,
+    options: &ExportOptions,
+    family_name: &str,
+    source_names: impl IntoIterator<Item = &'a String>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut filenames = BTreeMap::new();
+    for source_name in source_names {
+        let filename = match &options.name_template {
+            Some(template) => {
+                let location = options
+                    .instances
+                    .iter()
+                    .find(|instance| &instance.name == source_name)
+                    .map(|instance| &instance.location)
+                    .or_else(|| {
+                        let source = fontgarden.config.sources.get(source_name);
+                        source.map(|source| &source.location)
+                    })
+                    .or_else(|| {
+                        let instance = fontgarden.config.instances.get(source_name);
+                        instance.map(|instance| &instance.location)
+                    });
+                template.render(
+                    family_name,
+                    source_name,
+                    location.unwrap_or(&Location::new()),
+                )?
+            }
+            None => format!("{source_name}.ufo"),
+        };
+        if let Some(other) = filenames
+            .iter()
+            .find_map(|(other, f)| (f == &filename).then_some(other))
+        {
+            anyhow::bail!("{other} and {source_name} would both be exported to {filename}");
+        }
+        filenames.insert(source_name.clone(), filename);
+    }
+    Ok(filenames)
+}
+
+/// Export to a format that has all sources in one file or package.
+fn command_export_single_file(
+    fontgarden: &Fontgarden,
+    options: &ExportOptions,
+    output_dir: &Path,
+    family_name: &str,
+    format: ExportFormat,
+) -> anyhow::Result<Vec<String>> {
+    let mut fontgarden = fontgarden.clone();
+    // Copies of the fontgarden keep their advance links.
+    #[cfg(feature = "sqlite")]
+    let keeps_advance_links = format == ExportFormat::Sqlite;
+    #[cfg(not(feature = "sqlite"))]
+    let keeps_advance_links = false;
+    if !keeps_advance_links {
+        fontgarden.resolve_advance_links();
+    }
+    if let Some(selection) = &options.glyphs {
+        fontgarden.retain_selection(selection);
+    }
+    if !options.source_names.is_empty() {
+        for glyph in fontgarden.glyphs.values_mut() {
+            glyph
+                .layers
+                .retain(|layer_name, _| options.source_names.contains(&*layer_name.source));
+        }
+        fontgarden
+            .config
+            .sources
+            .retain(|source_name, _| options.source_names.contains(source_name));
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    match format {
+        ExportFormat::Fontra => {
+            fontgarden.export_fontra(&fontra::package_path(output_dir, family_name), family_name)?
+        }
+        ExportFormat::Glyphs => fontgarden.export_glyphs(
+            &output_dir.join(format!("{family_name}.glyphs")),
+            family_name,
+        )?,
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let path = output_dir
+                .join(family_name)
+                .with_extension(sqlite::EXTENSION);
+            fontgarden.save_sqlite(&path)?;
+        }
+        ExportFormat::Ufo => unreachable!("UFOs are exported per source"),
+    }
+    Ok(fontgarden.source_names().into_iter().collect())
+}
+
+fn command_render(
+    fontgarden: &Fontgarden,
+    source_name: &str,
+    glyph_names: &[String],
     output_dir: &Path,
-) -> Result<(), anyhow::Error> {
-    let sources: HashMap<String, norad::Font> = fontgarden.export_ufo_sources(source_names)?;
+) -> anyhow::Result<()> {
+    let glyph_names: Vec<&str> = if glyph_names.is_empty() {
+        fontgarden.glyphs.keys().map(|n| n.as_ref()).collect()
+    } else {
+        for name in glyph_names {
+            if !fontgarden.glyphs.contains_key(name.as_str()) {
+                anyhow::bail!("no glyph named '{name}' in the fontgarden");
+            }
+        }
+        glyph_names.iter().map(|n| n.as_str()).collect()
+    };
 
+    let layer_name = LayerName::main(source_name);
     std::fs::create_dir_all(output_dir)?;
-    sources
-        .into_par_iter()
-        .try_for_each(|(source_name, source)| {
-            source.save(output_dir.join(source_name).with_extension("ufo"))
-        })?;
+    glyph_names.into_par_iter().try_for_each(|name| {
+        let Some(svg) = fontgarden.render_svg(name, &layer_name) else {
+            eprintln!("glyph '{name}' has no layer '{source_name}', skipping");
+            return Ok(());
+        };
+        let svg_path = output_dir.join(format!("{}.svg", filenames::name_to_filename(name)));
+        std::fs::write(svg_path, svg)
+    })?;
+
+    Ok(())
+}
+
+/// Run the checks and print their findings, returning whether there were errors.
+/// With CI overrides, the findings are printed as annotations.
+fn command_check(
+    fontgarden_path: &Path,
+    profile: &str,
+    only: Vec<Check>,
+    options: &CheckOptions,
+    html_path: Option<&Path>,
+    ci_overrides: Option<&CiConfig>,
+) -> anyhow::Result<bool> {
+    let fontgarden = Fontgarden::load(fontgarden_path)?;
+    let checks = if only.is_empty() {
+        fontgarden.check_profile(profile)?
+    } else {
+        only
+    };
+    let mut findings = fontgarden.run_checks(&checks, options);
+    match ci_overrides {
+        Some(ci_overrides) => {
+            fontgarden.config.ci.apply(&mut findings);
+            ci_overrides.apply(&mut findings);
+            for check in &checks {
+                let mut group = findings.iter().filter(|f| f.check == *check).peekable();
+                if group.peek().is_none() {
+                    continue;
+                }
+                println!("::group::{check}");
+                for finding in group {
+                    println!("{}", finding.github_annotation(fontgarden_path));
+                }
+                println!("::endgroup::");
+            }
+            let errors = findings
+                .iter()
+                .filter(|f| f.severity == validate::Severity::Error)
+                .count();
+            println!("{errors} errors, {} warnings", findings.len() - errors);
+        }
+        None => {
+            for finding in &findings {
+                println!("{finding}");
+            }
+        }
+    }
+    if let Some(html_path) = html_path {
+        std::fs::write(html_path, report::html_report(&fontgarden, &findings))?;
+    }
+    Ok(findings
+        .iter()
+        .any(|f| f.severity == validate::Severity::Error))
+}
+
+/// Print the anchor consistency report, returning whether all glyphs are consistent.
+fn command_check_anchors(fontgarden: &Fontgarden, only_problems: bool) -> bool {
+    let mut consistent = true;
+    for report in fontgarden.anchor_consistency() {
+        if report.anchors.is_empty() || (only_problems && report.is_consistent()) {
+            continue;
+        }
+        consistent &= report.is_consistent();
+
+        println!("{}", report.glyph_name);
+        for (anchor_name, layer_names) in &report.anchors {
+            let layer_names: Vec<&str> = layer_names.iter().map(|n| n.as_str()).collect();
+            println!("  {anchor_name}: {}", layer_names.join(", "));
+        }
+        for (anchor_name, layer_names) in &report.missing {
+            let layer_names: Vec<&str> = layer_names.iter().map(|n| n.as_str()).collect();
+            println!("  MISSING {anchor_name} in: {}", layer_names.join(", "));
+        }
+    }
+    consistent
+}
+
+fn command_metrics_report(
+    fontgarden: &Fontgarden,
+    monospace: bool,
+    csv_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    let source_names = fontgarden.source_names();
+    let report = fontgarden.metrics_report(monospace);
 
+    let format_advance = |advance: &Option<f64>| match advance {
+        Some(advance) => advance.to_string(),
+        None => String::new(),
+    };
+
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        let mut header = vec!["glyph"];
+        header.extend(source_names.iter().map(|n| n.as_str()));
+        header.push("flags");
+        writer.write_record(&header)?;
+        for glyph_metrics in &report {
+            let mut record = vec![glyph_metrics.glyph_name.clone()];
+            record.extend(glyph_metrics.advances.values().map(format_advance));
+            record.push(
+                glyph_metrics
+                    .flags
+                    .iter()
+                    .map(|flag| flag.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+
+    for glyph_metrics in &report {
+        let advances: Vec<String> = glyph_metrics
+            .advances
+            .iter()
+            .map(|(source_name, advance)| format!("{source_name}={}", format_advance(advance)))
+            .collect();
+        println!("{}: {}", glyph_metrics.glyph_name, advances.join(" "));
+        for flag in &glyph_metrics.flags {
+            println!("  WARNING: {flag}");
+        }
+    }
+
+    Ok(())
+}
+
+fn command_query(fontgarden: &Fontgarden, query: &query::Query, json: bool) -> anyhow::Result<()> {
+    let glyph_names = fontgarden.query(query);
+    if !json {
+        for glyph_name in glyph_names {
+            println!("{glyph_name}");
+        }
+        return Ok(());
+    }
+
+    #[derive(serde::Serialize)]
+    struct QueryResult<'a> {
+        name: &'a str,
+        set: &'a str,
+        codepoints: Vec<String>,
+        #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+        tags: &'a BTreeSet<String>,
+    }
+
+    let results: Vec<QueryResult> = glyph_names
+        .into_iter()
+        .map(|name| {
+            let glyph = &fontgarden.glyphs[name];
+            QueryResult {
+                name,
+                set: glyph.set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME),
+                codepoints: glyph
+                    .codepoints
+                    .iter()
+                    .map(|c| format!("{:04X}", c as u32))
+                    .collect(),
+                tags: &glyph.tags,
+            }
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&results)?);
     Ok(())
 }
 
@@ -104,12 +2691,21 @@ fn error_and_exit(kind: clap::error::ErrorKind, message: impl std::fmt::Display)
 
 #[cfg(test)]
 mod tests {
+    use fontgardener2::{
+        errors::SourceLoadError,
+        structs::{Glyph, OpenTypeCategory},
+    };
     use norad::Codepoints;
 
-    use structs::{Glyph, OpenTypeCategory};
-
     use super::*;
 
+    #[test]
+    fn parse_thread_count() {
+        let cli = Cli::try_parse_from(["fontgardener2", "hash", "x.fontgarden", "--threads", "2"]);
+        assert_eq!(cli.unwrap().threads, NonZeroUsize::new(2));
+        assert!(Cli::try_parse_from(["fontgardener2", "--threads", "0", "hash", "x"]).is_err());
+    }
+
     #[test]
     fn roundtrip_empty() {
         let fontgarden = Fontgarden::new();
@@ -132,6 +2728,8 @@ mod tests {
                 opentype_category: OpenTypeCategory::Unassigned,
                 postscript_name: Some("a".into()),
                 set: None,
+                advance_link: None,
+                tags: Default::default(),
             },
         );
         fontgarden.glyphs.insert(
@@ -142,6 +2740,8 @@ mod tests {
                 opentype_category: OpenTypeCategory::Base,
                 postscript_name: None,
                 set: Some("Test".into()),
+                advance_link: Some("a".into()),
+                tags: ["review".to_string()].into(),
             },
         );
 
@@ -152,16 +2752,44 @@ mod tests {
         assert_eq!(fontgarden, roundtripped_fontgarden);
     }
 
+    #[test]
+    fn roundtrip_dotted_layer_names() {
+        let mut glyph = Glyph::default();
+        for layer_name in [
+            LayerName::main("Display.Italic"),
+            LayerName::sublayer("Display.Italic", "public.background.old"),
+            LayerName::main("100%"),
+        ] {
+            let layer = structs::Layer {
+                x_advance: Some(100.),
+                ..Default::default()
+            };
+            assert_eq!(layer_name.to_string().parse(), Ok(layer_name.clone()));
+            glyph.layers.insert(layer_name, layer);
+        }
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), glyph);
+
+        let fontgarden_path = tempfile::tempdir().unwrap();
+        fontgarden.save(fontgarden_path.path()).unwrap();
+        let roundtripped_fontgarden = Fontgarden::load(fontgarden_path.path()).unwrap();
+
+        assert_eq!(fontgarden, roundtripped_fontgarden);
+    }
+
     #[test]
     fn roundtrip_save_load() {
         let mut fontgarden = Fontgarden::new();
         fontgarden
-            .import_ufo_sources(&[
-                "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansBoldWide.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansLightWide.ufo/".into(),
-            ])
+            .import_ufo_sources(
+                &[
+                    "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansBoldWide.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansLightWide.ufo/".into(),
+                ],
+                &ImportOptions::default(),
+            )
             .unwrap();
 
         let fontgarden_path = tempfile::tempdir().unwrap();
@@ -175,28 +2803,481 @@ mod tests {
     fn roundtrip_export_import() {
         let mut fontgarden = Fontgarden::new();
         fontgarden
-            .import_ufo_sources(&[
-                "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansBoldWide.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
-                "testdata/mutatorSans/MutatorSansLightWide.ufo/".into(),
-            ])
+            .import_ufo_sources(
+                &[
+                    "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansBoldWide.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansLightWide.ufo/".into(),
+                ],
+                &ImportOptions::default(),
+            )
             .unwrap();
 
         let export_dir = tempfile::tempdir().unwrap();
 
-        command_export(&fontgarden, &HashSet::new(), export_dir.path()).unwrap();
+        command_export(
+            &fontgarden,
+            &ExportOptions::default(),
+            export_dir.path(),
+            "test",
+            false,
+        )
+        .unwrap();
 
         let mut roundtripped_fontgarden = Fontgarden::new();
         roundtripped_fontgarden
-            .import_ufo_sources(&[
-                export_dir.path().join("BoldCondensed.ufo"),
-                export_dir.path().join("BoldWide.ufo"),
-                export_dir.path().join("LightCondensed.ufo"),
-                export_dir.path().join("LightWide.ufo"),
-            ])
+            .import_ufo_sources(
+                &[
+                    export_dir.path().join("BoldCondensed.ufo"),
+                    export_dir.path().join("BoldWide.ufo"),
+                    export_dir.path().join("LightCondensed.ufo"),
+                    export_dir.path().join("LightWide.ufo"),
+                ],
+                &ImportOptions::default(),
+            )
             .unwrap();
 
         assert_eq!(fontgarden, roundtripped_fontgarden);
     }
+
+    #[test]
+    fn import_follows_components_into_other_sets() {
+        let sources: Vec<PathBuf> = vec![
+            "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+            "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+        ];
+
+        let mut fontgarden = Fontgarden::new();
+        let mut options = ImportOptions {
+            sets: HashSet::from(["Latin".to_string()]),
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert!(fontgarden.glyphs.contains_key("Aacute"));
+        assert!(!fontgarden.glyphs.contains_key("acute"));
+
+        let mut fontgarden = Fontgarden::new();
+        options.follow_components = true;
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert!(fontgarden.glyphs.contains_key("Aacute"));
+        assert!(fontgarden.glyphs.contains_key("acute"));
+        assert!(!fontgarden.glyphs.contains_key("arrowup"));
+    }
+
+    #[test]
+    fn import_names_sources() {
+        let light: PathBuf = "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into();
+        let bold: PathBuf = "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into();
+        let source_names =
+            |fontgarden: &Fontgarden| fontgarden.source_names().into_iter().collect::<Vec<_>>();
+
+        let mut options = ImportOptions::default();
+        let error = Fontgarden::new()
+            .import_ufo_sources(&[light.clone(), light.clone()], &options)
+            .unwrap_err();
+        assert!(matches!(error, SourceLoadError::DuplicateSourceName(..)));
+
+        options.collision_policy = CollisionPolicy::Number;
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(&[light.clone(), light.clone()], &options)
+            .unwrap();
+        assert_eq!(
+            source_names(&fontgarden),
+            vec!["LightCondensed", "LightCondensed 2"]
+        );
+
+        options.source_naming = SourceNaming::FileName;
+        options.source_names =
+            HashMap::from([("MutatorSansBoldCondensed.ufo".into(), "Bold".into())]);
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(&[light, bold], &options)
+            .unwrap();
+        assert_eq!(
+            source_names(&fontgarden),
+            vec!["Bold", "MutatorSansLightCondensed"]
+        );
+    }
+
+    #[test]
+    fn import_assigns_opentype_categories() {
+        let mut ufo = norad::Font::new();
+        ufo.font_info.style_name = Some("Regular".into());
+        let mut acutecomb = norad::Glyph::new("acutecomb");
+        acutecomb.codepoints = Codepoints::new(['\u{301}']);
+        acutecomb.anchors.push(norad::Anchor::new(
+            0.,
+            500.,
+            Some(norad::Name::new("_top").unwrap()),
+            None,
+            None,
+            None,
+        ));
+        let mut a = norad::Glyph::new("a");
+        a.codepoints = Codepoints::new(['a']);
+        for glyph in [acutecomb, a, norad::Glyph::new("f_i")] {
+            ufo.default_layer_mut().insert_glyph(glyph);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let ufo_path = dir.path().join("Regular.ufo");
+        ufo.save(&ufo_path).unwrap();
+
+        let mut fontgarden = Fontgarden::new();
+        let options = ImportOptions {
+            assign_opentype_categories: true,
+            ..Default::default()
+        };
+        fontgarden
+            .import_ufo_sources(&[ufo_path], &options)
+            .unwrap();
+
+        let category = |glyph_name: &str| fontgarden.glyphs[glyph_name].opentype_category.clone();
+        assert_eq!(category("acutecomb"), OpenTypeCategory::Mark);
+        assert_eq!(category("f_i"), OpenTypeCategory::Ligature);
+        assert_eq!(category("a"), OpenTypeCategory::Base);
+    }
+
+    #[test]
+    fn import_scales_to_units_per_em() {
+        let light: PathBuf = "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into();
+        let mut bold =
+            norad::Font::load("testdata/mutatorSans/MutatorSansBoldCondensed.ufo/").unwrap();
+        let bold_width = bold.default_layer().get_glyph("A").unwrap().width;
+        bold.font_info.units_per_em = Some(2000u32.into());
+        let bold_dir = tempfile::tempdir().unwrap();
+        let bold_path = bold_dir.path().join("Bold.ufo");
+        bold.save(&bold_path).unwrap();
+        let sources = [light, bold_path];
+
+        let mut fontgarden = Fontgarden::new();
+        let options = ImportOptions {
+            default_source: Some("LightCondensed".into()),
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert_eq!(fontgarden.config.units_per_em, Some(1000.));
+        let layer = &fontgarden.glyphs["A"].layers[&LayerName::main("BoldCondensed")];
+        assert_eq!(layer.x_advance, Some(bold_width));
+
+        let options = ImportOptions {
+            scale_to_upm: Some(1000.),
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        let layer = &fontgarden.glyphs["A"].layers[&LayerName::main("BoldCondensed")];
+        assert_eq!(layer.x_advance, Some(bold_width / 2.));
+
+        let options = ImportOptions {
+            scale_to_upm: Some(2048.),
+            ..Default::default()
+        };
+        let error = fontgarden
+            .import_ufo_sources(&sources, &options)
+            .unwrap_err();
+        assert!(matches!(error, SourceLoadError::UnitsPerEmMismatch(..)));
+    }
+
+    #[test]
+    fn import_deletes_according_to_policy() {
+        let sources: Vec<PathBuf> = vec![
+            "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+            "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+        ];
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(&sources, &ImportOptions::default())
+            .unwrap();
+        let stale_layer = fontgarden.glyphs["A"].layers[&LayerName::main("LightCondensed")].clone();
+        let glyph = fontgarden.glyphs.get_mut("A").unwrap();
+        glyph
+            .layers
+            .insert("LightCondensed.stale".into(), stale_layer.clone());
+        glyph
+            .layers
+            .insert(LayerName::main("LightWide"), stale_layer);
+        fontgarden
+            .glyphs
+            .insert("stale".into(), fontgarden.glyphs["A"].clone());
+        fontgarden
+            .glyphs
+            .insert("gone".into(), fontgarden.glyphs["B"].clone());
+
+        let mut options = ImportOptions {
+            delete_policy: DeletePolicy::None,
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert!(fontgarden.glyphs["A"]
+            .layers
+            .contains_key(&LayerName::sublayer("LightCondensed", "stale")));
+
+        options.delete_policy = DeletePolicy::Layers;
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert!(!fontgarden.glyphs["A"]
+            .layers
+            .contains_key(&LayerName::sublayer("LightCondensed", "stale")));
+        assert!(fontgarden.glyphs["A"]
+            .layers
+            .contains_key(&LayerName::main("LightWide")));
+        assert_eq!(
+            fontgarden.glyphs["stale"].layers.keys().collect::<Vec<_>>(),
+            vec![&LayerName::main("LightWide")]
+        );
+        assert!(!fontgarden.glyphs.contains_key("gone"));
+
+        options.delete_policy = DeletePolicy::Glyphs;
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        assert!(!fontgarden.glyphs.contains_key("stale"));
+        assert!(fontgarden.glyphs["A"]
+            .layers
+            .contains_key(&LayerName::main("LightWide")));
+    }
+
+    #[test]
+    fn import_deletes_layers_of_one_source_only() {
+        let sources: Vec<PathBuf> = vec![
+            "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+            "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+        ];
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(&sources, &ImportOptions::default())
+            .unwrap();
+        fontgarden
+            .glyphs
+            .insert("half".into(), fontgarden.glyphs["B"].clone());
+
+        fontgarden
+            .import_ufo_sources(&sources[..1], &ImportOptions::default())
+            .unwrap();
+        assert_eq!(
+            fontgarden.glyphs["half"].layers.keys().collect::<Vec<_>>(),
+            vec![&LayerName::main("BoldCondensed")]
+        );
+        assert!(fontgarden.glyphs["B"]
+            .layers
+            .contains_key(&LayerName::main("BoldCondensed")));
+    }
+
+    #[test]
+    fn import_only_selected_layers() {
+        let sources: Vec<PathBuf> =
+            vec!["testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into()];
+        let mut fontgarden = Fontgarden::new();
+        let options = ImportOptions {
+            layers: HashSet::from(["default".into(), "background".into()]),
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        let layer_names: BTreeSet<String> = fontgarden
+            .glyphs
+            .values()
+            .flat_map(|glyph| glyph.layers.keys().map(|n| n.to_string()))
+            .collect();
+        assert_eq!(
+            layer_names,
+            BTreeSet::from(["LightCondensed".into(), "LightCondensed.background".into()])
+        );
+
+        let options = ImportOptions {
+            layers: HashSet::from(["support".into()]),
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        let glyph = &fontgarden.glyphs["S"];
+        assert!(glyph
+            .layers
+            .contains_key(&LayerName::main("LightCondensed")));
+        assert!(glyph
+            .layers
+            .contains_key(&LayerName::sublayer("LightCondensed", "support")));
+    }
+
+    #[test]
+    fn import_only_some_parts() {
+        let sources: Vec<PathBuf> =
+            vec!["testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into()];
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(&sources, &ImportOptions::default())
+            .unwrap();
+        let imported = fontgarden.glyphs["A"].layers[&LayerName::main("LightCondensed")].clone();
+        let glyph = fontgarden.glyphs.get_mut("A").unwrap();
+        let layer = glyph
+            .layers
+            .get_mut(&LayerName::main("LightCondensed"))
+            .unwrap();
+        layer.x_advance = Some(1.);
+        layer.contours.clear();
+        glyph.codepoints = Codepoints::new(['x']);
+        fontgarden.glyphs.remove("B");
+
+        let options = ImportOptions {
+            only: HashSet::from([ImportChannel::Metrics]),
+            delete_policy: DeletePolicy::Glyphs,
+            ..Default::default()
+        };
+        fontgarden.import_ufo_sources(&sources, &options).unwrap();
+        let glyph = &fontgarden.glyphs["A"];
+        let layer = &glyph.layers[&LayerName::main("LightCondensed")];
+        assert_eq!(layer.x_advance, imported.x_advance);
+        assert!(layer.contours.is_empty());
+        assert_eq!(glyph.codepoints, Codepoints::new(['x']));
+        assert!(!fontgarden.glyphs.contains_key("B"));
+    }
+
+    #[test]
+    fn export_layered_ufo() {
+        let ufo = |glyph_names: &[&str], sublayer: Option<&str>| {
+            let mut ufo = norad::Font::new();
+            for glyph_name in glyph_names {
+                ufo.default_layer_mut()
+                    .insert_glyph(norad::Glyph::new(glyph_name));
+            }
+            if let Some(sublayer) = sublayer {
+                ufo.layers
+                    .new_layer(sublayer)
+                    .unwrap()
+                    .insert_glyph(norad::Glyph::new("a"));
+            }
+            ufo
+        };
+        let ufos = HashMap::from([
+            ("Regular".to_string(), ufo(&["a", "b"], Some("background"))),
+            ("Bold".to_string(), ufo(&["a"], Some("background"))),
+        ]);
+
+        let layered = ufo::layered_ufo(ufos, "Regular").unwrap();
+
+        let layer_names: Vec<&str> = layered.layers.names().map(|n| n.as_str()).collect();
+        assert_eq!(
+            layer_names,
+            ["public.default", "background", "Bold", "Bold.background"]
+        );
+        assert_eq!(layered.default_layer().len(), 2);
+        assert!(layered.layers.get("Bold").unwrap().contains_glyph("a"));
+    }
+
+    #[test]
+    fn export_without_sublayers() {
+        let layer = |location: Option<&[(&str, f64)]>| structs::Layer {
+            x_advance: Some(500.),
+            location: location.map(|location| {
+                location
+                    .iter()
+                    .map(|(axis, value)| (axis.to_string(), *value))
+                    .collect()
+            }),
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: HashMap::from([
+                    ("Regular".into(), layer(None)),
+                    ("Regular.background".into(), layer(None)),
+                    ("Regular.Medium".into(), layer(Some(&[("wght", 500.)]))),
+                ]),
+                ..Default::default()
+            },
+        );
+        let options = ExportOptions {
+            skip_sublayers: true,
+            ..Default::default()
+        };
+
+        let sources = fontgarden.export_ufo_sources(&options).unwrap();
+        let layer_names: Vec<&str> = sources["Regular"]
+            .layers
+            .names()
+            .map(|n| n.as_str())
+            .collect();
+        assert_eq!(layer_names, ["public.default", "Medium"]);
+    }
+
+    #[test]
+    fn export_in_sorted_order() {
+        let mut fontgarden = Fontgarden::new();
+        for (glyph_name, sublayer_name) in [("c", "sketch"), ("a", "support"), ("b", "background")]
+        {
+            fontgarden.glyphs.insert(
+                glyph_name.into(),
+                Glyph {
+                    layers: HashMap::from([
+                        ("Regular".into(), structs::Layer::default()),
+                        (
+                            format!("Regular.{sublayer_name}").as_str().into(),
+                            structs::Layer::default(),
+                        ),
+                    ]),
+                    postscript_name: Some(format!("{glyph_name}.alt")),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let sources = fontgarden
+            .export_ufo_sources(&ExportOptions::default())
+            .unwrap();
+        let layer_names: Vec<&str> = sources["Regular"]
+            .layers
+            .names()
+            .map(|n| n.as_str())
+            .collect();
+        assert_eq!(
+            layer_names,
+            ["public.default", "background", "sketch", "support"]
+        );
+        let postscript_names = sources["Regular"].lib["public.postscriptNames"]
+            .as_dictionary()
+            .unwrap();
+        assert_eq!(postscript_names.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn export_configured_style_names() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden
+            .import_ufo_sources(
+                &[
+                    "testdata/mutatorSans/MutatorSansBoldCondensed.ufo/".into(),
+                    "testdata/mutatorSans/MutatorSansLightCondensed.ufo/".into(),
+                ],
+                &ImportOptions::default(),
+            )
+            .unwrap();
+        fontgarden.config = toml::from_str(
+            r#"
+            [sources.BoldCondensed.naming]
+            style_name = "Bold Condensed"
+            style_map_style_name = "bold"
+            "#,
+        )
+        .unwrap();
+
+        let output_dir = tempfile::tempdir().unwrap();
+        for (source_name, source) in fontgarden
+            .export_ufo_sources(&ExportOptions::default())
+            .unwrap()
+        {
+            source
+                .save(output_dir.path().join(format!("{source_name}.ufo")))
+                .unwrap();
+        }
+
+        let bold = norad::Font::load(output_dir.path().join("BoldCondensed.ufo")).unwrap();
+        assert_eq!(bold.font_info.style_name.as_deref(), Some("Bold Condensed"));
+        assert_eq!(
+            bold.font_info.style_map_style_name,
+            Some(norad::fontinfo::StyleMapStyle::Bold)
+        );
+        let light = norad::Font::load(output_dir.path().join("LightCondensed.ufo")).unwrap();
+        assert_eq!(
+            light.font_info.style_name.as_deref(),
+            Some("LightCondensed")
+        );
+    }
 }
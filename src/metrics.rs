@@ -0,0 +1,727 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::MetricsError,
+    structs::{Fontgarden, Layer, LayerName, OpenTypeCategory},
+};
+
+/// The advance widths of a glyph in every source, plus anything that looks off.
+#[derive(Debug, PartialEq)]
+pub struct GlyphMetrics {
+    pub glyph_name: String,
+    /// Source name to advance width, `None` if the glyph has no layer there.
+    pub advances: BTreeMap<String, Option<f64>>,
+    pub flags: Vec<MetricsFlag>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MetricsFlag {
+    /// A glyph that isn't a mark has no advance width in a source.
+    ZeroWidth { source_name: String },
+    /// A glyph's advance width differs from the common width in a monospaced source.
+    MonospaceMismatch {
+        source_name: String,
+        width: f64,
+        expected: f64,
+    },
+}
+
+impl Display for MetricsFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricsFlag::ZeroWidth { source_name } => {
+                write!(f, "zero width in {source_name}")
+            }
+            MetricsFlag::MonospaceMismatch {
+                source_name,
+                width,
+                expected,
+            } => write!(f, "width {width} in {source_name}, expected {expected}"),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Gather advance widths of all glyphs across all sources, sorted by glyph name.
+    ///
+    /// With `monospace`, the most common width of each source is taken as the
+    /// expected width for all glyphs that aren't marks.
+    pub fn metrics_report(&self, monospace: bool) -> Vec<GlyphMetrics> {
+        let source_names = self.source_names();
+
+        let mut expected_widths: HashMap<&str, f64> = HashMap::new();
+        if monospace {
+            for source_name in &source_names {
+                let mut width_counts: HashMap<u64, usize> = HashMap::new();
+                for glyph in self.glyphs.values() {
+                    if glyph.opentype_category == OpenTypeCategory::Mark {
+                        continue;
+                    }
+                    if let Some(width) = glyph
+                        .layers
+                        .get(&LayerName::main(source_name.as_str()))
+                        .and_then(|layer| layer.x_advance)
+                        .filter(|width| *width != 0.)
+                    {
+                        *width_counts.entry(width.to_bits()).or_default() += 1;
+                    }
+                }
+                if let Some((width, _)) = width_counts
+                    .into_iter()
+                    .max_by_key(|(width, count)| (*count, *width))
+                {
+                    expected_widths.insert(source_name, f64::from_bits(width));
+                }
+            }
+        }
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+
+        let mut report = Vec::new();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            let is_spacing = glyph.opentype_category != OpenTypeCategory::Mark;
+
+            let mut advances = BTreeMap::new();
+            let mut flags = Vec::new();
+            for source_name in &source_names {
+                let layer = glyph.layers.get(&LayerName::main(source_name.as_str()));
+                let advance = layer.map(|layer| layer.x_advance.unwrap_or_default());
+                advances.insert(source_name.clone(), advance);
+
+                let (Some(layer), Some(width)) = (layer, advance) else {
+                    continue;
+                };
+                if !is_spacing {
+                    continue;
+                }
+                if width == 0. {
+                    if !layer.contours.is_empty() || !layer.components.is_empty() {
+                        flags.push(MetricsFlag::ZeroWidth {
+                            source_name: source_name.clone(),
+                        });
+                    }
+                } else if let Some(&expected) = expected_widths.get(source_name.as_str()) {
+                    if width != expected {
+                        flags.push(MetricsFlag::MonospaceMismatch {
+                            source_name: source_name.clone(),
+                            width,
+                            expected,
+                        });
+                    }
+                }
+            }
+
+            report.push(GlyphMetrics {
+                glyph_name: glyph_name.to_string(),
+                advances,
+                flags,
+            });
+        }
+        report
+    }
+}
+
+impl Fontgarden {
+    /// Make a glyph take its advance width from another one, or from its base
+    /// component if `target` is `None`, and apply it to the glyph's layers.
+    ///
+    /// The base component is the first component in the glyph's first layer
+    /// with components, in sorted layer name order.
+    pub fn link_advance(
+        &mut self,
+        glyph_name: &str,
+        target: Option<&str>,
+    ) -> Result<(), MetricsError> {
+        let glyph = self
+            .glyphs
+            .get(glyph_name)
+            .ok_or_else(|| MetricsError::UnknownGlyph(glyph_name.into()))?;
+        let target = match target {
+            Some(target) => target.to_string(),
+            None => {
+                let mut layers: Vec<_> = glyph.layers.iter().collect();
+                layers.sort_by_key(|(layer_name, _)| *layer_name);
+                layers
+                    .into_iter()
+                    .find_map(|(_, layer)| layer.components.first())
+                    .map(|component| component.name.to_string())
+                    .ok_or_else(|| MetricsError::NoBaseComponent(glyph_name.into()))?
+            }
+        };
+        if !self.glyphs.contains_key(target.as_str()) {
+            return Err(MetricsError::UnknownGlyph(target));
+        }
+
+        // Follow the links from the target to make sure they don't lead back.
+        let mut next = Some(target.as_str());
+        while let Some(linked) = next {
+            if linked == glyph_name {
+                return Err(MetricsError::Cycle(glyph_name.into(), target));
+            }
+            next = self.glyphs[linked].advance_link.as_deref();
+        }
+
+        self.glyphs.get_mut(glyph_name).unwrap().advance_link = Some(target);
+        self.resolve_advance_links();
+        Ok(())
+    }
+
+    /// Set the advance width of all layers of glyphs with an advance link to
+    /// that of the linked glyph, following links of linked glyphs. Returns the
+    /// number of layers that changed.
+    pub fn resolve_advance_links(&mut self) -> usize {
+        let mut resolved: Vec<(String, LayerName, f64)> = Vec::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            if glyph.advance_link.is_none() {
+                continue;
+            }
+            for (layer_name, layer) in &glyph.layers {
+                let mut stack = vec![glyph_name.as_ref()];
+                let Some(advance) = self.linked_advance(glyph_name, layer_name, &mut stack) else {
+                    continue;
+                };
+                if layer.x_advance != Some(advance) {
+                    resolved.push((glyph_name.to_string(), layer_name.clone(), advance));
+                }
+            }
+        }
+
+        let changed = resolved.len();
+        for (glyph_name, layer_name, advance) in resolved {
+            let glyph = self.glyphs.get_mut(glyph_name.as_str()).unwrap();
+            glyph.layers.get_mut(&layer_name).unwrap().x_advance = Some(advance);
+        }
+        changed
+    }
+
+    fn linked_advance<'a>(
+        &'a self,
+        glyph_name: &str,
+        layer_name: &LayerName,
+        stack: &mut Vec<&'a str>,
+    ) -> Option<f64> {
+        let target = self.glyphs.get(glyph_name)?.advance_link.as_deref()?;
+        if stack.contains(&target) {
+            return None;
+        }
+        stack.push(target);
+        self.linked_advance(target, layer_name, stack)
+            .or_else(|| self.component_layer(target, layer_name)?.x_advance)
+    }
+}
+
+/// The spacing of a glyph in a source, as a row of a spacing table. The
+/// sidebearings are empty for glyphs without outlines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpacingRow {
+    pub glyph: String,
+    pub advance: Option<f64>,
+    pub lsb: Option<f64>,
+    pub rsb: Option<f64>,
+}
+
+impl Fontgarden {
+    /// The advance widths and sidebearings of the glyphs with a layer in a
+    /// source, sorted by glyph name.
+    pub fn spacing_table(&self, source_name: &str) -> Vec<SpacingRow> {
+        let layer_name = LayerName::main(source_name);
+        let mut rows: Vec<SpacingRow> = self
+            .glyphs
+            .keys()
+            .filter_map(|glyph_name| self.spacing_table_row(glyph_name, &layer_name))
+            .collect();
+        rows.sort_by(|a, b| a.glyph.cmp(&b.glyph));
+        rows
+    }
+
+    /// Apply an edited spacing table to the layers of a source. Changed
+    /// sidebearings are set like [`Fontgarden::set_sidebearings`] does, and a
+    /// changed advance width is taken as is if the sidebearings are unchanged
+    /// or left empty. Returns the number of layers changed.
+    pub fn apply_spacing_table(
+        &mut self,
+        source_name: &str,
+        rows: &[SpacingRow],
+    ) -> Result<usize, MetricsError> {
+        let layer_name = LayerName::main(source_name);
+        for row in rows {
+            let glyph = self
+                .glyphs
+                .get(row.glyph.as_str())
+                .ok_or_else(|| MetricsError::UnknownGlyph(row.glyph.clone()))?;
+            if !glyph.layers.contains_key(&layer_name) {
+                return Err(MetricsError::MissingLayer(
+                    row.glyph.clone(),
+                    source_name.into(),
+                ));
+            }
+        }
+        let mut rows: Vec<&SpacingRow> = rows.iter().collect();
+        rows.sort_by_cached_key(|row| {
+            (
+                self.component_depth(&row.glyph, &mut Vec::new()),
+                row.glyph.clone(),
+            )
+        });
+
+        let mut changed = 0;
+        for row in rows {
+            // Measure only now, as moving base glyphs moves their composites.
+            let current = self
+                .spacing_table_row(&row.glyph, &layer_name)
+                .expect("layer checked above");
+            let lsb = row.lsb.filter(|lsb| Some(*lsb) != current.lsb);
+            let rsb = row.rsb.filter(|rsb| Some(*rsb) != current.rsb);
+            let advance = row
+                .advance
+                .filter(|advance| Some(*advance) != current.advance);
+            if lsb.is_none() && rsb.is_none() && advance.is_none() {
+                continue;
+            }
+            if let Some(target) = &self.glyphs[row.glyph.as_str()].advance_link {
+                return Err(MetricsError::Linked(row.glyph.clone(), target.clone()));
+            }
+            let moved = (lsb.is_some() || rsb.is_some())
+                && self.set_layer_sidebearings(&row.glyph, &layer_name, lsb, rsb);
+            if !moved {
+                let Some(advance) = advance else {
+                    continue;
+                };
+                let glyph = self.glyphs.get_mut(row.glyph.as_str()).unwrap();
+                glyph.layers.get_mut(&layer_name).unwrap().x_advance = Some(advance);
+            }
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    fn spacing_table_row(&self, glyph_name: &str, layer_name: &LayerName) -> Option<SpacingRow> {
+        let glyph = self.glyphs.get(glyph_name)?;
+        let advance = glyph.layers.get(layer_name)?.x_advance.unwrap_or_default();
+        let bounds = glyph.bounds(layer_name, self);
+        Some(SpacingRow {
+            glyph: glyph_name.into(),
+            advance: Some(advance),
+            lsb: bounds.map(|bounds| bounds.x0),
+            rsb: bounds.map(|bounds| advance - bounds.x1),
+        })
+    }
+}
+
+impl Fontgarden {
+    /// Set the left and/or right sidebearings of glyphs, measured from the
+    /// bounds of their outlines with components resolved, in the given sources
+    /// or all. Returns the number of layers changed.
+    ///
+    /// Setting the left sidebearing moves everything in the layer sideways and
+    /// the advance width along with it, as well as the backgrounds and other
+    /// sublayers of the source without a location of their own. Intermediate
+    /// layers are measured and changed on their own. Glyphs are done base
+    /// glyphs first, as moving a base glyph moves it in composites, too.
+    /// Layers without outlines are left alone.
+    pub fn set_sidebearings(
+        &mut self,
+        glyph_names: &[String],
+        source_names: &BTreeSet<String>,
+        lsb: Option<f64>,
+        rsb: Option<f64>,
+    ) -> Result<usize, MetricsError> {
+        for glyph_name in glyph_names {
+            let glyph = self
+                .glyphs
+                .get(glyph_name.as_str())
+                .ok_or_else(|| MetricsError::UnknownGlyph(glyph_name.clone()))?;
+            if let Some(target) = &glyph.advance_link {
+                return Err(MetricsError::Linked(glyph_name.clone(), target.clone()));
+            }
+        }
+        let mut glyph_names: Vec<&String> = glyph_names.iter().collect();
+        glyph_names.sort_by_cached_key(|glyph_name| {
+            (
+                self.component_depth(glyph_name, &mut Vec::new()),
+                *glyph_name,
+            )
+        });
+        glyph_names.dedup();
+
+        let mut changed = 0;
+        for glyph_name in glyph_names {
+            let mut layer_names: Vec<LayerName> = self.glyphs[glyph_name.as_str()]
+                .layers
+                .iter()
+                .filter(|(layer_name, layer)| layer_name.is_main() || layer.location.is_some())
+                .filter(|(layer_name, _)| {
+                    source_names.is_empty() || source_names.contains(&*layer_name.source)
+                })
+                .map(|(layer_name, _)| layer_name.clone())
+                .collect();
+            layer_names.sort();
+
+            for layer_name in layer_names {
+                if self.set_layer_sidebearings(glyph_name, &layer_name, lsb, rsb) {
+                    changed += 1;
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Set the sidebearings of a glyph layer as described for
+    /// [`Fontgarden::set_sidebearings`]. Returns whether the layer has an
+    /// outline to measure.
+    fn set_layer_sidebearings(
+        &mut self,
+        glyph_name: &str,
+        layer_name: &LayerName,
+        lsb: Option<f64>,
+        rsb: Option<f64>,
+    ) -> bool {
+        let Some(bounds) = self.glyphs[glyph_name].bounds(layer_name, self) else {
+            return false;
+        };
+        let shift = lsb.map(|lsb| lsb - bounds.x0).unwrap_or_default();
+        let glyph = self.glyphs.get_mut(glyph_name).unwrap();
+        let layer = glyph.layers.get_mut(layer_name).unwrap();
+        layer.shift_x(shift);
+        layer.x_advance = match rsb {
+            Some(rsb) => Some(bounds.x1 + shift + rsb),
+            None => Some(layer.x_advance.unwrap_or_default() + shift),
+        };
+
+        if layer_name.is_main() && shift != 0. {
+            for (sublayer_name, sublayer) in &mut glyph.layers {
+                if !sublayer_name.is_main()
+                    && sublayer_name.source == layer_name.source
+                    && sublayer.location.is_none()
+                {
+                    sublayer.shift_x(shift);
+                }
+            }
+        }
+        true
+    }
+
+    /// How deeply a glyph nests components: 0 for none, 1 for components
+    /// without components, and so on.
+    fn component_depth<'a>(&'a self, glyph_name: &'a str, stack: &mut Vec<&'a str>) -> usize {
+        let Some(glyph) = self.glyphs.get(glyph_name) else {
+            return 0;
+        };
+        stack.push(glyph_name);
+        let mut depth = 0;
+        for component in glyph.layers.values().flat_map(|layer| &layer.components) {
+            if !stack.contains(&component.name.as_ref()) {
+                depth = depth.max(self.component_depth(&component.name, stack) + 1);
+            }
+        }
+        stack.pop();
+        depth
+    }
+}
+
+impl Layer {
+    /// Move everything in the layer sideways, but not the advance width.
+    fn shift_x(&mut self, dx: f64) {
+        for point in self.contours.iter_mut().flat_map(|c| c.points.iter_mut()) {
+            point.x += dx;
+        }
+        for component in &mut self.components {
+            component.transformation.x_offset += dx;
+        }
+        for anchor in &mut self.anchors {
+            anchor.x += dx;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::{Component, Contour, ContourPoint, ExtraFields, Glyph, PointType},
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn report_zero_widths_and_monospace_mismatches() {
+        let glyph = |regular: f64, bold: f64| {
+            GlyphBuilder::new()
+                .layer(
+                    "Regular",
+                    LayerBuilder::new().width(regular).rect(50., 0., 150., 500.),
+                )
+                .layer(
+                    "Bold",
+                    LayerBuilder::new().width(bold).rect(50., 0., 150., 500.),
+                )
+        };
+        let fontgarden = FontgardenBuilder::new()
+            .glyph("a", glyph(600., 600.))
+            .glyph("b", glyph(600., 600.))
+            .glyph("i", glyph(600., 550.))
+            .glyph("o", glyph(0., 600.))
+            .glyph("acutecomb", glyph(0., 0.).category(OpenTypeCategory::Mark))
+            .glyph(
+                "space",
+                GlyphBuilder::new()
+                    .layer("Regular", LayerBuilder::new().width(600.))
+                    .layer("Bold", LayerBuilder::new()),
+            )
+            .glyph(
+                "nbspace",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new().width(600.)),
+            )
+            .build();
+        let flags = |report: &[GlyphMetrics], glyph_name: &str| -> Vec<String> {
+            let metrics = report.iter().find(|m| m.glyph_name == glyph_name).unwrap();
+            metrics.flags.iter().map(|flag| flag.to_string()).collect()
+        };
+
+        let report = fontgarden.metrics_report(false);
+        let glyph_names: Vec<&str> = report.iter().map(|m| m.glyph_name.as_str()).collect();
+        assert_eq!(
+            glyph_names,
+            ["a", "acutecomb", "b", "i", "nbspace", "o", "space"]
+        );
+        assert_eq!(
+            report[4].advances,
+            BTreeMap::from([("Bold".into(), None), ("Regular".into(), Some(600.))])
+        );
+        assert_eq!(flags(&report, "o"), ["zero width in Regular"]);
+        for glyph_name in ["a", "acutecomb", "i", "space"] {
+            assert!(flags(&report, glyph_name).is_empty());
+        }
+
+        let report = fontgarden.metrics_report(true);
+        assert_eq!(flags(&report, "i"), ["width 550 in Bold, expected 600"]);
+        assert_eq!(flags(&report, "o"), ["zero width in Regular"]);
+        for glyph_name in ["a", "acutecomb", "b", "nbspace", "space"] {
+            assert!(flags(&report, glyph_name).is_empty());
+        }
+    }
+
+    #[test]
+    fn link_and_resolve_advances() {
+        let mut fontgarden = Fontgarden::new();
+        let glyph = |x_advance: f64, base: Option<&str>| {
+            let layer = |x_advance: f64| Layer {
+                x_advance: Some(x_advance),
+                components: base
+                    .map(|name| Component {
+                        name: name.into(),
+                        transformation: Default::default(),
+                        location: None,
+                        extra: ExtraFields::new(),
+                    })
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            };
+            Glyph {
+                layers: [
+                    (LayerName::main("Regular"), layer(x_advance)),
+                    (LayerName::main("Bold"), layer(x_advance + 100.)),
+                ]
+                .into(),
+                ..Default::default()
+            }
+        };
+        fontgarden.glyphs.insert("a".into(), glyph(500., None));
+        fontgarden
+            .glyphs
+            .insert("aacute".into(), glyph(480., Some("a")));
+        fontgarden
+            .glyphs
+            .insert("aacute.alt".into(), glyph(400., Some("aacute")));
+
+        fontgarden.link_advance("aacute", None).unwrap();
+        fontgarden.link_advance("aacute.alt", None).unwrap();
+        let advance = |fontgarden: &Fontgarden, glyph_name: &str, source_name: &str| {
+            fontgarden.glyphs[glyph_name].layers[&LayerName::main(source_name)].x_advance
+        };
+        assert_eq!(advance(&fontgarden, "aacute", "Bold"), Some(600.));
+        assert_eq!(advance(&fontgarden, "aacute.alt", "Regular"), Some(500.));
+
+        fontgarden
+            .glyphs
+            .get_mut("a")
+            .unwrap()
+            .layers
+            .get_mut(&LayerName::main("Regular"))
+            .unwrap()
+            .x_advance = Some(520.);
+        assert_eq!(fontgarden.resolve_advance_links(), 2);
+        assert_eq!(advance(&fontgarden, "aacute.alt", "Regular"), Some(520.));
+
+        assert!(matches!(
+            fontgarden.link_advance("a", Some("aacute.alt")),
+            Err(MetricsError::Cycle(..))
+        ));
+        assert!(matches!(
+            fontgarden.link_advance("a", None),
+            Err(MetricsError::NoBaseComponent(_))
+        ));
+    }
+
+    #[test]
+    fn set_sidebearings_bases_first() {
+        let mut fontgarden = Fontgarden::new();
+        let square = Contour {
+            points: [(50., 0.), (450., 0.), (450., 400.), (50., 400.)]
+                .into_iter()
+                .map(|(x, y)| ContourPoint {
+                    x,
+                    y,
+                    typ: PointType::Line,
+                    smooth: false,
+                })
+                .collect(),
+            extra: ExtraFields::new(),
+        };
+        let mut a = Glyph::default();
+        a.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                contours: vec![square.clone()],
+                x_advance: Some(500.),
+                ..Default::default()
+            },
+        );
+        a.layers.insert(
+            LayerName::sublayer("Regular", "background"),
+            Layer {
+                contours: vec![square],
+                ..Default::default()
+            },
+        );
+        let mut aacute = Glyph::default();
+        aacute.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                components: vec![Component {
+                    name: "a".into(),
+                    transformation: Default::default(),
+                    location: None,
+                    extra: ExtraFields::new(),
+                }],
+                x_advance: Some(500.),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("a".into(), a);
+        fontgarden.glyphs.insert("aacute".into(), aacute);
+
+        let changed = fontgarden
+            .set_sidebearings(
+                &["aacute".into(), "a".into()],
+                &BTreeSet::new(),
+                Some(40.),
+                Some(60.),
+            )
+            .unwrap();
+
+        assert_eq!(changed, 2);
+        fn layer<'a>(
+            fontgarden: &'a Fontgarden,
+            glyph_name: &str,
+            layer_name: LayerName,
+        ) -> &'a Layer {
+            &fontgarden.glyphs[glyph_name].layers[&layer_name]
+        }
+        let a = layer(&fontgarden, "a", LayerName::main("Regular"));
+        assert_eq!((a.contours[0].points[0].x, a.x_advance), (40., Some(500.)));
+        let background = layer(
+            &fontgarden,
+            "a",
+            LayerName::sublayer("Regular", "background"),
+        );
+        assert_eq!(background.contours[0].points[0].x, 40.);
+        let aacute = layer(&fontgarden, "aacute", LayerName::main("Regular"));
+        assert_eq!(aacute.components[0].transformation.x_offset, 0.);
+        assert_eq!(aacute.x_advance, Some(500.));
+
+        let changed = fontgarden
+            .set_sidebearings(&["a".into()], &BTreeSet::new(), None, Some(100.))
+            .unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(
+            layer(&fontgarden, "a", LayerName::main("Regular")).x_advance,
+            Some(540.)
+        );
+    }
+
+    #[test]
+    fn apply_edited_spacing_table() {
+        let mut fontgarden = Fontgarden::new();
+        let mut o = Glyph::default();
+        o.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                contours: vec![Contour {
+                    points: [(50., 0.), (450., 0.), (450., 400.)]
+                        .into_iter()
+                        .map(|(x, y)| ContourPoint {
+                            x,
+                            y,
+                            typ: PointType::Line,
+                            smooth: false,
+                        })
+                        .collect(),
+                    extra: ExtraFields::new(),
+                }],
+                x_advance: Some(500.),
+                ..Default::default()
+            },
+        );
+        let mut space = Glyph::default();
+        space.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                x_advance: Some(250.),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("o".into(), o);
+        fontgarden.glyphs.insert("space".into(), space);
+
+        let mut table = fontgarden.spacing_table("Regular");
+        assert_eq!(
+            table[0],
+            SpacingRow {
+                glyph: "o".into(),
+                advance: Some(500.),
+                lsb: Some(50.),
+                rsb: Some(50.),
+            }
+        );
+        assert_eq!(
+            fontgarden.apply_spacing_table("Regular", &table).unwrap(),
+            0
+        );
+
+        table[0].lsb = Some(30.);
+        table[1].advance = Some(200.);
+        assert_eq!(
+            fontgarden.apply_spacing_table("Regular", &table).unwrap(),
+            2
+        );
+        let table = fontgarden.spacing_table("Regular");
+        assert_eq!(
+            (table[0].advance, table[0].lsb, table[0].rsb),
+            (Some(480.), Some(30.), Some(50.))
+        );
+        assert_eq!(table[1].advance, Some(200.));
+        assert!(matches!(
+            fontgarden.apply_spacing_table("Bold", &table),
+            Err(MetricsError::MissingLayer(..))
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+//! Names of exported files made from templates like `{family}-{style}.ufo`, for
+//! build scripts that expect their own naming conventions.
+
+use std::str::FromStr;
+
+use crate::{errors::NameTemplateError, interpolate::Location};
+
+/// A file name with placeholders in braces: `{family}` for the family name,
+/// `{style}` for the name of the source or instance and axis tags like
+/// `{wght}` for its location on that axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameTemplate(String);
+
+impl NameTemplate {
+    /// Fill in the placeholders for a source or instance.
+    pub fn render(
+        &self,
+        family_name: &str,
+        style_name: &str,
+        location: &Location,
+    ) -> Result<String, NameTemplateError> {
+        let mut name = String::new();
+        let mut rest = self.0.as_str();
+        while let Some((before, after)) = rest.split_once('{') {
+            name.push_str(before);
+            // Parsing made sure that every brace is closed.
+            let (placeholder, after) = after.split_once('}').unwrap();
+            match placeholder {
+                "family" => name.push_str(family_name),
+                "style" => name.push_str(style_name),
+                axis => match location.get(axis) {
+                    Some(value) => name.push_str(&value.to_string()),
+                    None => {
+                        return Err(NameTemplateError::UnknownPlaceholder(
+                            axis.into(),
+                            style_name.into(),
+                        ))
+                    }
+                },
+            }
+            rest = after;
+        }
+        name.push_str(rest);
+        Ok(name)
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = NameTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(['/', '\\']) {
+            return Err(NameTemplateError::NotAFileName(s.into()));
+        }
+        let mut rest = s;
+        while let Some((_, after)) = rest.split_once('{') {
+            match after.split_once('}') {
+                Some((placeholder, after))
+                    if !placeholder.is_empty() && !placeholder.contains('{') =>
+                {
+                    rest = after
+                }
+                _ => return Err(NameTemplateError::Unclosed(s.into())),
+            }
+        }
+        Ok(Self(s.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_name_templates() {
+        let template: NameTemplate = "{family}-{style}_{wght}.ufo".parse().unwrap();
+        let location = Location::from([("wght".into(), 700.)]);
+        assert_eq!(
+            template.render("Garden Sans", "Bold", &location).unwrap(),
+            "Garden Sans-Bold_700.ufo"
+        );
+        assert!(matches!(
+            template.render("Garden Sans", "Bold", &Location::new()),
+            Err(NameTemplateError::UnknownPlaceholder(axis, _)) if axis == "wght"
+        ));
+        assert!(matches!(
+            "{family.ufo".parse::<NameTemplate>(),
+            Err(NameTemplateError::Unclosed(_))
+        ));
+        assert!(matches!(
+            "fonts/{style}.ufo".parse::<NameTemplate>(),
+            Err(NameTemplateError::NotAFileName(_))
+        ));
+    }
+}
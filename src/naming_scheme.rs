@@ -0,0 +1,208 @@
+//! Converting the glyph names of a whole fontgarden between the human-readable
+//! names of the Glyphs app, like `acutecomb`, and production names, like
+//! `uni0301`, using the glyph data.
+
+use std::collections::BTreeMap;
+
+use glyphsinfo_rs::GlyphData;
+use norad::Codepoints;
+
+use crate::{errors::FilterError, structs::Fontgarden};
+
+/// Which kind of glyph names to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NamingScheme {
+    /// Human-readable names, like `acutecomb` or `a-cy`, with the production
+    /// names kept as PostScript names for export.
+    Nice,
+    /// Production names, like `uni0301` or `uni0430`.
+    Production,
+}
+
+impl Fontgarden {
+    /// Rename all glyphs to the naming scheme, fixing up components, and set
+    /// PostScript names to the production names where they differ from the
+    /// glyph names. Returns the old names of renamed glyphs with their new
+    /// ones.
+    ///
+    /// Explicit PostScript names win over the production names of the glyph
+    /// data. Suffixes like `.sc` are kept, and ligatures like `f_f_i` are
+    /// named part by part if the glyph data doesn't know them.
+    pub fn convert_naming_scheme(
+        &mut self,
+        scheme: NamingScheme,
+        glyph_info: &GlyphData,
+    ) -> Result<BTreeMap<String, String>, FilterError> {
+        let mut renames = BTreeMap::new();
+        for (glyph_name, glyph) in self.glyphs.iter_mut() {
+            let production = glyph
+                .postscript_name
+                .clone()
+                .unwrap_or_else(|| production_name(glyph_name, &glyph.codepoints, glyph_info));
+            let (name, postscript_name) = match scheme {
+                NamingScheme::Production => (production, None),
+                NamingScheme::Nice => {
+                    let name = nice_name(glyph_name, glyph_info);
+                    let postscript_name = (production != name).then_some(production);
+                    (name, postscript_name)
+                }
+            };
+            glyph.postscript_name = postscript_name;
+            if *name != **glyph_name {
+                renames.insert(glyph_name.to_string(), name);
+            }
+        }
+        self.rename_glyphs(&renames)?;
+        Ok(renames)
+    }
+}
+
+/// The production name of a glyph: that of its glyph data record, or
+/// `uniXXXX` for encoded glyphs the glyph data doesn't know.
+pub fn production_name(
+    glyph_name: &str,
+    codepoints: &Codepoints,
+    glyph_info: &GlyphData,
+) -> String {
+    let (base_name, suffix) = split_suffix(glyph_name);
+    let record = glyph_info.record_for_name(base_name).or_else(|| {
+        // Codepoints belong to the glyph, not to the base of a variant.
+        let codepoint = codepoints.iter().next().filter(|_| suffix.is_empty())?;
+        glyph_info.record_for_unicode(codepoint)
+    });
+    let base = match record {
+        Some(record) => record
+            .production_name
+            .clone()
+            .unwrap_or_else(|| record.name.clone()),
+        None => match codepoints.iter().next().filter(|_| suffix.is_empty()) {
+            Some(codepoint) if (codepoint as u32) <= 0xFFFF => {
+                format!("uni{:04X}", codepoint as u32)
+            }
+            Some(codepoint) => format!("u{:05X}", codepoint as u32),
+            None => map_ligature(base_name, |part| {
+                production_name(part, &Codepoints::default(), glyph_info)
+            }),
+        },
+    };
+    format!("{base}{suffix}")
+}
+
+/// The human-readable name of a glyph: that of its glyph data record, found by
+/// name or by the codepoint of a `uniXXXX` or `uXXXXX` name.
+pub fn nice_name(glyph_name: &str, glyph_info: &GlyphData) -> String {
+    let (base_name, suffix) = split_suffix(glyph_name);
+    let record = glyph_info.record_for_name(base_name).or_else(|| {
+        let hex = base_name
+            .strip_prefix("uni")
+            .filter(|hex| hex.len() == 4)
+            .or_else(|| base_name.strip_prefix('u').filter(|hex| hex.len() == 5))?;
+        let codepoint = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+        glyph_info.record_for_unicode(codepoint)
+    });
+    let base = match record {
+        Some(record) => record.name.clone(),
+        None => map_ligature(base_name, |part| nice_name(part, glyph_info)),
+    };
+    format!("{base}{suffix}")
+}
+
+/// Split a glyph name into its base name and suffix, like `a` and `.sc`.
+fn split_suffix(glyph_name: &str) -> (&str, &str) {
+    match glyph_name.find('.') {
+        Some(0) | None => (glyph_name, ""),
+        Some(index) => glyph_name.split_at(index),
+    }
+}
+
+/// Convert the parts of a ligature name like `f_f_i` one by one, or leave a
+/// name without parts as it is.
+fn map_ligature(base_name: &str, convert: impl Fn(&str) -> String) -> String {
+    if !base_name.contains('_') {
+        return base_name.into();
+    }
+    base_name
+        .split('_')
+        .map(convert)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Component, ExtraFields, Glyph, Layer, LayerName};
+
+    use super::*;
+
+    #[test]
+    fn convert_between_naming_schemes() {
+        let mut fontgarden = Fontgarden::new();
+        let glyph = |codepoint: Option<char>, component: Option<&str>| Glyph {
+            codepoints: codepoint.into_iter().collect(),
+            layers: [(
+                LayerName::main("Regular"),
+                Layer {
+                    components: component
+                        .map(|name| Component {
+                            name: name.into(),
+                            transformation: Default::default(),
+                            location: None,
+                            extra: ExtraFields::new(),
+                        })
+                        .into_iter()
+                        .collect(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        fontgarden
+            .glyphs
+            .insert("acutecomb".into(), glyph(Some('\u{301}'), None));
+        fontgarden
+            .glyphs
+            .insert("acutecomb.case".into(), glyph(None, None));
+        fontgarden
+            .glyphs
+            .insert("aacute".into(), glyph(Some('á'), Some("acutecomb")));
+        fontgarden
+            .glyphs
+            .insert("a-cy_a-cy".into(), glyph(None, None));
+        fontgarden
+            .glyphs
+            .insert("ahook".into(), glyph(Some('\u{1EA3}'), None));
+        let glyph_info = GlyphData::default();
+
+        let renames = fontgarden
+            .convert_naming_scheme(NamingScheme::Production, &glyph_info)
+            .unwrap();
+        assert_eq!(
+            renames.into_iter().collect::<Vec<_>>(),
+            [
+                ("a-cy_a-cy".into(), "uni0430_uni0430".into()),
+                ("acutecomb".into(), "uni0301".into()),
+                ("acutecomb.case".into(), "uni0301.case".into()),
+                ("ahook".into(), "uni1EA3".into()),
+            ]
+        );
+        let aacute = &fontgarden.glyphs["aacute"];
+        assert_eq!(
+            &*aacute.layers[&LayerName::main("Regular")].components[0].name,
+            "uni0301"
+        );
+
+        let renames = fontgarden
+            .convert_naming_scheme(NamingScheme::Nice, &glyph_info)
+            .unwrap();
+        assert_eq!(renames.len(), 3);
+        assert!(fontgarden.glyphs.contains_key("acutecomb.case"));
+        assert_eq!(
+            fontgarden.glyphs["acutecomb"].postscript_name.as_deref(),
+            Some("uni0301")
+        );
+        // Unknown to the glyph data, so it keeps its production name.
+        assert!(fontgarden.glyphs.contains_key("uni1EA3"));
+        assert_eq!(fontgarden.glyphs["aacute"].postscript_name, None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Checking that a fontgarden on disk is in the canonical form that saving
+//! writes, with sorted rows and fields and consistent number formatting, like
+//! psfnormalize does for UFOs. Meant for pre-commit hooks.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{errors::NormalizeError, storage::MemoryStorage, structs::Fontgarden};
+
+impl Fontgarden {
+    /// The files of the fontgarden saved at `path` that saving it again would
+    /// add, change or remove, relative to `path` and sorted. Hidden files are
+    /// left out.
+    pub fn normalization_changes(&self, path: &Path) -> Result<Vec<PathBuf>, NormalizeError> {
+        let storage = MemoryStorage::new();
+        self.save_to(&storage)?;
+        let normalized = storage.into_files();
+
+        let mut on_disk = BTreeMap::new();
+        collect_files(path, Path::new(""), &mut on_disk)?;
+
+        let mut changes: Vec<PathBuf> = on_disk
+            .iter()
+            .filter(|(file, contents)| normalized.get(*file) != Some(*contents))
+            .map(|(file, _)| file.clone())
+            .collect();
+        changes.extend(
+            normalized
+                .keys()
+                .filter(|file| !on_disk.contains_key(*file))
+                .cloned(),
+        );
+        changes.sort();
+        Ok(changes)
+    }
+}
+
+/// Read all files below a directory that aren't hidden, by their path
+/// relative to the directory.
+fn collect_files(
+    root: &Path,
+    relative: &Path,
+    files: &mut BTreeMap<PathBuf, Vec<u8>>,
+) -> Result<(), NormalizeError> {
+    let dir = root.join(relative);
+    let entries = fs::read_dir(&dir).map_err(|e| NormalizeError::Io(dir.clone(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| NormalizeError::Io(dir.clone(), e))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+        if path.is_dir() {
+            collect_files(root, &relative, files)?;
+        } else {
+            let contents = fs::read(&path).map_err(|e| NormalizeError::Io(path, e))?;
+            files.insert(relative, contents);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::JsonStyle,
+        structs::{Glyph, Layer, LayerName},
+    };
+
+    use super::*;
+
+    #[test]
+    fn find_files_to_normalize() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: [(
+                    LayerName::main("Regular"),
+                    Layer {
+                        x_advance: Some(500.),
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        let fontgarden = Fontgarden::load(&path).unwrap();
+        assert!(fontgarden.normalization_changes(&path).unwrap().is_empty());
+
+        let layer_path = path.join(Fontgarden::layer_path("a", &LayerName::main("Regular")));
+        let layer: serde_json::Value =
+            serde_json::from_slice(&fs::read(&layer_path).unwrap()).unwrap();
+        fs::write(&layer_path, layer.to_string()).unwrap();
+        fs::write(path.join(".gitignore"), "").unwrap();
+        fs::write(path.join("notes.txt"), "").unwrap();
+        let fontgarden = Fontgarden::load(&path).unwrap();
+        assert_eq!(
+            fontgarden.normalization_changes(&path).unwrap(),
+            [
+                PathBuf::from("glyphs/a/R_egular.json"),
+                PathBuf::from("notes.txt")
+            ]
+        );
+
+        let mut fontgarden = fontgarden;
+        fontgarden.config.set_json_style(JsonStyle::Compact);
+        fontgarden.save(&path).unwrap();
+        let fontgarden = Fontgarden::load(&path).unwrap();
+        assert_eq!(fontgarden.config.json_style, JsonStyle::Compact);
+        assert_eq!(
+            fs::read_to_string(&layer_path).unwrap(),
+            r#"{"anchors":[],"components":[],"contours":[],"x_advance":500.0}"#
+        );
+        assert!(fontgarden.normalization_changes(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! OpenStep-style property lists, the format of Glyphs files, which the plist
+//! crate doesn't read.
+//!
+//! Unquoted strings stay strings, as Glyphs 2 files have hex codepoints like
+//! `0041` that must not turn into numbers; use [`number`] to read numbers.
+//! Writing follows Glyphs 3, with arrays of plain values on one line.
+
+use plist::{Dictionary, Value};
+
+use crate::errors::OpenStepError;
+
+/// Parse the text of a property list.
+pub fn parse(text: &str) -> Result<Value, OpenStepError> {
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace()?;
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected text after the end"));
+    }
+    Ok(value)
+}
+
+/// Write a property list, with a newline at the end.
+pub fn to_string(value: &Value) -> String {
+    let mut text = String::new();
+    write_value(&mut text, value);
+    text.push('\n');
+    text
+}
+
+fn write_value(text: &mut String, value: &Value) {
+    match value {
+        Value::Dictionary(dictionary) => {
+            text.push_str("{\n");
+            for (key, value) in dictionary {
+                write_string(text, key);
+                text.push_str(" = ");
+                write_value(text, value);
+                text.push_str(";\n");
+            }
+            text.push('}');
+        }
+        Value::Array(array) => {
+            let is_flat = array
+                .iter()
+                .all(|value| !matches!(value, Value::Array(_) | Value::Dictionary(_)));
+            let separator = if is_flat { "," } else { ",\n" };
+            text.push_str(if is_flat { "(" } else { "(\n" });
+            for (index, value) in array.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(separator);
+                }
+                write_value(text, value);
+            }
+            text.push_str(if is_flat || array.is_empty() {
+                ")"
+            } else {
+                "\n)"
+            });
+        }
+        Value::String(s) => write_string(text, s),
+        Value::Integer(i) => text.push_str(&i.to_string()),
+        Value::Real(r) if r.fract() == 0. && r.abs() < 1e15 => {
+            text.push_str(&(*r as i64).to_string())
+        }
+        Value::Real(r) => text.push_str(&r.to_string()),
+        Value::Boolean(b) => text.push(if *b { '1' } else { '0' }),
+        Value::Data(data) => {
+            text.push('<');
+            for byte in data {
+                text.push_str(&format!("{byte:02x}"));
+            }
+            text.push('>');
+        }
+        _ => text.push_str("\"\""),
+    }
+}
+
+/// Write a string unquoted if it can be, which it can't if it would read back
+/// as a number.
+fn write_string(text: &mut String, s: &str) {
+    let is_bare = !s.is_empty()
+        && s.bytes()
+            .all(|c| c.is_ascii_alphanumeric() || b"._$/:-+".contains(&c))
+        && s.parse::<f64>().is_err();
+    if is_bare {
+        text.push_str(s);
+        return;
+    }
+    text.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\012"),
+            c => text.push(c),
+        }
+    }
+    text.push('"');
+}
+
+/// Read a number, written unquoted or quoted.
+pub fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Integer(i) => i.as_signed().map(|i| i as f64),
+        Value::Real(r) => Some(*r),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> OpenStepError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        OpenStepError(line, message)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), OpenStepError> {
+        loop {
+            let rest = &self.text[self.pos..];
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                let end = rest
+                    .find("*/")
+                    .ok_or_else(|| self.error("unclosed comment"))?;
+                self.pos += end + 2;
+            } else if self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+                self.pos += 1;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8, message: &'static str) -> Result<(), OpenStepError> {
+        self.skip_whitespace()?;
+        if self.peek() != Some(c) {
+            return Err(self.error(message));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, OpenStepError> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(b'{') => self.dictionary(),
+            Some(b'(') => self.array(),
+            Some(b'<') => self.data(),
+            Some(_) => self.string().map(Value::String),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn dictionary(&mut self) -> Result<Value, OpenStepError> {
+        self.pos += 1;
+        let mut dictionary = Dictionary::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(b'}') {
+                self.pos += 1;
+                return Ok(Value::Dictionary(dictionary));
+            }
+            let key = self.string()?;
+            self.expect(b'=', "expected '=' after a key")?;
+            let value = self.value()?;
+            self.expect(b';', "expected ';' after a value")?;
+            dictionary.insert(key, value);
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, OpenStepError> {
+        self.pos += 1;
+        let mut array = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(b')') {
+                self.pos += 1;
+                return Ok(Value::Array(array));
+            }
+            array.push(self.value()?);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b')') => (),
+                _ => return Err(self.error("expected ',' or ')' in an array")),
+            }
+        }
+    }
+
+    fn data(&mut self) -> Result<Value, OpenStepError> {
+        self.pos += 1;
+        let end = self.text[self.pos..]
+            .find('>')
+            .ok_or_else(|| self.error("unclosed data"))?;
+        let hex: Vec<u8> = self.text[self.pos..self.pos + end]
+            .bytes()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+        let bytes = hex
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| self.error("malformed data"))?;
+        self.pos += end + 1;
+        Ok(Value::Data(bytes))
+    }
+
+    fn string(&mut self) -> Result<String, OpenStepError> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(b'"') {
+            return self.quoted_string();
+        }
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || b"._$/:-+".contains(&c) || c >= 0x80)
+        {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("expected a string"));
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    fn quoted_string(&mut self) -> Result<String, OpenStepError> {
+        self.pos += 1;
+        let mut string = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += offset + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let Some((_, escaped)) = chars.next() else {
+                        break;
+                    };
+                    match escaped {
+                        'n' => string.push('\n'),
+                        't' => string.push('\t'),
+                        'r' => string.push('\r'),
+                        'U' => {
+                            let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("malformed \\U escape"))?;
+                            string.push(c);
+                        }
+                        '0'..='7' => {
+                            let mut code = escaped.to_digit(8).unwrap();
+                            for _ in 0..2 {
+                                let rest = chars.as_str();
+                                match rest.chars().next().and_then(|c| c.to_digit(8)) {
+                                    Some(digit) => {
+                                        code = code * 8 + digit;
+                                        chars.next();
+                                    }
+                                    None => break,
+                                }
+                            }
+                            string.extend(char::from_u32(code));
+                        }
+                        other => string.push(other),
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+        Err(self.error("unclosed string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_glyphs_like_plist() {
+        let value = parse(
+            r#"{
+            .formatVersion = 3;
+            // a comment
+            familyName = "My \"Font\"\012Sans";
+            unicode = 0041;
+            nodes = (
+            (10,-20.5,l),
+            (1,2,cs)
+            );
+            note = "\U00E9t\U00E9";
+            empty = ();
+            data = <48 69>;
+            }"#,
+        )
+        .unwrap();
+        let dictionary = value.as_dictionary().unwrap();
+        assert_eq!(
+            dictionary["familyName"].as_string(),
+            Some("My \"Font\"\nSans")
+        );
+        assert_eq!(dictionary["unicode"].as_string(), Some("0041"));
+        assert_eq!(number(&dictionary[".formatVersion"]), Some(3.));
+        let nodes = dictionary["nodes"].as_array().unwrap();
+        assert_eq!(number(&nodes[0].as_array().unwrap()[1]), Some(-20.5));
+        assert_eq!(nodes[1].as_array().unwrap()[2].as_string(), Some("cs"));
+        assert_eq!(dictionary["note"].as_string(), Some("été"));
+        assert_eq!(dictionary["data"].as_data(), Some(&b"Hi"[..]));
+        assert!(parse("{a = 1}").is_err());
+
+        let text = to_string(&value);
+        assert!(text.contains("nodes = (\n(\"10\",\"-20.5\",l),\n(\"1\",\"2\",cs)\n);"));
+        assert!(text.contains("unicode = \"0041\";"));
+        assert_eq!(parse(&text).unwrap(), value);
+        let node = Value::Array(vec![10.into(), (-20.5).into(), "l".into()]);
+        assert_eq!(to_string(&node), "(10,-20.5,l)\n");
+    }
+}
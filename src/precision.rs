@@ -0,0 +1,118 @@
+//! Rounding numbers to a configured number of decimal places when writing
+//! layer files and exporting UFOs, so that interpolation leftovers like
+//! `103.99999999999999` don't churn diffs.
+
+use crate::structs::Layer;
+
+/// Round a value to some decimal places, without negative zeros.
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    let rounded = (value * factor).round() / factor;
+    if rounded == 0. {
+        0.
+    } else {
+        rounded
+    }
+}
+
+impl Layer {
+    /// A copy of the layer with coordinates, transformations and metrics
+    /// rounded to some decimal places.
+    pub fn with_precision(&self, decimals: u32) -> Layer {
+        let mut layer = self.clone();
+        let round = |value: &mut f64| *value = round_to(*value, decimals);
+        for point in layer.contours.iter_mut().flat_map(|c| c.points.iter_mut()) {
+            round(&mut point.x);
+            round(&mut point.y);
+        }
+        for component in &mut layer.components {
+            let t = &mut component.transformation;
+            for value in [
+                &mut t.x_scale,
+                &mut t.xy_scale,
+                &mut t.yx_scale,
+                &mut t.y_scale,
+                &mut t.x_offset,
+                &mut t.y_offset,
+            ] {
+                round(value);
+            }
+        }
+        for anchor in &mut layer.anchors {
+            round(&mut anchor.x);
+            round(&mut anchor.y);
+        }
+        for value in [
+            &mut layer.x_advance,
+            &mut layer.y_advance,
+            &mut layer.vertical_origin,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            round(value);
+        }
+        layer
+    }
+}
+
+/// Round the coordinates, transformations and metrics of all glyphs of an
+/// exported UFO to some decimal places.
+pub fn round_ufo(ufo: &mut norad::Font, decimals: u32) {
+    let round = |value: &mut f64| *value = round_to(*value, decimals);
+    let layer_names: Vec<String> = ufo.layers.names().map(|name| name.to_string()).collect();
+    for layer_name in layer_names {
+        for glyph in ufo.layers.get_mut(&layer_name).unwrap().iter_mut() {
+            round(&mut glyph.width);
+            round(&mut glyph.height);
+            for point in glyph.contours.iter_mut().flat_map(|c| c.points.iter_mut()) {
+                round(&mut point.x);
+                round(&mut point.y);
+            }
+            for component in &mut glyph.components {
+                let t = &mut component.transform;
+                for value in [
+                    &mut t.x_scale,
+                    &mut t.xy_scale,
+                    &mut t.yx_scale,
+                    &mut t.y_scale,
+                    &mut t.x_offset,
+                    &mut t.y_offset,
+                ] {
+                    round(value);
+                }
+            }
+            for anchor in &mut glyph.anchors {
+                round(&mut anchor.x);
+                round(&mut anchor.y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, ExtraFields};
+
+    use super::*;
+
+    #[test]
+    fn round_to_precision() {
+        assert_eq!(round_to(103.99999999999999, 2), 104.);
+        assert_eq!(round_to(0.125, 2), 0.13);
+        assert_eq!(round_to(-0.0001, 2).to_string(), "0");
+        let layer = Layer {
+            anchors: vec![Anchor {
+                name: "top".into(),
+                x: 250.00000000000003,
+                y: 700.456,
+                extra: ExtraFields::new(),
+            }],
+            x_advance: Some(499.99999999999994),
+            ..Default::default()
+        };
+        let rounded = layer.with_precision(1);
+        assert_eq!((rounded.anchors[0].x, rounded.anchors[0].y), (250., 700.5));
+        assert_eq!(rounded.x_advance, Some(500.));
+    }
+}
@@ -0,0 +1,107 @@
+//! A self-contained build project around exported sources, so that CI can build
+//! the fonts with fontmake or fontc without knowing about fontgardens.
+
+use std::{fmt::Write, path::Path};
+
+/// The directory of a project that the sources are exported into.
+pub const SOURCES_DIR: &str = "sources";
+
+/// Write a justfile next to the sources directory, with recipes to build a
+/// variable font from the designspace if there is one, static fonts from every
+/// exported source and the font with fontc. There must be at least one
+/// source, given by the name of its UFO.
+pub fn write_justfile(
+    project_dir: &Path,
+    family_name: &str,
+    ufo_filenames: &[String],
+    has_designspace: bool,
+) -> std::io::Result<()> {
+    std::fs::write(
+        project_dir.join("justfile"),
+        justfile(family_name, ufo_filenames, has_designspace),
+    )
+}
+
+fn justfile(family_name: &str, ufo_filenames: &[String], has_designspace: bool) -> String {
+    let designspace = format!("{SOURCES_DIR}/{family_name}.designspace");
+    let ufos: Vec<String> = ufo_filenames
+        .iter()
+        .map(|filename| quote(&format!("{SOURCES_DIR}/{filename}")))
+        .collect();
+
+    let mut justfile = String::from(
+        "# Build the fonts from the sources exported by `fontgarden export --project`.\n\n",
+    );
+    if has_designspace {
+        justfile.push_str("default: variable\n\n");
+        writeln!(
+            justfile,
+            "# Build a variable font with fontmake.\nvariable:\n    \
+             fontmake -m {} -o variable --output-dir fonts/variable\n",
+            quote(&designspace)
+        )
+        .unwrap();
+    } else {
+        justfile.push_str("default: static\n\n");
+    }
+    writeln!(
+        justfile,
+        "# Build a static font per source with fontmake.\nstatic:\n    \
+         fontmake -u {} -o ttf otf --output-dir fonts/static\n",
+        ufos.join(" ")
+    )
+    .unwrap();
+    let (input, output) = if has_designspace {
+        (
+            quote(&designspace),
+            quote(&format!("fonts/variable/{family_name}-VF.ttf")),
+        )
+    } else {
+        (ufos[0].clone(), quote(&format!("fonts/{family_name}.ttf")))
+    };
+    writeln!(
+        justfile,
+        "# Build the font with fontc.\nfontc:\n    fontc {input} -o {output}"
+    )
+    .unwrap();
+    justfile
+}
+
+/// Quote a path for the shell, for names with spaces in them.
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn justfile_with_and_without_designspace() {
+        let ufo_filenames = ["Bold.ufo".to_string(), "Light Condensed.ufo".to_string()];
+        assert_eq!(
+            justfile("Family", &ufo_filenames, true),
+            "# Build the fonts from the sources exported by `fontgarden export --project`.\n\
+             \n\
+             default: variable\n\
+             \n\
+             # Build a variable font with fontmake.\n\
+             variable:\n    \
+                 fontmake -m 'sources/Family.designspace' -o variable --output-dir fonts/variable\n\
+             \n\
+             # Build a static font per source with fontmake.\n\
+             static:\n    \
+                 fontmake -u 'sources/Bold.ufo' 'sources/Light Condensed.ufo' -o ttf otf \
+                 --output-dir fonts/static\n\
+             \n\
+             # Build the font with fontc.\n\
+             fontc:\n    \
+                 fontc 'sources/Family.designspace' -o 'fonts/variable/Family-VF.ttf'\n"
+        );
+
+        let single = justfile("Family", &ufo_filenames[..1], false);
+        assert!(single.contains("default: static\n"));
+        assert!(!single.contains("variable:"));
+        assert!(single.ends_with("fontc 'sources/Bold.ufo' -o 'fonts/Family.ttf'\n"));
+    }
+}
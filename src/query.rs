@@ -0,0 +1,201 @@
+use crate::{
+    selection::Selection,
+    structs::{Fontgarden, Glyph, Layer, LayerName},
+};
+
+/// Structural conditions to find glyphs by. All given conditions must hold.
+///
+/// Conditions on layers hold if any layer fulfills them, or only the given
+/// `layer` if there is one.
+#[derive(Debug, Default)]
+pub struct Query {
+    pub set: Option<String>,
+    pub layer: Option<LayerName>,
+    pub uses_component: Option<String>,
+    pub has_anchor: Option<String>,
+    pub missing_anchor: Option<String>,
+    pub more_points_than: Option<usize>,
+    pub select: Option<Selection>,
+}
+
+impl Query {
+    pub fn matches(&self, glyph_name: &str, glyph: &Glyph) -> bool {
+        if let Some(selection) = &self.select {
+            if !selection.matches(glyph_name, glyph) {
+                return false;
+            }
+        }
+        if let Some(set) = &self.set {
+            if !glyph.is_in_set(set) {
+                return false;
+            }
+        }
+
+        let layers: Vec<&Layer> = match &self.layer {
+            Some(layer_name) => glyph.layers.get(layer_name).into_iter().collect(),
+            None => glyph.layers.values().collect(),
+        };
+        let any_layer = |predicate: &dyn Fn(&Layer) -> bool| layers.iter().any(|l| predicate(l));
+
+        if let Some(component_name) = &self.uses_component {
+            if !any_layer(&|layer| layer.components.iter().any(|c| *c.name == **component_name)) {
+                return false;
+            }
+        }
+        if let Some(anchor_name) = &self.has_anchor {
+            if !any_layer(&|layer| layer.anchors.iter().any(|a| &a.name == anchor_name)) {
+                return false;
+            }
+        }
+        if let Some(anchor_name) = &self.missing_anchor {
+            if !any_layer(&|layer| layer.anchors.iter().all(|a| &a.name != anchor_name)) {
+                return false;
+            }
+        }
+        if let Some(point_count) = self.more_points_than {
+            if !any_layer(&|layer| layer.point_count() > point_count) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Layer {
+    /// The number of points in all contours of the layer.
+    pub fn point_count(&self) -> usize {
+        self.contours.iter().map(|c| c.points.len()).sum()
+    }
+}
+
+impl Fontgarden {
+    /// Find the names of all glyphs matching the query, sorted.
+    pub fn query(&self, query: &Query) -> Vec<&str> {
+        let mut glyph_names: Vec<&str> = self
+            .glyphs
+            .iter()
+            .filter(|(name, glyph)| query.matches(name, glyph))
+            .map(|(name, _)| name.as_ref())
+            .collect();
+        glyph_names.sort();
+        glyph_names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    #[test]
+    fn query_by_each_condition() {
+        let fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "a",
+                GlyphBuilder::new()
+                    .set("Latin")
+                    .layer(
+                        "Regular",
+                        LayerBuilder::new()
+                            .rect(0., 0., 400., 500.)
+                            .anchor("top", 200., 500.),
+                    )
+                    .layer("Bold", LayerBuilder::new().rect(0., 0., 450., 500.)),
+            )
+            .glyph(
+                "aacute",
+                GlyphBuilder::new().set("Latin").layer(
+                    "Regular",
+                    LayerBuilder::new()
+                        .component("a", 0., 0.)
+                        .component("acutecomb", 200., 0.)
+                        .anchor("top", 200., 700.),
+                ),
+            )
+            .glyph(
+                "acutecomb",
+                GlyphBuilder::new().set("Latin").layer(
+                    "Regular",
+                    LayerBuilder::new()
+                        .polygon(&[(0., 550.), (50., 650.), (80., 650.)])
+                        .anchor("_top", 0., 500.),
+                ),
+            )
+            .glyph(
+                "alpha",
+                GlyphBuilder::new().set("Greek").layer(
+                    "Regular",
+                    LayerBuilder::new()
+                        .rect(0., 0., 100., 500.)
+                        .rect(300., 0., 400., 500.)
+                        .anchor("top", 200., 500.),
+                ),
+            )
+            .build();
+        let query = |query: Query| fontgarden.query(&query);
+
+        assert_eq!(
+            query(Query {
+                set: Some("Greek".into()),
+                ..Default::default()
+            }),
+            ["alpha"]
+        );
+        assert_eq!(
+            query(Query {
+                uses_component: Some("a".into()),
+                ..Default::default()
+            }),
+            ["aacute"]
+        );
+        assert_eq!(
+            query(Query {
+                has_anchor: Some("top".into()),
+                ..Default::default()
+            }),
+            ["a", "aacute", "alpha"]
+        );
+        assert_eq!(
+            query(Query {
+                missing_anchor: Some("top".into()),
+                ..Default::default()
+            }),
+            ["a", "acutecomb"]
+        );
+        assert_eq!(
+            query(Query {
+                layer: Some(LayerName::main("Regular")),
+                missing_anchor: Some("top".into()),
+                ..Default::default()
+            }),
+            ["acutecomb"]
+        );
+        assert_eq!(
+            query(Query {
+                more_points_than: Some(3),
+                ..Default::default()
+            }),
+            ["a", "alpha"]
+        );
+        assert_eq!(
+            query(Query {
+                select: Some("name:a* and not set:Greek".parse().unwrap()),
+                has_anchor: Some("top".into()),
+                ..Default::default()
+            }),
+            ["a", "aacute"]
+        );
+        assert!(query(Query {
+            layer: Some(LayerName::main("Bold")),
+            uses_component: Some("a".into()),
+            ..Default::default()
+        })
+        .is_empty());
+        assert!(query(Query {
+            has_anchor: Some("bottom".into()),
+            ..Default::default()
+        })
+        .is_empty());
+    }
+}
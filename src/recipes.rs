@@ -0,0 +1,478 @@
+//! Building composite glyphs from Glyphs-style recipes, like
+//! `aacute=a+acutecomb@top`, one per line.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::Path,
+    str::FromStr,
+};
+
+use unicode_normalization::char::decompose_canonical;
+
+use crate::{
+    errors::RecipeError,
+    structs::{
+        AffineTransformation, Anchor, Component, ExtraFields, Fontgarden, Glyph, Layer, LayerName,
+        OpenTypeCategory,
+    },
+};
+
+/// A composite glyph and the glyphs to build it from. The first component is
+/// the base, the following ones are marks attached to its anchors or, if they
+/// have no attaching anchors, are not categorized as marks and no anchor is
+/// given, placed after it like the parts of a ligature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub glyph_name: String,
+    /// Component glyph names and the anchor to attach them at, if given.
+    pub components: Vec<(String, Option<String>)>,
+}
+
+impl FromStr for Recipe {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (glyph_name, components) = s.split_once('=').ok_or(())?;
+        let glyph_name = glyph_name.trim();
+        if glyph_name.is_empty() {
+            return Err(());
+        }
+        let components = components
+            .split('+')
+            .map(|component| {
+                let (name, anchor) = match component.trim().split_once('@') {
+                    Some((name, anchor)) => (name, Some(anchor.to_string())),
+                    None => (component.trim(), None),
+                };
+                match name.is_empty() || anchor.as_deref() == Some("") {
+                    true => Err(()),
+                    false => Ok((name.to_string(), anchor)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Recipe {
+            glyph_name: glyph_name.to_string(),
+            components,
+        })
+    }
+}
+
+/// Parse recipes, one per line. Empty lines and lines starting with `#` are
+/// skipped.
+pub fn parse_recipes(text: &str) -> Result<Vec<Recipe>, RecipeError> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            line.parse()
+                .map_err(|_| RecipeError::Parse(line_number, line.to_string()))
+        })
+        .collect()
+}
+
+/// Read recipes from a file, as with [`parse_recipes`].
+pub fn load_recipes(path: &Path) -> Result<Vec<Recipe>, RecipeError> {
+    let text = std::fs::read_to_string(path).map_err(|e| RecipeError::Io(path.to_path_buf(), e))?;
+    parse_recipes(&text)
+}
+
+/// Why a composite could not be built in a source.
+#[derive(Debug, PartialEq)]
+pub struct RecipeProblem {
+    pub glyph_name: String,
+    pub source_name: String,
+    pub kind: RecipeProblemKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecipeProblemKind {
+    /// The glyph already has a layer in the source and replacing was not asked for.
+    AlreadyExists,
+    /// A component glyph has no layer in the source.
+    MissingLayer(String),
+    /// A mark component has no anchor to attach to what comes before it.
+    MissingAnchor {
+        mark_name: String,
+        anchor_name: String,
+    },
+}
+
+impl Display for RecipeProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            glyph_name,
+            source_name,
+            kind,
+        } = self;
+        match kind {
+            RecipeProblemKind::AlreadyExists => {
+                write!(f, "glyph {glyph_name} already exists in source {source_name}")
+            }
+            RecipeProblemKind::MissingLayer(component_name) => write!(
+                f,
+                "glyph {glyph_name}: component {component_name} is missing in source {source_name}"
+            ),
+            RecipeProblemKind::MissingAnchor {
+                mark_name,
+                anchor_name,
+            } => write!(
+                f,
+                "glyph {glyph_name}: nothing to attach {mark_name} to at anchor {anchor_name} in source {source_name}"
+            ),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Build the composite glyphs of the recipes in every source in which all
+    /// their components exist, and return the problems in the others.
+    ///
+    /// New glyphs go into the set of their base glyph. Layers of existing
+    /// glyphs that have contours or components are kept unless `replace` is
+    /// set; the anchors of replaced layers are kept.
+    pub fn build_composites(
+        &mut self,
+        recipes: &[Recipe],
+        replace: bool,
+    ) -> Result<Vec<RecipeProblem>, RecipeError> {
+        for recipe in recipes {
+            for (component_name, _) in &recipe.components {
+                if !self.glyphs.contains_key(component_name.as_str()) {
+                    return Err(RecipeError::UnknownGlyph(
+                        recipe.glyph_name.clone(),
+                        component_name.clone(),
+                    ));
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        for recipe in recipes {
+            let mut layers = HashMap::new();
+            for source_name in self.source_names() {
+                let layer_name = LayerName::main(source_name.as_str());
+                let existing = self
+                    .glyphs
+                    .get(recipe.glyph_name.as_str())
+                    .and_then(|glyph| glyph.layers.get(&layer_name));
+                let problem = |kind| RecipeProblem {
+                    glyph_name: recipe.glyph_name.clone(),
+                    source_name: source_name.clone(),
+                    kind,
+                };
+                if existing.is_some_and(has_outline) && !replace {
+                    problems.push(problem(RecipeProblemKind::AlreadyExists));
+                    continue;
+                }
+                match self.composite_layer(recipe, &layer_name) {
+                    Ok(mut layer) => {
+                        if let Some(existing) = existing {
+                            layer.anchors = existing.anchors.clone();
+                        }
+                        layers.insert(layer_name, layer);
+                    }
+                    Err(kind) => problems.push(problem(kind)),
+                }
+            }
+            if layers.is_empty() {
+                continue;
+            }
+            let base_set = self.glyphs[recipe.components[0].0.as_str()].set.clone();
+            let glyph = self
+                .glyphs
+                .entry(recipe.glyph_name.as_str().into())
+                .or_insert_with(|| Glyph {
+                    set: base_set,
+                    ..Default::default()
+                });
+            glyph.layers.extend(layers);
+        }
+        Ok(problems)
+    }
+
+    /// Make recipes for encoded glyphs without contours or components in any
+    /// source from the canonical Unicode decomposition of their first
+    /// codepoint, like `aacute=a+acutecomb` for U+00E1, if there are glyphs
+    /// for all parts of it. Sorted by glyph name.
+    pub fn precomposed_recipes(&self) -> Vec<Recipe> {
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        // The first glyph by name wins where several have a codepoint.
+        let mut glyphs_by_codepoint: BTreeMap<char, &str> = BTreeMap::new();
+        for glyph_name in glyph_names.iter().rev() {
+            for codepoint in self.glyphs[*glyph_name].codepoints.iter() {
+                glyphs_by_codepoint.insert(codepoint, glyph_name);
+            }
+        }
+
+        let mut recipes = Vec::new();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            let Some(codepoint) = glyph.codepoints.iter().next() else {
+                continue;
+            };
+            if glyph.layers.values().any(has_outline) {
+                continue;
+            }
+            let mut parts = Vec::new();
+            decompose_canonical(codepoint, |part| parts.push(part));
+            if parts == [codepoint] {
+                continue;
+            }
+            let components: Option<Vec<(String, Option<String>)>> = parts
+                .iter()
+                .map(|part| {
+                    let component_name = glyphs_by_codepoint.get(part)?;
+                    Some((component_name.to_string(), None))
+                })
+                .collect();
+            if let Some(components) = components {
+                recipes.push(Recipe {
+                    glyph_name: glyph_name.to_string(),
+                    components,
+                });
+            }
+        }
+        recipes
+    }
+
+    fn composite_layer(
+        &self,
+        recipe: &Recipe,
+        layer_name: &LayerName,
+    ) -> Result<Layer, RecipeProblemKind> {
+        // Anchors to attach further marks to, in the composite's coordinates.
+        let mut anchors: Vec<Anchor> = Vec::new();
+        let mut layer = Layer::default();
+        let mut x_advance = 0.;
+
+        for (index, (component_name, anchor_name)) in recipe.components.iter().enumerate() {
+            let Some(component_layer) = self.glyphs[component_name.as_str()].layers.get(layer_name)
+            else {
+                return Err(RecipeProblemKind::MissingLayer(component_name.clone()));
+            };
+            let mut stack = vec![component_name.as_str()];
+            let component_anchors = self.propagated_anchors(component_name, layer_name, &mut stack);
+            let is_mark = anchor_name.is_some()
+                || self.glyphs[component_name.as_str()].opentype_category == OpenTypeCategory::Mark
+                || component_anchors.iter().any(|a| a.name.starts_with('_'));
+
+            let offset = if index == 0 || !is_mark {
+                // A base, or a spacing glyph placed after what came before.
+                let offset = (x_advance, 0.);
+                x_advance += component_layer.x_advance.unwrap_or_default();
+                offset
+            } else {
+                let attachment = component_anchors.iter().find_map(|mark_anchor| {
+                    let name = mark_anchor.name.strip_prefix('_')?;
+                    if anchor_name.as_ref().is_some_and(|wanted| wanted != name) {
+                        return None;
+                    }
+                    let anchor = anchors.iter().find(|a| a.name == name)?;
+                    Some((anchor.x - mark_anchor.x, anchor.y - mark_anchor.y))
+                });
+                let Some(offset) = attachment else {
+                    return Err(RecipeProblemKind::MissingAnchor {
+                        mark_name: component_name.clone(),
+                        anchor_name: anchor_name.clone().unwrap_or_else(|| "any".into()),
+                    });
+                };
+                offset
+            };
+
+            for anchor in component_anchors
+                .iter()
+                .filter(|a| !a.name.starts_with('_'))
+            {
+                let moved = Anchor {
+                    name: anchor.name.clone(),
+                    x: anchor.x + offset.0,
+                    y: anchor.y + offset.1,
+                    extra: ExtraFields::new(),
+                };
+                match anchors.iter_mut().find(|a| a.name == anchor.name) {
+                    Some(existing) => *existing = moved,
+                    None => anchors.push(moved),
+                }
+            }
+            layer.components.push(Component {
+                name: component_name.as_str().into(),
+                transformation: AffineTransformation {
+                    x_offset: offset.0,
+                    y_offset: offset.1,
+                    ..Default::default()
+                },
+                location: None,
+                extra: ExtraFields::new(),
+            });
+        }
+
+        layer.x_advance = Some(x_advance);
+        Ok(layer)
+    }
+}
+
+fn has_outline(layer: &Layer) -> bool {
+    !layer.contours.is_empty() || !layer.components.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(x_advance: f64, anchors: &[(&str, f64, f64)]) -> Layer {
+        Layer {
+            x_advance: Some(x_advance),
+            anchors: anchors
+                .iter()
+                .map(|(name, x, y)| Anchor {
+                    name: name.to_string(),
+                    x: *x,
+                    y: *y,
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_composites_from_recipes() {
+        let mut fontgarden = Fontgarden::new();
+        let mut add = |glyph_name: &str, set: Option<&str>, sources: &[(&str, Layer)]| {
+            let glyph = Glyph {
+                set: set.map(String::from),
+                layers: sources
+                    .iter()
+                    .map(|(source_name, layer)| (LayerName::main(*source_name), layer.clone()))
+                    .collect(),
+                ..Default::default()
+            };
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        };
+        add(
+            "a",
+            Some("Latin"),
+            &[
+                ("Regular", layer(500., &[("top", 250., 500.)])),
+                ("Bold", layer(600., &[("top", 300., 520.)])),
+            ],
+        );
+        let mark = layer(0., &[("_top", 100., 450.), ("top", 100., 650.)]);
+        add(
+            "acutecomb",
+            None,
+            &[("Regular", mark.clone()), ("Bold", mark)],
+        );
+        add("f", None, &[("Regular", layer(300., &[]))]);
+        add("i", None, &[("Regular", layer(250., &[]))]);
+
+        let recipes = parse_recipes(
+            "# Accents\n\
+             aacute=a+acutecomb@top\n\
+             \n\
+             ahungarumlaut = a + acutecomb + acutecomb\n\
+             f_i=f+i\n",
+        )
+        .unwrap();
+        let problems = fontgarden.build_composites(&recipes, false).unwrap();
+
+        let offsets = |glyph_name: &str, source_name: &str| -> Vec<(f64, f64)> {
+            fontgarden.glyphs[glyph_name].layers[&LayerName::main(source_name)]
+                .components
+                .iter()
+                .map(|c| (c.transformation.x_offset, c.transformation.y_offset))
+                .collect()
+        };
+        assert_eq!(offsets("aacute", "Regular"), [(0., 0.), (150., 50.)]);
+        assert_eq!(offsets("aacute", "Bold"), [(0., 0.), (200., 70.)]);
+        assert_eq!(
+            offsets("ahungarumlaut", "Regular"),
+            [(0., 0.), (150., 50.), (150., 250.)]
+        );
+        assert_eq!(offsets("f_i", "Regular"), [(0., 0.), (300., 0.)]);
+        assert_eq!(
+            fontgarden.glyphs["f_i"].layers[&LayerName::main("Regular")].x_advance,
+            Some(550.)
+        );
+        assert_eq!(fontgarden.glyphs["aacute"].set.as_deref(), Some("Latin"));
+        assert_eq!(
+            problems,
+            [RecipeProblem {
+                glyph_name: "f_i".into(),
+                source_name: "Bold".into(),
+                kind: RecipeProblemKind::MissingLayer("f".into()),
+            }]
+        );
+
+        let problems = fontgarden.build_composites(&recipes[..1], false).unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(matches!(
+            parse_recipes("aacute=a+\n"),
+            Err(RecipeError::Parse(1, _))
+        ));
+    }
+
+    #[test]
+    fn recipes_from_unicode_decompositions() {
+        let mut fontgarden = Fontgarden::new();
+        let mut add = |glyph_name: &str, codepoint: char, layer: Layer| {
+            let glyph = Glyph {
+                codepoints: norad::Codepoints::new([codepoint]),
+                layers: [(LayerName::main("Regular"), layer)].into(),
+                ..Default::default()
+            };
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        };
+        add("a", 'a', layer(500., &[("top", 250., 500.)]));
+        add(
+            "acutecomb",
+            '\u{301}',
+            layer(0., &[("_top", 100., 450.), ("top", 100., 650.)]),
+        );
+        add(
+            "ringcomb",
+            '\u{30A}',
+            layer(0., &[("_top", 100., 450.), ("top", 100., 650.)]),
+        );
+        // Has an anchor, but no outline yet.
+        add("aacute", 'á', layer(500., &[("top", 250., 700.)]));
+        add("aringacute", 'ǻ', Layer::default());
+        // There is no glyph for the acute to go on.
+        add("eacute", 'é', Layer::default());
+        add(
+            "A",
+            'A',
+            Layer {
+                contours: vec![Default::default()],
+                ..Default::default()
+            },
+        );
+        // Already has an outline.
+        add(
+            "Aacute",
+            'Á',
+            Layer {
+                contours: vec![Default::default()],
+                ..Default::default()
+            },
+        );
+
+        let recipes = fontgarden.precomposed_recipes();
+        assert_eq!(
+            recipes,
+            [
+                "aacute=a+acutecomb".parse().unwrap(),
+                "aringacute=a+ringcomb+acutecomb".parse().unwrap(),
+            ]
+        );
+
+        let problems = fontgarden.build_composites(&recipes, false).unwrap();
+        assert!(problems.is_empty());
+        let aacute = &fontgarden.glyphs["aacute"].layers[&LayerName::main("Regular")];
+        assert_eq!(aacute.components.len(), 2);
+        assert_eq!(aacute.anchors[0].y, 700.);
+    }
+}
@@ -0,0 +1,206 @@
+//! Glyph names to use in the fontgarden instead of those of the sources, for
+//! importing sources that use legacy names. Rename maps are CSV files with the
+//! source name and the fontgarden name of a glyph per row:
+//!
+//! ```csv
+//! # source name, fontgarden name
+//! uni00A0,nbspace
+//! Euro,euro
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    errors::{RenameMapError, SourceLoadError},
+    ufo::ImportedGlyph,
+};
+
+/// Source glyph names to fontgarden glyph names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenameMap(BTreeMap<String, String>);
+
+impl RenameMap {
+    pub fn load(path: &Path) -> Result<Self, RenameMapError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .comment(Some(b'#'))
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(|e| RenameMapError::Read(path.into(), e))?;
+        let mut renames = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| RenameMapError::Read(path.into(), e))?;
+            let line = record.position().map(|p| p.line()).unwrap_or_default();
+            let [from, to] = [0, 1].map(|i| record.get(i).unwrap_or_default());
+            if record.len() != 2 || from.is_empty() || to.is_empty() {
+                return Err(RenameMapError::Malformed(path.into(), line));
+            }
+            norad::Name::new(to).map_err(|_| RenameMapError::InvalidName(to.into()))?;
+            if renames.insert(from.to_string(), to.to_string()).is_some() {
+                return Err(RenameMapError::Duplicate(from.into()));
+            }
+        }
+        Self::try_from(renames)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The fontgarden name of a source glyph name.
+    pub fn rename<'a>(&'a self, name: &'a str) -> &'a str {
+        self.0.get(name).map_or(name, String::as_str)
+    }
+
+    /// Rename the glyphs of all layers of a UFO source, along with the
+    /// components using them and the glyph names in the PostScript name and
+    /// OpenType category maps of the lib.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font) -> Result<(), SourceLoadError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let layer_names: Vec<String> = ufo.layers.names().map(|name| name.to_string()).collect();
+        for layer_name in layer_names {
+            let layer = ufo.layers.get_mut(&layer_name).unwrap();
+            // Take out all glyphs to rename first, so that names can be swapped.
+            let taken: Vec<norad::Glyph> = self
+                .0
+                .keys()
+                .filter_map(|name| layer.remove_glyph(name))
+                .collect();
+            for glyph in taken {
+                let name = self.0[glyph.name().as_str()].as_str();
+                if layer.contains_glyph(name) {
+                    return Err(SourceLoadError::RenameCollision(name.into()));
+                }
+                layer.insert_glyph(renamed_glyph(glyph, name));
+            }
+            for component in layer
+                .iter_mut()
+                .flat_map(|glyph| glyph.components.iter_mut())
+            {
+                if let Some(name) = self.0.get(component.base.as_str()) {
+                    // Names were checked when loading the map.
+                    component.base = norad::Name::new(name).unwrap();
+                }
+            }
+        }
+        for key in ["public.postscriptNames", "public.openTypeCategories"] {
+            if let Some(names) = ufo.lib.get_mut(key).and_then(|v| v.as_dictionary_mut()) {
+                *names = std::mem::take(names)
+                    .into_iter()
+                    .map(|(name, value)| (self.rename(&name).to_string(), value))
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename glyphs read from a Fontra package or Glyphs file, along with the
+    /// components using them.
+    pub fn apply_to_imported(&self, glyphs: &mut [ImportedGlyph]) -> Result<(), SourceLoadError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut names = HashSet::new();
+        for glyph in glyphs.iter_mut() {
+            glyph.name = self.rename(&glyph.name).to_string();
+            if !names.insert(glyph.name.clone()) {
+                return Err(SourceLoadError::RenameCollision(glyph.name.clone()));
+            }
+            for component in glyph
+                .layers
+                .iter_mut()
+                .flat_map(|(_, layer)| layer.components.iter_mut())
+            {
+                component.name = self.rename(&component.name).into();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for RenameMap {
+    type Error = RenameMapError;
+
+    fn try_from(renames: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let mut targets = HashSet::new();
+        if let Some(target) = renames.values().find(|target| !targets.insert(*target)) {
+            return Err(RenameMapError::Collision(target.clone()));
+        }
+        Ok(Self(renames))
+    }
+}
+
+/// A copy of a glyph under another name, which norad only allows to set when
+/// making a glyph.
+fn renamed_glyph(glyph: norad::Glyph, name: &str) -> norad::Glyph {
+    let mut renamed = norad::Glyph::new(name);
+    renamed.height = glyph.height;
+    renamed.width = glyph.width;
+    renamed.codepoints = glyph.codepoints;
+    renamed.note = glyph.note;
+    renamed.guidelines = glyph.guidelines;
+    renamed.anchors = glyph.anchors;
+    renamed.components = glyph.components;
+    renamed.contours = glyph.contours;
+    renamed.image = glyph.image;
+    renamed.lib = glyph.lib;
+    renamed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_ufo_glyphs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("renames.csv");
+        std::fs::write(&path, "# legacy names\nuni0061,a\nb,c\nc, b\n").unwrap();
+        let rename_map = RenameMap::load(&path).unwrap();
+
+        let mut ufo = norad::Font::new();
+        let layer = ufo.layers.default_layer_mut();
+        for (width, name) in ["uni0061", "b", "c", "aacute"].into_iter().enumerate() {
+            let mut glyph = norad::Glyph::new(name);
+            glyph.width = width as f64;
+            if name == "aacute" {
+                glyph.components.push(norad::Component::new(
+                    norad::Name::new("uni0061").unwrap(),
+                    Default::default(),
+                    None,
+                    None,
+                ));
+            }
+            layer.insert_glyph(glyph);
+        }
+        rename_map.apply_to_ufo(&mut ufo).unwrap();
+        let layer = ufo.default_layer();
+        let width = |name: &str| layer.get_glyph(name).unwrap().width;
+        assert!(!layer.contains_glyph("uni0061"));
+        assert_eq!((width("a"), width("b"), width("c")), (0., 2., 1.));
+        assert_eq!(
+            layer.get_glyph("aacute").unwrap().components[0]
+                .base
+                .as_str(),
+            "a"
+        );
+
+        std::fs::write(&path, "b,a\n").unwrap();
+        let rename_map = RenameMap::load(&path).unwrap();
+        assert!(matches!(
+            rename_map.apply_to_ufo(&mut ufo),
+            Err(SourceLoadError::RenameCollision(name)) if name == "a"
+        ));
+        std::fs::write(&path, "a,x\nb,x\n").unwrap();
+        assert!(matches!(
+            RenameMap::load(&path),
+            Err(RenameMapError::Collision(_))
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use kurbo::Shape;
+
+use crate::structs::{Fontgarden, LayerName};
+
+impl Fontgarden {
+    /// Render a glyph's layer into a standalone SVG document.
+    ///
+    /// The view box spans the advance width horizontally and the outline bounds
+    /// vertically, with the y-axis flipped to match font coordinates.
+    pub fn render_svg(&self, glyph_name: &str, layer_name: &LayerName) -> Option<String> {
+        let (view_box, path_data) = self.svg_parts(glyph_name, layer_name)?;
+        Some(format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{}">"#,
+                "\n",
+                r#"  <path transform="scale(1 -1)" d="{}"/>"#,
+                "\n</svg>\n",
+            ),
+            view_box, path_data,
+        ))
+    }
+
+    /// The view box and path data of [`render_svg`](Self::render_svg), for
+    /// embedding the outline elsewhere.
+    pub(crate) fn svg_parts(
+        &self,
+        glyph_name: &str,
+        layer_name: &LayerName,
+    ) -> Option<(String, String)> {
+        let path = self.resolved_bez_path(glyph_name, layer_name)?;
+        let layer = self.glyphs[glyph_name].layers.get(layer_name);
+        let advance = layer.and_then(|layer| layer.x_advance).unwrap_or_default();
+
+        let bounds = path.bounding_box();
+        let (x_min, x_max) = if path.elements().is_empty() {
+            (0., advance)
+        } else {
+            (bounds.x0.min(0.), bounds.x1.max(advance))
+        };
+        let (y_min, y_max) = if path.elements().is_empty() {
+            (0., 0.)
+        } else {
+            (bounds.y0, bounds.y1)
+        };
+        let width = (x_max - x_min).max(1.);
+        let height = (y_max - y_min).max(1.);
+
+        Some((
+            format!("{} {} {} {}", x_min, -y_max, width, height),
+            path.to_svg(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::{Contour, ContourPoint, PointType},
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn render_cubic_with_more_than_two_off_curve_points() {
+        let mut fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "n",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new().width(100.)),
+            )
+            .build();
+        let point = |x, y, typ| ContourPoint {
+            x,
+            y,
+            typ,
+            smooth: false,
+        };
+        let layer_name = LayerName::main("Regular");
+        let glyph = fontgarden.glyphs.get_mut("n").unwrap();
+        glyph
+            .layers
+            .get_mut(&layer_name)
+            .unwrap()
+            .contours
+            .push(Contour {
+                points: vec![
+                    point(0., 0., PointType::Line),
+                    point(0., 100., PointType::OffCurve),
+                    point(50., 150., PointType::OffCurve),
+                    point(100., 100., PointType::OffCurve),
+                    point(100., 0., PointType::Curve),
+                ],
+                extra: Default::default(),
+            });
+
+        let (_, path_data) = fontgarden.svg_parts("n", &layer_name).unwrap();
+        assert_eq!(
+            path_data,
+            "M100,0 L0,0 C0,100 25,125 50,125 C75,125 100,100 100,0 Z"
+        );
+    }
+}
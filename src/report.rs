@@ -0,0 +1,190 @@
+//! Self-contained HTML reports of check findings, for attaching to reviews or
+//! keeping as CI artifacts.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::{
+    check::Finding,
+    structs::{Fontgarden, LayerName},
+    validate::Severity,
+};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+tr.error td:first-child { border-left: 4px solid #c00; }
+tr.warning td:first-child { border-left: 4px solid #e90; }
+svg { height: 80px; max-width: 160px; }
+svg path { fill: #222; }
+svg circle { fill: none; stroke-width: 12; }
+td.error svg { outline: 2px solid #c00; }
+td.error circle { stroke: #c00; }
+td.warning svg { outline: 2px solid #e90; }
+td.warning circle { stroke: #e90; }
+ul { margin: 0; padding-left: 1.2em; }
+";
+
+/// Write the findings as an HTML page, with a row per glyph that has findings,
+/// showing it in all sources. Sources with findings are highlighted and the
+/// problem points circled.
+pub fn html_report(fontgarden: &Fontgarden, findings: &[Finding]) -> String {
+    let source_names = fontgarden.source_names();
+    let mut by_glyph: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    let mut font_wide = Vec::new();
+    for finding in findings {
+        match &finding.glyph_name {
+            Some(glyph_name) => by_glyph.entry(glyph_name).or_default().push(finding),
+            None => font_wide.push(finding),
+        }
+    }
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Fontgarden check report</title>\n");
+    let _ = writeln!(html, "<style>\n{STYLE}</style>\n</head>\n<body>");
+    html.push_str("<h1>Fontgarden check report</h1>\n");
+    let _ = writeln!(
+        html,
+        "<p>{} errors and {} warnings in {} of {} glyphs.</p>",
+        count(Severity::Error),
+        count(Severity::Warning),
+        by_glyph.len(),
+        fontgarden.glyphs.len()
+    );
+
+    if !font_wide.is_empty() {
+        html.push_str("<h2>Font-wide</h2>\n<ul>\n");
+        for finding in font_wide {
+            let _ = writeln!(html, "<li>{}</li>", escape(&finding.to_string()));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if !by_glyph.is_empty() {
+        html.push_str("<h2>Glyphs</h2>\n<table>\n<tr><th>Glyph</th>");
+        for source_name in &source_names {
+            let _ = write!(html, "<th>{}</th>", escape(source_name));
+        }
+        html.push_str("<th>Findings</th></tr>\n");
+        for (glyph_name, findings) in by_glyph {
+            write_glyph_row(&mut html, fontgarden, &source_names, glyph_name, &findings);
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn write_glyph_row(
+    html: &mut String,
+    fontgarden: &Fontgarden,
+    source_names: &BTreeSet<String>,
+    glyph_name: &str,
+    findings: &[&Finding],
+) {
+    let worst = |findings: &mut dyn Iterator<Item = &&Finding>| {
+        findings.map(|finding| finding.severity).max()
+    };
+    let status = worst(&mut findings.iter()).map(severity_class);
+    let _ = write!(
+        html,
+        "<tr class=\"{}\"><td>{}</td>",
+        status.unwrap_or_default(),
+        escape(glyph_name)
+    );
+
+    for source_name in source_names {
+        // Findings about any layer of the source, or about the glyph as a whole.
+        let mut in_source = findings.iter().filter(|finding| match &finding.layer_name {
+            Some(layer_name) => *layer_name.source == **source_name,
+            None => true,
+        });
+        let class = worst(&mut in_source).map(severity_class);
+        let layer_name = LayerName::main(source_name.as_str());
+        let Some((view_box, path_data)) = fontgarden.svg_parts(glyph_name, &layer_name) else {
+            html.push_str("<td></td>");
+            continue;
+        };
+        let _ = write!(
+            html,
+            "<td class=\"{}\"><svg viewBox=\"{view_box}\"><g transform=\"scale(1 -1)\">\
+             <path d=\"{path_data}\"/>",
+            class.unwrap_or_default()
+        );
+        for (x, y) in findings
+            .iter()
+            .filter(|finding| finding.layer_name.as_ref() == Some(&layer_name))
+            .filter_map(|finding| finding.position)
+        {
+            let _ = write!(html, "<circle cx=\"{x}\" cy=\"{y}\" r=\"40\"/>");
+        }
+        html.push_str("</g></svg></td>");
+    }
+
+    html.push_str("<td><ul>");
+    for finding in findings {
+        let _ = write!(html, "<li>{}</li>", escape(&finding.to_string()));
+    }
+    html.push_str("</ul></td></tr>\n");
+}
+
+fn severity_class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::Check,
+        structs::{Glyph, Layer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn report_highlights_problem_layer() {
+        let mut fontgarden = Fontgarden::new();
+        let mut glyph = Glyph::default();
+        for source_name in ["Bold", "Regular"] {
+            glyph.layers.insert(
+                LayerName::main(source_name),
+                Layer {
+                    x_advance: Some(500.),
+                    ..Default::default()
+                },
+            );
+        }
+        fontgarden.glyphs.insert("a<b".into(), glyph);
+        let findings = [Finding {
+            check: Check::Outlines,
+            severity: Severity::Warning,
+            glyph_name: Some("a<b".into()),
+            layer_name: Some(LayerName::main("Regular")),
+            position: Some((10., 20.)),
+            message: "something".into(),
+        }];
+
+        let html = html_report(&fontgarden, &findings);
+
+        assert!(html.contains("<p>0 errors and 1 warnings in 1 of 1 glyphs.</p>"));
+        assert!(html.contains("<tr class=\"warning\"><td>a&lt;b</td><td class=\"\"><svg"));
+        assert!(html.contains("<td class=\"warning\"><svg"));
+        assert_eq!(html.matches("<circle cx=\"10\" cy=\"20\"").count(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    par::*,
+    structs::{Fontgarden, Layer},
+};
+
+impl Layer {
+    /// Scale everything measured in font units by the factor, as when changing
+    /// the units per em. Component scales are proportions and stay as they are.
+    /// Glyph hints can't be scaled and are dropped.
+    pub fn scale(&mut self, factor: f64) {
+        self.truetype_instructions = None;
+        self.postscript_hints.clear();
+        for point in self
+            .contours
+            .iter_mut()
+            .flat_map(|contour| contour.points.iter_mut())
+        {
+            point.x *= factor;
+            point.y *= factor;
+        }
+        for component in &mut self.components {
+            component.transformation.x_offset *= factor;
+            component.transformation.y_offset *= factor;
+        }
+        for anchor in &mut self.anchors {
+            anchor.x *= factor;
+            anchor.y *= factor;
+        }
+        for value in [
+            &mut self.x_advance,
+            &mut self.y_advance,
+            &mut self.vertical_origin,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *value *= factor;
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Scale all layers from one units per em to another, and record the new
+    /// units per em in the configuration.
+    pub fn scale_units_per_em(&mut self, from: f64, to: f64) {
+        let factor = to / from;
+        self.glyphs
+            .par_iter_mut()
+            .flat_map(|(_, glyph)| glyph.layers.par_iter_mut())
+            .for_each(|(_, layer)| layer.scale(factor));
+        for hinting in self.hinting.values_mut() {
+            hinting.postscript.scale(factor);
+        }
+        self.config.set_units_per_em(to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, ExtraFields, Glyph, LayerName};
+
+    use super::*;
+
+    #[test]
+    fn scale_units_per_em() {
+        let mut fontgarden = Fontgarden::new();
+        let mut glyph = Glyph::default();
+        glyph.layers.insert(
+            LayerName::main("Regular"),
+            Layer {
+                anchors: vec![Anchor {
+                    name: "top".into(),
+                    x: 250.,
+                    y: 700.,
+                    extra: ExtraFields::new(),
+                }],
+                x_advance: Some(500.),
+                ..Default::default()
+            },
+        );
+        fontgarden.glyphs.insert("a".into(), glyph);
+
+        fontgarden.scale_units_per_em(1000., 2000.);
+
+        let layer = &fontgarden.glyphs["a"].layers[&LayerName::main("Regular")];
+        assert_eq!(layer.x_advance, Some(1000.));
+        assert_eq!((layer.anchors[0].x, layer.anchors[0].y), (500., 1400.));
+        assert_eq!(fontgarden.config.units_per_em, Some(2000.));
+    }
+}
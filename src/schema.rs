@@ -0,0 +1,93 @@
+//! JSON Schemas for the files in a fontgarden, generated from the types they
+//! are read into, so that editors and external tools can validate and complete
+//! them.
+
+use schemars::{gen::SchemaSettings, schema::RootSchema, JsonSchema};
+
+use crate::structs::{Layer, SetRecord};
+
+/// A kind of file in a fontgarden to describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// A layer file, `glyphs/<glyph>/<layer>.json`.
+    Layer,
+    /// A row of a set file, `set.<name>.csv`, with its columns in order.
+    SetCsv,
+}
+
+impl SchemaKind {
+    pub fn schema(self) -> RootSchema {
+        let generator = SchemaSettings::draft07().into_generator();
+        let mut schema = match self {
+            SchemaKind::Layer => generator.into_root_schema_for::<Layer>(),
+            SchemaKind::SetCsv => generator.into_root_schema_for::<SetRecord>(),
+        };
+        let metadata = schema.schema.metadata();
+        metadata.title = Some(
+            match self {
+                SchemaKind::Layer => "Fontgarden layer",
+                SchemaKind::SetCsv => "Fontgarden set row",
+            }
+            .into(),
+        );
+        if self == SchemaKind::SetCsv {
+            metadata.description = Some(format!(
+                "A row of a set CSV file. The file starts with a header row naming the \
+                 columns {}, in that order; empty cells are missing values.",
+                column_names::<SetRecord>().join(", ")
+            ));
+        }
+        schema
+    }
+}
+
+/// The names of the properties of a struct, in declaration order.
+fn column_names<T: JsonSchema>() -> Vec<String> {
+    let schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    schema
+        .schema
+        .object
+        .map(|object| object.properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Fontgarden, Glyph};
+
+    use super::*;
+
+    #[test]
+    fn set_csv_schema_matches_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), Glyph::default());
+        fontgarden.save(&path).unwrap();
+        let csv = std::fs::read_to_string(path.join("set.C_ommon.csv")).unwrap();
+        let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+
+        assert_eq!(column_names::<SetRecord>(), header);
+        let schema = serde_json::to_value(SchemaKind::SetCsv.schema()).unwrap();
+        assert_eq!(
+            schema["properties"]["opentype_category"]["default"],
+            "unassigned"
+        );
+    }
+
+    #[test]
+    fn layer_schema_requires_outline_fields() {
+        let schema = serde_json::to_value(SchemaKind::Layer.schema()).unwrap();
+
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["anchors", "components", "contours"])
+        );
+        assert_eq!(
+            schema["definitions"]["PointType"]["enum"],
+            serde_json::json!(["OffCurve", "Move", "Line", "Curve", "QCurve"])
+        );
+    }
+}
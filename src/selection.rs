@@ -0,0 +1,500 @@
+//! A small language to select glyphs by, like
+//! `set:Latin and has:anchor=top and not name:*.sc`.
+//!
+//! Terms are `key:value` pairs and combine with `and`, `or`, `not` and
+//! parentheses, with `not` binding tightest and `or` loosest:
+//!
+//! - `name:GLOB` matches glyph names, with `*` for any run of characters and
+//!   `?` for any one character.
+//! - `set:NAME` matches glyphs in a set or the sets nested in it.
+//! - `tag:TAG` matches glyphs with the tag.
+//! - `category:CATEGORY` matches the OpenType category, like `mark`.
+//! - `codepoint:HEX` or `codepoint:HEX-HEX` matches glyphs mapped to the
+//!   codepoint or any in the range.
+//! - `has:anchor=NAME`, `has:component=NAME` and `has:layer=LAYER_NAME` match
+//!   glyphs with the anchor or component in any layer, or with the layer.
+//!   `has:codepoints` matches glyphs mapped to any codepoint.
+//! - `uses:NAME` is short for `has:component=NAME`.
+//! - `points:>N` and `points:<N` match glyphs with more or fewer points than
+//!   `N` in any layer.
+
+use std::{collections::HashSet, ops::RangeInclusive, str::FromStr};
+
+use crate::{
+    errors::SelectionError,
+    structs::{Fontgarden, Glyph, LayerName, OpenTypeCategory},
+};
+
+/// A parsed glyph selection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection(Expression);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Name(String),
+    Set(String),
+    Tag(String),
+    Category(OpenTypeCategory),
+    Codepoints(RangeInclusive<u32>),
+    HasAnchor(String),
+    HasComponent(String),
+    HasLayer(LayerName),
+    HasCodepoints,
+    MorePointsThan(usize),
+    FewerPointsThan(usize),
+}
+
+impl Selection {
+    pub fn matches(&self, glyph_name: &str, glyph: &Glyph) -> bool {
+        self.0.matches(glyph_name, glyph)
+    }
+}
+
+impl Expression {
+    fn matches(&self, glyph_name: &str, glyph: &Glyph) -> bool {
+        match self {
+            Expression::And(left, right) => {
+                left.matches(glyph_name, glyph) && right.matches(glyph_name, glyph)
+            }
+            Expression::Or(left, right) => {
+                left.matches(glyph_name, glyph) || right.matches(glyph_name, glyph)
+            }
+            Expression::Not(inner) => !inner.matches(glyph_name, glyph),
+            Expression::Term(term) => term.matches(glyph_name, glyph),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, glyph_name: &str, glyph: &Glyph) -> bool {
+        let mut layers = glyph.layers.values();
+        match self {
+            Term::Name(pattern) => glob_matches(pattern, glyph_name),
+            Term::Set(set_name) => glyph.is_in_set(set_name),
+            Term::Tag(tag) => glyph.tags.contains(tag),
+            Term::Category(category) => &glyph.opentype_category == category,
+            Term::Codepoints(range) => glyph
+                .codepoints
+                .iter()
+                .any(|codepoint| range.contains(&(codepoint as u32))),
+            Term::HasAnchor(anchor_name) => {
+                layers.any(|layer| layer.anchors.iter().any(|a| &a.name == anchor_name))
+            }
+            Term::HasComponent(component_name) => {
+                layers.any(|layer| layer.components.iter().any(|c| *c.name == **component_name))
+            }
+            Term::HasLayer(layer_name) => glyph.layers.contains_key(layer_name),
+            Term::HasCodepoints => !glyph.codepoints.is_empty(),
+            Term::MorePointsThan(count) => layers.any(|layer| layer.point_count() > *count),
+            Term::FewerPointsThan(count) => layers.any(|layer| layer.point_count() < *count),
+        }
+    }
+
+    fn parse(token: &str) -> Result<Self, SelectionError> {
+        let invalid = || SelectionError::InvalidValue(token.to_string());
+        let Some((key, value)) = token.split_once(':') else {
+            return Err(SelectionError::Unexpected(token.to_string()));
+        };
+        if value.is_empty() {
+            return Err(invalid());
+        }
+        let term = match key {
+            "name" => Term::Name(value.to_string()),
+            "set" => Term::Set(value.to_string()),
+            "tag" => Term::Tag(value.to_string()),
+            "category" => Term::Category(value.parse().map_err(|_| invalid())?),
+            "codepoint" => {
+                let parse = |hex: &str| {
+                    let hex = hex
+                        .strip_prefix("U+")
+                        .or_else(|| hex.strip_prefix("u+"))
+                        .unwrap_or(hex);
+                    u32::from_str_radix(hex, 16).map_err(|_| invalid())
+                };
+                match value.split_once('-') {
+                    Some((start, end)) => Term::Codepoints(parse(start)?..=parse(end)?),
+                    None => {
+                        let codepoint = parse(value)?;
+                        Term::Codepoints(codepoint..=codepoint)
+                    }
+                }
+            }
+            "has" => match value.split_once('=') {
+                Some(("anchor", name)) if !name.is_empty() => Term::HasAnchor(name.to_string()),
+                Some(("component", name)) if !name.is_empty() => {
+                    Term::HasComponent(name.to_string())
+                }
+                Some(("layer", name)) if !name.is_empty() => Term::HasLayer(name.into()),
+                None if value == "codepoints" => Term::HasCodepoints,
+                _ => return Err(invalid()),
+            },
+            "uses" => Term::HasComponent(value.to_string()),
+            "points" => {
+                let count = |n: &str| n.parse().map_err(|_| invalid());
+                match (value.strip_prefix('>'), value.strip_prefix('<')) {
+                    (Some(n), _) => Term::MorePointsThan(count(n)?),
+                    (_, Some(n)) => Term::FewerPointsThan(count(n)?),
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(SelectionError::UnknownKey(key.to_string())),
+        };
+        Ok(term)
+    }
+}
+
+/// Whether the name matches a pattern with `*` and `?` wildcards.
+pub(crate) fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // The pattern position after the last `*`, and the name position it was
+    // tried at, to backtrack to.
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl FromStr for Selection {
+    type Err = SelectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        match parser.next() {
+            None => Ok(Selection(expression)),
+            Some(token) => Err(SelectionError::Unexpected(token.to_string())),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expression, SelectionError> {
+        let mut expression = self.and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression, SelectionError> {
+        let mut expression = self.not()?;
+        while self.peek() == Some("and") {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.not()?));
+        }
+        Ok(expression)
+    }
+
+    fn not(&mut self) -> Result<Expression, SelectionError> {
+        match self.next() {
+            Some("not") => Ok(Expression::Not(Box::new(self.not()?))),
+            Some("(") => {
+                let expression = self.or()?;
+                match self.next() {
+                    Some(")") => Ok(expression),
+                    Some(token) => Err(SelectionError::Unexpected(token.to_string())),
+                    None => Err(SelectionError::UnclosedParenthesis),
+                }
+            }
+            Some(token @ (")" | "and" | "or")) => {
+                Err(SelectionError::Unexpected(token.to_string()))
+            }
+            Some(token) => Ok(Expression::Term(Term::parse(token)?)),
+            None => Err(SelectionError::UnexpectedEnd),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Find the names of all glyphs in the selection, sorted.
+    pub fn select(&self, selection: &Selection) -> Vec<&str> {
+        let mut glyph_names: Vec<&str> = self
+            .glyphs
+            .iter()
+            .filter(|(name, glyph)| selection.matches(name, glyph))
+            .map(|(name, _)| name.as_ref())
+            .collect();
+        glyph_names.sort();
+        glyph_names
+    }
+
+    /// Keep only the glyphs in the selection and the glyphs they use as
+    /// components, directly or through other components.
+    pub fn retain_selection(&mut self, selection: &Selection) {
+        let glyph_names: Vec<String> = self
+            .select(selection)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.retain_with_components(glyph_names);
+    }
+
+    /// Keep only the given glyphs and the glyphs they use as components,
+    /// directly or through other components.
+    pub fn retain_with_components(&mut self, glyph_names: impl IntoIterator<Item = String>) {
+        let mut kept: HashSet<String> = HashSet::new();
+        let mut pending: Vec<String> = glyph_names.into_iter().collect();
+        while let Some(glyph_name) = pending.pop() {
+            let Some(glyph) = self.glyphs.get(glyph_name.as_str()) else {
+                continue;
+            };
+            for layer in glyph.layers.values() {
+                pending.extend(
+                    layer
+                        .components
+                        .iter()
+                        .filter(|c| !kept.contains(&*c.name))
+                        .map(|c| c.name.to_string()),
+                );
+            }
+            kept.insert(glyph_name);
+        }
+        self.glyphs
+            .retain(|glyph_name, _| kept.contains(&**glyph_name));
+    }
+
+    /// A copy of the fontgarden with only the glyphs in the given sets or the
+    /// selection, and the glyphs they use as components. Metadata of sets left
+    /// without glyphs is dropped.
+    pub fn subset(&self, set_names: &[String], selection: Option<&Selection>) -> Fontgarden {
+        let glyph_names: Vec<String> = self
+            .glyphs
+            .iter()
+            .filter(|(glyph_name, glyph)| {
+                set_names.iter().any(|name| glyph.is_in_set(name))
+                    || selection.is_some_and(|selection| selection.matches(glyph_name, glyph))
+            })
+            .map(|(glyph_name, _)| glyph_name.to_string())
+            .collect();
+        let mut subset = self.clone();
+        subset.retain_with_components(glyph_names);
+        let remaining_sets: HashSet<&str> = subset
+            .glyphs
+            .values()
+            .map(|glyph| glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME))
+            .collect();
+        subset
+            .set_metadata
+            .retain(|set_name, _| remaining_sets.contains(set_name.as_str()));
+        subset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, Component, ExtraFields, Layer};
+
+    use super::*;
+
+    #[test]
+    fn select_glyphs() {
+        let mut fontgarden = Fontgarden::new();
+        let glyph = |set: &str, anchor: &str| Glyph {
+            set: Some(set.into()),
+            layers: [(
+                LayerName::main("Regular"),
+                Layer {
+                    anchors: vec![Anchor {
+                        name: anchor.into(),
+                        x: 0.,
+                        y: 0.,
+                        extra: ExtraFields::new(),
+                    }],
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        fontgarden.glyphs.insert("a".into(), glyph("Latin", "top"));
+        fontgarden
+            .glyphs
+            .insert("a.sc".into(), glyph("Latin", "top"));
+        fontgarden
+            .glyphs
+            .insert("b".into(), glyph("Latin", "bottom"));
+        fontgarden
+            .glyphs
+            .insert("alpha".into(), glyph("Greek", "top"));
+
+        let select =
+            |expression: &str| -> Vec<&str> { fontgarden.select(&expression.parse().unwrap()) };
+        assert_eq!(
+            select("set:Latin and has:anchor=top and not name:*.sc"),
+            vec!["a"]
+        );
+        assert_eq!(select("name:a* and not (set:Greek or name:?.*)"), vec!["a"]);
+        assert_eq!(select("set:Greek or has:anchor=bottom"), vec!["alpha", "b"]);
+        assert_eq!(select("not not has:layer=Regular").len(), 4);
+    }
+
+    #[test]
+    fn retain_selection_with_components() {
+        let mut fontgarden = Fontgarden::new();
+        let glyph = |components: &[&str]| Glyph {
+            layers: [(
+                LayerName::main("Regular"),
+                Layer {
+                    components: components
+                        .iter()
+                        .map(|name| Component {
+                            name: (*name).into(),
+                            transformation: Default::default(),
+                            location: None,
+                            extra: ExtraFields::new(),
+                        })
+                        .collect(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        fontgarden.glyphs.insert("a".into(), glyph(&[]));
+        fontgarden.glyphs.insert("acutecomb".into(), glyph(&[]));
+        fontgarden
+            .glyphs
+            .insert("aacute".into(), glyph(&["a", "acutecomb"]));
+        fontgarden
+            .glyphs
+            .insert("aacute.sc".into(), glyph(&["aacute"]));
+        fontgarden.glyphs.insert("b".into(), glyph(&[]));
+
+        fontgarden.retain_selection(&"name:*.sc".parse().unwrap());
+
+        let mut glyph_names: Vec<&str> = fontgarden.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        assert_eq!(glyph_names, ["a", "aacute", "aacute.sc", "acutecomb"]);
+    }
+
+    #[test]
+    fn subset_by_set() {
+        let mut fontgarden = Fontgarden::new();
+        let glyph = |set: Option<&str>, components: &[&str]| Glyph {
+            set: set.map(String::from),
+            layers: [(
+                LayerName::main("Regular"),
+                Layer {
+                    components: components
+                        .iter()
+                        .map(|name| Component {
+                            name: (*name).into(),
+                            transformation: Default::default(),
+                            location: None,
+                            extra: ExtraFields::new(),
+                        })
+                        .collect(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        fontgarden
+            .glyphs
+            .insert("aacute".into(), glyph(Some("Latin"), &["a", "acutecomb"]));
+        fontgarden
+            .glyphs
+            .insert("a".into(), glyph(Some("Latin"), &[]));
+        fontgarden
+            .glyphs
+            .insert("acutecomb".into(), glyph(None, &[]));
+        fontgarden
+            .glyphs
+            .insert("alpha".into(), glyph(Some("Greek"), &[]));
+        fontgarden.glyphs.insert("period".into(), glyph(None, &[]));
+        for set_name in ["Latin", "Greek", "Common"] {
+            fontgarden
+                .set_metadata
+                .insert(set_name.into(), Default::default());
+        }
+
+        let subset = fontgarden.subset(&["Latin".into()], Some(&"name:period".parse().unwrap()));
+
+        let mut glyph_names: Vec<&str> = subset.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        assert_eq!(glyph_names, ["a", "aacute", "acutecomb", "period"]);
+        let set_names: Vec<&String> = subset.set_metadata.keys().collect();
+        assert_eq!(set_names, ["Common", "Latin"]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |expression: &str| expression.parse::<Selection>().unwrap_err();
+        assert!(matches!(parse(""), SelectionError::UnexpectedEnd));
+        assert!(matches!(
+            parse("set:Latin and"),
+            SelectionError::UnexpectedEnd
+        ));
+        assert!(matches!(
+            parse("(set:Latin"),
+            SelectionError::UnclosedParenthesis
+        ));
+        assert!(matches!(parse("set:Latin)"), SelectionError::Unexpected(t) if t == ")"));
+        assert!(matches!(parse("colour:red"), SelectionError::UnknownKey(k) if k == "colour"));
+        assert!(matches!(
+            parse("points:many"),
+            SelectionError::InvalidValue(_)
+        ));
+        assert!(matches!(parse("Latin"), SelectionError::Unexpected(_)));
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_matches("*.sc", "a.sc"));
+        assert!(glob_matches("a*b*c", "aXbYbc"));
+        assert!(glob_matches("a?", "ab"));
+        assert!(!glob_matches("a?", "abc"));
+        assert!(!glob_matches("*.sc", "a.sc.ss01"));
+        assert!(glob_matches("*", ""));
+    }
+}
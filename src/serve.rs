@@ -0,0 +1,360 @@
+//! A read-only HTTP API over a loaded fontgarden, for dashboards and review
+//! tools that want garden data without reading the files themselves.
+//!
+//! - `GET /sets`: the sets with their metadata and glyph counts.
+//! - `GET /glyphs`: all glyph names, sorted.
+//! - `GET /glyphs/{glyph}`: a glyph's metadata and the names of its layers.
+//! - `GET /glyphs/{glyph}/layers/{layer}`: a layer as in its layer file.
+//! - `GET /glyphs/{glyph}/layers/{layer}/svg`: the layer rendered as SVG.
+//! - `GET /coverage`: glyph and codepoint counts, per source and per set.
+//!
+//! Path segments are percent-decoded, so a layer `Display%2EItalic` is asked
+//! for as `Display%252EItalic`. Errors come back as `{"error": "..."}`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{
+    errors::ServeError,
+    set_metadata::SetMetadata,
+    structs::{codepoints_to_string, Fontgarden, Glyph, LayerName},
+};
+
+/// A glyph's metadata, with the names of its layers instead of their data.
+#[derive(Debug, Serialize)]
+pub struct GlyphInfo<'a> {
+    pub codepoints: String,
+    pub set: &'a str,
+    pub opentype_category: String,
+    pub postscript_name: Option<&'a str>,
+    pub advance_link: Option<&'a str>,
+    pub tags: Vec<&'a str>,
+    pub layers: Vec<String>,
+}
+
+impl<'a> GlyphInfo<'a> {
+    pub fn new(glyph: &'a Glyph) -> Self {
+        let mut layers: Vec<String> = glyph.layers.keys().map(|name| name.to_string()).collect();
+        layers.sort();
+        Self {
+            codepoints: codepoints_to_string(&glyph.codepoints),
+            set: glyph.set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME),
+            opentype_category: glyph.opentype_category.to_string(),
+            postscript_name: glyph.postscript_name.as_deref(),
+            advance_link: glyph.advance_link.as_deref(),
+            tags: glyph.tags.iter().map(String::as_str).collect(),
+            layers,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SetInfo<'a> {
+    name: &'a str,
+    glyphs: usize,
+    #[serde(flatten)]
+    metadata: Option<&'a SetMetadata>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Coverage<'a> {
+    glyphs: usize,
+    codepoints: usize,
+    /// Per source, how many glyphs have a main layer and which lack one.
+    sources: BTreeMap<String, SourceCoverage<'a>>,
+    /// Per set, how many glyphs and codepoints it has.
+    sets: BTreeMap<&'a str, SetCoverage>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SourceCoverage<'a> {
+    glyphs: usize,
+    missing: Vec<&'a str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SetCoverage {
+    glyphs: usize,
+    codepoints: usize,
+}
+
+/// An answer to a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).expect("API data serializes"),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(&serde_json::json!({ "error": message.into() }))
+                .expect("messages serialize"),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Answer a request for `url`, a path with an optional query, which is
+    /// ignored.
+    pub fn respond(&self, method: &str, url: &str) -> Response {
+        if method != "GET" && method != "HEAD" {
+            return Response::error(405, "the API is read-only");
+        }
+        let path = url.split_once('?').map_or(url, |(path, _)| path);
+        let segments: Option<Vec<String>> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect();
+        let Some(segments) = segments else {
+            return Response::error(400, "malformed percent-encoding in the path");
+        };
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match segments[..] {
+            ["sets"] => Response::json(&self.set_infos()),
+            ["glyphs"] => {
+                let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|name| &**name).collect();
+                glyph_names.sort_unstable();
+                Response::json(&glyph_names)
+            }
+            ["glyphs", glyph_name] => match self.glyphs.get(glyph_name) {
+                Some(glyph) => Response::json(&GlyphInfo::new(glyph)),
+                None => no_glyph(glyph_name),
+            },
+            ["glyphs", glyph_name, "layers", layer_name] => {
+                let Some(glyph) = self.glyphs.get(glyph_name) else {
+                    return no_glyph(glyph_name);
+                };
+                match glyph.layers.get(&LayerName::from(layer_name)) {
+                    Some(layer) => Response::json(layer),
+                    None => no_layer(glyph_name, layer_name),
+                }
+            }
+            ["glyphs", glyph_name, "layers", layer_name, "svg"] => {
+                if !self.glyphs.contains_key(glyph_name) {
+                    return no_glyph(glyph_name);
+                }
+                match self.render_svg(glyph_name, &LayerName::from(layer_name)) {
+                    Some(svg) => Response {
+                        status: 200,
+                        content_type: "image/svg+xml",
+                        body: svg.into_bytes(),
+                    },
+                    None => no_layer(glyph_name, layer_name),
+                }
+            }
+            ["coverage"] => Response::json(&self.coverage()),
+            _ => Response::error(404, format!("no such endpoint: {path}")),
+        }
+    }
+
+    /// Serve the API on `address`, like `127.0.0.1:8000`, until the process
+    /// is stopped.
+    pub fn serve(&self, address: &str) -> Result<(), ServeError> {
+        let server =
+            tiny_http::Server::http(address).map_err(|e| ServeError::Listen(address.into(), e))?;
+        for request in server.incoming_requests() {
+            let response = self.respond(request.method().as_str(), request.url());
+            let content_type = tiny_http::Header::from_bytes("Content-Type", response.content_type)
+                .expect("content types are valid header values");
+            let answer = tiny_http::Response::from_data(response.body)
+                .with_status_code(response.status)
+                .with_header(content_type);
+            // A client hanging up early is no reason to stop serving others.
+            let _ = request.respond(answer);
+        }
+        Ok(())
+    }
+
+    fn set_infos(&self) -> Vec<SetInfo<'_>> {
+        let mut glyph_counts: BTreeMap<&str, usize> = self
+            .set_metadata
+            .keys()
+            .map(|set_name| (set_name.as_str(), 0))
+            .collect();
+        for glyph in self.glyphs.values() {
+            let set_name = glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME);
+            *glyph_counts.entry(set_name).or_default() += 1;
+        }
+        glyph_counts
+            .into_iter()
+            .map(|(name, glyphs)| SetInfo {
+                name,
+                glyphs,
+                metadata: self.set_metadata.get(name),
+            })
+            .collect()
+    }
+
+    fn coverage(&self) -> Coverage<'_> {
+        let mut coverage = Coverage {
+            glyphs: self.glyphs.len(),
+            ..Default::default()
+        };
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|name| &**name).collect();
+        glyph_names.sort_unstable();
+
+        let mut codepoints = BTreeSet::new();
+        let mut set_codepoints: BTreeMap<&str, BTreeSet<char>> = BTreeMap::new();
+        for glyph_name in &glyph_names {
+            let glyph = &self.glyphs[*glyph_name];
+            let set_name = glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME);
+            coverage.sets.entry(set_name).or_default().glyphs += 1;
+            codepoints.extend(glyph.codepoints.iter());
+            set_codepoints
+                .entry(set_name)
+                .or_default()
+                .extend(glyph.codepoints.iter());
+        }
+        coverage.codepoints = codepoints.len();
+        for (set_name, codepoints) in set_codepoints {
+            coverage.sets.entry(set_name).or_default().codepoints = codepoints.len();
+        }
+
+        for source_name in self.source_names() {
+            let main_layer = LayerName::main(source_name.as_str());
+            let mut source = SourceCoverage::default();
+            for glyph_name in &glyph_names {
+                if self.glyphs[*glyph_name].layers.contains_key(&main_layer) {
+                    source.glyphs += 1;
+                } else {
+                    source.missing.push(glyph_name);
+                }
+            }
+            coverage.sources.insert(source_name, source);
+        }
+        coverage
+    }
+}
+
+fn no_glyph(glyph_name: &str) -> Response {
+    Response::error(
+        404,
+        format!("no glyph named '{glyph_name}' in the fontgarden"),
+    )
+}
+
+fn no_layer(glyph_name: &str, layer_name: &str) -> Response {
+    Response::error(
+        404,
+        format!("glyph {glyph_name} has no layer '{layer_name}'"),
+    )
+}
+
+/// Decode `%XX` escapes in a URL path segment, or `None` if they are
+/// malformed or don't decode to UTF-8.
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    fn fontgarden() -> Fontgarden {
+        FontgardenBuilder::new()
+            .glyph(
+                "A",
+                GlyphBuilder::new()
+                    .codepoints(['A'])
+                    .layer(
+                        "Regular",
+                        LayerBuilder::new().width(600.).rect(0., 0., 600., 700.),
+                    )
+                    .layer("Bold", LayerBuilder::new().width(650.))
+                    .layer("Display%2EItalic", LayerBuilder::new().width(600.)),
+            )
+            .glyph(
+                "alpha",
+                GlyphBuilder::new()
+                    .codepoints(['α'])
+                    .set("Greek")
+                    .layer("Regular", LayerBuilder::new().width(550.)),
+            )
+            .build()
+    }
+
+    fn json(response: Response) -> serde_json::Value {
+        assert_eq!(response.content_type, "application/json");
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn answer_requests() {
+        let fontgarden = fontgarden();
+
+        let sets = json(fontgarden.respond("GET", "/sets"));
+        assert_eq!(
+            sets,
+            serde_json::json!([
+                { "name": "Common", "glyphs": 1 },
+                { "name": "Greek", "glyphs": 1 },
+            ])
+        );
+        let glyphs = json(fontgarden.respond("GET", "/glyphs?ignored=1"));
+        assert_eq!(glyphs, serde_json::json!(["A", "alpha"]));
+
+        let glyph = json(fontgarden.respond("GET", "/glyphs/alpha"));
+        assert_eq!(glyph["set"], "Greek");
+        assert_eq!(glyph["codepoints"], "03B1");
+        let layer = json(fontgarden.respond("GET", "/glyphs/A/layers/Display%252EItalic"));
+        assert_eq!(layer["x_advance"], 600.);
+
+        let svg = fontgarden.respond("GET", "/glyphs/A/layers/Regular/svg");
+        assert_eq!(svg.status, 200);
+        assert_eq!(svg.content_type, "image/svg+xml");
+        assert!(String::from_utf8(svg.body).unwrap().contains("<path"));
+
+        let missing = fontgarden.respond("GET", "/glyphs/A/layers/Light");
+        assert_eq!(missing.status, 404);
+        assert_eq!(json(missing)["error"], "glyph A has no layer 'Light'");
+        assert_eq!(fontgarden.respond("GET", "/glyphs/B").status, 404);
+        assert_eq!(fontgarden.respond("GET", "/fonts").status, 404);
+        assert_eq!(fontgarden.respond("GET", "/glyphs/%4").status, 400);
+        assert_eq!(fontgarden.respond("POST", "/glyphs").status, 405);
+    }
+
+    #[test]
+    fn report_coverage() {
+        let coverage = json(fontgarden().respond("GET", "/coverage"));
+        assert_eq!(coverage["glyphs"], 2);
+        assert_eq!(coverage["codepoints"], 2);
+        assert_eq!(
+            coverage["sources"]["Bold"],
+            serde_json::json!({ "glyphs": 1, "missing": ["alpha"] })
+        );
+        assert_eq!(coverage["sources"]["Regular"]["glyphs"], 2);
+        assert_eq!(coverage["sources"]["Display.Italic"]["glyphs"], 1);
+        assert_eq!(
+            coverage["sets"]["Greek"],
+            serde_json::json!({ "glyphs": 1, "codepoints": 1 })
+        );
+    }
+}
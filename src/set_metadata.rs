@@ -0,0 +1,198 @@
+//! License and attribution of sets, stored in `set.<name>.toml` next to the
+//! set's CSV file, for fontgardens that mix glyphs from several contributors,
+//! and whether a set is locked against imports.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    path::Path,
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::LoadError,
+    storage::Storage,
+    structs::{Fontgarden, Glyph},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetMetadata {
+    /// The license of the glyphs, ideally as an SPDX identifier like `OFL-1.1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub designers: Vec<String>,
+    /// Where the glyphs come from, like the repository of a shared set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_url: Option<String>,
+    /// Whether imports must leave the glyphs of the set and the sets nested
+    /// in it alone, unless forced.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+impl SetMetadata {
+    pub fn load(storage: &impl Storage, path: &Path) -> Result<Self, LoadError> {
+        let text = storage
+            .read_to_string(path)
+            .map_err(|e| LoadError::Io(storage.location(path), e))?;
+        toml::from_str(&text).map_err(|e| LoadError::LoadSetMetadata(storage.location(path), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn has_credits(&self) -> bool {
+        self.license.is_some() || !self.designers.is_empty() || self.upstream_url.is_some()
+    }
+}
+
+impl Fontgarden {
+    /// The credits for the sets of the given glyphs that have metadata, as
+    /// plain text for a CREDITS file, or `None` if there are none.
+    pub fn credits<'a>(&self, glyph_names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let set_names: BTreeSet<&str> = glyph_names
+            .into_iter()
+            .filter_map(|glyph_name| self.glyphs.get(glyph_name))
+            .map(|glyph| glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME))
+            .collect();
+        let mut credits = String::new();
+        for set_name in set_names {
+            let Some(metadata) = self
+                .set_metadata
+                .get(set_name)
+                .filter(|metadata| metadata.has_credits())
+            else {
+                continue;
+            };
+            if !credits.is_empty() {
+                credits.push('\n');
+            }
+            writeln!(credits, "{set_name}").unwrap();
+            if let Some(license) = &metadata.license {
+                writeln!(credits, "  License: {license}").unwrap();
+            }
+            if !metadata.designers.is_empty() {
+                writeln!(credits, "  Designers: {}", metadata.designers.join(", ")).unwrap();
+            }
+            if let Some(upstream_url) = &metadata.upstream_url {
+                writeln!(credits, "  Upstream: {upstream_url}").unwrap();
+            }
+        }
+        (!credits.is_empty()).then_some(credits)
+    }
+
+    /// Whether a glyph is in a locked set or one nested in it.
+    pub fn is_locked(&self, glyph: &Glyph) -> bool {
+        self.set_metadata
+            .iter()
+            .any(|(set_name, metadata)| metadata.locked && glyph.is_in_set(set_name))
+    }
+
+    /// Lock or unlock a set.
+    pub fn set_locked(&mut self, set_name: &str, locked: bool) {
+        let metadata = self.set_metadata.entry(set_name.into()).or_default();
+        metadata.locked = locked;
+        if metadata.is_empty() {
+            self.set_metadata.remove(set_name);
+        }
+    }
+
+    /// The names of the glyphs of locked sets that were changed or removed
+    /// since `glyphs_before`, sorted.
+    pub fn locked_glyph_changes(&self, glyphs_before: &HashMap<Arc<str>, Glyph>) -> Vec<String> {
+        let mut glyph_names: Vec<String> = glyphs_before
+            .iter()
+            .filter(|(glyph_name, glyph)| {
+                self.is_locked(glyph) && self.glyphs.get(*glyph_name) != Some(*glyph)
+            })
+            .map(|(glyph_name, _)| glyph_name.to_string())
+            .collect();
+        glyph_names.sort();
+        glyph_names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::Glyph;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip_and_credits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        let mut fontgarden = Fontgarden::new();
+        for (glyph_name, set) in [
+            ("a", Some("Latin")),
+            ("alpha", Some("Greek")),
+            ("period", None),
+        ] {
+            let glyph = Glyph {
+                set: set.map(String::from),
+                ..Default::default()
+            };
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        }
+        fontgarden.set_metadata.insert(
+            "Latin".into(),
+            SetMetadata {
+                license: Some("OFL-1.1".into()),
+                designers: vec!["A. Designer".into(), "B. Designer".into()],
+                upstream_url: None,
+                locked: false,
+            },
+        );
+        fontgarden.set_metadata.insert(
+            "Common".into(),
+            SetMetadata {
+                upstream_url: Some("https://example.com/common.fontgarden".into()),
+                ..Default::default()
+            },
+        );
+        fontgarden.save(&path).unwrap();
+
+        let loaded = Fontgarden::load(&path).unwrap();
+
+        assert_eq!(loaded.set_metadata, fontgarden.set_metadata);
+        assert!(path.join("set.L_atin.toml").exists());
+        assert_eq!(
+            loaded.credits(["a", "alpha", "period", ".notdef"]).unwrap(),
+            "Common\n  \
+               Upstream: https://example.com/common.fontgarden\n\
+             \n\
+             Latin\n  \
+               License: OFL-1.1\n  \
+               Designers: A. Designer, B. Designer\n"
+        );
+        assert_eq!(loaded.credits(["alpha"]), None);
+    }
+
+    #[test]
+    fn locked_sets() {
+        let mut fontgarden = Fontgarden::new();
+        for (glyph_name, set) in [("a", "Latin/Core"), ("alpha", "Greek")] {
+            let glyph = Glyph {
+                set: Some(set.into()),
+                ..Default::default()
+            };
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        }
+        fontgarden.set_locked("Latin", true);
+        let glyphs_before = fontgarden.glyphs.clone();
+
+        fontgarden.glyphs.get_mut("alpha").unwrap().postscript_name = Some("uni03B1".into());
+        assert!(fontgarden.locked_glyph_changes(&glyphs_before).is_empty());
+        fontgarden.glyphs.remove("a");
+        assert_eq!(fontgarden.locked_glyph_changes(&glyphs_before), ["a"]);
+
+        assert_eq!(fontgarden.credits(["alpha"]), None);
+        fontgarden.set_locked("Latin", false);
+        assert!(fontgarden.set_metadata.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! A plain text summary of a glyph, as printed by `fontgarden show`.
+
+use std::fmt::Write;
+
+use crate::{
+    errors::ShowError,
+    structs::{Fontgarden, LayerName},
+};
+
+impl Fontgarden {
+    /// Describe a glyph's data and each of its layers, sorted by name. With
+    /// `json`, the layers are followed by their data as in the layer files.
+    pub fn describe_glyph(&self, glyph_name: &str, json: bool) -> Result<String, ShowError> {
+        let glyph = self
+            .glyphs
+            .get(glyph_name)
+            .ok_or_else(|| ShowError::UnknownGlyph(glyph_name.into()))?;
+
+        let codepoints = glyph
+            .codepoints
+            .iter()
+            .map(|c| format!("U+{:04X}", c as u32))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // Writing to a string doesn't fail.
+        let mut text = String::new();
+        writeln!(text, "{glyph_name}").unwrap();
+        writeln!(text, "  codepoints: {codepoints}").unwrap();
+        writeln!(
+            text,
+            "  set: {}",
+            glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME)
+        )
+        .unwrap();
+        writeln!(text, "  opentype category: {}", glyph.opentype_category).unwrap();
+        writeln!(
+            text,
+            "  postscript name: {}",
+            glyph.postscript_name.as_deref().unwrap_or("")
+        )
+        .unwrap();
+        if !glyph.tags.is_empty() {
+            let tags: Vec<&str> = glyph.tags.iter().map(String::as_str).collect();
+            writeln!(text, "  tags: {}", tags.join(", ")).unwrap();
+        }
+
+        let mut layer_names: Vec<&LayerName> = glyph.layers.keys().collect();
+        layer_names.sort();
+        writeln!(text, "  layers:").unwrap();
+        for layer_name in layer_names {
+            let layer = &glyph.layers[layer_name];
+            let advance = match layer.x_advance {
+                Some(x_advance) => x_advance.to_string(),
+                None => "none".to_string(),
+            };
+            writeln!(
+                text,
+                "    {layer_name}: {} contours, {} components, {} anchors, advance {advance}",
+                layer.contours.len(),
+                layer.components.len(),
+                layer.anchors.len(),
+            )
+            .unwrap();
+            if json {
+                let layer_json = serde_json::to_string_pretty(layer).expect("layers serialize");
+                for line in layer_json.lines() {
+                    writeln!(text, "      {line}").unwrap();
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::OpenTypeCategory,
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    #[test]
+    fn describe_glyph() {
+        let fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "aacute",
+                GlyphBuilder::new()
+                    .codepoints(['á'])
+                    .set("Latin")
+                    .category(OpenTypeCategory::Base)
+                    .postscript_name("uni00E1")
+                    .tag("review")
+                    .tag("v2")
+                    .layer(
+                        "Regular",
+                        LayerBuilder::new()
+                            .width(500.)
+                            .component("a", 0., 0.)
+                            .component("acutecomb", 250., 0.)
+                            .anchor("top", 250., 700.),
+                    )
+                    .layer(
+                        "Regular.background",
+                        LayerBuilder::new().rect(0., 0., 10., 10.),
+                    ),
+            )
+            .glyph(
+                "space",
+                GlyphBuilder::new()
+                    .codepoints([' '])
+                    .postscript_name("uni0020")
+                    .layer("Regular", LayerBuilder::new().width(250.)),
+            )
+            .build();
+
+        assert_eq!(
+            fontgarden.describe_glyph("aacute", false).unwrap(),
+            "aacute
+  codepoints: U+00E1
+  set: Latin
+  opentype category: base
+  postscript name: uni00E1
+  tags: review, v2
+  layers:
+    Regular: 0 contours, 2 components, 1 anchors, advance 500
+    Regular.background: 1 contours, 0 components, 0 anchors, advance none
+"
+        );
+        assert_eq!(
+            fontgarden.describe_glyph("space", true).unwrap(),
+            r#"space
+  codepoints: U+0020
+  set: Common
+  opentype category: unassigned
+  postscript name: uni0020
+  layers:
+    Regular: 0 contours, 0 components, 0 anchors, advance 250
+      {
+        "anchors": [],
+        "components": [],
+        "contours": [],
+        "x_advance": 250.0
+      }
+"#
+        );
+        let error = fontgarden.describe_glyph("b", false).unwrap_err();
+        assert_eq!(error.to_string(), "no glyph named 'b' in the fontgarden");
+    }
+}
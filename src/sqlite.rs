@@ -0,0 +1,425 @@
+//! Fontgardens in a single SQLite file, for very large gardens where millions
+//! of small files are impractical. Glyph metadata and layers get a table each,
+//! with indexes on the set and codepoints of glyphs for querying the file with
+//! other tools. The remaining files of a fontgarden, like its configuration,
+//! are kept as they are in a table of their own.
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Deserialize;
+
+use crate::{
+    errors::{LoadError, SaveError, SqliteImportError},
+    par::*,
+    storage::{MemoryStorage, Storage},
+    structs::{codepoints_to_string, Fontgarden, Glyph, Layer, LayerName, SetRecord},
+    ufo::{ImportOptions, ImportedGlyph},
+};
+
+pub const EXTENSION: &str = "sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS files (
+        path TEXT PRIMARY KEY,
+        contents BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS glyphs (
+        name TEXT PRIMARY KEY,
+        set_name TEXT,
+        codepoints TEXT NOT NULL,
+        record TEXT NOT NULL,
+        hash TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS glyphs_by_set ON glyphs (set_name);
+    CREATE INDEX IF NOT EXISTS glyphs_by_codepoints ON glyphs (codepoints);
+    CREATE TABLE IF NOT EXISTS layers (
+        glyph TEXT NOT NULL,
+        layer TEXT NOT NULL,
+        json BLOB NOT NULL,
+        PRIMARY KEY (glyph, layer)
+    );
+";
+
+/// A glyph ready to be written: its metadata row and its non-empty layers as
+/// JSON.
+struct GlyphRow<'a> {
+    name: &'a Arc<str>,
+    set_name: Option<&'a str>,
+    codepoints: String,
+    record: String,
+    hash: String,
+    layers: Vec<(String, Vec<u8>)>,
+}
+
+impl Fontgarden {
+    /// Load a fontgarden from a SQLite file.
+    pub fn load_sqlite(path: &Path) -> Result<Self, LoadError> {
+        let sqlite_error = |e| LoadError::Sqlite(path.into(), e);
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(sqlite_error)?;
+
+        let files = MemoryStorage::new();
+        let mut statement = connection
+            .prepare("SELECT path, contents FROM files")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(sqlite_error)?;
+        for row in rows {
+            let (file, contents) = row.map_err(sqlite_error)?;
+            files
+                .write(Path::new(&file), &contents)
+                .expect("writing to memory doesn't fail");
+        }
+        let mut fontgarden = Fontgarden::load_from(&files)?;
+
+        let mut statement = connection
+            .prepare("SELECT name, set_name, record FROM glyphs")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(sqlite_error)?;
+        for row in rows {
+            let (glyph_name, set, record) = row.map_err(sqlite_error)?;
+            // Through a value, so that escaped strings can be borrowed.
+            let (glyph_name, glyph) = serde_json::from_str::<serde_json::Value>(&record)
+                .and_then(|record| Ok(SetRecord::deserialize(&record)?.into_glyph(set)))
+                .map_err(|e| LoadError::LoadGlyphRecord(path.into(), glyph_name, e))?;
+            fontgarden.glyphs.insert(glyph_name, glyph);
+        }
+
+        let mut statement = connection
+            .prepare("SELECT glyph, layer, json FROM layers")
+            .map_err(sqlite_error)?;
+        let rows: Vec<(String, String, Vec<u8>)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(sqlite_error)?
+            .collect::<Result<_, _>>()
+            .map_err(sqlite_error)?;
+        let layers: Vec<(String, LayerName, Layer)> = rows
+            .into_par_iter()
+            .map(|(glyph_name, layer_name, json)| {
+                let layer = serde_json::from_slice(&json)
+                    .map_err(|e| LoadError::LoadLayerJson(path.into(), glyph_name.clone(), e))?;
+                Ok((glyph_name, LayerName::from(layer_name.as_str()), layer))
+            })
+            .collect::<Result<_, LoadError>>()?;
+        for (glyph_name, layer_name, layer) in layers {
+            if let Some(glyph) = fontgarden.glyphs.get_mut(glyph_name.as_str()) {
+                glyph.layers.insert(layer_name, layer);
+            }
+        }
+
+        fontgarden.intern_names();
+        Ok(fontgarden)
+    }
+
+    /// Save the fontgarden to a SQLite file, creating it if needed. Only the
+    /// glyphs whose content hash changed since the file was last saved are
+    /// written, and how many that were is returned.
+    pub fn save_sqlite(&self, path: &Path) -> Result<usize, SaveError> {
+        let sqlite_error = |e| SaveError::Sqlite(path.into(), e);
+        let mut connection = Connection::open(path).map_err(sqlite_error)?;
+        connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+
+        // Everything but the set data and layers, written the way saving to
+        // files does.
+        let metadata = Fontgarden {
+            glyphs: HashMap::new(),
+            config: self.config.clone(),
+            lib: self.lib.clone(),
+            set_metadata: self.set_metadata.clone(),
+            hinting: self.hinting.clone(),
+            source_libs: self.source_libs.clone(),
+            modified: self.modified.clone(),
+        };
+        let files = MemoryStorage::new();
+        metadata.save_to(&files)?;
+        transaction
+            .execute("DELETE FROM files", [])
+            .map_err(sqlite_error)?;
+        for (file, contents) in files.into_files() {
+            transaction
+                .execute(
+                    "INSERT INTO files (path, contents) VALUES (?1, ?2)",
+                    params![file.to_string_lossy(), contents],
+                )
+                .map_err(sqlite_error)?;
+        }
+
+        let mut stored_hashes: HashMap<String, String> = HashMap::new();
+        {
+            let mut statement = transaction
+                .prepare("SELECT name, hash FROM glyphs")
+                .map_err(sqlite_error)?;
+            let rows = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(sqlite_error)?;
+            for row in rows {
+                let (glyph_name, hash) = row.map_err(sqlite_error)?;
+                stored_hashes.insert(glyph_name, hash);
+            }
+        }
+
+        let changed: Vec<GlyphRow> = self
+            .glyphs
+            .par_iter()
+            .filter_map(|(name, glyph)| {
+                self.glyph_row(name, glyph, stored_hashes.get(&**name))
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+        for row in &changed {
+            transaction
+                .execute("DELETE FROM layers WHERE glyph = ?1", [&**row.name])
+                .map_err(sqlite_error)?;
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO glyphs (name, set_name, codepoints, record, hash)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        &**row.name,
+                        row.set_name,
+                        row.codepoints,
+                        row.record,
+                        row.hash
+                    ],
+                )
+                .map_err(sqlite_error)?;
+            for (layer_name, json) in &row.layers {
+                transaction
+                    .execute(
+                        "INSERT INTO layers (glyph, layer, json) VALUES (?1, ?2, ?3)",
+                        params![&**row.name, layer_name, json],
+                    )
+                    .map_err(sqlite_error)?;
+            }
+        }
+        for glyph_name in stored_hashes.keys() {
+            if self.glyphs.contains_key(glyph_name.as_str()) {
+                continue;
+            }
+            transaction
+                .execute("DELETE FROM layers WHERE glyph = ?1", [glyph_name])
+                .map_err(sqlite_error)?;
+            transaction
+                .execute("DELETE FROM glyphs WHERE name = ?1", [glyph_name])
+                .map_err(sqlite_error)?;
+        }
+
+        transaction.commit().map_err(sqlite_error)?;
+        Ok(changed.len())
+    }
+
+    /// The row of a glyph if its hash differs from the stored one, with layers
+    /// rounded to the configured precision before hashing so that loading and
+    /// saving again writes nothing.
+    fn glyph_row<'a>(
+        &self,
+        name: &'a Arc<str>,
+        glyph: &'a Glyph,
+        stored_hash: Option<&String>,
+    ) -> Result<Option<GlyphRow<'a>>, SaveError> {
+        let rounded;
+        let glyph_to_hash = match self.config.precision {
+            Some(decimals) => {
+                let mut glyph = glyph.clone();
+                for layer in glyph.layers.values_mut() {
+                    *layer = layer.with_precision(decimals);
+                }
+                rounded = glyph;
+                &rounded
+            }
+            None => glyph,
+        };
+        let hash = glyph_to_hash.content_hash();
+        if stored_hash == Some(&hash) {
+            return Ok(None);
+        }
+        let mut layers = Vec::new();
+        for (layer_name, layer) in glyph_to_hash
+            .layers
+            .iter()
+            .filter(|(_, layer)| !layer.is_empty())
+        {
+            let json = serde_json::to_vec(layer).map_err(|e| {
+                SaveError::SaveLayerJson(name.to_string(), layer_name.to_string(), e)
+            })?;
+            layers.push((layer_name.to_string(), json));
+        }
+        Ok(Some(GlyphRow {
+            name,
+            set_name: glyph.set.as_deref(),
+            codepoints: codepoints_to_string(&glyph.codepoints),
+            record: serde_json::to_string(&SetRecord::new(name, glyph))
+                .expect("set records are valid JSON"),
+            hash,
+            layers,
+        }))
+    }
+
+    /// Import the glyphs of a fontgarden saved to a SQLite file, merged like
+    /// those of other sources. Into an empty fontgarden, the configuration and
+    /// other garden-wide data come along, which converts the garden to files.
+    pub fn import_sqlite(
+        &mut self,
+        path: &Path,
+        options: &ImportOptions,
+    ) -> Result<(), SqliteImportError> {
+        let mut imported = Fontgarden::load_sqlite(path)?;
+        let source_names: Vec<String> = imported.source_names().into_iter().collect();
+        let is_conversion = self.glyphs.is_empty();
+        if is_conversion {
+            self.config = imported.config.clone();
+            self.lib = std::mem::take(&mut imported.lib);
+            self.set_metadata = std::mem::take(&mut imported.set_metadata);
+            self.hinting = std::mem::take(&mut imported.hinting);
+            self.source_libs = std::mem::take(&mut imported.source_libs);
+        }
+        for source_name in &source_names {
+            if let Some(source) = imported.config.sources.get(source_name) {
+                if !self.config.axes.is_empty() && !self.config.sources.contains_key(source_name) {
+                    self.config.add_source(source_name, source.location.clone());
+                }
+            }
+        }
+        self.record_default_source(&source_names, options)?;
+        let scale_factor = match imported.config.units_per_em {
+            Some(units_per_em) => self.file_scale_factor(units_per_em, options)?,
+            None => None,
+        };
+        let glyphs = imported
+            .glyphs
+            .into_iter()
+            .map(|(glyph_name, glyph)| ImportedGlyph {
+                name: glyph_name.to_string(),
+                codepoints: glyph.codepoints,
+                set: glyph.set,
+                opentype_category: Some(glyph.opentype_category),
+                postscript_name: glyph.postscript_name,
+                advance_link: glyph.advance_link,
+                tags: glyph.tags,
+                layers: glyph.layers.into_iter().collect(),
+            })
+            .collect();
+        self.merge_imported_glyphs(glyphs, &source_names, scale_factor, options)?;
+        if is_conversion {
+            let mut modified = imported.modified;
+            modified
+                .0
+                .retain(|glyph_name, _| self.glyphs.contains_key(glyph_name.as_str()));
+            self.modified = modified;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+        ufo::ImportChannel,
+    };
+
+    use super::*;
+
+    fn fontgarden() -> Fontgarden {
+        let glyph = |name: &str, width: f64| {
+            let layer = || LayerBuilder::new().width(width);
+            GlyphBuilder::new()
+                .codepoints(name.chars())
+                .set("Latin")
+                .tag("review")
+                .layer("Regular", layer())
+                .layer("Regular.background", layer())
+        };
+        FontgardenBuilder::new()
+            .glyph("a", glyph("a", 500.))
+            .glyph("b", glyph("b", 600.).advance_link("a"))
+            .build()
+    }
+
+    #[test]
+    fn save_only_changed_glyphs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.sqlite");
+        let mut fontgarden = fontgarden();
+        assert_eq!(fontgarden.save_sqlite(&path).unwrap(), 2);
+        assert_eq!(Fontgarden::load_sqlite(&path).unwrap(), fontgarden);
+        assert_eq!(fontgarden.save_sqlite(&path).unwrap(), 0);
+
+        fontgarden.glyphs.get_mut("a").unwrap().tags.clear();
+        fontgarden.glyphs.remove("b");
+        assert_eq!(fontgarden.save_sqlite(&path).unwrap(), 1);
+        assert_eq!(Fontgarden::load_sqlite(&path).unwrap(), fontgarden);
+
+        // Saving goes by the file extension.
+        fontgarden.glyphs.get_mut("a").unwrap().postscript_name = Some("uni0061".into());
+        fontgarden.save(&path).unwrap();
+        assert_eq!(Fontgarden::load(&path).unwrap(), fontgarden);
+
+        // Records are JSON, where names like these are escaped.
+        let mut glyph = fontgarden.glyphs["a"].clone();
+        glyph.postscript_name = Some(r#"quoted"name"#.into());
+        fontgarden.glyphs.insert(r"back\slash".into(), glyph);
+        assert_eq!(fontgarden.save_sqlite(&path).unwrap(), 1);
+        assert_eq!(Fontgarden::load_sqlite(&path).unwrap(), fontgarden);
+    }
+
+    #[test]
+    fn import_into_empty_fontgarden() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.sqlite");
+        let fontgarden = fontgarden();
+        fontgarden.save_sqlite(&path).unwrap();
+
+        let mut converted = Fontgarden::new();
+        converted
+            .import_sqlite(&path, &ImportOptions::default())
+            .unwrap();
+        assert_eq!(converted, fontgarden);
+    }
+
+    #[test]
+    fn import_into_empty_fontgarden_with_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.sqlite");
+        let mut fontgarden = fontgarden();
+        let alpha = GlyphBuilder::new()
+            .set("Greek")
+            .layer("Regular", LayerBuilder::new().width(550.))
+            .build();
+        fontgarden.glyphs.insert("alpha".into(), alpha);
+        fontgarden.save_sqlite(&path).unwrap();
+
+        let mut converted = Fontgarden::new();
+        let options = ImportOptions {
+            sets: HashSet::from(["Greek".into()]),
+            ..Default::default()
+        };
+        converted.import_sqlite(&path, &options).unwrap();
+        let glyph_names: Vec<&str> = converted.glyphs.keys().map(|name| &**name).collect();
+        assert_eq!(glyph_names, ["alpha"]);
+
+        // Only parts of glyphs already in the fontgarden are taken.
+        let mut converted = Fontgarden::new();
+        let options = ImportOptions {
+            only: HashSet::from([ImportChannel::Anchors]),
+            ..Default::default()
+        };
+        converted.import_sqlite(&path, &options).unwrap();
+        assert!(converted.glyphs.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Version and build stamps in exported sources, so that fonts built from them
+//! can be traced back to the state of the garden they came from.
+
+use std::{path::Path, process::Command, str::FromStr, time::SystemTime};
+
+use crate::{errors::StampError, history::format_timestamp};
+
+/// The lib key of the build information in exported sources.
+pub const BUILD_LIB_KEY: &str = "com.github.madig.fontgardener2.build";
+
+/// A font version like `1.002`, with the minor version as written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontVersion {
+    pub major: i32,
+    pub minor: u32,
+}
+
+impl FromStr for FontVersion {
+    type Err = StampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+        match (major.parse(), minor.parse()) {
+            (Ok(major), Ok(minor)) if major >= 0 => Ok(Self { major, minor }),
+            _ => Err(StampError::InvalidVersion(s.into())),
+        }
+    }
+}
+
+/// What to stamp into the fontinfo and lib of exported sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildStamp {
+    pub version: FontVersion,
+    /// When the sources were exported, in UTC.
+    pub created: SystemTime,
+    /// The git commit of the garden, with `-dirty` if it has uncommitted
+    /// changes, if it is in a git repository.
+    pub revision: Option<String>,
+}
+
+impl BuildStamp {
+    /// Stamp a version with the current time and the git revision of the
+    /// garden at a path.
+    pub fn new(version: FontVersion, fontgarden_path: &Path) -> Self {
+        Self {
+            version,
+            created: SystemTime::now(),
+            revision: git_revision(fontgarden_path),
+        }
+    }
+
+    /// Set the version, creation date and name table version string of an
+    /// exported source, and record the build in its lib.
+    pub fn apply_to_ufo(&self, ufo: &mut norad::Font) {
+        let timestamp = format_timestamp(self.created);
+        let font_info = &mut ufo.font_info;
+        font_info.version_major = Some(self.version.major);
+        font_info.version_minor = Some(self.version.minor);
+        // From `2024-05-01T12:30:00Z` to `2024/05/01 12:30:00`.
+        font_info.open_type_head_created = Some(
+            timestamp
+                .trim_end_matches('Z')
+                .replace('-', "/")
+                .replace('T', " "),
+        );
+        let tool = format!("fontgardener2 {}", env!("CARGO_PKG_VERSION"));
+        let mut version_string = format!(
+            "Version {}.{:03}; {tool}",
+            self.version.major, self.version.minor
+        );
+        if let Some(revision) = &self.revision {
+            version_string.push_str(&format!("; {revision}"));
+        }
+        font_info.open_type_name_version = Some(version_string);
+
+        let mut build = plist::Dictionary::new();
+        build.insert("created".into(), timestamp.into());
+        build.insert("tool".into(), tool.into());
+        if let Some(revision) = &self.revision {
+            build.insert("revision".into(), revision.clone().into());
+        }
+        ufo.lib.insert(BUILD_LIB_KEY.into(), build.into());
+    }
+}
+
+/// The commit checked out where a garden is, if git can tell.
+fn git_revision(fontgarden_path: &Path) -> Option<String> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(fontgarden_path)
+            .args(args)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "--short", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--", "."]).is_some_and(|s| !s.is_empty());
+    Some(if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn stamp_exported_sources() {
+        assert!("1.x".parse::<FontVersion>().is_err());
+        assert!("-1.000".parse::<FontVersion>().is_err());
+        let stamp = BuildStamp {
+            version: "2.013".parse().unwrap(),
+            created: SystemTime::UNIX_EPOCH + Duration::from_secs(951_827_696),
+            revision: Some("abc1234".into()),
+        };
+        let mut ufo = norad::Font::new();
+        stamp.apply_to_ufo(&mut ufo);
+        assert_eq!(ufo.font_info.version_major, Some(2));
+        assert_eq!(ufo.font_info.version_minor, Some(13));
+        assert_eq!(
+            ufo.font_info.open_type_head_created.as_deref(),
+            Some("2000/02/29 12:34:56")
+        );
+        assert_eq!(
+            ufo.font_info.open_type_name_version,
+            Some(format!(
+                "Version 2.013; fontgardener2 {}; abc1234",
+                env!("CARGO_PKG_VERSION")
+            ))
+        );
+        let build = ufo.lib[BUILD_LIB_KEY].as_dictionary().unwrap();
+        assert_eq!(build["revision"].as_string(), Some("abc1234"));
+    }
+}
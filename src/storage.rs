@@ -0,0 +1,378 @@
+//! Where the files of a fontgarden are kept. Loading and saving go through the
+//! `Storage` trait with paths relative to the garden root, so that the same
+//! code reads and writes directories, zip archives and gardens held in memory.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The files of one fontgarden, by their path relative to the garden root.
+/// Saving writes from several threads at once.
+pub trait Storage: Send + Sync {
+    /// The contents of a file.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// The contents of a text file.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write a file, creating the directories it is in.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// The names of the files directly in a directory, or none if there is no
+    /// such directory.
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Remove all files, before saving a fontgarden anew.
+    fn clear(&self) -> io::Result<()>;
+
+    /// Where a file is, for error messages.
+    fn location(&self, path: &Path) -> PathBuf {
+        path.into()
+    }
+}
+
+/// A fontgarden directory on disk.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = self.root.join(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(self.root.join(dir)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.metadata()?.is_file() {
+                // TODO: Return an error if filename conversion to UTF-8 fails?
+                if let Some(name) = entry.file_name().to_str() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root)?;
+        }
+        fs::create_dir_all(&self.root)
+    }
+
+    fn location(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+/// A fontgarden packed into a zip archive, opened either for reading or for
+/// writing a new archive.
+pub struct ZipStorage {
+    path: PathBuf,
+    archive: Mutex<ZipArchive>,
+}
+
+enum ZipArchive {
+    Reading(zip::ZipArchive<File>),
+    Writing(zip::ZipWriter<File>),
+}
+
+impl ZipStorage {
+    pub const EXTENSION: &str = "zip";
+
+    /// Open an existing archive for reading.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let archive = zip::ZipArchive::new(File::open(path)?)?;
+        Ok(Self {
+            path: path.into(),
+            archive: Mutex::new(ZipArchive::Reading(archive)),
+        })
+    }
+
+    /// Create a new, empty archive for writing, replacing any file at `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let writer = zip::ZipWriter::new(File::create(path)?);
+        Ok(Self {
+            path: path.into(),
+            archive: Mutex::new(ZipArchive::Writing(writer)),
+        })
+    }
+
+    /// Write the central directory of an archive opened for writing.
+    pub fn finish(self) -> io::Result<()> {
+        match self
+            .archive
+            .into_inner()
+            .expect("no thread panicked while writing")
+        {
+            ZipArchive::Reading(_) => Ok(()),
+            ZipArchive::Writing(mut writer) => writer.finish().map(|_| ()).map_err(Into::into),
+        }
+    }
+}
+
+/// The name of a file in a zip archive, which always uses forward slashes.
+fn archive_name(path: &Path) -> String {
+    path.iter()
+        .map(|component| component.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn wrong_mode(mode: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("the archive is open for {mode}"),
+    )
+}
+
+impl Storage for ZipStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut archive = self
+            .archive
+            .lock()
+            .expect("no thread panicked while reading");
+        let ZipArchive::Reading(archive) = &mut *archive else {
+            return Err(wrong_mode("writing"));
+        };
+        let mut file = archive.by_name(&archive_name(path))?;
+        let mut contents = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut archive = self
+            .archive
+            .lock()
+            .expect("no thread panicked while writing");
+        let ZipArchive::Writing(writer) = &mut *archive else {
+            return Err(wrong_mode("reading"));
+        };
+        writer.start_file(archive_name(path), zip::write::FileOptions::default())?;
+        writer.write_all(contents)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let archive = self
+            .archive
+            .lock()
+            .expect("no thread panicked while reading");
+        let ZipArchive::Reading(archive) = &*archive else {
+            return Err(wrong_mode("writing"));
+        };
+        let prefix = match archive_name(dir) {
+            dir if dir.is_empty() => dir,
+            dir => dir + "/",
+        };
+        Ok(archive
+            .file_names()
+            .filter_map(|name| name.strip_prefix(&prefix))
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .map(String::from)
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let archive = self
+            .archive
+            .lock()
+            .expect("no thread panicked while reading");
+        match &*archive {
+            ZipArchive::Reading(archive) => {
+                let name = archive_name(path);
+                archive.file_names().any(|file_name| file_name == name)
+            }
+            ZipArchive::Writing(_) => false,
+        }
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        match &*self
+            .archive
+            .lock()
+            .expect("no thread panicked while writing")
+        {
+            // Archives are only ever written new.
+            ZipArchive::Writing(_) => Ok(()),
+            ZipArchive::Reading(_) => Err(wrong_mode("reading")),
+        }
+    }
+
+    fn location(&self, path: &Path) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+/// A fontgarden held in memory, for comparing against one on disk and for
+/// tests.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All files, by their path relative to the garden root.
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files
+            .into_inner()
+            .expect("no thread panicked while writing")
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let files = self.files.lock().expect("no thread panicked while writing");
+        files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut files = self.files.lock().expect("no thread panicked while writing");
+        files.insert(path.into(), contents.into());
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let files = self.files.lock().expect("no thread panicked while writing");
+        Ok(files
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .filter_map(|path| path.file_name()?.to_str().map(String::from))
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let files = self.files.lock().expect("no thread panicked while writing");
+        files.contains_key(path)
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        self.files
+            .lock()
+            .expect("no thread panicked while writing")
+            .clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::Fontgarden,
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    fn fontgarden() -> Fontgarden {
+        let layer = || LayerBuilder::new().width(500.);
+        let glyph = || {
+            GlyphBuilder::new()
+                .layer("Regular", layer())
+                .layer("Regular.background", layer())
+        };
+        FontgardenBuilder::new()
+            .glyph("a", glyph())
+            .glyph("b", glyph().set("Latin"))
+            .build()
+    }
+
+    #[test]
+    fn load_and_save_in_memory() {
+        let fontgarden = fontgarden();
+        let storage = MemoryStorage::new();
+        fontgarden.save_to(&storage).unwrap();
+        assert_eq!(
+            storage.list(Path::new("glyphs/b")).unwrap(),
+            ["R_egular.background.json", "R_egular.json"]
+        );
+        assert!(storage.list(Path::new("glyphs/c")).unwrap().is_empty());
+        assert_eq!(
+            Fontgarden::load_from(&storage).unwrap().glyphs,
+            fontgarden.glyphs
+        );
+
+        // Saving again drops files of glyphs that are gone.
+        let mut smaller = fontgarden.clone();
+        smaller.glyphs.remove("b");
+        smaller.save_to(&storage).unwrap();
+        assert!(!storage.exists(Path::new("set.Latin.csv")));
+        assert_eq!(
+            Fontgarden::load_from(&storage).unwrap().glyphs,
+            smaller.glyphs
+        );
+    }
+
+    #[test]
+    fn load_and_save_zip_archives() {
+        let fontgarden = fontgarden();
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("test.zip");
+        fontgarden.save(&archive_path).unwrap();
+        assert!(archive_path.is_file());
+        assert_eq!(
+            Fontgarden::load(&archive_path).unwrap().glyphs,
+            fontgarden.glyphs
+        );
+
+        // The archive holds the same files as a directory.
+        let garden_path = dir.path().join("test.fontgarden");
+        fontgarden.save(&garden_path).unwrap();
+        let archive = ZipStorage::open(&archive_path).unwrap();
+        let on_disk = FileStorage::new(&garden_path);
+        for glyph_dir in ["", "glyphs/a", "glyphs/b"].map(Path::new) {
+            let mut archived = archive.list(glyph_dir).unwrap();
+            archived.sort();
+            let mut files = on_disk.list(glyph_dir).unwrap();
+            files.sort();
+            assert_eq!(archived, files);
+            for file in files {
+                let path = glyph_dir.join(file);
+                assert_eq!(archive.read(&path).unwrap(), on_disk.read(&path).unwrap());
+            }
+        }
+        assert!(archive.write(Path::new("notes.txt"), b"").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Loading that also rejects whatever a garden holds but plain loading skips,
+//! for checking gardens in CI.
+
+use std::{collections::BTreeSet, ffi::OsStr, fs, path::Path};
+
+use crate::{
+    config::Config,
+    errors::LoadError,
+    filenames::filename_to_name,
+    garden_lib::GardenLib,
+    hashing::HashIndex,
+    history::ModificationIndex,
+    structs::{Fontgarden, LayerName},
+};
+
+/// The columns of a `set.<name>.csv` file.
+const SET_COLUMNS: [&str; 6] = [
+    "name",
+    "postscript_name",
+    "codepoints",
+    "opentype_category",
+    "advance_link",
+    "tags",
+];
+
+impl Fontgarden {
+    /// Load a fontgarden, failing on unexpected files, glyph directories of
+    /// glyphs in no set, unknown set columns and layer files for unknown
+    /// sources. Hidden files like `.git` are fine. Only directory gardens can
+    /// be checked, as zip archives and SQLite files have no stray files.
+    pub fn load_strict(path: &Path) -> Result<Self, LoadError> {
+        if !path.is_dir() {
+            return Err(LoadError::StrictNeedsDirectory(path.into()));
+        }
+        let fontgarden = Self::load(path)?;
+        let mut problems = Vec::new();
+
+        for entry in read_dir(path)? {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_dir = entry.path().is_dir();
+            if file_name.starts_with('.') || (is_dir && file_name == "glyphs") {
+                continue;
+            }
+            let expected = !is_dir
+                && (file_name == Config::FILENAME
+                    || file_name == GardenLib::FILENAME
+                    || file_name == ModificationIndex::FILENAME
+                    || file_name == HashIndex::FILENAME
+                    || is_sidecar(&file_name, "set.", &["csv", "toml"])
+                    || is_sidecar(&file_name, "hinting.", &["json"])
+                    || is_sidecar(&file_name, "lib.", &["json"]));
+            if !expected {
+                problems.push(format!("unexpected file {file_name}"));
+            } else if file_name.starts_with("set.") && file_name.ends_with(".csv") {
+                problems.extend(unknown_set_columns(&entry.path())?);
+            }
+        }
+
+        let glyphs_dir = path.join("glyphs");
+        // Configured sources are all there is to a garden that lists them.
+        let source_names = if fontgarden.config.sources.is_empty() {
+            fontgarden.source_names()
+        } else {
+            fontgarden.config.sources.keys().cloned().collect()
+        };
+        let mut unknown_sources = BTreeSet::new();
+        for entry in glyphs_dir
+            .is_dir()
+            .then(|| read_dir(&glyphs_dir))
+            .transpose()?
+            .into_iter()
+            .flatten()
+        {
+            let dir_name = entry.file_name().to_string_lossy().into_owned();
+            if dir_name.starts_with('.') {
+                continue;
+            }
+            if !entry.path().is_dir() {
+                problems.push(format!("unexpected file glyphs/{dir_name}"));
+                continue;
+            }
+            if !fontgarden
+                .glyphs
+                .contains_key(filename_to_name(&dir_name).as_str())
+            {
+                problems.push(format!(
+                    "glyph directory glyphs/{dir_name} belongs to no glyph in a set"
+                ));
+                continue;
+            }
+            for layer_entry in read_dir(&entry.path())? {
+                let layer_path = layer_entry.path();
+                let file_name = layer_entry.file_name().to_string_lossy().into_owned();
+                let stem = layer_path.file_stem().and_then(OsStr::to_str);
+                let is_layer_file = layer_path.is_file()
+                    && layer_path.extension().and_then(OsStr::to_str) == Some("json");
+                match stem.filter(|_| is_layer_file) {
+                    Some(stem) => {
+                        let layer_name: LayerName = filename_to_name(stem).as_str().into();
+                        if !source_names.contains(&*layer_name.source) {
+                            unknown_sources.insert(layer_name.source);
+                        }
+                    }
+                    None if file_name.starts_with('.') => (),
+                    None => problems.push(format!("unexpected file glyphs/{dir_name}/{file_name}")),
+                }
+            }
+        }
+        problems.extend(
+            unknown_sources
+                .into_iter()
+                .map(|source| format!("layer files for unknown source '{source}'")),
+        );
+
+        if !problems.is_empty() {
+            return Err(LoadError::Strict(problems));
+        }
+        Ok(fontgarden)
+    }
+}
+
+/// The entries of a directory, sorted by name.
+fn read_dir(path: &Path) -> Result<Vec<fs::DirEntry>, LoadError> {
+    let mut entries = fs::read_dir(path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| LoadError::Io(path.into(), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+/// Whether a file name is like `<prefix><name>.<extension>`.
+fn is_sidecar(file_name: &str, prefix: &str, extensions: &[&str]) -> bool {
+    file_name.strip_prefix(prefix).is_some_and(|rest| {
+        rest.rsplit_once('.')
+            .is_some_and(|(name, extension)| !name.is_empty() && extensions.contains(&extension))
+    })
+}
+
+fn unknown_set_columns(path: &Path) -> Result<Vec<String>, LoadError> {
+    let mut reader =
+        csv::Reader::from_path(path).map_err(|e| LoadError::LoadSetData(path.into(), e))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| LoadError::LoadSetData(path.into(), e))?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    Ok(headers
+        .iter()
+        .filter(|column| !SET_COLUMNS.contains(column))
+        .map(|column| format!("unknown column '{column}' in {file_name}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::{Glyph, Layer},
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn reject_unexpected_contents() {
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: [(
+                    "Regular".into(),
+                    Layer {
+                        x_advance: Some(500.),
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        fs::create_dir(path.join(".git")).unwrap();
+        Fontgarden::load_strict(&path).unwrap();
+
+        fs::write(path.join("notes.txt"), "").unwrap();
+        fs::create_dir(path.join("glyphs/b")).unwrap();
+        fs::write(
+            path.join("glyphs/a/B_old.background.json"),
+            r#"{"anchors": [], "components": [], "contours": [], "x_advance": 500}"#,
+        )
+        .unwrap();
+        fs::write(
+            path.join("set.C_ommon.csv"),
+            "name,postscript_name,codepoints,opentype_category,advance_link,note\na,,,unassigned,,\n",
+        )
+        .unwrap();
+
+        let Err(LoadError::Strict(problems)) = Fontgarden::load_strict(&path) else {
+            panic!("strict loading should fail");
+        };
+        assert_eq!(
+            problems,
+            [
+                "unexpected file notes.txt",
+                "unknown column 'note' in set.C_ommon.csv",
+                "glyph directory glyphs/b belongs to no glyph in a set",
+                "layer files for unknown source 'Bold'",
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_archives() {
+        let fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "a",
+                GlyphBuilder::new().layer("Regular", LayerBuilder::new().width(500.)),
+            )
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.zip");
+        fontgarden.save(&path).unwrap();
+        Fontgarden::load(&path).unwrap();
+
+        let error = Fontgarden::load_strict(&path).unwrap_err();
+        assert!(matches!(error, LoadError::StrictNeedsDirectory(_)));
+        assert!(error
+            .to_string()
+            .starts_with("strict loading needs a directory garden"));
+    }
+}
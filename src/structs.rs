@@ -1,23 +1,46 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     ffi::OsStr,
-    fs::{self, File},
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 
 use norad::Codepoints;
-use rayon::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
+    alternates::ConditionSet,
+    config::{Config, JsonStyle},
     errors::{LoadError, SaveError},
-    filenames::{filename_to_name, name_to_filename},
+    filenames::{filename_to_name, name_to_filename, set_filename_to_name, set_name_to_filename},
+    garden_lib::GardenLib,
+    hashing::HashIndex,
+    hinting::{postscript_hints_from_lib, SourceHinting, TrueTypeInstructions},
+    history::ModificationIndex,
+    interpolate::Location,
+    lib_keys::SourceLib,
+    par::*,
+    set_metadata::SetMetadata,
+    storage::{FileStorage, Storage, ZipStorage},
+    variable_components::variable_components_from_lib,
 };
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Fontgarden {
-    pub glyphs: HashMap<String, Glyph>,
+    pub glyphs: HashMap<Arc<str>, Glyph>,
+    pub config: Config,
+    /// Project metadata from `lib.json`.
+    pub lib: GardenLib,
+    /// License and attribution by set name.
+    pub set_metadata: BTreeMap<String, SetMetadata>,
+    /// Font-wide hinting data by source name.
+    pub hinting: BTreeMap<String, SourceHinting>,
+    /// Kept font and layer lib keys by source name.
+    pub source_libs: BTreeMap<String, SourceLib>,
+    /// When glyph layers last changed on import, from `modified.json`.
+    pub modified: ModificationIndex,
 }
 
 impl Fontgarden {
@@ -25,111 +48,267 @@ impl Fontgarden {
         Self::default()
     }
 
-    const COMMON_SET_NAME: &str = "Common";
+    pub const COMMON_SET_NAME: &str = "Common";
 
+    /// What separates the names of nested sets from their parent's, like in
+    /// `Latin/Extended`.
+    pub const SET_SEPARATOR: char = '/';
+
+    /// The path of a glyph's layer file, relative to the fontgarden.
+    pub fn layer_path(glyph_name: &str, layer_name: &LayerName) -> PathBuf {
+        // Can't use `with_extension()` here because with layer names like
+        // "Bla.background" it would replace the "background"!
+        let layer_filename = format!("{}.json", name_to_filename(&layer_name.to_string()));
+        Path::new("glyphs")
+            .join(name_to_filename(glyph_name))
+            .join(layer_filename)
+    }
+
+    /// Find the layer of a component's base glyph that goes with `layer_name`.
+    ///
+    /// That is the same layer if the base glyph has it. Sparse sublayers like
+    /// backgrounds fall back to the main layer of their source.
+    pub fn component_layer(&self, base_name: &str, layer_name: &LayerName) -> Option<&Layer> {
+        let glyph = self.glyphs.get(base_name)?;
+        glyph
+            .layers
+            .get(layer_name)
+            .or_else(|| glyph.layers.get(&layer_name.main_layer()))
+    }
+
+    /// Names of all sources: those in the configuration and those that some
+    /// glyph has a main layer for.
+    ///
+    /// `config.sources` is the registry of sources, with their locations and
+    /// naming. Sources imported without an entry there only exist through
+    /// their layers, so they are found here rather than kept in a second list
+    /// that loading, importing and deleting glyphs would have to keep in step.
+    pub fn source_names(&self) -> BTreeSet<String> {
+        self.config
+            .sources
+            .keys()
+            .cloned()
+            .chain(
+                self.glyphs
+                    .values()
+                    .flat_map(|glyph| glyph.layers.keys())
+                    .filter(|layer_name| layer_name.is_main())
+                    .map(|layer_name| layer_name.source.to_string()),
+            )
+            .collect()
+    }
+
+    /// Load the fontgarden at `path`, a directory or a zip archive, or a
+    /// SQLite file with the `sqlite` feature.
     pub fn load(path: &Path) -> Result<Self, LoadError> {
+        #[cfg(feature = "sqlite")]
+        if path.extension() == Some(OsStr::new(crate::sqlite::EXTENSION)) {
+            return Self::load_sqlite(path);
+        }
+        if path.is_file() && path.extension() == Some(OsStr::new(ZipStorage::EXTENSION)) {
+            let storage = ZipStorage::open(path).map_err(|e| LoadError::Io(path.into(), e))?;
+            return Self::load_from(&storage);
+        }
         if !path.is_dir() {
             return Err(LoadError::NotAFontgarden);
         }
+        Self::load_from(&FileStorage::new(path))
+    }
 
-        let mut glyphs: HashMap<String, Glyph> = HashMap::new();
-
-        for entry in fs::read_dir(path).map_err(|e| LoadError::Io(path.into(), e))? {
-            let entry = entry.map_err(|e| LoadError::Io(path.into(), e))?;
-            let metadata = entry
-                .metadata()
-                .map_err(|e| LoadError::Io(path.into(), e))?;
-            if !metadata.is_file() {
-                continue;
-            }
-            let path = entry.path();
-            if path.extension().and_then(OsStr::to_str) != Some("csv") {
+    pub fn load_from(storage: &impl Storage) -> Result<Self, LoadError> {
+        let config = Config::load(storage)?;
+        let lib = GardenLib::load(storage)?;
+        let modified = ModificationIndex::load(storage)?;
+        let mut set_files: Vec<(String, PathBuf)> = Vec::new();
+        let mut set_metadata: BTreeMap<String, SetMetadata> = BTreeMap::new();
+        let mut hinting: BTreeMap<String, SourceHinting> = BTreeMap::new();
+        let mut source_libs: BTreeMap<String, SourceLib> = BTreeMap::new();
+
+        let root = Path::new("");
+        for filename in storage
+            .list(root)
+            .map_err(|e| LoadError::Io(storage.location(root), e))?
+        {
+            let path = PathBuf::from(filename);
+            let extension = path.extension().and_then(OsStr::to_str);
+            if !matches!(extension, Some("csv" | "toml" | "json")) {
                 continue;
             }
             let Some(path_stem) = path.file_stem().map(|s| s.to_string_lossy()) else {
                 continue;
             };
+            if extension == Some("json") {
+                if let Some(source_filename) = path_stem.strip_prefix("hinting.") {
+                    hinting.insert(
+                        filename_to_name(source_filename),
+                        SourceHinting::load(storage, &path)?,
+                    );
+                } else if let Some(source_filename) = path_stem.strip_prefix("lib.") {
+                    source_libs.insert(
+                        filename_to_name(source_filename),
+                        SourceLib::load(storage, &path)?,
+                    );
+                }
+                continue;
+            }
             let Some(set_filename) = path_stem.strip_prefix("set.") else {
                 continue;
             };
 
-            let set_name = filename_to_name(set_filename);
-
-            let mut reader = csv::Reader::from_path(&path)
-                .map_err(|e| LoadError::LoadSetData(path.clone(), e))?;
-
-            for result in reader.deserialize() {
-                let record: SetRecord =
-                    result.map_err(|e| LoadError::LoadSetData(path.clone(), e))?;
-
-                if glyphs.contains_key(&record.name) {
-                    return Err(LoadError::DuplicateGlyphs(set_name, record.name));
-                }
-
-                glyphs.insert(
-                    record.name,
-                    Glyph {
-                        codepoints: record.codepoints,
-                        layers: HashMap::new(),
-                        opentype_category: record.opentype_category,
-                        postscript_name: record.postscript_name,
-                        set: match set_name.as_ref() {
-                            Self::COMMON_SET_NAME => None,
-                            _ => Some(set_name.clone()),
-                        },
-                    },
-                );
+            let set_name = set_filename_to_name(set_filename);
+            if extension == Some("toml") {
+                set_metadata.insert(set_name, SetMetadata::load(storage, &path)?);
+                continue;
             }
+
+            set_files.push((set_name, path));
         }
+        let mut glyphs = load_metadata(storage, set_files)?;
 
+        let glyphs_dir = Path::new("glyphs");
         glyphs
             .par_iter_mut()
-            .map(|(glyph_name, glyph)| {
-                (
-                    glyph_name.as_str(),
-                    glyph,
-                    path.join("glyphs").join(name_to_filename(glyph_name)),
-                )
-            })
-            .filter(|(_, _, glyph_dir)| glyph_dir.exists())
-            .try_for_each(|(glyph_name, glyph, glyph_dir)| -> Result<(), LoadError> {
-                for entry in fs::read_dir(&glyph_dir).map_err(|e| LoadError::Io(glyph_dir.clone(), e))? {
-                    let entry = entry.map_err(|e| LoadError::Io(glyph_dir.clone(), e))?; // Should be entry path?
-                    let layer_path = entry.path();
-                    let metadata = entry
-                        .metadata()
-                        .map_err(|e| LoadError::Io(layer_path.clone(), e))?;
-                    if !metadata.is_file() {
-                        continue;
-                    }
-                    // TODO: Return an error if filename conversion to UTF-8 fails?
-                    let Some(layer_filename_stem) = layer_path.file_stem().and_then(OsStr::to_str) else {
+            .try_for_each(|(glyph_name, glyph)| -> Result<(), LoadError> {
+                let glyph_dir = glyphs_dir.join(name_to_filename(glyph_name));
+                let layer_filenames = storage
+                    .list(&glyph_dir)
+                    .map_err(|e| LoadError::Io(storage.location(&glyph_dir), e))?;
+                for layer_filename in layer_filenames {
+                    let layer_path = glyph_dir.join(layer_filename);
+                    let Some(layer_filename_stem) = layer_path.file_stem().and_then(OsStr::to_str)
+                    else {
                         continue;
                     };
                     let Some("json") = layer_path.extension().and_then(OsStr::to_str) else {
                         continue;
                     };
 
-                    let layer_file =
-                    File::open(&layer_path).map_err(|e| LoadError::Io(layer_path.clone(), e))?;
-                    let layer: Layer = serde_json::from_reader(layer_file).map_err(|e| {
-                        LoadError::LoadLayerJson(layer_path.clone(), glyph_name.into(), e)
+                    let json = storage
+                        .read(&layer_path)
+                        .map_err(|e| LoadError::Io(storage.location(&layer_path), e))?;
+                    let layer: Layer = serde_json::from_slice(&json).map_err(|e| {
+                        LoadError::LoadLayerJson(
+                            storage.location(&layer_path),
+                            glyph_name.to_string(),
+                            e,
+                        )
                     })?;
-                    glyph.layers.insert(filename_to_name(layer_filename_stem), layer);
+                    glyph.layers.insert(filename_to_name(layer_filename_stem).as_str().into(), layer);
                 }
                 Ok(())
             })?;
 
-        Ok(Fontgarden { glyphs })
+        let mut fontgarden = Fontgarden {
+            glyphs,
+            config,
+            lib,
+            set_metadata,
+            hinting,
+            source_libs,
+            modified,
+        };
+        fontgarden.intern_names();
+        Ok(fontgarden)
+    }
+
+    /// Make equal names share one allocation: the source and sublayer names
+    /// repeated in the layer names of every glyph, and glyph names used as
+    /// components. Run after loading or importing many glyphs.
+    pub(crate) fn intern_names(&mut self) {
+        let mut interner = NameInterner(self.glyphs.keys().cloned().collect());
+        for glyph in self.glyphs.values_mut() {
+            glyph.layers = std::mem::take(&mut glyph.layers)
+                .into_iter()
+                .map(|(layer_name, mut layer)| {
+                    for component in &mut layer.components {
+                        component.name = interner.intern(&component.name);
+                    }
+                    let layer_name = LayerName {
+                        source: interner.intern(&layer_name.source),
+                        sublayer: layer_name
+                            .sublayer
+                            .map(|sublayer| interner.intern(&sublayer)),
+                    };
+                    (layer_name, layer)
+                })
+                .collect();
+        }
     }
 
+    /// Save the fontgarden to `path`, as a zip archive if the path ends in
+    /// `.zip`, as a SQLite file if it ends in `.sqlite` and the `sqlite`
+    /// feature is on, and as a directory otherwise, replacing what is there.
     pub fn save(&self, path: &Path) -> Result<(), SaveError> {
-        if path.exists() {
-            std::fs::remove_dir_all(path).map_err(SaveError::Cleanup)?;
+        #[cfg(feature = "sqlite")]
+        if path.extension() == Some(OsStr::new(crate::sqlite::EXTENSION)) {
+            return self.save_sqlite(path).map(|_| ());
+        }
+        if !path.is_dir() && path.extension() == Some(OsStr::new(ZipStorage::EXTENSION)) {
+            let storage = ZipStorage::create(path).map_err(SaveError::CreateDir)?;
+            self.save_to(&storage)?;
+            return storage.finish().map_err(SaveError::FinishArchive);
         }
-        std::fs::create_dir(path).map_err(SaveError::CreateDir)?;
+        self.save_to(&FileStorage::new(path))
+    }
+
+    pub fn save_to(&self, storage: &impl Storage) -> Result<(), SaveError> {
+        storage.clear().map_err(SaveError::Cleanup)?;
 
-        let mut sorted_glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_str()).collect();
+        if let Some(raw_config) = &self.config.raw {
+            storage
+                .write(Path::new(Config::FILENAME), raw_config.as_bytes())
+                .map_err(SaveError::SaveConfig)?;
+        }
+        if !self.lib.is_empty() {
+            let json = serde_json::to_string_pretty(&self.lib).expect("the lib is valid JSON");
+            storage
+                .write(Path::new(GardenLib::FILENAME), (json + "\n").as_bytes())
+                .map_err(SaveError::SaveLib)?;
+        }
+        if !self.modified.is_empty() {
+            let json =
+                serde_json::to_string_pretty(&self.modified).expect("the index is valid JSON");
+            storage
+                .write(
+                    Path::new(ModificationIndex::FILENAME),
+                    (json + "\n").as_bytes(),
+                )
+                .map_err(SaveError::SaveModificationIndex)?;
+        }
+        if !self.glyphs.is_empty() {
+            let hashes = self.content_hashes();
+            let json = serde_json::to_string_pretty(&hashes).expect("hashes are valid JSON");
+            storage
+                .write(Path::new(HashIndex::FILENAME), (json + "\n").as_bytes())
+                .map_err(SaveError::SaveHashIndex)?;
+        }
+        for (set_name, metadata) in &self.set_metadata {
+            let metadata_path = set_name_to_filename(set_name, "toml");
+            let toml = toml::to_string_pretty(metadata).expect("set metadata is valid TOML");
+            storage
+                .write(Path::new(&metadata_path), toml.as_bytes())
+                .map_err(|e| SaveError::SaveSetMetadata(set_name.clone(), e))?;
+        }
+        for (source_name, hinting) in self.hinting.iter().filter(|(_, h)| !h.is_empty()) {
+            let json = serde_json::to_string_pretty(hinting).expect("hinting data is valid JSON");
+            storage
+                .write(
+                    Path::new(&SourceHinting::filename(source_name)),
+                    (json + "\n").as_bytes(),
+                )
+                .map_err(|e| SaveError::SaveHinting(source_name.clone(), e))?;
+        }
+        for (source_name, source_lib) in self.source_libs.iter().filter(|(_, l)| !l.is_empty()) {
+            let json = serde_json::to_string_pretty(source_lib).expect("lib keys are valid JSON");
+            storage
+                .write(
+                    Path::new(&SourceLib::filename(source_name)),
+                    (json + "\n").as_bytes(),
+                )
+                .map_err(|e| SaveError::SaveSourceLib(source_name.clone(), e))?;
+        }
+
+        let mut sorted_glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
         sorted_glyph_names.sort();
         let mut glyphs_by_set: HashMap<&str, Vec<&str>> = HashMap::new();
         for name in sorted_glyph_names.iter() {
@@ -141,48 +320,50 @@ impl Fontgarden {
         }
 
         for (set_name, glyph_names) in glyphs_by_set {
-            let set_info_path = path.join(name_to_filename(&format!("set.{set_name}.csv")));
-            let mut writer = csv::Writer::from_path(&set_info_path)
-                .map_err(|e| SaveError::SaveSetData(set_name.into(), e))?;
+            let mut writer = csv::Writer::from_writer(Vec::new());
 
             for name in glyph_names {
-                let glyph = &self.glyphs[name];
+                let (name, glyph) = self.glyphs.get_key_value(name).unwrap();
 
                 writer
-                    .serialize(SetRecord {
-                        name: name.to_string(),
-                        postscript_name: glyph.postscript_name.clone(),
-                        codepoints: glyph.codepoints.clone(),
-                        opentype_category: glyph.opentype_category.clone(),
-                    })
+                    .serialize(SetRecord::new(name, glyph))
                     .map_err(|e| SaveError::SaveSetData(set_name.into(), e))?;
             }
-            writer
-                .flush()
+            let csv = writer
+                .into_inner()
+                .map_err(|e| SaveError::SaveSetData(set_name.into(), e.into_error().into()))?;
+            storage
+                .write(Path::new(&set_name_to_filename(set_name, "csv")), &csv)
                 .map_err(|e| SaveError::SaveSetData(set_name.into(), e.into()))?;
         }
 
-        let glyphs_dir = path.join("glyphs");
         self.glyphs
             .par_iter()
             .filter(|(_, glyph)| !glyph.is_empty())
             .try_for_each(|(name, glyph)| {
-                let this_glyph_dir = glyphs_dir.join(name_to_filename(name));
-                std::fs::create_dir_all(&this_glyph_dir)
-                    .map_err(|e| SaveError::CreateGlyphDir(name.clone(), e))?;
                 for (layer_name, layer) in
                     glyph.layers.iter().filter(|(_, layer)| !layer.is_empty())
                 {
-                    // Can't use `with_extension()` here because with layer
-                    // names like "Bla.background" it would replace the
-                    // "background"!
-                    let layer_filename = format!("{}.json", name_to_filename(layer_name));
-                    let layer_path = this_glyph_dir.join(layer_filename);
-                    let layer_file = std::fs::File::create(&layer_path)
-                        .map_err(|e| SaveError::SaveLayer(name.clone(), layer_name.clone(), e))?;
-                    serde_json::to_writer_pretty(&layer_file, layer).map_err(|e| {
-                        SaveError::SaveLayerJson(name.clone(), layer_name.clone(), e)
+                    let rounded;
+                    let layer = match self.config.precision {
+                        Some(decimals) => {
+                            rounded = layer.with_precision(decimals);
+                            &rounded
+                        }
+                        None => layer,
+                    };
+                    let layer_path = Self::layer_path(name, layer_name);
+                    let layer_name = layer_name.to_string();
+                    let json = match self.config.json_style {
+                        JsonStyle::Pretty => serde_json::to_vec_pretty(layer),
+                        JsonStyle::Compact => serde_json::to_vec(layer),
+                    }
+                    .map_err(|e| {
+                        SaveError::SaveLayerJson(name.to_string(), layer_name.clone(), e)
                     })?;
+                    storage
+                        .write(&layer_path, &json)
+                        .map_err(|e| SaveError::SaveLayer(name.to_string(), layer_name, e))?;
                 }
                 Ok(())
             })?;
@@ -191,16 +372,143 @@ impl Fontgarden {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SetRecord {
-    name: String,
-    postscript_name: Option<String>,
+/// Hands out one shared allocation per distinct name.
+#[derive(Debug, Default)]
+struct NameInterner(HashSet<Arc<str>>);
+
+impl NameInterner {
+    fn intern(&mut self, name: &Arc<str>) -> Arc<str> {
+        match self.0.get(name) {
+            Some(interned) => interned.clone(),
+            None => {
+                self.0.insert(name.clone());
+                name.clone()
+            }
+        }
+    }
+}
+
+/// Load the glyphs of all set files, parsing the files in parallel. Glyphs come
+/// without layers.
+fn load_metadata(
+    storage: &impl Storage,
+    mut set_files: Vec<(String, PathBuf)>,
+) -> Result<HashMap<Arc<str>, Glyph>, LoadError> {
+    // Merge in set order, so that a glyph in two sets is reported the same way
+    // whatever order the files are listed in.
+    set_files.sort();
+    let sets: Vec<Vec<(Arc<str>, Glyph)>> = set_files
+        .par_iter()
+        .map(|(set_name, path)| load_set_file(storage, set_name, path))
+        .collect::<Result<_, _>>()?;
+
+    let mut glyphs: HashMap<Arc<str>, Glyph> =
+        HashMap::with_capacity(sets.iter().map(Vec::len).sum());
+    for ((set_name, _), set_glyphs) in set_files.into_iter().zip(sets) {
+        for (glyph_name, glyph) in set_glyphs {
+            if glyphs.contains_key(&glyph_name) {
+                return Err(LoadError::DuplicateGlyphs(set_name, glyph_name.to_string()));
+            }
+            glyphs.insert(glyph_name, glyph);
+        }
+    }
+    Ok(glyphs)
+}
+
+/// Load the glyphs of one set file, deserializing every row from the same
+/// reused record and borrowing from it until the glyph is built.
+fn load_set_file(
+    storage: &impl Storage,
+    set_name: &str,
+    path: &Path,
+) -> Result<Vec<(Arc<str>, Glyph)>, LoadError> {
+    let csv = storage
+        .read(path)
+        .map_err(|e| LoadError::Io(storage.location(path), e))?;
+    let mut reader = csv::Reader::from_reader(csv.as_slice());
+    let headers = reader
+        .headers()
+        .map_err(|e| LoadError::LoadSetData(storage.location(path), e))?
+        .clone();
+    let set = match set_name {
+        Fontgarden::COMMON_SET_NAME => None,
+        _ => Some(set_name.to_string()),
+    };
+
+    let mut glyphs = Vec::new();
+    let mut row = csv::StringRecord::new();
+    while reader
+        .read_record(&mut row)
+        .map_err(|e| LoadError::LoadSetData(storage.location(path), e))?
+    {
+        let record: SetRecord = row
+            .deserialize(Some(&headers))
+            .map_err(|e| LoadError::LoadSetData(storage.location(path), e))?;
+        glyphs.push(record.into_glyph(set.clone()));
+    }
+    Ok(glyphs)
+}
+
+/// A row of a `set.<name>.csv` file, with the columns in this order. Names
+/// are borrowed from the glyph when saving and from the row when loading.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct SetRecord<'a> {
+    /// The glyph name.
+    name: &'a str,
+    /// The production name, if different from the glyph name.
+    #[serde(borrow)]
+    postscript_name: Option<&'a str>,
+    /// Space-separated hex codepoints, like `0041 0061`.
     #[serde(with = "codepoints_serde")]
+    #[schemars(with = "String", regex(pattern = r"^([0-9A-Fa-f]+( [0-9A-Fa-f]+)*)?$"))]
     codepoints: Codepoints,
     // Note: Can't skip serializing if default because it will cut off the last column
     // if the first glyph in the set has the default category "unassigned" (?).
     #[serde(default)]
     opentype_category: OpenTypeCategory,
+    /// The glyph whose advance width this glyph takes.
+    #[serde(default, borrow)]
+    advance_link: Option<&'a str>,
+    /// Semicolon-separated freeform tags, like `review;v2`.
+    #[serde(default, with = "tags_serde")]
+    #[schemars(with = "String")]
+    tags: BTreeSet<String>,
+}
+
+impl<'a> SetRecord<'a> {
+    pub(crate) fn new(name: &'a str, glyph: &'a Glyph) -> Self {
+        SetRecord {
+            name,
+            postscript_name: glyph.postscript_name.as_deref(),
+            codepoints: glyph.codepoints.clone(),
+            opentype_category: glyph.opentype_category.clone(),
+            advance_link: glyph.advance_link.as_deref(),
+            tags: glyph.tags.clone(),
+        }
+    }
+
+    /// The glyph of the record, without layers.
+    pub(crate) fn into_glyph(self, set: Option<String>) -> (Arc<str>, Glyph) {
+        let glyph = Glyph {
+            codepoints: self.codepoints,
+            layers: HashMap::new(),
+            opentype_category: self.opentype_category,
+            postscript_name: self.postscript_name.map(String::from),
+            advance_link: self.advance_link.map(String::from),
+            tags: self.tags,
+            set,
+        };
+        (self.name.into(), glyph)
+    }
+}
+
+/// Format codepoints as space-separated hex values, like in the set CSV files.
+pub fn codepoints_to_string(codepoints: &Codepoints) -> String {
+    codepoints
+        .iter()
+        .map(|c| format!("{:04X}", c as usize))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Custom parsing and serilaizing for codepoints, because we use hex-style strings in
@@ -216,13 +524,7 @@ mod codepoints_serde {
     where
         S: Serializer,
     {
-        let codepoints_str = codepoints
-            .iter()
-            .map(|c| format!("{:04X}", c as usize))
-            .collect::<Vec<_>>()
-            .join(" ");
-
-        serializer.serialize_str(&codepoints_str)
+        serializer.serialize_str(&codepoints_to_string(codepoints))
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Codepoints, D::Error>
@@ -245,22 +547,133 @@ mod codepoints_serde {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+/// Custom parsing and serializing for tags, as they are one column in the set
+/// CSV files.
+mod tags_serde {
+    use serde::Serializer;
+
+    use crate::tags::TAG_SEPARATOR;
+
+    use super::*;
+
+    pub fn serialize<S>(tags: &BTreeSet<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        serializer.serialize_str(&tags.join(&TAG_SEPARATOR.to_string()))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeSet<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: &str = Deserialize::deserialize(deserializer)?;
+        Ok(value
+            .split(TAG_SEPARATOR)
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Glyph {
     pub codepoints: Codepoints,
-    pub layers: HashMap<String, Layer>,
+    pub layers: HashMap<LayerName, Layer>,
     pub opentype_category: OpenTypeCategory,
     pub postscript_name: Option<String>,
     pub set: Option<String>,
+    /// The glyph whose advance width this glyph takes in every layer, like a
+    /// composite following its base, resolved on export.
+    pub advance_link: Option<String>,
+    /// Freeform tags, like `needs-kerning` or `review`.
+    pub tags: BTreeSet<String>,
 }
 
 impl Glyph {
     pub fn is_empty(&self) -> bool {
         self.layers.values().all(|layer| layer.is_empty())
     }
+
+    /// Whether the glyph is in a set or in one nested in it, like
+    /// `Latin/Extended` in `Latin`.
+    pub fn is_in_set(&self, set_name: &str) -> bool {
+        let own_set = self.set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME);
+        own_set
+            .strip_prefix(set_name)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(Fontgarden::SET_SEPARATOR))
+    }
+}
+
+/// Which layer of which source a layer is. Sources have a main layer and any
+/// number of sublayers, like backgrounds or intermediate masters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LayerName {
+    pub source: Arc<str>,
+    /// The sublayer name, like `background`, or `None` for the main layer.
+    pub sublayer: Option<Arc<str>>,
+}
+
+impl LayerName {
+    pub fn main(source: impl Into<Arc<str>>) -> Self {
+        Self {
+            source: source.into(),
+            sublayer: None,
+        }
+    }
+
+    pub fn sublayer(source: impl Into<Arc<str>>, sublayer: impl Into<Arc<str>>) -> Self {
+        Self {
+            source: source.into(),
+            sublayer: Some(sublayer.into()),
+        }
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.sublayer.is_none()
+    }
+
+    /// The main layer of the same source.
+    pub fn main_layer(&self) -> Self {
+        Self::main(self.source.clone())
+    }
+}
+
+/// Layer names are written as e.g. "Bold" or "Bold.background", in file names
+/// and on the command line. Periods (and percent signs) in the source name are
+/// percent-escaped, so that "Display.Italic" is written "Display%2EItalic"; the
+/// sublayer name is everything after the first period and needs no escaping.
+impl std::fmt::Display for LayerName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = self.source.replace('%', "%25").replace('.', "%2E");
+        match &self.sublayer {
+            Some(sublayer) => write!(f, "{source}.{sublayer}"),
+            None => f.write_str(&source),
+        }
+    }
+}
+
+impl FromStr for LayerName {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unescape = |source: &str| source.replace("%2E", ".").replace("%25", "%");
+        Ok(match s.split_once('.') {
+            Some((source, sublayer)) => Self::sublayer(unescape(source), sublayer),
+            None => Self::main(unescape(s)),
+        })
+    }
+}
+
+impl From<&str> for LayerName {
+    fn from(s: &str) -> Self {
+        s.parse().unwrap()
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Layer {
     pub anchors: Vec<Anchor>,
     pub components: Vec<Component>,
@@ -271,24 +684,54 @@ pub struct Layer {
     pub x_advance: Option<f64>,
     #[serde(default, skip_serializing_if = "is_default")]
     pub y_advance: Option<f64>,
+    /// Where in the design space an intermediate layer is, for layers other than
+    /// a source's main one.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub location: Option<Location>,
+    /// Where a sublayer replaces its source's main layer, for alternates like
+    /// bracket layers.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub condition: Option<ConditionSet>,
+    /// Kept for compilers only, as the garden doesn't hint.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub truetype_instructions: Option<TrueTypeInstructions>,
+    /// PostScript hints by glyph lib key, kept for compilers only.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub postscript_hints: BTreeMap<String, serde_json::Value>,
+    /// Glyph lib keys kept for other tools, as configured in `[lib_keys]`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub lib: BTreeMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 impl Layer {
+    /// Whether the layer holds nothing at all, not even data kept for other
+    /// tools, so that it can be left out on save.
     pub fn is_empty(&self) -> bool {
         self.anchors.is_empty()
             && self.components.is_empty()
             && self.contours.is_empty()
+            && self.vertical_origin.is_none()
             && self.x_advance.is_none()
             && self.y_advance.is_none()
+            && self.location.is_none()
+            && self.condition.is_none()
+            && self.truetype_instructions.is_none()
+            && self.postscript_hints.is_empty()
+            && self.lib.is_empty()
+            && self.extra.is_empty()
     }
 }
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Contour {
     pub points: Vec<ContourPoint>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ContourPoint {
     pub x: f64,
     pub y: f64,
@@ -298,11 +741,16 @@ pub struct ContourPoint {
     pub smooth: bool,
 }
 
+/// Fields of layer files that this version doesn't know, written back as they
+/// were so that gardens touched by newer versions or other tools keep them.
+/// Points don't have them, as they make up most of a garden.
+pub type ExtraFields = BTreeMap<String, serde_json::Value>;
+
 fn is_default<T: Default + PartialEq>(t: &T) -> bool {
     t == &T::default()
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum PointType {
     #[default]
     OffCurve,
@@ -312,21 +760,29 @@ pub enum PointType {
     QCurve,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Anchor {
     pub name: String,
     pub x: f64,
     pub y: f64,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Component {
-    pub name: String,
+    pub name: Arc<str>,
     #[serde(default, skip_serializing_if = "is_default")]
     pub transformation: AffineTransformation,
+    /// Where in the base glyph's design space to take it from, for variable
+    /// (smart) components. Axes are the base glyph's own or the font's.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub location: Option<Location>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AffineTransformation {
     #[serde(default = "one", skip_serializing_if = "is_one")]
     pub x_scale: f64,
@@ -378,7 +834,7 @@ impl Default for AffineTransformation {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum OpenTypeCategory {
     #[default]
@@ -404,6 +860,19 @@ impl FromStr for OpenTypeCategory {
     }
 }
 
+impl std::fmt::Display for OpenTypeCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Unassigned => "unassigned",
+            Self::Base => "base",
+            Self::Ligature => "ligature",
+            Self::Mark => "mark",
+            Self::Component => "component",
+        };
+        f.write_str(name)
+    }
+}
+
 // TODO: Derive Deserialize and deal with the `parse()` call elsewhere differently.
 impl<'de> Deserialize<'de> for OpenTypeCategory {
     fn deserialize<D>(deserializer: D) -> Result<OpenTypeCategory, D::Error>
@@ -427,11 +896,22 @@ impl From<&norad::Glyph> for Layer {
 
         Self {
             anchors: glyph.anchors.iter().map(|x| x.into()).collect(),
-            components: glyph.components.iter().map(|x| x.into()).collect(),
+            components: glyph
+                .components
+                .iter()
+                .map(|x| x.into())
+                .chain(variable_components_from_lib(&glyph.lib))
+                .collect(),
             contours: glyph.contours.iter().map(|x| x.into()).collect(),
             vertical_origin,
             x_advance: glyph.width.into(),
             y_advance,
+            location: None,
+            condition: None,
+            truetype_instructions: TrueTypeInstructions::from_lib(&glyph.lib),
+            postscript_hints: postscript_hints_from_lib(&glyph.lib),
+            lib: BTreeMap::new(),
+            extra: ExtraFields::new(),
         }
     }
 }
@@ -446,6 +926,7 @@ impl From<&norad::Anchor> for Anchor {
                 .unwrap_or_default(),
             x: anchor.x,
             y: anchor.y,
+            extra: ExtraFields::new(),
         }
     }
 }
@@ -469,6 +950,7 @@ impl From<&norad::Contour> for Contour {
     fn from(value: &norad::Contour) -> Self {
         Self {
             points: value.points.iter().map(|x| x.into()).collect(),
+            extra: ExtraFields::new(),
         }
     }
 }
@@ -531,8 +1013,10 @@ impl From<PointType> for norad::PointType {
 impl From<&norad::Component> for Component {
     fn from(component: &norad::Component) -> Self {
         Self {
-            name: component.base.to_string(),
+            name: component.base.as_str().into(),
             transformation: component.transform.into(),
+            location: None,
+            extra: ExtraFields::new(),
         }
     }
 }
@@ -575,3 +1059,226 @@ impl From<AffineTransformation> for norad::AffineTransform {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_unknown_fields_of_layers() {
+        let json = serde_json::json!({
+            "anchors": [{"name": "top", "x": 100.0, "y": 700.0, "mark_class": "above"}],
+            "components": [{"name": "a", "flipped": true}],
+            "contours": [{"points": [{"x": 0.0, "y": 0.0, "typ": "Line"}], "closed": false}],
+            "x_advance": 500.0,
+            "guidelines": [{"x": 250.0}],
+        });
+        let layer: Layer = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(layer.extra["guidelines"], serde_json::json!([{"x": 250.0}]));
+        assert_eq!(layer.contours[0].extra["closed"], false);
+        assert_eq!(serde_json::to_value(&layer).unwrap(), json);
+    }
+
+    #[test]
+    fn keep_layers_with_only_unknown_data() {
+        let mut layer = Layer::default();
+        layer
+            .extra
+            .insert("guidelines".into(), serde_json::json!([{"x": 250.0}]));
+        let mut lib_only = Layer::default();
+        lib_only
+            .lib
+            .insert("com.example.mark".into(), serde_json::json!(1));
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: HashMap::from([
+                    (LayerName::main("Regular"), layer),
+                    (LayerName::sublayer("Regular", "background"), lib_only),
+                ]),
+                ..Default::default()
+            },
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+
+        assert_eq!(Fontgarden::load(&path).unwrap(), fontgarden);
+    }
+
+    #[test]
+    fn nested_sets() {
+        let glyph = |set: &str| Glyph {
+            set: Some(set.into()),
+            layers: [(
+                "Regular".into(),
+                Layer {
+                    x_advance: Some(500.),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), glyph("Latin/Core"));
+        fontgarden
+            .glyphs
+            .insert("aogonek".into(), glyph("Latin/Extended"));
+        fontgarden.glyphs.insert("alpha".into(), glyph("LatinX"));
+
+        assert!(fontgarden.glyphs["a"].is_in_set("Latin"));
+        assert!(fontgarden.glyphs["a"].is_in_set("Latin/Core"));
+        assert!(!fontgarden.glyphs["a"].is_in_set("Latin/Extended"));
+        assert!(!fontgarden.glyphs["alpha"].is_in_set("Latin"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        assert!(path.join("set.L_atin~E_xtended.csv").exists());
+        assert_eq!(Fontgarden::load(&path).unwrap(), fontgarden);
+    }
+
+    #[test]
+    fn load_many_sets() {
+        let mut fontgarden = Fontgarden::new();
+        for index in 0..20 {
+            for letter in 'a'..='z' {
+                fontgarden.glyphs.insert(
+                    format!("{letter}.ss{index:02}").into(),
+                    Glyph {
+                        set: Some(format!("Set{index:02}")),
+                        tags: ["review".to_string()].into(),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        assert_eq!(Fontgarden::load(&path).unwrap(), fontgarden);
+
+        std::fs::write(
+            path.join("set.S_et99.csv"),
+            "name,postscript_name,codepoints,opentype_category,advance_link,tags\n\
+             a.ss03,,,unassigned,,\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            Fontgarden::load(&path),
+            Err(LoadError::DuplicateGlyphs(set_name, glyph_name))
+                if set_name == "Set99" && glyph_name == "a.ss03"
+        ));
+    }
+
+    #[test]
+    fn share_names_after_load() {
+        let layer = |component_name: &str| Layer {
+            components: vec![Component {
+                name: component_name.into(),
+                transformation: AffineTransformation::default(),
+                location: None,
+                extra: ExtraFields::new(),
+            }],
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert("a".into(), Glyph::default());
+        for glyph_name in ["aacute", "agrave"] {
+            fontgarden.glyphs.insert(
+                glyph_name.into(),
+                Glyph {
+                    layers: HashMap::from([
+                        (LayerName::main("Regular"), layer("a")),
+                        (LayerName::sublayer("Regular", "background"), layer("a")),
+                    ]),
+                    ..Default::default()
+                },
+            );
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+
+        let loaded = Fontgarden::load(&path).unwrap();
+        assert_eq!(loaded, fontgarden);
+        let (a, _) = loaded.glyphs.get_key_value("a").unwrap();
+        let layers: Vec<(&LayerName, &Layer)> = ["aacute", "agrave"]
+            .iter()
+            .flat_map(|glyph_name| &loaded.glyphs[*glyph_name].layers)
+            .collect();
+        assert_eq!(layers.len(), 4);
+        for (layer_name, layer) in &layers {
+            assert!(Arc::ptr_eq(&layer.components[0].name, a));
+            assert!(Arc::ptr_eq(&layer_name.source, &layers[0].0.source));
+        }
+    }
+
+    #[test]
+    fn roundtrip_compact_json() {
+        let point = |x, y, typ| ContourPoint {
+            x,
+            y,
+            typ,
+            smooth: false,
+        };
+        let layer = Layer {
+            anchors: vec![Anchor {
+                name: "top".into(),
+                x: 250.0,
+                y: 700.0,
+                extra: ExtraFields::new(),
+            }],
+            components: vec![Component {
+                name: "acutecomb".into(),
+                transformation: AffineTransformation {
+                    x_offset: 100.0,
+                    ..Default::default()
+                },
+                location: None,
+                extra: ExtraFields::new(),
+            }],
+            contours: vec![Contour {
+                points: vec![
+                    point(0.0, 0.0, PointType::Line),
+                    point(0.0, 500.0, PointType::OffCurve),
+                    point(500.0, 500.0, PointType::QCurve),
+                ],
+                extra: ExtraFields::new(),
+            }],
+            x_advance: Some(500.0),
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        fontgarden.glyphs.insert(
+            "a".into(),
+            Glyph {
+                layers: HashMap::from([(LayerName::main("Regular"), layer)]),
+                ..Default::default()
+            },
+        );
+        fontgarden.config.set_json_style(JsonStyle::Compact);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+
+        let json = std::fs::read_to_string(
+            path.join(Fontgarden::layer_path("a", &LayerName::main("Regular"))),
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"anchors":[{"name":"top","x":250.0,"y":700.0}],"#,
+                r#""components":[{"name":"acutecomb","transformation":{"x_offset":100.0}}],"#,
+                r#""contours":[{"points":[{"x":0.0,"y":0.0,"typ":"Line"},{"x":0.0,"y":500.0},"#,
+                r#"{"x":500.0,"y":500.0,"typ":"QCurve"}]}],"x_advance":500.0}"#,
+            )
+        );
+        let loaded = Fontgarden::load(&path).unwrap();
+        assert_eq!(loaded.config.json_style, JsonStyle::Compact);
+        assert_eq!(loaded.glyphs, fontgarden.glyphs);
+    }
+}
@@ -0,0 +1,298 @@
+//! Keeping sets that two fontgardens share identical, like a Currency set used
+//! by both a Sans and a Serif project, and keeping anchors in step with UFO
+//! sources that are edited elsewhere.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use crate::{
+    errors::{SourceLoadError, SyncError},
+    structs::{Fontgarden, Glyph},
+    ufo::{ImportChannel, ImportOptions, SourceNaming},
+};
+
+/// Which way to apply the differences between two fontgardens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SyncDirection {
+    /// Make the sets here like those of the other fontgarden.
+    Pull,
+    /// Make the sets of the other fontgarden like those here.
+    Push,
+}
+
+/// How the glyphs of shared sets differ between two fontgardens, with glyph
+/// names sorted.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    pub only_here: Vec<String>,
+    pub only_there: Vec<String>,
+    pub differing: Vec<String>,
+}
+
+impl SyncReport {
+    pub fn is_empty(&self) -> bool {
+        self.only_here.is_empty() && self.only_there.is_empty() && self.differing.is_empty()
+    }
+}
+
+impl Fontgarden {
+    /// Compare the glyphs in the given sets, with the sets nested in them, with
+    /// those of another fontgarden. Only the layers of sources both have are
+    /// compared, and tags, which are for each project's own workflow, not at
+    /// all.
+    pub fn compare_sets(
+        &self,
+        other: &Fontgarden,
+        set_names: &[String],
+    ) -> Result<SyncReport, SyncError> {
+        if let Some(set_name) = set_names.iter().find(|set_name| {
+            set_name.as_str() != Self::COMMON_SET_NAME
+                && ![self, other]
+                    .iter()
+                    .any(|fontgarden| fontgarden.glyphs.values().any(|g| g.is_in_set(set_name)))
+        }) {
+            return Err(SyncError::UnknownSet(set_name.clone()));
+        }
+
+        let shared_sources: BTreeSet<String> = self
+            .source_names()
+            .intersection(&other.source_names())
+            .cloned()
+            .collect();
+        let in_sets = |glyph: &Glyph| set_names.iter().any(|name| glyph.is_in_set(name));
+        let mut report = SyncReport::default();
+        for (glyph_name, glyph) in self.glyphs.iter().filter(|(_, glyph)| in_sets(glyph)) {
+            match other.glyphs.get(glyph_name).filter(|glyph| in_sets(glyph)) {
+                None => report.only_here.push(glyph_name.to_string()),
+                Some(other_glyph) => {
+                    if comparable(glyph, &shared_sources)
+                        != comparable(other_glyph, &shared_sources)
+                    {
+                        report.differing.push(glyph_name.to_string());
+                    }
+                }
+            }
+        }
+        report.only_there = other
+            .glyphs
+            .iter()
+            .filter(|(glyph_name, glyph)| {
+                in_sets(glyph) && !self.glyphs.get(*glyph_name).is_some_and(&in_sets)
+            })
+            .map(|(glyph_name, _)| glyph_name.to_string())
+            .collect();
+        report.only_here.sort();
+        report.only_there.sort();
+        report.differing.sort();
+        Ok(report)
+    }
+
+    /// Make the given sets like those of another fontgarden: take the glyphs
+    /// that differ or are missing here and remove those the other lacks,
+    /// returning what differed. Layers of sources the other fontgarden doesn't
+    /// have and tags are kept, and so is whether a set is locked.
+    pub fn take_sets(
+        &mut self,
+        other: &Fontgarden,
+        set_names: &[String],
+    ) -> Result<SyncReport, SyncError> {
+        let report = self.compare_sets(other, set_names)?;
+        let shared_sources: BTreeSet<String> = self
+            .source_names()
+            .intersection(&other.source_names())
+            .cloned()
+            .collect();
+        for glyph_name in &report.only_here {
+            self.glyphs.remove(glyph_name.as_str());
+        }
+        for glyph_name in report.only_there.iter().chain(&report.differing) {
+            let mut glyph = comparable(&other.glyphs[glyph_name.as_str()], &shared_sources);
+            if let Some(existing) = self.glyphs.remove(glyph_name.as_str()) {
+                glyph.tags = existing.tags;
+                glyph.layers.extend(
+                    existing
+                        .layers
+                        .into_iter()
+                        .filter(|(layer_name, _)| !shared_sources.contains(&*layer_name.source)),
+                );
+            }
+            self.glyphs.insert(glyph_name.as_str().into(), glyph);
+        }
+        for set_name in set_names {
+            if let Some(metadata) = other.set_metadata.get(set_name) {
+                let mut metadata = metadata.clone();
+                metadata.locked = self
+                    .set_metadata
+                    .get(set_name)
+                    .is_some_and(|metadata| metadata.locked);
+                self.set_metadata.insert(set_name.clone(), metadata);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Take the anchors of the glyph layers here from UFO sources, leaving
+    /// everything else alone, and return how many layers' anchors changed.
+    /// Glyphs and layers that are only in the sources are left out.
+    pub fn sync_anchors(
+        &mut self,
+        sources: &[PathBuf],
+        source_naming: SourceNaming,
+        source_names: HashMap<String, String>,
+    ) -> Result<usize, SourceLoadError> {
+        let options = ImportOptions {
+            source_naming,
+            source_names,
+            only: HashSet::from([ImportChannel::Anchors]),
+            ..Default::default()
+        };
+        let glyphs_before = self.glyphs.clone();
+        self.import_ufo_sources(sources, &options)?;
+        Ok(self
+            .glyphs
+            .iter()
+            .map(|(glyph_name, glyph)| {
+                let before = &glyphs_before[glyph_name];
+                glyph
+                    .layers
+                    .iter()
+                    .filter(|(layer_name, layer)| {
+                        before.layers.get(layer_name).map(|b| &b.anchors) != Some(&layer.anchors)
+                    })
+                    .count()
+            })
+            .sum())
+    }
+}
+
+/// A glyph with only the layers of the given sources and no tags.
+fn comparable(glyph: &Glyph, source_names: &BTreeSet<String>) -> Glyph {
+    let mut glyph = glyph.clone();
+    glyph
+        .layers
+        .retain(|layer_name, _| source_names.contains(&*layer_name.source));
+    glyph.tags.clear();
+    glyph
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        structs::{Anchor, Layer, LayerName},
+        testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn sync_shared_sets() {
+        let glyph = |set: &str, sources: &[(&str, f64)]| Glyph {
+            set: Some(set.into()),
+            layers: sources
+                .iter()
+                .map(|(source_name, x_advance)| {
+                    let layer = Layer {
+                        x_advance: Some(*x_advance),
+                        ..Default::default()
+                    };
+                    ((*source_name).into(), layer)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut sans = Fontgarden::new();
+        sans.glyphs.insert(
+            "euro".into(),
+            glyph("Currency", &[("Regular", 500.), ("Italic", 480.)]),
+        );
+        sans.glyphs
+            .insert("sterling".into(), glyph("Currency", &[("Regular", 500.)]));
+        sans.glyphs
+            .insert("a".into(), glyph("Latin", &[("Regular", 500.)]));
+        sans.glyphs
+            .get_mut("euro")
+            .unwrap()
+            .tags
+            .insert("review".into());
+        let mut serif = Fontgarden::new();
+        serif.glyphs.insert(
+            "euro".into(),
+            glyph("Currency", &[("Regular", 520.), ("Bold", 560.)]),
+        );
+        serif
+            .glyphs
+            .insert("yen".into(), glyph("Currency/Asian", &[("Regular", 500.)]));
+        serif
+            .glyphs
+            .insert("a".into(), glyph("Latin", &[("Regular", 600.)]));
+
+        let sets = ["Currency".to_string()];
+        let report = sans.compare_sets(&serif, &sets).unwrap();
+        assert_eq!(
+            report,
+            SyncReport {
+                only_here: vec!["sterling".into()],
+                only_there: vec!["yen".into()],
+                differing: vec!["euro".into()],
+            }
+        );
+        assert!(matches!(
+            sans.compare_sets(&serif, &["Greek".into()]),
+            Err(SyncError::UnknownSet(_))
+        ));
+
+        sans.take_sets(&serif, &sets).unwrap();
+        assert!(sans.compare_sets(&serif, &sets).unwrap().is_empty());
+        let euro = &sans.glyphs["euro"];
+        assert_eq!(euro.layers[&"Regular".into()].x_advance, Some(520.));
+        assert_eq!(euro.layers[&"Italic".into()].x_advance, Some(480.));
+        assert!(!euro.layers.contains_key(&"Bold".into()));
+        assert!(euro.tags.contains("review"));
+        assert!(!sans.glyphs.contains_key("sterling"));
+        assert_eq!(
+            sans.glyphs["a"].layers[&"Regular".into()].x_advance,
+            Some(500.)
+        );
+    }
+
+    #[test]
+    fn sync_only_anchors() {
+        let layer = || {
+            LayerBuilder::new()
+                .width(999.)
+                .rect(0., 0., 100., 100.)
+                .component("A", 10., 0.)
+        };
+        let mut fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "E",
+                GlyphBuilder::new().layer("LightCondensed", layer().anchor("top", 0., 0.)),
+            )
+            .glyph("F", GlyphBuilder::new().layer("LightCondensed", layer()))
+            .build();
+        let mut expected = fontgarden.clone();
+
+        let changed = fontgarden
+            .sync_anchors(
+                &["testdata/mutatorSans/MutatorSansLightCondensed.ufo".into()],
+                SourceNaming::default(),
+                HashMap::new(),
+            )
+            .unwrap();
+
+        assert_eq!(changed, 1);
+        let e = expected.glyphs.get_mut("E").unwrap();
+        e.layers
+            .get_mut(&LayerName::main("LightCondensed"))
+            .unwrap()
+            .anchors = vec![Anchor {
+            name: "top".into(),
+            x: 207.,
+            y: 766.,
+            extra: Default::default(),
+        }];
+        assert_eq!(fontgarden.glyphs, expected.glyphs);
+    }
+}
@@ -0,0 +1,141 @@
+//! Freeform tags on glyphs, like `needs-kerning` or `review`, kept in the
+//! `tags` column of the set files.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{errors::TagError, structs::Fontgarden};
+
+/// What separates tags in the `tags` column.
+pub const TAG_SEPARATOR: char = ';';
+
+impl Fontgarden {
+    /// Tag glyphs, returning the names of those that didn't have the tag yet.
+    pub fn tag_glyphs(
+        &mut self,
+        glyph_names: &[String],
+        tag: &str,
+    ) -> Result<Vec<String>, TagError> {
+        check_tag(tag)?;
+        self.check_glyphs(glyph_names)?;
+        Ok(glyph_names
+            .iter()
+            .filter(|glyph_name| {
+                let glyph = self.glyphs.get_mut(glyph_name.as_str()).unwrap();
+                glyph.tags.insert(tag.to_string())
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Untag glyphs, returning the names of those that had the tag.
+    pub fn untag_glyphs(
+        &mut self,
+        glyph_names: &[String],
+        tag: &str,
+    ) -> Result<Vec<String>, TagError> {
+        self.check_glyphs(glyph_names)?;
+        Ok(glyph_names
+            .iter()
+            .filter(|glyph_name| {
+                let glyph = self.glyphs.get_mut(glyph_name.as_str()).unwrap();
+                glyph.tags.remove(tag)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Every tag in use, with the names of the glyphs that have it.
+    pub fn tags(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut tags: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (glyph_name, glyph) in &self.glyphs {
+            for tag in &glyph.tags {
+                tags.entry(tag).or_default().insert(glyph_name);
+            }
+        }
+        tags
+    }
+
+    fn check_glyphs(&self, glyph_names: &[String]) -> Result<(), TagError> {
+        match glyph_names
+            .iter()
+            .find(|glyph_name| !self.glyphs.contains_key(glyph_name.as_str()))
+        {
+            Some(glyph_name) => Err(TagError::UnknownGlyph(glyph_name.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Tags can't be empty or contain the separator or whitespace, so that the
+/// `tags` column stays readable.
+fn check_tag(tag: &str) -> Result<(), TagError> {
+    if tag.is_empty() || tag.contains(|c: char| c == TAG_SEPARATOR || c.is_whitespace()) {
+        return Err(TagError::InvalidTag(tag.into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Glyph, Layer};
+
+    use super::*;
+
+    #[test]
+    fn tag_and_untag_glyphs() {
+        let mut fontgarden = Fontgarden::new();
+        for glyph_name in ["a", "b"] {
+            fontgarden.glyphs.insert(
+                glyph_name.into(),
+                Glyph {
+                    layers: [(
+                        "Regular".into(),
+                        Layer {
+                            x_advance: Some(500.),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            );
+        }
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            fontgarden.tag_glyphs(&names(&["a"]), "review").unwrap(),
+            ["a"]
+        );
+        assert_eq!(
+            fontgarden
+                .tag_glyphs(&names(&["a", "b"]), "review")
+                .unwrap(),
+            ["b"]
+        );
+        fontgarden.tag_glyphs(&names(&["a"]), "v2").unwrap();
+        assert!(matches!(
+            fontgarden.tag_glyphs(&names(&["a"]), "needs kerning"),
+            Err(TagError::InvalidTag(_))
+        ));
+        assert!(matches!(
+            fontgarden.tag_glyphs(&names(&["c"]), "v2"),
+            Err(TagError::UnknownGlyph(_))
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.fontgarden");
+        fontgarden.save(&path).unwrap();
+        let csv = std::fs::read_to_string(path.join("set.C_ommon.csv")).unwrap();
+        assert!(csv.contains("review;v2"));
+        let mut fontgarden = Fontgarden::load(&path).unwrap();
+
+        assert_eq!(
+            fontgarden.untag_glyphs(&names(&["a", "b"]), "v2").unwrap(),
+            ["a"]
+        );
+        assert_eq!(
+            fontgarden.tags(),
+            BTreeMap::from([("review", BTreeSet::from(["a", "b"]))])
+        );
+    }
+}
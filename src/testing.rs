@@ -0,0 +1,239 @@
+//! Builders for fontgardens, glyphs and layers, and a way to save and load a
+//! fontgarden in memory, for constructing test fixtures without tempdirs and
+//! UFOs. Compiled for this crate's tests and with the `testing` feature.
+
+use std::path::Path;
+
+use crate::{
+    config::Config,
+    errors::{LoadError, SaveError},
+    storage::{MemoryStorage, Storage},
+    structs::{
+        AffineTransformation, Anchor, Component, Contour, ContourPoint, Fontgarden, Glyph, Layer,
+        LayerName, OpenTypeCategory, PointType,
+    },
+};
+
+/// Save a fontgarden to memory and load it back, to see what a real save and
+/// load would keep.
+pub fn roundtrip(fontgarden: &Fontgarden) -> Result<Fontgarden, RoundtripError> {
+    let storage = MemoryStorage::new();
+    fontgarden.save_to(&storage)?;
+    Ok(Fontgarden::load_from(&storage)?)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoundtripError {
+    #[error(transparent)]
+    Save(#[from] SaveError),
+    #[error(transparent)]
+    Load(#[from] Box<LoadError>),
+}
+
+impl From<LoadError> for RoundtripError {
+    fn from(error: LoadError) -> Self {
+        RoundtripError::Load(Box::new(error))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FontgardenBuilder {
+    fontgarden: Fontgarden,
+}
+
+impl FontgardenBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the configuration of a `fontgarden.toml` with this text.
+    ///
+    /// Panics if the text isn't a valid configuration.
+    pub fn config(mut self, toml: &str) -> Self {
+        let storage = MemoryStorage::new();
+        storage
+            .write(Path::new(Config::FILENAME), toml.as_bytes())
+            .expect("writing to memory doesn't fail");
+        self.fontgarden.config = Config::load(&storage).expect("the configuration is valid");
+        self
+    }
+
+    pub fn glyph(mut self, name: &str, glyph: GlyphBuilder) -> Self {
+        self.fontgarden.glyphs.insert(name.into(), glyph.build());
+        self
+    }
+
+    pub fn build(self) -> Fontgarden {
+        self.fontgarden
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlyphBuilder {
+    glyph: Glyph,
+}
+
+impl GlyphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn codepoints(mut self, codepoints: impl IntoIterator<Item = char>) -> Self {
+        self.glyph.codepoints = norad::Codepoints::new(codepoints);
+        self
+    }
+
+    pub fn set(mut self, set_name: &str) -> Self {
+        self.glyph.set = Some(set_name.into());
+        self
+    }
+
+    pub fn category(mut self, category: OpenTypeCategory) -> Self {
+        self.glyph.opentype_category = category;
+        self
+    }
+
+    pub fn postscript_name(mut self, postscript_name: &str) -> Self {
+        self.glyph.postscript_name = Some(postscript_name.into());
+        self
+    }
+
+    pub fn advance_link(mut self, glyph_name: &str) -> Self {
+        self.glyph.advance_link = Some(glyph_name.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.glyph.tags.insert(tag.into());
+        self
+    }
+
+    /// Add a layer, named like a layer file without its extension: `Bold` for
+    /// the main layer of the Bold source, `Bold.background` for a sublayer.
+    pub fn layer(mut self, layer_name: &str, layer: LayerBuilder) -> Self {
+        self.glyph
+            .layers
+            .insert(LayerName::from(layer_name), layer.build());
+        self
+    }
+
+    pub fn build(self) -> Glyph {
+        self.glyph
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LayerBuilder {
+    layer: Layer,
+}
+
+impl LayerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn width(mut self, x_advance: f64) -> Self {
+        self.layer.x_advance = Some(x_advance);
+        self
+    }
+
+    pub fn anchor(mut self, name: &str, x: f64, y: f64) -> Self {
+        self.layer.anchors.push(Anchor {
+            name: name.into(),
+            x,
+            y,
+            extra: Default::default(),
+        });
+        self
+    }
+
+    /// Add a closed contour of straight lines through the points.
+    pub fn polygon(mut self, points: &[(f64, f64)]) -> Self {
+        self.layer.contours.push(Contour {
+            points: points
+                .iter()
+                .map(|&(x, y)| ContourPoint {
+                    x,
+                    y,
+                    typ: PointType::Line,
+                    smooth: false,
+                })
+                .collect(),
+            extra: Default::default(),
+        });
+        self
+    }
+
+    /// Add a rectangle from its lower left to its upper right corner.
+    pub fn rect(self, x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Self {
+        self.polygon(&[
+            (x_min, y_min),
+            (x_min, y_max),
+            (x_max, y_max),
+            (x_max, y_min),
+        ])
+    }
+
+    pub fn component(mut self, base_name: &str, x_offset: f64, y_offset: f64) -> Self {
+        self.layer.components.push(Component {
+            name: base_name.into(),
+            transformation: AffineTransformation {
+                x_offset,
+                y_offset,
+                ..Default::default()
+            },
+            location: None,
+            extra: Default::default(),
+        });
+        self
+    }
+
+    pub fn build(self) -> Layer {
+        self.layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_and_roundtrip_fixtures() {
+        let fontgarden = FontgardenBuilder::new()
+            .config("[axes.wght]\nminimum = 100\ndefault = 400\nmaximum = 900\n")
+            .glyph(
+                "A",
+                GlyphBuilder::new()
+                    .codepoints(['A'])
+                    .category(OpenTypeCategory::Base)
+                    .layer(
+                        "Regular",
+                        LayerBuilder::new()
+                            .width(600.)
+                            .polygon(&[(0., 0.), (300., 700.), (600., 0.)])
+                            .anchor("top", 300., 700.),
+                    )
+                    .layer("Regular.background", LayerBuilder::new().width(600.)),
+            )
+            .glyph(
+                "Aacute",
+                GlyphBuilder::new()
+                    .set("Latin")
+                    .advance_link("A")
+                    .tag("review")
+                    .layer("Regular", LayerBuilder::new().component("A", 0., 0.)),
+            )
+            .build();
+
+        assert!(fontgarden.config.axes.contains_key("wght"));
+        let glyph = &fontgarden.glyphs["A"];
+        assert_eq!(glyph.layers.len(), 2);
+        assert!(glyph
+            .layers
+            .contains_key(&LayerName::sublayer("Regular", "background")));
+
+        let loaded = roundtrip(&fontgarden).unwrap();
+        assert_eq!(loaded.glyphs, fontgarden.glyphs);
+        assert_eq!(loaded.config.axes, fontgarden.config.axes);
+    }
+}
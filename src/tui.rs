@@ -0,0 +1,534 @@
+//! An interactive terminal browser over the sets, glyphs and layers of a
+//! fontgarden, for a quick look over SSH where no editor is at hand. Layers
+//! are previewed as outlines drawn with block characters or as SVG source,
+//! and can be written out as SVG files.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use kurbo::{BezPath, Point, Shape};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    filenames::name_to_filename,
+    serve::GlyphInfo,
+    structs::{Fontgarden, LayerName},
+};
+
+/// Browse `fontgarden` until the user quits. SVG files are written into
+/// `output_dir`.
+pub fn browse(fontgarden: &Fontgarden, output_dir: &Path) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = Browser::new(fontgarden, output_dir).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Sets,
+    Glyphs,
+    Layers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Preview {
+    Outline,
+    Svg,
+}
+
+pub struct Browser<'a> {
+    fontgarden: &'a Fontgarden,
+    output_dir: PathBuf,
+    /// Set names, after an entry for all glyphs.
+    sets: Vec<&'a str>,
+    set_state: ListState,
+    /// The glyphs of the selected set that match the search, sorted.
+    glyphs: Vec<&'a str>,
+    glyph_state: ListState,
+    layers: Vec<LayerName>,
+    layer_state: ListState,
+    focus: Pane,
+    search: String,
+    searching: bool,
+    preview: Preview,
+    status: String,
+}
+
+const ALL_GLYPHS: &str = "All glyphs";
+
+impl<'a> Browser<'a> {
+    pub fn new(fontgarden: &'a Fontgarden, output_dir: &Path) -> Self {
+        let mut set_names: BTreeSet<&str> = fontgarden
+            .glyphs
+            .values()
+            .map(|glyph| glyph.set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME))
+            .collect();
+        set_names.extend(fontgarden.set_metadata.keys().map(String::as_str));
+        let mut browser = Self {
+            fontgarden,
+            output_dir: output_dir.into(),
+            sets: std::iter::once(ALL_GLYPHS).chain(set_names).collect(),
+            set_state: ListState::default().with_selected(Some(0)),
+            glyphs: Vec::new(),
+            glyph_state: ListState::default(),
+            layers: Vec::new(),
+            layer_state: ListState::default(),
+            focus: Pane::Glyphs,
+            search: String::new(),
+            searching: false,
+            preview: Preview::Outline,
+            status: String::new(),
+        };
+        browser.update_glyphs();
+        browser
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// React to a key press. Returns false when the user quits.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.searching {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.update_glyphs();
+                }
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.update_glyphs();
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.search.clear();
+                self.update_glyphs();
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = match self.focus {
+                    Pane::Sets | Pane::Glyphs => Pane::Sets,
+                    Pane::Layers => Pane::Glyphs,
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = match self.focus {
+                    Pane::Sets => Pane::Glyphs,
+                    Pane::Glyphs | Pane::Layers => Pane::Layers,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::Char('t') => {
+                self.preview = match self.preview {
+                    Preview::Outline => Preview::Svg,
+                    Preview::Svg => Preview::Outline,
+                }
+            }
+            KeyCode::Char('w') => {
+                self.status = match self.write_svg() {
+                    Ok(Some(path)) => format!("wrote {}", path.display()),
+                    Ok(None) => "nothing to write".into(),
+                    Err(e) => format!("failed to write the SVG file: {e}"),
+                };
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let (state, len) = match self.focus {
+            Pane::Sets => (&mut self.set_state, self.sets.len()),
+            Pane::Glyphs => (&mut self.glyph_state, self.glyphs.len()),
+            Pane::Layers => (&mut self.layer_state, self.layers.len()),
+        };
+        let Some(selected) = state.selected() else {
+            return;
+        };
+        let selected = selected
+            .saturating_add_signed(by)
+            .min(len.saturating_sub(1));
+        state.select(Some(selected));
+        match self.focus {
+            Pane::Sets => self.update_glyphs(),
+            Pane::Glyphs => self.update_layers(),
+            Pane::Layers => {}
+        }
+    }
+
+    /// Filter the glyphs by the selected set and the search, which matches
+    /// names containing it, ignoring case, or the glyph of a single character.
+    fn update_glyphs(&mut self) {
+        let set_name = self
+            .set_state
+            .selected()
+            .filter(|&index| index > 0)
+            .map(|index| self.sets[index]);
+        let search = self.search.to_lowercase();
+        let mut search_chars = self.search.chars();
+        let search_char = match (search_chars.next(), search_chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        };
+        self.glyphs = self
+            .fontgarden
+            .glyphs
+            .iter()
+            .filter(|(_, glyph)| {
+                set_name.is_none_or(|set_name| {
+                    glyph.set.as_deref().unwrap_or(Fontgarden::COMMON_SET_NAME) == set_name
+                })
+            })
+            .filter(|(name, glyph)| {
+                name.to_lowercase().contains(&search)
+                    || search_char.is_some_and(|c| glyph.codepoints.contains(c))
+            })
+            .map(|(name, _)| &**name)
+            .collect();
+        self.glyphs.sort_unstable();
+        self.glyph_state
+            .select((!self.glyphs.is_empty()).then_some(0));
+        self.update_layers();
+    }
+
+    /// List the layers of the selected glyph, keeping the selected layer
+    /// name if the glyph has it too.
+    fn update_layers(&mut self) {
+        let previous = self.selected_layer().cloned();
+        self.layers = match self.selected_glyph() {
+            Some(glyph_name) => self.fontgarden.glyphs[glyph_name]
+                .layers
+                .keys()
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        self.layers.sort();
+        let index = previous
+            .and_then(|previous| self.layers.iter().position(|layer| *layer == previous))
+            .or((!self.layers.is_empty()).then_some(0));
+        self.layer_state.select(index);
+    }
+
+    fn selected_glyph(&self) -> Option<&'a str> {
+        self.glyph_state.selected().map(|index| self.glyphs[index])
+    }
+
+    fn selected_layer(&self) -> Option<&LayerName> {
+        self.layer_state.selected().map(|index| &self.layers[index])
+    }
+
+    /// Write the selected layer as an SVG file named after the glyph and
+    /// layer.
+    fn write_svg(&self) -> io::Result<Option<PathBuf>> {
+        let (Some(glyph_name), Some(layer_name)) = (self.selected_glyph(), self.selected_layer())
+        else {
+            return Ok(None);
+        };
+        let Some(svg) = self.fontgarden.render_svg(glyph_name, layer_name) else {
+            return Ok(None);
+        };
+        let path = self.output_dir.join(format!(
+            "{}.{}.svg",
+            name_to_filename(glyph_name),
+            name_to_filename(&layer_name.to_string())
+        ));
+        std::fs::write(&path, svg)?;
+        Ok(Some(path))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [sets, glyphs, details] = Layout::horizontal([
+            Constraint::Length(24),
+            Constraint::Length(30),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+
+        let title = format!("Glyphs ({})", self.glyphs.len());
+        self.draw_list(frame, sets, Pane::Sets, "Sets".into());
+        self.draw_list(frame, glyphs, Pane::Glyphs, title);
+
+        let info = self.glyph_info();
+        let layers_height = (self.layers.len() as u16 + 2).min(10);
+        let [metadata, layers, preview] = Layout::vertical([
+            Constraint::Length(info.len() as u16 + 2),
+            Constraint::Length(layers_height),
+            Constraint::Min(0),
+        ])
+        .areas(details);
+        frame.render_widget(
+            Paragraph::new(info).block(Block::bordered().title("Glyph")),
+            metadata,
+        );
+        self.draw_list(frame, layers, Pane::Layers, "Layers".into());
+        self.draw_preview(frame, preview);
+
+        let status_line = if self.searching {
+            format!("search: {}", self.search)
+        } else if !self.status.is_empty() {
+            self.status.clone()
+        } else {
+            "←/→ pane  ↑/↓ select  / search  t outline/SVG  w write SVG  q quit".into()
+        };
+        frame.render_widget(Paragraph::new(status_line).reversed(), status);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect, pane: Pane, title: String) {
+        let (items, state): (Vec<String>, _) = match pane {
+            Pane::Sets => (
+                self.sets.iter().map(|set| set.to_string()).collect(),
+                &mut self.set_state,
+            ),
+            Pane::Glyphs => (
+                self.glyphs.iter().map(|glyph| glyph.to_string()).collect(),
+                &mut self.glyph_state,
+            ),
+            Pane::Layers => (
+                self.layers.iter().map(|layer| layer.to_string()).collect(),
+                &mut self.layer_state,
+            ),
+        };
+        let mut block = Block::bordered().title(title);
+        if self.focus == pane {
+            block = block.border_style(Style::new().bold());
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, area, state);
+    }
+
+    fn glyph_info(&self) -> Vec<Line<'static>> {
+        let Some(glyph_name) = self.selected_glyph() else {
+            return vec![Line::from("no glyphs")];
+        };
+        let info = GlyphInfo::new(&self.fontgarden.glyphs[glyph_name]);
+        let mut lines = vec![
+            Line::from(glyph_name.to_string()).bold(),
+            Line::from(format!("codepoints: {}", info.codepoints)),
+            Line::from(format!("set: {}", info.set)),
+            Line::from(format!("opentype category: {}", info.opentype_category)),
+        ];
+        if let Some(postscript_name) = info.postscript_name {
+            lines.push(Line::from(format!("postscript name: {postscript_name}")));
+        }
+        if let Some(advance_link) = info.advance_link {
+            lines.push(Line::from(format!("advance from: {advance_link}")));
+        }
+        if !info.tags.is_empty() {
+            lines.push(Line::from(format!("tags: {}", info.tags.join(", "))));
+        }
+        lines
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(match self.preview {
+            Preview::Outline => "Outline",
+            Preview::Svg => "SVG",
+        });
+        let inner = block.inner(area);
+        let text = match (self.selected_glyph(), self.selected_layer()) {
+            (Some(glyph_name), Some(layer_name)) => match self.preview {
+                Preview::Outline => self
+                    .fontgarden
+                    .resolved_bez_path(glyph_name, layer_name)
+                    .map(|path| {
+                        let advance = self.fontgarden.glyphs[glyph_name].layers[layer_name]
+                            .x_advance
+                            .unwrap_or_default();
+                        draw_outline(&path, advance, inner.width, inner.height).join("\n")
+                    }),
+                Preview::Svg => self.fontgarden.render_svg(glyph_name, layer_name),
+            },
+            _ => None,
+        };
+        frame.render_widget(
+            Paragraph::new(text.unwrap_or_default())
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+}
+
+/// Draw an outline filled with block characters, scaled to fit `columns` and
+/// `rows` cells, which are about twice as tall as they are wide. The drawing
+/// spans the advance width and the outline's vertical bounds.
+pub fn draw_outline(path: &BezPath, advance: f64, columns: u16, rows: u16) -> Vec<String> {
+    let bounds = path.bounding_box();
+    if path.elements().is_empty() || columns == 0 || rows == 0 {
+        return Vec::new();
+    }
+    let (x_min, x_max) = (bounds.x0.min(0.), bounds.x1.max(advance));
+    let (y_min, y_max) = (bounds.y0, bounds.y1);
+    let cell_width = ((x_max - x_min) / f64::from(columns))
+        .max((y_max - y_min) / f64::from(rows) / 2.)
+        .max(f64::EPSILON);
+    let cell_height = cell_width * 2.;
+    let used_columns = ((x_max - x_min) / cell_width).ceil() as usize;
+    let used_rows = ((y_max - y_min) / cell_height).ceil() as usize;
+    (0..used_rows.max(1))
+        .map(|row| {
+            let y = y_max - (row as f64 + 0.5) * cell_height;
+            (0..used_columns.max(1))
+                .map(|column| {
+                    let x = x_min + (column as f64 + 0.5) * cell_width;
+                    if path.winding(Point::new(x, y)) != 0 {
+                        '█'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, crossterm::event::KeyModifiers, Terminal};
+
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    fn fontgarden() -> Fontgarden {
+        FontgardenBuilder::new()
+            .glyph(
+                "A",
+                GlyphBuilder::new().codepoints(['A']).layer(
+                    "Regular",
+                    LayerBuilder::new()
+                        .width(600.)
+                        .polygon(&[(0., 0.), (300., 700.), (600., 0.)]),
+                ),
+            )
+            .glyph(
+                "alpha",
+                GlyphBuilder::new()
+                    .codepoints(['α'])
+                    .set("Greek")
+                    .layer("Regular", LayerBuilder::new().width(500.))
+                    .layer("Regular.background", LayerBuilder::new().width(500.)),
+            )
+            .glyph(
+                "Aacute",
+                GlyphBuilder::new().layer(
+                    "Regular",
+                    LayerBuilder::new().width(600.).component("A", 0., 0.),
+                ),
+            )
+            .build()
+    }
+
+    fn press(browser: &mut Browser, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '←' => KeyCode::Left,
+                '→' => KeyCode::Right,
+                '↑' => KeyCode::Up,
+                '↓' => KeyCode::Down,
+                '⏎' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            assert!(browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE)));
+        }
+    }
+
+    #[test]
+    fn browse_sets_glyphs_and_layers() {
+        let fontgarden = fontgarden();
+        let dir = tempfile::tempdir().unwrap();
+        let mut browser = Browser::new(&fontgarden, dir.path());
+        assert_eq!(browser.sets, [ALL_GLYPHS, "Common", "Greek"]);
+        assert_eq!(browser.glyphs, ["A", "Aacute", "alpha"]);
+
+        press(&mut browser, "←↓↓→");
+        assert_eq!(browser.glyphs, ["alpha"]);
+        assert_eq!(
+            browser.layers,
+            [
+                LayerName::main("Regular"),
+                LayerName::sublayer("Regular", "background")
+            ]
+        );
+        press(&mut browser, "→↓");
+        assert_eq!(browser.selected_layer(), Some(&"Regular.background".into()));
+
+        // Searching by name or character, within all glyphs.
+        press(&mut browser, "←←↑↑/aa⏎");
+        assert_eq!(browser.glyphs, ["Aacute"]);
+        press(&mut browser, "/α⏎");
+        assert_eq!(browser.glyphs, ["alpha"]);
+
+        press(&mut browser, "/A⏎w");
+        assert!(dir.path().join("A_.R_egular.svg").is_file());
+        assert!(!browser.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn draw_the_browser() {
+        let fontgarden = fontgarden();
+        let mut browser = Browser::new(&fontgarden, Path::new("."));
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| browser.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Greek"));
+        assert!(screen.contains("codepoints: 0041"));
+        assert!(screen.contains('█'));
+    }
+
+    #[test]
+    fn draw_outlines() {
+        let fontgarden = fontgarden();
+        let path = fontgarden
+            .resolved_bez_path("Aacute", &LayerName::main("Regular"))
+            .unwrap();
+        let lines = draw_outline(&path, 600., 12, 7);
+        assert_eq!(lines.len(), 7);
+        assert!(lines.iter().all(|line| line.chars().count() <= 12));
+        // The triangle is wider at the bottom than at the top.
+        let filled = |line: &String| line.chars().filter(|c| *c == '█').count();
+        assert!(filled(&lines[0]) < filled(&lines[6]));
+    }
+}
@@ -1,57 +1,351 @@
 use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use glyphsinfo_rs::GlyphData;
 use norad::Codepoints;
 
 use crate::{
+    alternates::conditions_hold,
+    categorize::{opentype_category, set_for_glyph},
     errors::{SourceLoadError, SourceSaveError},
-    structs::{Fontgarden, Layer, OpenTypeCategory},
+    filters::{FilterStep, FlattenComponents, UFO2FT_FILTERS_LIB_KEY},
+    hinting::{postscript_hints_to_lib, SourceHinting, TRUETYPE_LIB_KEY},
+    interpolate::Instance,
+    lib_keys::{insert_lib_keys, SourceLib},
+    name_template::NameTemplate,
+    precision::round_ufo,
+    rename_map::RenameMap,
+    selection::Selection,
+    stamp::BuildStamp,
+    structs::{
+        codepoints_to_string, Component, Fontgarden, Glyph, Layer, LayerName, OpenTypeCategory,
+    },
+    variable_components::{variable_components_to_lib, VARIABLE_COMPONENTS_LIB_KEY},
 };
 
+/// Knobs for importing UFO sources into a fontgarden.
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    pub codepoint_policy: CodepointPolicy,
+    /// Derive OpenType categories from the glyph data for imported glyphs that
+    /// neither the garden nor the source assign one to.
+    pub assign_opentype_categories: bool,
+    /// Only import glyphs belonging to these sets [default: all]. Glyphs already in
+    /// the fontgarden keep their set, new ones are sorted by guessing.
+    pub sets: HashSet<String>,
+    /// Also import glyphs outside of `sets` that selected glyphs use as components.
+    pub follow_components: bool,
+    pub delete_policy: DeletePolicy,
+    pub source_naming: SourceNaming,
+    /// Source names to use instead of deriving them, by the source's path as
+    /// given or its file name.
+    pub source_names: HashMap<String, String>,
+    pub collision_policy: CollisionPolicy,
+    /// The source whose codepoints and font-wide glyph data win, recorded in the
+    /// configuration for later imports [default: see `default_source_name`]
+    pub default_source: Option<String>,
+    /// Scale sources with other units per em to these.
+    pub scale_to_upm: Option<f64>,
+    /// Only import these layers, by the names they get in the fontgarden:
+    /// `default` for the main layer, `background` and the names of other
+    /// sublayers [default: all]. Other layers are left alone.
+    pub layers: HashSet<String>,
+    /// Only take these parts of the imported glyphs [default: all]. Glyphs
+    /// and layers new to the fontgarden are left out then, and nothing is
+    /// deleted.
+    pub only: HashSet<ImportChannel>,
+    /// Names to give glyphs in the fontgarden instead of their names in the
+    /// sources, with components changed to match.
+    pub rename_map: RenameMap,
+}
+
+/// A part of glyphs that an import can take on its own, like only the
+/// advances from a spacing pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum ImportChannel {
+    /// Contours and components, with their hints.
+    Outlines,
+    Anchors,
+    /// Advances and vertical origins.
+    Metrics,
+    /// Codepoints, PostScript names, OpenType categories and lib keys of
+    /// glyphs, and the hinting and lib keys of sources.
+    Metadata,
+}
+
+impl ImportOptions {
+    /// The name that `layers` gives the main layer by.
+    pub const DEFAULT_LAYER: &str = "default";
+
+    /// Whether to import a layer, by its sublayer name or `None` for the main
+    /// layer.
+    pub fn imports_layer(&self, sublayer: Option<&str>) -> bool {
+        self.layers.is_empty()
+            || self
+                .layers
+                .contains(sublayer.unwrap_or(Self::DEFAULT_LAYER))
+    }
+
+    /// Whether to take a part of the imported glyphs.
+    pub fn imports(&self, channel: ImportChannel) -> bool {
+        self.only.is_empty() || self.only.contains(&channel)
+    }
+
+    /// An existing layer with the parts of an imported one that are taken.
+    fn merge_channels(&self, existing: &Layer, imported: Layer) -> Layer {
+        let mut layer = existing.clone();
+        if self.imports(ImportChannel::Outlines) {
+            layer.contours = imported.contours;
+            layer.components = imported.components;
+            layer.truetype_instructions = imported.truetype_instructions;
+            layer.postscript_hints = imported.postscript_hints;
+        }
+        if self.imports(ImportChannel::Anchors) {
+            layer.anchors = imported.anchors;
+        }
+        if self.imports(ImportChannel::Metrics) {
+            layer.x_advance = imported.x_advance;
+            layer.y_advance = imported.y_advance;
+            layer.vertical_origin = imported.vertical_origin;
+        }
+        if self.imports(ImportChannel::Metadata) {
+            layer.lib = imported.lib;
+        }
+        layer
+    }
+}
+
+/// A glyph from a format that keeps all sources in one file, like a Fontra
+/// package or a Glyphs file, to merge into the fontgarden.
+#[derive(Debug, Default)]
+pub struct ImportedGlyph {
+    pub name: String,
+    pub codepoints: Codepoints,
+    /// The set the file puts the glyph in, for glyphs new to the fontgarden.
+    pub set: Option<String>,
+    pub opentype_category: Option<OpenTypeCategory>,
+    pub postscript_name: Option<String>,
+    /// Only fontgardens in other forms, like SQLite files, have advance links
+    /// and tags.
+    pub advance_link: Option<String>,
+    pub tags: BTreeSet<String>,
+    pub layers: Vec<(LayerName, Layer)>,
+}
+
+/// Knobs for exporting UFO sources from a fontgarden.
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    /// Sources to export glyphs for, with all their layers [default: all]
+    pub source_names: HashSet<String>,
+    /// Give composites the anchors of their components before exporting.
+    pub propagate_anchors: bool,
+    /// Resolve components pointing to other composites before exporting.
+    pub flatten_components: Option<FlattenComponents>,
+    /// Static instances to interpolate from the sources and export alongside them.
+    pub instances: Vec<Instance>,
+    /// Glyphs to export, with the glyphs they use as components [default: all]
+    pub glyphs: Option<Selection>,
+    /// Make up `.notdef`, `space` and other glyphs every font needs if missing.
+    pub add_default_glyphs: bool,
+    /// Write `languagesystem` statements and a `locl` feature inferred from the
+    /// glyphs into the features of every exported source.
+    pub generate_features: bool,
+    /// How to name the exported UFOs [default: `{style}.ufo`]
+    pub name_template: Option<NameTemplate>,
+    /// Stamp a version and build information into every exported source.
+    pub stamp: Option<BuildStamp>,
+    /// Leave out sublayers like backgrounds and sketches, keeping only those
+    /// with a location, which are sparse sources of the design space.
+    pub skip_sublayers: bool,
+}
+
+impl ExportOptions {
+    fn has_filters(&self) -> bool {
+        self.propagate_anchors
+            || self.flatten_components.is_some()
+            || self.glyphs.is_some()
+            || self.add_default_glyphs
+            || self.skip_sublayers
+    }
+}
+
+/// What to export a fontgarden to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A UFO per source, with a designspace if there are axes.
+    #[default]
+    Ufo,
+    /// A single Fontra package with all sources.
+    Fontra,
+    /// A single Glyphs 3 file with a master per source.
+    Glyphs,
+    /// A copy of the fontgarden in a single SQLite file.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+/// What to do when a glyph already in the fontgarden has different codepoints in
+/// the imported default source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CodepointPolicy {
+    /// Take the codepoints from the source.
+    #[default]
+    Source,
+    /// Keep the codepoints already in the fontgarden.
+    Garden,
+    /// Abort the import.
+    Fail,
+}
+
+/// What to remove from the fontgarden when it isn't in the imported sources.
+/// Only glyphs in the sets selected for import are considered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeletePolicy {
+    /// Never remove anything.
+    None,
+    /// Remove layers belonging to the imported sources that the sources no longer
+    /// have, so that a glyph removed from one source stays in the others. Glyphs
+    /// left without any layers are removed.
+    #[default]
+    Layers,
+    /// Like `layers`, and also remove glyphs that none of the sources have.
+    Glyphs,
+}
+
+/// How to name the sources that aren't named explicitly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SourceNaming {
+    /// Use the style name, like "Bold".
+    #[default]
+    Style,
+    /// Use the family and style names, like "Sans Bold", for importing several
+    /// families into one fontgarden.
+    FamilyStyle,
+    /// Use the file name without extension, like "Sans-Bold".
+    FileName,
+}
+
+/// What to do when two imported sources end up with the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollisionPolicy {
+    /// Abort the import.
+    #[default]
+    Fail,
+    /// Append a number to the names of later sources, like "Regular 2".
+    Number,
+}
+
 impl Fontgarden {
-    pub fn import_ufo_sources(&mut self, sources: &[PathBuf]) -> Result<(), SourceLoadError> {
-        let sources = load_sources(sources)?;
-        let default_source = match sources.get("Regular") {
-            Some(font) => font,
-            None => sources.values().next().unwrap(),
-        };
+    pub fn import_ufo_sources(
+        &mut self,
+        sources: &[PathBuf],
+        options: &ImportOptions,
+    ) -> Result<(), SourceLoadError> {
+        let sources = load_sources(sources, options)?;
+        if let Some(source_name) = &options.default_source {
+            if !sources.contains_key(source_name) {
+                return Err(SourceLoadError::UnknownDefaultSource(source_name.clone()));
+            }
+            if self.config.default_source.as_ref() != Some(source_name) {
+                self.config.set_default_source(source_name);
+            }
+        }
+        let default_source_name = self.default_source_name(sources.keys());
+        let default_source = &sources[default_source_name];
+        let scale_factors =
+            self.units_per_em_scale_factors(&sources, default_source_name, options)?;
 
         let glyph_info = glyphsinfo_rs::GlyphData::default();
+        let existing_glyphs: HashSet<Arc<str>> = self.glyphs.keys().cloned().collect();
+        let selected_glyphs = (!options.sets.is_empty())
+            .then(|| self.select_glyphs(&sources, default_source, &glyph_info, options));
+        let is_selected = |name: &str| match &selected_glyphs {
+            Some(selected) => selected.contains(name),
+            None => true,
+        };
 
         // Todo: Remember which glyphs are present in a fontgarden already to only guess the
         // set of new arrivals.
 
+        let mut imported_layers: HashSet<(String, LayerName)> = HashSet::new();
+        let mut skipped_layers: BTreeSet<String> = BTreeSet::new();
+        let mut found_layers: HashSet<String> = HashSet::new();
         for (source_name, source) in &sources {
             for layer in source.iter_layers() {
-                // Todo: think of another char or way to separate main from subordinate
-                // layer, as '.' might be legitimately be used in a layer name.
                 let layer_name = if std::ptr::eq(layer, source.layers.default_layer()) {
-                    source_name.clone()
+                    LayerName::main(source_name.as_str())
                 } else if layer.name() == &"public.background" {
-                    format!("{}.{}", &source_name, "background")
+                    LayerName::sublayer(source_name.as_str(), "background")
                 } else {
-                    format!("{}.{}", &source_name, layer.name())
+                    LayerName::sublayer(source_name.as_str(), layer.name().as_str())
                 };
+                let sublayer_name = layer_name.sublayer.as_deref();
+                if !options.imports_layer(sublayer_name) {
+                    skipped_layers.insert(sublayer_name.unwrap_or_default().to_string());
+                    continue;
+                }
+                found_layers.insert(
+                    sublayer_name
+                        .unwrap_or(ImportOptions::DEFAULT_LAYER)
+                        .to_string(),
+                );
 
                 for glyph in layer.iter() {
-                    let mut fontgarden_glyph =
-                        self.glyphs.entry(glyph.name().to_string()).or_default();
+                    if !is_selected(glyph.name()) {
+                        continue;
+                    }
+                    let is_existing_layer = self
+                        .glyphs
+                        .get(glyph.name().as_str())
+                        .is_some_and(|glyph| glyph.layers.contains_key(&layer_name));
+                    if !options.only.is_empty() && !is_existing_layer {
+                        continue;
+                    }
+                    let fontgarden_glyph =
+                        self.glyphs.entry(glyph.name().as_str().into()).or_default();
 
                     // Try and source codepoints for a glyph from the default source. Also
                     // try to guess which script (for set-determining purposes) a glyph
                     // belongs to, if it doesn't belong to one yet.
                     if std::ptr::eq(source, default_source)
                         && std::ptr::eq(layer, default_source.layers.default_layer())
+                        && options.imports(ImportChannel::Metadata)
                     {
-                        fontgarden_glyph.codepoints = glyph.codepoints.clone();
+                        let is_existing = existing_glyphs.contains(glyph.name().as_str());
+                        merge_codepoints(
+                            glyph.name(),
+                            fontgarden_glyph,
+                            &glyph.codepoints,
+                            is_existing,
+                            options.codepoint_policy,
+                        )?;
                         if fontgarden_glyph.set.is_none() {
-                            fontgarden_glyph.set = categorize_glyph(glyph, &glyph_info);
+                            fontgarden_glyph.set = set_for_glyph(
+                                glyph.name(),
+                                &glyph.codepoints,
+                                &glyph_info,
+                                &self.config.sets,
+                            );
+                        }
+                    }
+                    imported_layers.insert((glyph.name().to_string(), layer_name.clone()));
+                    let mut fontgarden_layer: Layer = glyph.into();
+                    fontgarden_layer.lib = self.config.lib_keys.take(&glyph.lib);
+                    if let Some(factor) = scale_factors.get(source_name) {
+                        fontgarden_layer.scale(*factor);
+                    }
+                    if let Some(existing_layer) = fontgarden_glyph.layers.get(&layer_name) {
+                        if options.only.is_empty() {
+                            fontgarden_layer.location = existing_layer.location.clone();
+                            fontgarden_layer.condition = existing_layer.condition.clone();
+                            fontgarden_layer.extra = existing_layer.extra.clone();
+                        } else {
+                            fontgarden_layer =
+                                options.merge_channels(existing_layer, fontgarden_layer);
                         }
                     }
-                    let fontgarden_layer: Layer = glyph.into();
                     fontgarden_glyph
                         .layers
                         .insert(layer_name.clone(), fontgarden_layer);
@@ -59,15 +353,48 @@ impl Fontgarden {
             }
         }
 
+        report_layer_filter(options, &skipped_layers, &found_layers);
+
+        for (source_name, source) in sources
+            .iter()
+            .filter(|_| options.imports(ImportChannel::Metadata))
+        {
+            let mut hinting = SourceHinting::from_ufo(source);
+            if let Some(factor) = scale_factors.get(source_name) {
+                hinting.postscript.scale(*factor);
+            }
+            if hinting.is_empty() {
+                self.hinting.remove(source_name);
+            } else {
+                self.hinting.insert(source_name.clone(), hinting);
+            }
+            let source_lib = SourceLib::from_ufo(source, &self.config.lib_keys);
+            if source_lib.is_empty() {
+                self.source_libs.remove(source_name);
+            } else {
+                self.source_libs.insert(source_name.clone(), source_lib);
+            }
+        }
+
+        if options.delete_policy != DeletePolicy::None && options.only.is_empty() {
+            let is_imported_source = |source_name: &str| sources.contains_key(source_name);
+            self.delete_missing(&is_imported_source, &imported_layers, options, &is_selected);
+        }
+        self.intern_names();
+
+        if !options.imports(ImportChannel::Metadata) {
+            return Ok(());
+        }
+
         if let Some(names) = default_source
             .lib
             .get("public.postscriptNames")
             .and_then(|v| v.as_dictionary())
         {
-            for (glyph, name) in names.iter() {
-                self.glyphs
-                    .entry(glyph.to_string())
-                    .and_modify(|g| g.postscript_name = name.as_string().map(|n| n.to_string()));
+            for (glyph, name) in names.iter().filter(|(glyph, _)| is_selected(glyph)) {
+                if let Some(g) = self.glyphs.get_mut(glyph.as_str()) {
+                    g.postscript_name = name.as_string().map(|n| n.to_string());
+                }
             }
         }
 
@@ -76,87 +403,754 @@ impl Fontgarden {
             .get("public.openTypeCategories")
             .and_then(|v| v.as_dictionary())
         {
-            for (glyph, name) in names.iter() {
-                self.glyphs.entry(glyph.to_string()).and_modify(|g| {
+            for (glyph, name) in names.iter().filter(|(glyph, _)| is_selected(glyph)) {
+                if let Some(g) = self.glyphs.get_mut(glyph.as_str()) {
                     g.opentype_category = name
                         .as_string()
                         .map(|n| n.parse().unwrap_or_default())
-                        .unwrap_or_default()
-                });
+                        .unwrap_or_default();
+                }
             }
         }
 
+        if options.assign_opentype_categories {
+            for glyph in default_source.default_layer().iter() {
+                if !is_selected(glyph.name()) {
+                    continue;
+                }
+                let Some(fontgarden_glyph) = self.glyphs.get_mut(glyph.name().as_str()) else {
+                    continue;
+                };
+                if fontgarden_glyph.opentype_category == OpenTypeCategory::Unassigned {
+                    fontgarden_glyph.opentype_category =
+                        opentype_category(glyph.name(), &fontgarden_glyph.codepoints, &glyph_info);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merge glyphs read from a file of all sources, named `source_names`, the
+    /// way `import_ufo_sources` merges the glyphs of UFO sources.
+    ///
+    /// Of the options, `rename_map`, `sets` with `follow_components`, `layers`,
+    /// `only`, the codepoint policy, `assign_opentype_categories` and the
+    /// delete policy apply here. Importers record the default source and scale
+    /// to `scale_to_upm` themselves; sources are named by the file, so the
+    /// options for naming them and for name collisions do not apply.
+    pub fn merge_imported_glyphs(
+        &mut self,
+        mut glyphs: Vec<ImportedGlyph>,
+        source_names: &[String],
+        scale_factor: Option<f64>,
+        options: &ImportOptions,
+    ) -> Result<(), SourceLoadError> {
+        options.rename_map.apply_to_imported(&mut glyphs)?;
+        let glyph_info = glyphsinfo_rs::GlyphData::default();
+        let sets: HashMap<&str, Option<String>> = glyphs
+            .iter()
+            .map(|glyph| {
+                let set = match self.glyphs.get(glyph.name.as_str()) {
+                    Some(existing) => existing.set.clone(),
+                    None => glyph.set.clone().or_else(|| {
+                        set_for_glyph(
+                            &glyph.name,
+                            &glyph.codepoints,
+                            &glyph_info,
+                            &self.config.sets,
+                        )
+                    }),
+                };
+                (glyph.name.as_str(), set)
+            })
+            .collect();
+        let mut selected: HashSet<&str> = sets
+            .iter()
+            .filter(|(_, set)| {
+                let set = set.as_deref().unwrap_or(Self::COMMON_SET_NAME);
+                options.sets.is_empty() || options.sets.contains(set)
+            })
+            .map(|(glyph_name, _)| *glyph_name)
+            .collect();
+        if options.follow_components && !options.sets.is_empty() {
+            let glyphs_by_name: HashMap<&str, &ImportedGlyph> = glyphs
+                .iter()
+                .map(|glyph| (glyph.name.as_str(), glyph))
+                .collect();
+            let mut pending: Vec<&str> = selected.iter().copied().collect();
+            while let Some(glyph_name) = pending.pop() {
+                for (_, layer) in &glyphs_by_name[glyph_name].layers {
+                    for component in &layer.components {
+                        let name = component.name.as_ref();
+                        if glyphs_by_name.contains_key(name) && selected.insert(name) {
+                            pending.push(name);
+                        }
+                    }
+                }
+            }
+        }
+        let selected: HashSet<String> = selected.into_iter().map(String::from).collect();
+        let sets: HashMap<String, Option<String>> = sets
+            .into_iter()
+            .map(|(glyph_name, set)| (glyph_name.to_string(), set))
+            .collect();
+
+        let mut imported_layers: HashSet<(String, LayerName)> = HashSet::new();
+        let mut skipped_layers: BTreeSet<String> = BTreeSet::new();
+        let mut found_layers: HashSet<String> = HashSet::new();
+        for glyph in glyphs {
+            if !selected.contains(&glyph.name) {
+                continue;
+            }
+            let is_existing = self.glyphs.contains_key(glyph.name.as_str());
+            if !options.only.is_empty() && !is_existing {
+                continue;
+            }
+            let fontgarden_glyph = self.glyphs.entry(glyph.name.as_str().into()).or_default();
+            fontgarden_glyph.set.clone_from(&sets[&glyph.name]);
+            if options.imports(ImportChannel::Metadata) {
+                merge_codepoints(
+                    &glyph.name,
+                    fontgarden_glyph,
+                    &glyph.codepoints,
+                    is_existing,
+                    options.codepoint_policy,
+                )?;
+                if let Some(category) = glyph.opentype_category {
+                    fontgarden_glyph.opentype_category = category;
+                }
+                if glyph.postscript_name.is_some() {
+                    fontgarden_glyph.postscript_name = glyph.postscript_name;
+                }
+                if glyph.advance_link.is_some() {
+                    fontgarden_glyph.advance_link = glyph.advance_link;
+                }
+                fontgarden_glyph.tags.extend(glyph.tags);
+                if options.assign_opentype_categories
+                    && fontgarden_glyph.opentype_category == OpenTypeCategory::Unassigned
+                {
+                    fontgarden_glyph.opentype_category =
+                        opentype_category(&glyph.name, &fontgarden_glyph.codepoints, &glyph_info);
+                }
+            }
+            for (layer_name, mut layer) in glyph.layers {
+                let sublayer_name = layer_name.sublayer.as_deref();
+                if !options.imports_layer(sublayer_name) {
+                    skipped_layers.insert(sublayer_name.unwrap_or_default().to_string());
+                    continue;
+                }
+                found_layers.insert(
+                    sublayer_name
+                        .unwrap_or(ImportOptions::DEFAULT_LAYER)
+                        .to_string(),
+                );
+                if let Some(factor) = scale_factor {
+                    layer.scale(factor);
+                }
+                match fontgarden_glyph.layers.get(&layer_name) {
+                    Some(existing_layer) if !options.only.is_empty() => {
+                        layer = options.merge_channels(existing_layer, layer);
+                    }
+                    Some(existing_layer) => {
+                        if layer.location.is_none() {
+                            layer.location = existing_layer.location.clone();
+                        }
+                        if layer.condition.is_none() {
+                            layer.condition = existing_layer.condition.clone();
+                        }
+                    }
+                    None if !options.only.is_empty() => continue,
+                    None => (),
+                }
+                imported_layers.insert((glyph.name.clone(), layer_name.clone()));
+                fontgarden_glyph.layers.insert(layer_name, layer);
+            }
+            // New glyphs with none of the layers asked for stay out.
+            if fontgarden_glyph.layers.is_empty() {
+                self.glyphs.remove(glyph.name.as_str());
+            }
+        }
+
+        if options.delete_policy != DeletePolicy::None && options.only.is_empty() {
+            let is_imported_source =
+                |source_name: &str| source_names.iter().any(|name| name == source_name);
+            let is_selected = |glyph_name: &str| selected.contains(glyph_name);
+            self.delete_missing(&is_imported_source, &imported_layers, options, &is_selected);
+        }
+        report_layer_filter(options, &skipped_layers, &found_layers);
+        self.intern_names();
         Ok(())
     }
 
+    /// Check the default source asked for is among the imported ones and record
+    /// it in the configuration.
+    pub(crate) fn record_default_source(
+        &mut self,
+        source_names: &[String],
+        options: &ImportOptions,
+    ) -> Result<(), SourceLoadError> {
+        if let Some(source_name) = &options.default_source {
+            if !source_names.contains(source_name) {
+                return Err(SourceLoadError::UnknownDefaultSource(source_name.clone()));
+            }
+            if self.config.default_source.as_ref() != Some(source_name) {
+                self.config.set_default_source(source_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `units_per_em_scale_factors`, for a file of all sources with the
+    /// given units per em.
+    pub(crate) fn file_scale_factor(
+        &mut self,
+        units_per_em: f64,
+        options: &ImportOptions,
+    ) -> Result<Option<f64>, SourceLoadError> {
+        let expected = match (options.scale_to_upm, self.config.units_per_em) {
+            (Some(target), Some(garden_upm)) if target != garden_upm && !self.glyphs.is_empty() => {
+                return Err(SourceLoadError::UnitsPerEmMismatch(garden_upm, target));
+            }
+            (Some(target), _) => target,
+            (None, Some(garden_upm)) => garden_upm,
+            (None, None) => units_per_em,
+        };
+        if self.config.units_per_em != Some(expected) {
+            self.config.set_units_per_em(expected);
+        }
+        if units_per_em == expected {
+            return Ok(None);
+        }
+        if options.scale_to_upm.is_some() {
+            eprintln!("scaling the file from {units_per_em} to {expected} units per em");
+            return Ok(Some(expected / units_per_em));
+        }
+        eprintln!(
+            "warning: the file has {units_per_em} units per em, but the fontgarden has \
+             {expected}; import it with --scale-to-upm {expected} to scale it"
+        );
+        Ok(None)
+    }
+
+    /// Compare the units per em of the sources with the fontgarden's, and work
+    /// out the factors to scale sources by to get to `scale_to_upm`. A
+    /// fontgarden without units per em takes them from the default source.
+    fn units_per_em_scale_factors(
+        &mut self,
+        sources: &HashMap<String, norad::Font>,
+        default_source_name: &str,
+        options: &ImportOptions,
+    ) -> Result<HashMap<String, f64>, SourceLoadError> {
+        let units_per_em =
+            |source_name: &str| sources[source_name].font_info.units_per_em.map(|upm| *upm);
+        let expected = match (options.scale_to_upm, self.config.units_per_em) {
+            (Some(target), Some(garden_upm)) if target != garden_upm && !self.glyphs.is_empty() => {
+                return Err(SourceLoadError::UnitsPerEmMismatch(garden_upm, target));
+            }
+            (Some(target), _) => Some(target),
+            (None, Some(garden_upm)) => Some(garden_upm),
+            (None, None) => units_per_em(default_source_name),
+        };
+        let Some(expected) = expected else {
+            return Ok(HashMap::new());
+        };
+        if self.config.units_per_em != Some(expected) {
+            self.config.set_units_per_em(expected);
+        }
+
+        let mut source_names: Vec<&String> = sources.keys().collect();
+        source_names.sort();
+        let mut scale_factors = HashMap::new();
+        for source_name in source_names {
+            let Some(upm) = units_per_em(source_name).filter(|upm| *upm != expected) else {
+                continue;
+            };
+            if options.scale_to_upm.is_some() {
+                eprintln!("scaling source {source_name} from {upm} to {expected} units per em");
+                scale_factors.insert(source_name.clone(), expected / upm);
+            } else {
+                eprintln!(
+                    "warning: source {source_name} has {upm} units per em, but the fontgarden has \
+                     {expected}; import it with --scale-to-upm {expected} to scale it"
+                );
+            }
+        }
+        Ok(scale_factors)
+    }
+
+    /// Pick the default source among the given ones: the one configured as the
+    /// default, else the one at the default location of all axes, else one
+    /// named "Regular", else the first by name.
+    pub fn default_source_name<'a>(
+        &self,
+        source_names: impl Iterator<Item = &'a String>,
+    ) -> &'a str {
+        let mut source_names: Vec<&str> = source_names.map(|n| n.as_str()).collect();
+        source_names.sort();
+        let is_at_default_location = |source_name: &str| {
+            self.config.sources.get(source_name).is_some_and(|source| {
+                self.normalize_location(&source.location)
+                    .is_ok_and(|location| location.is_empty())
+            })
+        };
+        source_names
+            .iter()
+            .find(|name| self.config.default_source.as_deref() == Some(**name))
+            .or_else(|| {
+                source_names
+                    .iter()
+                    .find(|name| is_at_default_location(name))
+            })
+            .or_else(|| source_names.iter().find(|name| **name == "Regular"))
+            .or(source_names.first())
+            .copied()
+            .expect("there is at least one source")
+    }
+
+    /// Remove what the sources no longer have, according to the delete policy.
+    pub(crate) fn delete_missing(
+        &mut self,
+        is_imported_source: &dyn Fn(&str) -> bool,
+        imported_layers: &HashSet<(String, LayerName)>,
+        options: &ImportOptions,
+        is_selected: &dyn Fn(&str) -> bool,
+    ) {
+        let imported_glyphs: HashSet<&str> = imported_layers
+            .iter()
+            .map(|(glyph_name, _)| glyph_name.as_str())
+            .collect();
+
+        let mut glyph_names: Vec<String> = self.glyphs.keys().map(|n| n.to_string()).collect();
+        glyph_names.sort();
+        for glyph_name in glyph_names {
+            let glyph = self.glyphs.get_mut(glyph_name.as_str()).unwrap();
+            if !options.sets.is_empty()
+                && !options.sets.iter().any(|set| glyph.is_in_set(set))
+                && !is_selected(&glyph_name)
+            {
+                continue;
+            }
+
+            // Without the main layers, which glyphs the sources have is unknown.
+            if options.delete_policy == DeletePolicy::Glyphs
+                && options.imports_layer(None)
+                && !imported_glyphs.contains(glyph_name.as_str())
+            {
+                eprintln!("removing glyph {glyph_name}, which is not in the sources");
+                self.glyphs.remove(glyph_name.as_str());
+                continue;
+            }
+
+            let mut layer_names: Vec<LayerName> = glyph.layers.keys().cloned().collect();
+            layer_names.sort();
+            for layer_name in layer_names {
+                if is_imported_source(&layer_name.source)
+                    && options.imports_layer(layer_name.sublayer.as_deref())
+                    && !imported_layers.contains(&(glyph_name.clone(), layer_name.clone()))
+                {
+                    eprintln!(
+                        "removing layer '{layer_name}' of glyph {glyph_name}, which is not in \
+                         the sources"
+                    );
+                    glyph.layers.remove(&layer_name);
+                    if glyph.layers.is_empty() {
+                        eprintln!("removing glyph {glyph_name}, which has no layers left");
+                        self.glyphs.remove(glyph_name.as_str());
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Determine the glyphs of the sources that belong to the sets selected for
+    /// import, following components into other sets if asked to.
+    fn select_glyphs(
+        &self,
+        sources: &HashMap<String, norad::Font>,
+        default_source: &norad::Font,
+        glyph_info: &glyphsinfo_rs::GlyphData,
+        options: &ImportOptions,
+    ) -> HashSet<String> {
+        let mut glyphs_in_sources: HashSet<&str> = HashSet::new();
+        for source in sources.values() {
+            for layer in source.iter_layers() {
+                glyphs_in_sources.extend(layer.iter().map(|glyph| glyph.name().as_str()));
+            }
+        }
+
+        let mut selected: HashSet<String> = HashSet::new();
+        for &glyph_name in &glyphs_in_sources {
+            let set = match self.glyphs.get(glyph_name) {
+                Some(glyph) => glyph.set.clone(),
+                None => {
+                    let codepoints = default_source
+                        .default_layer()
+                        .get_glyph(glyph_name)
+                        .map(|glyph| glyph.codepoints.clone())
+                        .unwrap_or_default();
+                    set_for_glyph(glyph_name, &codepoints, glyph_info, &self.config.sets)
+                }
+            };
+            let set = set.as_deref().unwrap_or(Self::COMMON_SET_NAME);
+            if options.sets.contains(set) {
+                selected.insert(glyph_name.to_string());
+            }
+        }
+
+        let mut warned: HashSet<(String, &str)> = HashSet::new();
+        let mut stack: Vec<String> = selected.iter().cloned().collect();
+        while let Some(glyph_name) = stack.pop() {
+            for source in sources.values() {
+                for layer in source.iter_layers() {
+                    let Some(glyph) = layer.get_glyph(glyph_name.as_str()) else {
+                        continue;
+                    };
+                    for component in &glyph.components {
+                        let base = component.base.as_str();
+                        if selected.contains(base)
+                            || self.glyphs.contains_key(base)
+                            || warned.contains(&(glyph_name.clone(), base))
+                        {
+                            continue;
+                        }
+                        if !glyphs_in_sources.contains(base) {
+                            eprintln!(
+                                "warning: glyph {glyph_name} uses component {base}, which is \
+                                 neither in the sources nor in the fontgarden"
+                            );
+                            warned.insert((glyph_name.clone(), base));
+                        } else if options.follow_components {
+                            selected.insert(base.to_string());
+                            stack.push(base.to_string());
+                        } else {
+                            eprintln!(
+                                "warning: glyph {glyph_name} uses component {base}, which is \
+                                 not in the selected sets and will be missing"
+                            );
+                            warned.insert((glyph_name.clone(), base));
+                        }
+                    }
+                }
+            }
+        }
+
+        selected
+    }
+
     pub fn export_ufo_sources(
         &self,
-        source_names: &HashSet<&str>,
+        options: &ExportOptions,
     ) -> Result<HashMap<String, norad::Font>, SourceSaveError> {
+        let filtered;
+        let mut alternates = Vec::new();
+        let has_alternates = self
+            .glyphs
+            .values()
+            .any(|glyph| glyph.layers.values().any(|layer| layer.condition.is_some()));
+        let has_advance_links = self.glyphs.values().any(|g| g.advance_link.is_some());
+        let fontgarden = if options.has_filters() || has_alternates || has_advance_links {
+            let mut fontgarden = self.clone();
+            fontgarden.resolve_advance_links();
+            if let Some(selection) = &options.glyphs {
+                fontgarden.retain_selection(selection);
+            }
+            if options.add_default_glyphs {
+                fontgarden.add_default_glyphs();
+            }
+            alternates = fontgarden.materialize_alternates();
+            if options.skip_sublayers {
+                for glyph in fontgarden.glyphs.values_mut() {
+                    glyph.layers.retain(|layer_name, layer| {
+                        layer_name.is_main() || layer.location.is_some()
+                    });
+                }
+            }
+            // Propagate first, as flattening may remove the components to take
+            // anchors from.
+            if options.propagate_anchors {
+                fontgarden.propagate_anchors();
+            }
+            if let Some(mode) = options.flatten_components {
+                fontgarden.flatten_components(mode)?;
+            }
+            filtered = fontgarden;
+            &filtered
+        } else {
+            self
+        };
         let mut ufos: HashMap<String, norad::Font> = HashMap::new();
 
-        let mut postscript_names = plist::Dictionary::new();
-        let mut opentype_categories = plist::Dictionary::new();
+        // Sources sharing a pipeline are filtered together.
+        let mut source_names: BTreeSet<&str> = BTreeSet::new();
+        for glyph in fontgarden.glyphs.values() {
+            source_names.extend(
+                glyph.layers.keys().map(|n| n.source.as_ref()).filter(|n| {
+                    options.source_names.is_empty() || options.source_names.contains(*n)
+                }),
+            );
+        }
+        let mut sources_by_pipeline: Vec<(&[FilterStep], BTreeSet<&str>)> = Vec::new();
+        for source_name in source_names {
+            let pipeline = fontgarden.config.export.source_pipeline(source_name);
+            match sources_by_pipeline.iter_mut().find(|(p, _)| *p == pipeline) {
+                Some((_, source_names)) => {
+                    source_names.insert(source_name);
+                }
+                None => sources_by_pipeline.push((pipeline, BTreeSet::from([source_name]))),
+            }
+        }
+        for (pipeline, source_names) in sources_by_pipeline {
+            let is_selected = |source_name: &str| source_names.contains(source_name);
+            if pipeline.is_empty() {
+                fontgarden.export_layers(&is_selected, &mut ufos)?;
+                continue;
+            }
+            let mut filtered = fontgarden.clone();
+            for glyph in filtered.glyphs.values_mut() {
+                glyph
+                    .layers
+                    .retain(|layer_name, _| is_selected(&layer_name.source));
+            }
+            filtered.apply_pipeline(pipeline)?;
+            filtered.export_layers(&is_selected, &mut ufos)?;
+            if pipeline.contains(&FilterStep::RemoveOverlaps) {
+                for source_name in source_names {
+                    request_overlap_removal(ufos.get_mut(source_name).unwrap());
+                }
+            }
+        }
+
+        for instance in &options.instances {
+            if ufos.contains_key(&instance.name) {
+                return Err(SourceSaveError::InstanceNameClash(instance.name.clone()));
+            }
+            let mut interpolation = fontgarden.interpolate(&instance.location)?;
+            for (glyph_name, incompatibility) in &interpolation.incompatible {
+                eprintln!(
+                    "warning: leaving glyph {glyph_name} out of instance {}: {incompatibility}",
+                    instance.name
+                );
+            }
+            // A static instance has no rules to switch to alternates, so swap
+            // them in right away.
+            for alternate in &alternates {
+                let Some(layer) = interpolation.layers.remove(&alternate.alternate_name) else {
+                    continue;
+                };
+                if conditions_hold(
+                    &alternate.conditions,
+                    &instance.location,
+                    &fontgarden.config,
+                ) && interpolation.layers.contains_key(&alternate.glyph_name)
+                {
+                    interpolation
+                        .layers
+                        .insert(alternate.glyph_name.clone(), layer);
+                }
+            }
+
+            // Make the instance a fontgarden of its own to run its pipeline.
+            let mut instance_garden = Fontgarden::new();
+            for (glyph_name, layer) in interpolation.layers {
+                let glyph = &fontgarden.glyphs[glyph_name.as_str()];
+                instance_garden.glyphs.insert(
+                    glyph_name.into(),
+                    Glyph {
+                        codepoints: glyph.codepoints.clone(),
+                        layers: HashMap::from([(LayerName::main(&*instance.name), layer)]),
+                        opentype_category: glyph.opentype_category.clone(),
+                        postscript_name: glyph.postscript_name.clone(),
+                        set: glyph.set.clone(),
+                        advance_link: None,
+                        tags: glyph.tags.clone(),
+                    },
+                );
+            }
+            let pipeline = fontgarden.config.export.instance_pipeline(&instance.name);
+            instance_garden.apply_pipeline(pipeline)?;
+            instance_garden.export_layers(&|_| true, &mut ufos)?;
+            let ufo = ufos.entry(instance.name.clone()).or_default();
+            if pipeline.contains(&FilterStep::RemoveOverlaps) {
+                request_overlap_removal(ufo);
+            }
+        }
+
+        let units_per_em = fontgarden
+            .config
+            .units_per_em
+            .and_then(|upm| upm.try_into().ok());
+        let features = options
+            .generate_features
+            .then(|| fontgarden.generate_features(&glyphsinfo_rs::GlyphData::default()));
+        for (source_name, source) in ufos.iter_mut() {
+            source.font_info.style_name = Some(source_name.clone());
+            if let Some(naming) = fontgarden.config.style_naming(source_name) {
+                naming.apply_to_ufo(source);
+            }
+            source.font_info.units_per_em = units_per_em;
+            if let Some(features) = &features {
+                source.features = features.clone();
+            }
+            if let Some(source_lib) = fontgarden.source_libs.get(source_name) {
+                source_lib.apply_to_ufo(source, &fontgarden.config.lib_keys);
+            }
+            fontgarden.lib.apply_to_ufo(source);
+            if let Some(hinting) = fontgarden.hinting.get(source_name) {
+                hinting.apply_to_ufo(source);
+            }
+            if let Some(stamp) = &options.stamp {
+                stamp.apply_to_ufo(source);
+            }
+            if let Some(decimals) = fontgarden.config.precision {
+                round_ufo(source, decimals);
+            }
+        }
+
+        Ok(ufos)
+    }
+
+    /// Write the layers of the selected sources into UFOs by source name, with
+    /// the glyph data of the glyphs in their main layers in the UFO lib.
+    fn export_layers(
+        &self,
+        is_selected: &dyn Fn(&str) -> bool,
+        ufos: &mut HashMap<String, norad::Font>,
+    ) -> Result<(), SourceSaveError> {
+        let mut postscript_names: HashMap<&str, plist::Dictionary> = HashMap::new();
+        let mut opentype_categories: HashMap<&str, plist::Dictionary> = HashMap::new();
 
-        for (glyph_name, glyph) in self.glyphs.iter() {
+        // Create the sublayers up front in sorted order, and go through glyphs
+        // and layers in sorted order, so that exporting twice gives the same
+        // layercontents.plist and lib dictionaries.
+        let mut sublayer_names: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for layer_name in self.glyphs.values().flat_map(|glyph| glyph.layers.keys()) {
+            if let Some(sublayer_name) = &layer_name.sublayer {
+                if is_selected(&layer_name.source) {
+                    sublayer_names
+                        .entry(&layer_name.source)
+                        .or_default()
+                        .insert(sublayer_name);
+                }
+            }
+        }
+        for (source_name, sublayer_names) in sublayer_names {
+            let ufo: &mut norad::Font = ufos.entry(source_name.into()).or_default();
+            for sublayer_name in sublayer_names {
+                ufo.layers
+                    .get_or_create_layer(sublayer_name)
+                    .map_err(|e| SourceSaveError::GlyphNamingError(sublayer_name.into(), e))?;
+            }
+        }
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
             let ufo_glyph_name = norad::Name::new(glyph_name)
-                .map_err(|e| SourceSaveError::GlyphNamingError(glyph_name.clone(), e))?;
-            for (layer_name, layer) in glyph.layers.iter().filter(|(layer_name, _)| {
-                source_names.is_empty() || source_names.contains(layer_name.as_str())
-            }) {
-                match layer_name.split_once('.') {
-                    Some((base, suffix)) => {
-                        let ufo: &mut norad::Font = ufos.entry(base.to_string()).or_default();
+                .map_err(|e| SourceSaveError::GlyphNamingError(glyph_name.to_string(), e))?;
+            let mut layers: Vec<_> = glyph
+                .layers
+                .iter()
+                .filter(|(layer_name, _)| is_selected(&layer_name.source))
+                .collect();
+            layers
+                .sort_by(|(a, _), (b, _)| (&a.source, &a.sublayer).cmp(&(&b.source, &b.sublayer)));
+            for (layer_name, layer) in layers {
+                let ufo: &mut norad::Font = ufos.entry(layer_name.source.to_string()).or_default();
+                match &layer_name.sublayer {
+                    Some(sublayer_name) => {
                         let ufo_glyph = layer.export_to_ufo_glyph(ufo_glyph_name.clone(), None)?;
                         ufo.layers
-                            .get_or_create_layer(suffix)
-                            .map_err(|e| SourceSaveError::GlyphNamingError(suffix.into(), e))?
+                            .get_or_create_layer(sublayer_name)
+                            .map_err(|e| {
+                                SourceSaveError::GlyphNamingError(sublayer_name.to_string(), e)
+                            })?
                             .insert_glyph(ufo_glyph);
                     }
                     None => {
-                        let ufo: &mut norad::Font = ufos.entry(layer_name.to_string()).or_default();
                         let ufo_glyph = layer
                             .export_to_ufo_glyph(ufo_glyph_name.clone(), Some(&glyph.codepoints))?;
                         ufo.layers.default_layer_mut().insert_glyph(ufo_glyph);
 
                         if let Some(postscript_name) = &glyph.postscript_name {
                             postscript_names
+                                .entry(&layer_name.source)
+                                .or_default()
                                 .insert(glyph_name.into(), postscript_name.clone().into());
                         }
                         if glyph.opentype_category != OpenTypeCategory::Unassigned {
                             let otc: String =
                                 serde_json::to_string(&glyph.opentype_category).unwrap();
-                            opentype_categories.insert(glyph_name.into(), otc.into());
+                            opentype_categories
+                                .entry(&layer_name.source)
+                                .or_default()
+                                .insert(glyph_name.into(), otc.into());
                         }
                     }
                 }
             }
         }
 
-        for (source_name, source) in ufos.iter_mut() {
-            source.font_info.style_name = Some(source_name.clone());
+        for (source_name, postscript_names) in postscript_names {
+            ufos.get_mut(source_name)
+                .unwrap()
+                .lib
+                .insert("public.postscriptNames".into(), postscript_names.into());
         }
-
-        if !postscript_names.is_empty() {
-            for source in ufos.values_mut() {
-                source.lib.insert(
-                    "public.postscriptNames".into(),
-                    postscript_names.clone().into(),
-                );
-            }
+        for (source_name, opentype_categories) in opentype_categories {
+            ufos.get_mut(source_name).unwrap().lib.insert(
+                "public.openTypeCategories".into(),
+                opentype_categories.into(),
+            );
         }
+        Ok(())
+    }
+}
 
-        if !opentype_categories.is_empty() {
-            for source in ufos.values_mut() {
-                source.lib.insert(
-                    "public.openTypeCategories".into(),
-                    opentype_categories.clone().into(),
-                );
+/// Merge exported sources into a single UFO, with the default source in the
+/// default layer and its sublayers, and every other source in a layer of its
+/// own, with its sublayers named like `Bold.background`.
+pub fn layered_ufo(
+    mut ufos: HashMap<String, norad::Font>,
+    default_source_name: &str,
+) -> Result<norad::Font, SourceSaveError> {
+    let mut layered = ufos
+        .remove(default_source_name)
+        .expect("the default source is among the sources");
+    let mut source_names: Vec<&String> = ufos.keys().collect();
+    source_names.sort();
+    for source_name in source_names {
+        let ufo = &ufos[source_name];
+        let default_layer_name = ufo.layers.default_layer().name();
+        for layer in ufo.layers.iter() {
+            let layer_name = if layer.name() == default_layer_name {
+                LayerName::main(source_name.as_str())
+            } else {
+                LayerName::sublayer(source_name.as_str(), layer.name().as_str())
+            }
+            .to_string();
+            let target = layered
+                .layers
+                .new_layer(&layer_name)
+                .map_err(|e| SourceSaveError::LayerNamingError(layer_name.clone(), e))?;
+            for glyph in layer.iter() {
+                target.insert_glyph(glyph.clone());
             }
         }
-
-        Ok(ufos)
     }
+    Ok(layered)
+}
+
+/// Ask ufo2ft to remove overlaps when compiling the UFO.
+fn request_overlap_removal(ufo: &mut norad::Font) {
+    let mut filter = plist::Dictionary::new();
+    filter.insert("name".into(), "removeOverlaps".into());
+    ufo.lib.insert(
+        UFO2FT_FILTERS_LIB_KEY.into(),
+        plist::Value::Array(vec![filter.into()]),
+    );
 }
 
 impl Layer {
@@ -166,6 +1160,7 @@ impl Layer {
         codepoints: Option<&Codepoints>,
     ) -> Result<norad::Glyph, SourceSaveError> {
         let mut ufo_glyph = norad::Glyph::new(&name);
+        insert_lib_keys(&self.lib, &mut ufo_glyph.lib);
 
         if let Some(codepoints) = codepoints {
             ufo_glyph.codepoints = codepoints.clone();
@@ -178,6 +1173,12 @@ impl Layer {
                 .lib
                 .insert("public.verticalOrigin".into(), vertical_origin.into());
         }
+        if let Some(instructions) = &self.truetype_instructions {
+            ufo_glyph
+                .lib
+                .insert(TRUETYPE_LIB_KEY.into(), instructions.to_lib());
+        }
+        postscript_hints_to_lib(&self.postscript_hints, &mut ufo_glyph.lib);
 
         ufo_glyph.anchors = self
             .anchors
@@ -186,9 +1187,18 @@ impl Layer {
             .collect::<Result<_, _>>()
             .map_err(|e| SourceSaveError::AnchorNamingError(name.to_string(), e))?;
         ufo_glyph.contours = self.contours.iter().map(|contour| contour.into()).collect();
-        ufo_glyph.components = self
+        let (variable_components, components): (Vec<&Component>, Vec<&Component>) = self
             .components
             .iter()
+            .partition(|component| component.location.is_some());
+        if !variable_components.is_empty() {
+            ufo_glyph.lib.insert(
+                VARIABLE_COMPONENTS_LIB_KEY.into(),
+                variable_components_to_lib(variable_components),
+            );
+        }
+        ufo_glyph.components = components
+            .into_iter()
             .map(|component| component.try_into())
             .collect::<Result<_, _>>()
             .map_err(|e| SourceSaveError::ComponentNamingError(name.to_string(), e))?;
@@ -197,43 +1207,135 @@ impl Layer {
     }
 }
 
-fn load_sources(sources: &[PathBuf]) -> Result<HashMap<String, norad::Font>, SourceLoadError> {
+/// Give a glyph the codepoints it has in the default source, unless it is
+/// already in the fontgarden with others and the policy says to keep those.
+pub(crate) fn merge_codepoints(
+    glyph_name: &str,
+    fontgarden_glyph: &mut Glyph,
+    codepoints: &Codepoints,
+    is_existing: bool,
+    policy: CodepointPolicy,
+) -> Result<(), SourceLoadError> {
+    if !is_existing || fontgarden_glyph.codepoints == *codepoints {
+        fontgarden_glyph.codepoints = codepoints.clone();
+        return Ok(());
+    }
+    let garden_codepoints = codepoints_to_string(&fontgarden_glyph.codepoints);
+    let source_codepoints = codepoints_to_string(codepoints);
+    match policy {
+        CodepointPolicy::Source => {
+            eprintln!(
+                "warning: codepoints of glyph {glyph_name} changed from [{garden_codepoints}] to \
+                 [{source_codepoints}]"
+            );
+            fontgarden_glyph.codepoints = codepoints.clone();
+        }
+        CodepointPolicy::Garden => eprintln!(
+            "warning: keeping codepoints [{garden_codepoints}] of glyph {glyph_name}, source has \
+             [{source_codepoints}]"
+        ),
+        CodepointPolicy::Fail => {
+            return Err(SourceLoadError::CodepointConflict(
+                glyph_name.to_string(),
+                garden_codepoints,
+                source_codepoints,
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Tell which layers an import with `--layers` skipped, and which of the layers
+/// asked for no source has.
+fn report_layer_filter(
+    options: &ImportOptions,
+    skipped_layers: &BTreeSet<String>,
+    found_layers: &HashSet<String>,
+) {
+    if !skipped_layers.is_empty() {
+        let layer_names: Vec<&str> = skipped_layers.iter().map(|n| n.as_str()).collect();
+        eprintln!("skipping layers not asked for: {}", layer_names.join(", "));
+    }
+    let mut missing_layers: Vec<&str> = options
+        .layers
+        .iter()
+        .filter(|layer_name| !found_layers.contains(*layer_name))
+        .map(|layer_name| layer_name.as_str())
+        .collect();
+    missing_layers.sort();
+    if !missing_layers.is_empty() {
+        eprintln!(
+            "warning: no source has these layers: {}",
+            missing_layers.join(", ")
+        );
+    }
+}
+
+fn load_sources(
+    sources: &[PathBuf],
+    options: &ImportOptions,
+) -> Result<HashMap<String, norad::Font>, SourceLoadError> {
     let mut source_by_name = HashMap::new();
     for source_path in sources {
-        let ufo_source = norad::Font::load(source_path)
+        let mut ufo_source = norad::Font::load(source_path)
             .map_err(|e| SourceLoadError::Ufo(source_path.clone(), e))?;
-        let source_name = ufo_source
-            .font_info
-            .style_name
-            .as_ref()
-            .map(|s| s.to_string())
-            .unwrap_or(String::from("Regular"));
+        options.rename_map.apply_to_ufo(&mut ufo_source)?;
+        let mut source_name = source_name(source_path, &ufo_source, options);
         if source_by_name.contains_key(&source_name) {
-            return Err(SourceLoadError::DuplicateLayerName(
-                source_name,
-                source_path.clone(),
-            ));
+            match options.collision_policy {
+                CollisionPolicy::Fail => {
+                    return Err(SourceLoadError::DuplicateSourceName(
+                        source_name,
+                        source_path.clone(),
+                    ))
+                }
+                CollisionPolicy::Number => {
+                    let base_name = source_name;
+                    let mut number = 1;
+                    source_name = loop {
+                        number += 1;
+                        let name = format!("{base_name} {number}");
+                        if !source_by_name.contains_key(&name) {
+                            break name;
+                        }
+                    };
+                    eprintln!(
+                        "importing {} as {source_name}, as {base_name} is taken",
+                        source_path.display()
+                    );
+                }
+            }
         }
         source_by_name.insert(source_name, ufo_source);
     }
     Ok(source_by_name)
 }
 
-fn categorize_glyph(glyph: &norad::Glyph, glyph_info: &GlyphData) -> Option<String> {
-    if let Some(unicode) = glyph.codepoints.iter().next() {
-        return glyph_info
-            .record_for_unicode(unicode)
-            .and_then(|record| record.script.as_ref().map(|s| format!("{s:?}")));
-    }
-    if let Some(record) = glyph_info.record_for_name(glyph.name()) {
-        return record.script.as_ref().map(|s| format!("{s:?}"));
+/// Name a source according to the import options. Sources without a style
+/// name are named after their file.
+fn source_name(source_path: &Path, ufo_source: &norad::Font, options: &ImportOptions) -> String {
+    let file_name = source_path.file_name().map(|n| n.to_string_lossy());
+    let explicit_name = options
+        .source_names
+        .get(&*source_path.to_string_lossy())
+        .or_else(|| options.source_names.get(&*file_name?));
+    if let Some(name) = explicit_name {
+        return name.clone();
     }
-    // FIXME: This also categorizes danda-deva.loclBENG as Devanagari because the parent
-    // is. Local variants should stay with their scripts if possible.
-    if let Some((base_name, _)) = glyph.name().split_once('.') {
-        return glyph_info
-            .record_for_name(base_name)
-            .and_then(|record| record.script.as_ref().map(|s| format!("{s:?}")));
+
+    let file_stem = || {
+        source_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let font_info = &ufo_source.font_info;
+    match (options.source_naming, &font_info.style_name) {
+        (SourceNaming::FileName, _) | (_, None) => file_stem(),
+        (SourceNaming::Style, Some(style_name)) => style_name.clone(),
+        (SourceNaming::FamilyStyle, Some(style_name)) => match &font_info.family_name {
+            Some(family_name) => format!("{family_name} {style_name}"),
+            None => style_name.clone(),
+        },
     }
-    None
 }
@@ -0,0 +1,611 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::{
+    contours::WindingConvention,
+    structs::{Fontgarden, Glyph, LayerName, OpenTypeCategory},
+};
+
+/// Which anchors a glyph has in which of its source layers.
+#[derive(Debug, PartialEq)]
+pub struct AnchorConsistency {
+    pub glyph_name: String,
+    /// Anchor name to the source layers that contain it.
+    pub anchors: BTreeMap<String, BTreeSet<String>>,
+    /// Anchor name to the source layers that lack it, for anchors that are present
+    /// in some but not all source layers.
+    pub missing: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl AnchorConsistency {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+impl Fontgarden {
+    /// Collect the anchors of every glyph per source layer, sorted by glyph name.
+    ///
+    /// Only the main source layers are compared, as sublayers like backgrounds
+    /// don't take part in interpolation.
+    pub fn anchor_consistency(&self) -> Vec<AnchorConsistency> {
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+
+        glyph_names
+            .into_iter()
+            .map(|glyph_name| anchor_consistency(glyph_name, &self.glyphs[glyph_name]))
+            .collect()
+    }
+}
+
+/// A problem with how glyphs are mapped to codepoints.
+#[derive(Debug, PartialEq)]
+pub enum CodepointProblem {
+    /// Several glyphs are mapped to the same codepoint.
+    Duplicate {
+        codepoint: char,
+        glyph_names: Vec<String>,
+    },
+    /// A glyph named like `uni00E1` or `u1F600` is mapped to other codepoints.
+    NameMismatch { glyph_name: String, codepoint: char },
+}
+
+impl Display for CodepointProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodepointProblem::Duplicate {
+                codepoint,
+                glyph_names,
+            } => write!(
+                f,
+                "U+{:04X} is mapped to several glyphs: {}",
+                *codepoint as u32,
+                glyph_names.join(", ")
+            ),
+            CodepointProblem::NameMismatch {
+                glyph_name,
+                codepoint,
+            } => write!(
+                f,
+                "glyph {glyph_name} is named after U+{:04X} but not mapped to it",
+                *codepoint as u32
+            ),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Find codepoints shared by several glyphs and glyphs whose `uniXXXX`-style
+    /// names disagree with their codepoints.
+    pub fn codepoint_problems(&self) -> Vec<CodepointProblem> {
+        let mut glyphs_by_codepoint: BTreeMap<char, Vec<String>> = BTreeMap::new();
+        let mut problems = Vec::new();
+
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            for codepoint in glyph.codepoints.iter() {
+                glyphs_by_codepoint
+                    .entry(codepoint)
+                    .or_default()
+                    .push(glyph_name.to_string());
+            }
+            if let Some(codepoint) = codepoint_from_name(glyph_name) {
+                if !glyph.codepoints.is_empty() && !glyph.codepoints.contains(codepoint) {
+                    problems.push(CodepointProblem::NameMismatch {
+                        glyph_name: glyph_name.to_string(),
+                        codepoint,
+                    });
+                }
+            }
+        }
+
+        let duplicates = glyphs_by_codepoint
+            .into_iter()
+            .filter(|(_, glyph_names)| glyph_names.len() > 1)
+            .map(|(codepoint, glyph_names)| CodepointProblem::Duplicate {
+                codepoint,
+                glyph_names,
+            });
+        duplicates.chain(problems).collect()
+    }
+}
+
+/// A mark anchor like `_top` without a matching `top` anchor on any base glyph
+/// that the mark could attach to.
+#[derive(Debug, PartialEq)]
+pub struct UnmatchedMarkAnchor {
+    pub mark_name: String,
+    pub anchor_name: String,
+    /// The set whose bases were searched, `None` for all sets.
+    pub set_name: Option<String>,
+    /// Sources in which no base has the matching anchor.
+    pub source_names: BTreeSet<String>,
+}
+
+impl Display for UnmatchedMarkAnchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let base_anchor_name = &self.anchor_name[1..];
+        let source_names: Vec<&str> = self.source_names.iter().map(|n| n.as_str()).collect();
+        match &self.set_name {
+            Some(set_name) => write!(
+                f,
+                "mark {} has anchor {} but no base in set {set_name} has anchor {base_anchor_name} in: {}",
+                self.mark_name,
+                self.anchor_name,
+                source_names.join(", "),
+            ),
+            None => write!(
+                f,
+                "mark {} has anchor {} but no base has anchor {base_anchor_name} in: {}",
+                self.mark_name,
+                self.anchor_name,
+                source_names.join(", "),
+            ),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Find mark anchors like `_top` that no base glyph in the mark's set has a
+    /// `top` counterpart for, per source.
+    ///
+    /// Marks are glyphs with the OpenType category "mark", or with an anchor
+    /// starting with an underscore. Marks in the Common set are usually shared
+    /// between scripts and are checked against bases in all sets.
+    pub fn unmatched_mark_anchors(&self) -> Vec<UnmatchedMarkAnchor> {
+        let is_mark = |glyph: &Glyph| {
+            glyph.opentype_category == OpenTypeCategory::Mark
+                || glyph
+                    .layers
+                    .values()
+                    .any(|layer| layer.anchors.iter().any(|a| a.name.starts_with('_')))
+        };
+
+        // Set name (`None` for all sets) to source name to the anchor names found
+        // on bases there.
+        let mut base_anchors: BTreeMap<Option<&str>, BTreeMap<&str, BTreeSet<&str>>> =
+            BTreeMap::new();
+        for glyph in self.glyphs.values().filter(|glyph| !is_mark(glyph)) {
+            for (layer_name, layer) in &glyph.layers {
+                if !layer_name.is_main() {
+                    continue;
+                }
+                let set_name = glyph.set.as_deref().unwrap_or(Self::COMMON_SET_NAME);
+                for key in [Some(set_name), None] {
+                    base_anchors
+                        .entry(key)
+                        .or_default()
+                        .entry(&layer_name.source)
+                        .or_default()
+                        .extend(layer.anchors.iter().map(|a| a.name.as_str()));
+                }
+            }
+        }
+
+        let mut mark_names: Vec<&str> = self
+            .glyphs
+            .iter()
+            .filter(|(_, glyph)| is_mark(glyph))
+            .map(|(name, _)| &**name)
+            .collect();
+        mark_names.sort();
+
+        let mut unmatched = Vec::new();
+        for mark_name in mark_names {
+            let mark = &self.glyphs[mark_name];
+            let set_name = mark.set.as_deref();
+            let empty = BTreeMap::new();
+            let bases = base_anchors.get(&set_name).unwrap_or(&empty);
+
+            let mut missing: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+            for (layer_name, layer) in &mark.layers {
+                if !layer_name.is_main() {
+                    continue;
+                }
+                for anchor in &layer.anchors {
+                    let Some(base_anchor_name) = anchor.name.strip_prefix('_') else {
+                        continue;
+                    };
+                    let has_base = bases
+                        .get(layer_name.source.as_ref())
+                        .is_some_and(|anchors| anchors.contains(base_anchor_name));
+                    if !has_base {
+                        missing
+                            .entry(anchor.name.as_str())
+                            .or_default()
+                            .insert(layer_name.source.to_string());
+                    }
+                }
+            }
+
+            unmatched.extend(missing.into_iter().map(|(anchor_name, source_names)| {
+                UnmatchedMarkAnchor {
+                    mark_name: mark_name.to_string(),
+                    anchor_name: anchor_name.to_string(),
+                    set_name: set_name.map(|s| s.to_string()),
+                    source_names,
+                }
+            }));
+        }
+        unmatched
+    }
+}
+
+/// How bad a problem is. Errors break the font, warnings are likely mistakes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem with a contour in a glyph layer.
+#[derive(Debug, PartialEq)]
+pub struct PathProblem {
+    pub glyph_name: String,
+    pub layer_name: LayerName,
+    pub contour_index: usize,
+    pub kind: PathProblemKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PathProblemKind {
+    /// The contour winds against the convention for outer contours or counters.
+    WrongDirection,
+    /// The contour is open in a glyph that is exported.
+    OpenContour,
+    /// The segment ending at this point starts at the same position and has
+    /// no extent.
+    ZeroLengthSegment { point_index: usize },
+    /// The point is at the same position as the one before it.
+    DuplicatePoint { point_index: usize },
+    /// The curve segment ending at this point, running between the given
+    /// coordinates, has no points at its horizontal or vertical extrema.
+    MissingExtremum {
+        point_index: usize,
+        start: (f64, f64),
+        end: (f64, f64),
+    },
+}
+
+impl PathProblem {
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            PathProblemKind::OpenContour => Severity::Error,
+            PathProblemKind::WrongDirection
+            | PathProblemKind::ZeroLengthSegment { .. }
+            | PathProblemKind::DuplicatePoint { .. }
+            | PathProblemKind::MissingExtremum { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Display for PathProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: glyph {}, layer '{}', contour {}: {}",
+            self.severity(),
+            self.glyph_name,
+            self.layer_name,
+            self.contour_index,
+            self.kind
+        )
+    }
+}
+
+impl Display for PathProblemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathProblemKind::WrongDirection => write!(f, "wrong direction"),
+            PathProblemKind::OpenContour => write!(f, "open contour"),
+            PathProblemKind::ZeroLengthSegment { point_index } => {
+                write!(f, "zero-length segment ending at point {point_index}")
+            }
+            PathProblemKind::DuplicatePoint { point_index } => {
+                write!(f, "point {point_index} duplicates the previous point")
+            }
+            PathProblemKind::MissingExtremum {
+                point_index,
+                start: (start_x, start_y),
+                end: (end_x, end_y),
+            } => write!(
+                f,
+                "curve segment from ({start_x}, {start_y}) to ({end_x}, {end_y}) ending at \
+                 point {point_index} lacks points at its extrema"
+            ),
+        }
+    }
+}
+
+impl Fontgarden {
+    /// Find contours with the wrong direction, open contours in exported glyphs,
+    /// zero-length segments, duplicate points and curves missing points at their
+    /// extrema, sorted by glyph and layer.
+    ///
+    /// Glyphs with names starting with an underscore are taken to be building
+    /// blocks that aren't exported, and may have open contours.
+    pub fn path_problems(&self, convention: WindingConvention) -> Vec<PathProblem> {
+        let mut glyph_names: Vec<&str> = self.glyphs.keys().map(|n| n.as_ref()).collect();
+        glyph_names.sort();
+
+        let mut problems = Vec::new();
+        for glyph_name in glyph_names {
+            let glyph = &self.glyphs[glyph_name];
+            let is_exported = !glyph_name.starts_with('_');
+            let mut layer_names: Vec<&LayerName> = glyph.layers.keys().collect();
+            layer_names.sort();
+            for layer_name in layer_names {
+                let layer = &glyph.layers[layer_name];
+                let mut problem = |contour_index, kind| {
+                    problems.push(PathProblem {
+                        glyph_name: glyph_name.to_string(),
+                        layer_name: layer_name.clone(),
+                        contour_index,
+                        kind,
+                    })
+                };
+                let misdirected = layer.misdirected_contours(convention);
+                for (contour_index, contour) in layer.contours.iter().enumerate() {
+                    if misdirected.contains(&contour_index) {
+                        problem(contour_index, PathProblemKind::WrongDirection);
+                    }
+                    if is_exported && !contour.is_closed() {
+                        problem(contour_index, PathProblemKind::OpenContour);
+                    }
+                    for point_index in contour.zero_length_segments() {
+                        problem(
+                            contour_index,
+                            PathProblemKind::ZeroLengthSegment { point_index },
+                        );
+                    }
+                    for point_index in contour.duplicate_points() {
+                        problem(
+                            contour_index,
+                            PathProblemKind::DuplicatePoint { point_index },
+                        );
+                    }
+                    for (start, end) in contour.missing_extrema() {
+                        let position =
+                            |index: usize| (contour.points[index].x, contour.points[index].y);
+                        problem(
+                            contour_index,
+                            PathProblemKind::MissingExtremum {
+                                point_index: end,
+                                start: position(start),
+                                end: position(end),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// Parse the codepoint out of names like `uni00E1` and `u1F600`. Names with
+/// suffixes like `uni00E1.sc` denote variants and aren't considered.
+fn codepoint_from_name(glyph_name: &str) -> Option<char> {
+    let hex = match (glyph_name.strip_prefix("uni"), glyph_name.strip_prefix('u')) {
+        (Some(hex), _) if hex.len() == 4 => hex,
+        (_, Some(hex)) if (4..=6).contains(&hex.len()) => hex,
+        _ => return None,
+    };
+    if !hex
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase())
+    {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+fn anchor_consistency(glyph_name: &str, glyph: &Glyph) -> AnchorConsistency {
+    let source_layers: BTreeSet<String> = glyph
+        .layers
+        .keys()
+        .filter(|layer_name| layer_name.is_main())
+        .map(|layer_name| layer_name.source.to_string())
+        .collect();
+
+    let mut anchors: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for layer_name in &source_layers {
+        for anchor in &glyph.layers[&LayerName::main(layer_name.as_str())].anchors {
+            anchors
+                .entry(anchor.name.clone())
+                .or_default()
+                .insert(layer_name.clone());
+        }
+    }
+
+    let missing = anchors
+        .iter()
+        .filter(|(_, present_in)| present_in.len() != source_layers.len())
+        .map(|(anchor_name, present_in)| {
+            (
+                anchor_name.clone(),
+                source_layers.difference(present_in).cloned().collect(),
+            )
+        })
+        .collect();
+
+    AnchorConsistency {
+        glyph_name: glyph_name.into(),
+        anchors,
+        missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Anchor, Contour, ContourPoint, ExtraFields, Layer, PointType};
+
+    use super::*;
+
+    fn layer_with_anchors(names: &[&str]) -> Layer {
+        Layer {
+            anchors: names
+                .iter()
+                .map(|name| Anchor {
+                    name: name.to_string(),
+                    x: 0.,
+                    y: 0.,
+                    extra: ExtraFields::new(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn anchors_missing_in_some_sources() {
+        let mut glyph = Glyph::default();
+        glyph
+            .layers
+            .insert("Light".into(), layer_with_anchors(&["top", "bottom"]));
+        glyph
+            .layers
+            .insert("Bold".into(), layer_with_anchors(&["top"]));
+        glyph
+            .layers
+            .insert("Bold.background".into(), layer_with_anchors(&[]));
+
+        let report = anchor_consistency("a", &glyph);
+
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.missing,
+            BTreeMap::from([("bottom".into(), BTreeSet::from(["Bold".into()]))])
+        );
+        assert_eq!(
+            report.anchors["top"],
+            BTreeSet::from(["Bold".into(), "Light".into()])
+        );
+    }
+
+    #[test]
+    fn mark_anchors_without_bases() {
+        let mut fontgarden = Fontgarden::new();
+        let mut base = Glyph {
+            set: Some("Latin".into()),
+            ..Default::default()
+        };
+        base.layers
+            .insert("Light".into(), layer_with_anchors(&["top"]));
+        base.layers
+            .insert(LayerName::main("Bold"), layer_with_anchors(&[]));
+        let mut mark = Glyph {
+            set: Some("Latin".into()),
+            ..Default::default()
+        };
+        for source_name in ["Light", "Bold"] {
+            mark.layers
+                .insert(source_name.into(), layer_with_anchors(&["_top", "_bottom"]));
+        }
+        fontgarden.glyphs.insert("a".into(), base);
+        fontgarden.glyphs.insert("acutecomb".into(), mark);
+
+        let unmatched = fontgarden.unmatched_mark_anchors();
+
+        assert_eq!(
+            unmatched,
+            vec![
+                UnmatchedMarkAnchor {
+                    mark_name: "acutecomb".into(),
+                    anchor_name: "_bottom".into(),
+                    set_name: Some("Latin".into()),
+                    source_names: BTreeSet::from(["Bold".into(), "Light".into()]),
+                },
+                UnmatchedMarkAnchor {
+                    mark_name: "acutecomb".into(),
+                    anchor_name: "_top".into(),
+                    set_name: Some("Latin".into()),
+                    source_names: BTreeSet::from(["Bold".into()]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn path_problems_per_contour() {
+        let point = |x: f64, y: f64, typ: PointType| ContourPoint {
+            x,
+            y,
+            typ,
+            smooth: false,
+        };
+        let layer = Layer {
+            contours: vec![
+                Contour {
+                    points: vec![
+                        point(0., 0., PointType::Move),
+                        point(100., 0., PointType::Line),
+                        point(100., 0., PointType::Line),
+                    ],
+                    extra: ExtraFields::new(),
+                },
+                Contour {
+                    points: vec![
+                        point(0., 0., PointType::Line),
+                        point(0., 100., PointType::Line),
+                        point(0., 100., PointType::OffCurve),
+                        point(100., 100., PointType::OffCurve),
+                        point(100., 0., PointType::Curve),
+                    ],
+                    extra: ExtraFields::new(),
+                },
+            ],
+            ..Default::default()
+        };
+        let mut fontgarden = Fontgarden::new();
+        for glyph_name in ["a", "_part"] {
+            let mut glyph = Glyph::default();
+            glyph
+                .layers
+                .insert(LayerName::main("Regular"), layer.clone());
+            fontgarden.glyphs.insert(glyph_name.into(), glyph);
+        }
+
+        let problems = fontgarden.path_problems(WindingConvention::PostScript);
+
+        use PathProblemKind::*;
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| (p.glyph_name.as_str(), p.contour_index, &p.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("_part", 0, &ZeroLengthSegment { point_index: 2 }),
+                ("_part", 1, &WrongDirection),
+                ("_part", 1, &DuplicatePoint { point_index: 2 }),
+                ("a", 0, &OpenContour),
+                ("a", 0, &ZeroLengthSegment { point_index: 2 }),
+                ("a", 1, &WrongDirection),
+                ("a", 1, &DuplicatePoint { point_index: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn codepoints_from_names() {
+        assert_eq!(codepoint_from_name("uni00E1"), Some('á'));
+        assert_eq!(codepoint_from_name("u1F600"), Some('😀'));
+        assert_eq!(codepoint_from_name("uni00E1.sc"), None);
+        assert_eq!(codepoint_from_name("uni00e1"), None);
+        assert_eq!(codepoint_from_name("uhorn"), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Variable (smart) components in UFOs, stored in the glyph lib the way Fontra
+//! and Black Foundry tools do, since plain UFO components have no location.
+
+use kurbo::Affine;
+
+use crate::structs::{Component, ExtraFields};
+
+pub const VARIABLE_COMPONENTS_LIB_KEY: &str = "com.black-foundry.variable-components";
+
+/// Read the variable components from a glyph lib, skipping malformed entries.
+pub fn variable_components_from_lib(lib: &plist::Dictionary) -> Vec<Component> {
+    let Some(entries) = lib
+        .get(VARIABLE_COMPONENTS_LIB_KEY)
+        .and_then(|value| value.as_array())
+    else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let entry = entry.as_dictionary()?;
+            let name = entry.get("base")?.as_string()?.to_string();
+            let transformation = match entry.get("transformation") {
+                Some(value) => {
+                    DecomposedTransform::from_dictionary(value.as_dictionary()?).to_affine()
+                }
+                None => Affine::IDENTITY,
+            };
+            let location = entry
+                .get("location")
+                .and_then(|value| value.as_dictionary())
+                .map(|location| {
+                    location
+                        .iter()
+                        .filter_map(|(axis, value)| Some((axis.clone(), as_number(value)?)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(Component {
+                name: name.into(),
+                transformation: transformation.into(),
+                location: Some(location),
+                extra: ExtraFields::new(),
+            })
+        })
+        .collect()
+}
+
+/// Write variable components to a glyph lib value.
+pub fn variable_components_to_lib<'a>(
+    components: impl IntoIterator<Item = &'a Component>,
+) -> plist::Value {
+    let entries = components
+        .into_iter()
+        .map(|component| {
+            let mut entry = plist::Dictionary::new();
+            entry.insert("base".into(), component.name.to_string().into());
+            let location: plist::Dictionary = component
+                .location
+                .iter()
+                .flatten()
+                .map(|(axis, value)| (axis.clone(), plist::Value::Real(*value)))
+                .collect();
+            entry.insert("location".into(), location.into());
+            let transformation =
+                DecomposedTransform::from_affine(Affine::from(&component.transformation));
+            if transformation != DecomposedTransform::default() {
+                entry.insert(
+                    "transformation".into(),
+                    transformation.to_dictionary().into(),
+                );
+            }
+            entry.into()
+        })
+        .collect();
+    plist::Value::Array(entries)
+}
+
+fn as_number(value: &plist::Value) -> Option<f64> {
+    value
+        .as_real()
+        .or_else(|| value.as_signed_integer().map(|i| i as f64))
+}
+
+/// A transformation as separate steps, with angles in degrees, as stored in the
+/// lib. Port of fontTools' `DecomposedTransform`.
+#[derive(Debug, Clone, PartialEq)]
+struct DecomposedTransform {
+    translate_x: f64,
+    translate_y: f64,
+    rotation: f64,
+    scale_x: f64,
+    scale_y: f64,
+    skew_x: f64,
+    skew_y: f64,
+    t_center_x: f64,
+    t_center_y: f64,
+}
+
+impl Default for DecomposedTransform {
+    fn default() -> Self {
+        Self {
+            translate_x: 0.,
+            translate_y: 0.,
+            rotation: 0.,
+            scale_x: 1.,
+            scale_y: 1.,
+            skew_x: 0.,
+            skew_y: 0.,
+            t_center_x: 0.,
+            t_center_y: 0.,
+        }
+    }
+}
+
+const FIELDS: [&str; 9] = [
+    "translateX",
+    "translateY",
+    "rotation",
+    "scaleX",
+    "scaleY",
+    "skewX",
+    "skewY",
+    "tCenterX",
+    "tCenterY",
+];
+
+impl DecomposedTransform {
+    fn fields_mut(&mut self) -> [&mut f64; 9] {
+        [
+            &mut self.translate_x,
+            &mut self.translate_y,
+            &mut self.rotation,
+            &mut self.scale_x,
+            &mut self.scale_y,
+            &mut self.skew_x,
+            &mut self.skew_y,
+            &mut self.t_center_x,
+            &mut self.t_center_y,
+        ]
+    }
+
+    fn from_dictionary(dictionary: &plist::Dictionary) -> Self {
+        let mut transform = Self::default();
+        for (key, field) in FIELDS.iter().zip(transform.fields_mut()) {
+            if let Some(value) = dictionary.get(key).and_then(as_number) {
+                *field = value;
+            }
+        }
+        transform
+    }
+
+    /// The fields that differ from the default.
+    fn to_dictionary(&self) -> plist::Dictionary {
+        let mut values = self.clone();
+        let mut defaults = Self::default();
+        FIELDS
+            .iter()
+            .zip(values.fields_mut().into_iter().zip(defaults.fields_mut()))
+            .filter(|(_, (value, default))| value != default)
+            .map(|(key, (value, _))| (key.to_string(), plist::Value::Real(*value)))
+            .collect()
+    }
+
+    fn from_affine(affine: Affine) -> Self {
+        let [mut a, mut b, c, d, x, y] = affine.as_coeffs();
+        let sign_x = 1f64.copysign(a);
+        if sign_x < 0. {
+            a *= sign_x;
+            b *= sign_x;
+        }
+        let delta = a * d - b * c;
+
+        let (mut rotation, mut scale_x, mut scale_y, mut skew_x, mut skew_y) = (0., 0., 0., 0., 0.);
+        if a != 0. || b != 0. {
+            let r = a.hypot(b);
+            rotation = if b >= 0. {
+                (a / r).acos()
+            } else {
+                -(a / r).acos()
+            };
+            (scale_x, scale_y) = (r, delta / r);
+            skew_x = ((a * c + b * d) / (r * r)).atan();
+        } else if c != 0. || d != 0. {
+            let s = c.hypot(d);
+            rotation = std::f64::consts::FRAC_PI_2
+                - if d >= 0. {
+                    (-c / s).acos()
+                } else {
+                    -(c / s).acos()
+                };
+            (scale_x, scale_y) = (delta / s, s);
+            skew_y = ((a * c + b * d) / (s * s)).atan();
+        }
+
+        Self {
+            translate_x: x,
+            translate_y: y,
+            rotation: rotation.to_degrees(),
+            scale_x: sign_x * scale_x,
+            scale_y,
+            skew_x: -skew_x.to_degrees() * sign_x,
+            skew_y: skew_y.to_degrees(),
+            t_center_x: 0.,
+            t_center_y: 0.,
+        }
+    }
+
+    fn to_affine(&self) -> Affine {
+        Affine::translate((
+            self.translate_x + self.t_center_x,
+            self.translate_y + self.t_center_y,
+        )) * Affine::rotate(self.rotation.to_radians())
+            * Affine::scale_non_uniform(self.scale_x, self.scale_y)
+            * Affine::skew(
+                (-self.skew_x.to_radians()).tan(),
+                self.skew_y.to_radians().tan(),
+            )
+            * Affine::translate((-self.t_center_x, -self.t_center_y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::AffineTransformation;
+
+    use super::*;
+
+    #[test]
+    fn decomposed_transform_roundtrip() {
+        let affines = [
+            Affine::IDENTITY,
+            Affine::translate((10., -20.)),
+            Affine::scale_non_uniform(-1., 1.),
+            Affine::rotate(0.5) * Affine::scale_non_uniform(2., 0.5),
+            Affine::skew(0.3, 0.),
+            Affine::new([0., 1., -1., 0., 5., 5.]),
+            Affine::new([0., 0., 0.5, 2., 0., 0.]),
+        ];
+        for affine in affines {
+            let roundtripped = DecomposedTransform::from_affine(affine).to_affine();
+            for (expected, actual) in affine.as_coeffs().iter().zip(roundtripped.as_coeffs()) {
+                assert!(
+                    (expected - actual).abs() < 1e-9,
+                    "{affine:?} became {roundtripped:?}"
+                );
+            }
+        }
+        assert_eq!(
+            DecomposedTransform::from_affine(Affine::IDENTITY),
+            DecomposedTransform::default()
+        );
+    }
+
+    #[test]
+    fn variable_components_lib_roundtrip() {
+        let components = vec![Component {
+            name: "stem".into(),
+            transformation: AffineTransformation {
+                x_offset: 100.,
+                ..Default::default()
+            },
+            location: Some([("height".to_string(), 0.5)].into()),
+            extra: ExtraFields::new(),
+        }];
+
+        let mut lib = plist::Dictionary::new();
+        lib.insert(
+            VARIABLE_COMPONENTS_LIB_KEY.into(),
+            variable_components_to_lib(&components),
+        );
+
+        assert_eq!(variable_components_from_lib(&lib), components);
+    }
+}
@@ -0,0 +1,177 @@
+//! A JavaScript API for reading fontgardens client-side, in web tools such as
+//! a reviewer. There is no file system in the browser, so a garden is loaded
+//! from its files handed over one by one and kept in memory. Built for
+//! WebAssembly without rayon:
+//!
+//! ```sh
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/fontgardener2.wasm
+//! ```
+//!
+//! Glyphs and layers are returned as plain objects in the shape of the layer
+//! files.
+
+use std::path::Path;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    serve::GlyphInfo,
+    storage::{MemoryStorage, Storage},
+    structs::{Fontgarden, Glyph, LayerName},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WasmError {
+    #[error("no glyph named '{0}' in the fontgarden")]
+    NoGlyph(String),
+    #[error("glyph {0} has no layer '{1}'")]
+    NoLayer(String, String),
+}
+
+/// The files of a fontgarden, gathered before loading it.
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct GardenFiles {
+    storage: MemoryStorage,
+}
+
+#[wasm_bindgen]
+impl GardenFiles {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file by its path relative to the garden root, with forward
+    /// slashes, e.g. `glyphs/A_/R_egular.json`.
+    pub fn add(&self, path: &str, contents: &[u8]) {
+        self.storage
+            .write(Path::new(path), contents)
+            .expect("writing to memory doesn't fail");
+    }
+}
+
+/// A fontgarden held in memory.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Garden {
+    fontgarden: Fontgarden,
+}
+
+#[wasm_bindgen]
+impl Garden {
+    /// Load a fontgarden from its files.
+    pub fn load(files: GardenFiles) -> Result<Garden, JsError> {
+        let fontgarden = Fontgarden::load_from(&files.storage)?;
+        Ok(Self { fontgarden })
+    }
+
+    /// The names of all glyphs, sorted.
+    #[wasm_bindgen(js_name = glyphNames)]
+    pub fn glyph_names(&self) -> Vec<String> {
+        let mut glyph_names: Vec<String> = self
+            .fontgarden
+            .glyphs
+            .keys()
+            .map(|name| name.to_string())
+            .collect();
+        glyph_names.sort();
+        glyph_names
+    }
+
+    /// The names of all sources, sorted.
+    #[wasm_bindgen(js_name = sourceNames)]
+    pub fn source_names(&self) -> Vec<String> {
+        self.fontgarden.source_names().into_iter().collect()
+    }
+
+    /// A glyph's metadata and the names of its layers.
+    pub fn glyph(&self, glyph_name: &str) -> Result<JsValue, JsError> {
+        to_js(&GlyphInfo::new(self.get_glyph(glyph_name)?))
+    }
+
+    /// A layer of a glyph, named like its file, e.g. `Bold` or
+    /// `Bold.background`.
+    pub fn layer(&self, glyph_name: &str, layer_name: &str) -> Result<JsValue, JsError> {
+        let glyph = self.get_glyph(glyph_name)?;
+        let layer = glyph
+            .layers
+            .get(&LayerName::from(layer_name))
+            .ok_or_else(|| WasmError::NoLayer(glyph_name.into(), layer_name.into()))?;
+        to_js(layer)
+    }
+
+    /// A layer of a glyph with components decomposed, as an SVG document.
+    #[wasm_bindgen(js_name = renderSvg)]
+    pub fn render_svg(&self, glyph_name: &str, layer_name: &str) -> Result<String, JsError> {
+        self.get_glyph(glyph_name)?;
+        Ok(self
+            .fontgarden
+            .render_svg(glyph_name, &LayerName::from(layer_name))
+            .ok_or_else(|| WasmError::NoLayer(glyph_name.into(), layer_name.into()))?)
+    }
+}
+
+impl Garden {
+    fn get_glyph(&self, glyph_name: &str) -> Result<&Glyph, WasmError> {
+        self.fontgarden
+            .glyphs
+            .get(glyph_name)
+            .ok_or_else(|| WasmError::NoGlyph(glyph_name.into()))
+    }
+}
+
+/// Plain objects rather than `Map`s, like `JSON.parse` would give.
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    Ok(value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{FontgardenBuilder, GlyphBuilder, LayerBuilder};
+
+    use super::*;
+
+    #[test]
+    fn load_from_files() {
+        let fontgarden = FontgardenBuilder::new()
+            .glyph(
+                "b",
+                GlyphBuilder::new()
+                    .set("Latin")
+                    .layer("Regular", LayerBuilder::new().width(500.))
+                    .layer("Regular.background", LayerBuilder::new().width(500.)),
+            )
+            .glyph(
+                "a",
+                GlyphBuilder::new().layer(
+                    "Regular",
+                    LayerBuilder::new().width(400.).rect(50., 0., 350., 500.),
+                ),
+            )
+            .build();
+        let saved = MemoryStorage::new();
+        fontgarden.save_to(&saved).unwrap();
+
+        let files = GardenFiles::new();
+        for (path, contents) in saved.into_files() {
+            let path: Vec<_> = path.iter().map(|part| part.to_str().unwrap()).collect();
+            files.add(&path.join("/"), &contents);
+        }
+        let garden = Garden::load(files).unwrap();
+        assert_eq!(garden.glyph_names(), ["a", "b"]);
+        assert_eq!(garden.source_names(), ["Regular"]);
+
+        let glyph = GlyphInfo::new(garden.get_glyph("b").unwrap());
+        assert_eq!(glyph.set, "Latin");
+        assert_eq!(glyph.layers, ["Regular", "Regular.background"]);
+        assert!(garden.get_glyph("c").is_err());
+
+        let svg = garden.render_svg("a", "Regular").unwrap();
+        assert!(svg.contains("<path"));
+    }
+}
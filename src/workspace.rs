@@ -0,0 +1,268 @@
+//! Workspaces of several fontgardens, like the Sans, Serif and Mono of a
+//! superfamily sharing a garden of symbols, described by a
+//! `fontgarden-workspace.toml`:
+//!
+//! ```toml
+//! [gardens.Sans]
+//! path = "Sans.fontgarden"
+//! uses = ["Symbols"]
+//!
+//! [gardens.Symbols]
+//! path = "Symbols.fontgarden"
+//! ```
+//!
+//! Components that the glyphs of a garden use but that it doesn't have come
+//! from the gardens it uses, in order, and from those that these use.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{errors::WorkspaceError, structs::Fontgarden};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    /// The directory of the manifest, which garden paths are relative to.
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// Gardens by name.
+    pub gardens: BTreeMap<String, WorkspaceGarden>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceGarden {
+    pub path: PathBuf,
+    /// The gardens to take missing components from, in order.
+    #[serde(default)]
+    pub uses: Vec<String>,
+}
+
+/// A garden of a workspace with the glyphs it takes from other gardens.
+#[derive(Debug)]
+pub struct ResolvedGarden {
+    pub fontgarden: Fontgarden,
+    /// The name of the garden each added glyph comes from, by glyph name.
+    pub borrowed: BTreeMap<String, String>,
+}
+
+impl Workspace {
+    pub const FILENAME: &str = "fontgarden-workspace.toml";
+
+    /// Load a workspace manifest, given by path or by the directory it is in.
+    pub fn load(path: &Path) -> Result<Self, WorkspaceError> {
+        let manifest_path = if path.is_dir() {
+            path.join(Self::FILENAME)
+        } else {
+            path.to_path_buf()
+        };
+        let text = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| WorkspaceError::Io(manifest_path.clone(), e))?;
+        let mut workspace: Self =
+            toml::from_str(&text).map_err(|e| WorkspaceError::Parse(manifest_path.clone(), e))?;
+        for (name, garden) in &workspace.gardens {
+            if let Some(used) = garden
+                .uses
+                .iter()
+                .find(|used| !workspace.gardens.contains_key(*used))
+            {
+                return Err(WorkspaceError::UnknownGarden(name.clone(), used.clone()));
+            }
+        }
+        workspace.root = manifest_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Ok(workspace)
+    }
+
+    pub fn garden_path(&self, name: &str) -> PathBuf {
+        self.root.join(&self.gardens[name].path)
+    }
+
+    /// Load every garden of the workspace.
+    pub fn load_gardens(&self) -> Result<BTreeMap<String, Fontgarden>, WorkspaceError> {
+        self.gardens
+            .keys()
+            .map(|name| {
+                let fontgarden = Fontgarden::load(&self.garden_path(name))
+                    .map_err(|e| WorkspaceError::Load(name.clone(), Box::new(e)))?;
+                Ok((name.clone(), fontgarden))
+            })
+            .collect()
+    }
+
+    /// The gardens a garden takes missing components from, in the order they
+    /// are searched: those it uses, then those these use.
+    fn used_gardens(&self, name: &str) -> Vec<&str> {
+        let mut used: Vec<&str> = Vec::new();
+        let mut pending: Vec<&str> = vec![name];
+        while !pending.is_empty() {
+            let mut next = Vec::new();
+            for garden_name in pending {
+                for used_name in &self.gardens[garden_name].uses {
+                    if used_name != name && !used.contains(&used_name.as_str()) {
+                        used.push(used_name);
+                        next.push(used_name.as_str());
+                    }
+                }
+            }
+            pending = next;
+        }
+        used
+    }
+
+    /// A copy of a garden with the glyphs its glyphs use as components, directly
+    /// or through other components, taken from the gardens it uses where it
+    /// lacks them. Only the layers of the garden's own sources are taken.
+    pub fn resolve(&self, gardens: &BTreeMap<String, Fontgarden>, name: &str) -> ResolvedGarden {
+        let mut fontgarden = gardens[name].clone();
+        let source_names = fontgarden.source_names();
+        let used_gardens = self.used_gardens(name);
+        let mut borrowed = BTreeMap::new();
+        let mut pending: Vec<String> = fontgarden
+            .glyphs
+            .values()
+            .flat_map(|glyph| glyph.layers.values())
+            .flat_map(|layer| layer.components.iter().map(|c| c.name.to_string()))
+            .collect();
+        let mut seen: BTreeSet<String> = BTreeSet::new();
+        while let Some(glyph_name) = pending.pop() {
+            if fontgarden.glyphs.contains_key(glyph_name.as_str())
+                || !seen.insert(glyph_name.clone())
+            {
+                continue;
+            }
+            let Some((garden_name, glyph)) = used_gardens.iter().find_map(|garden_name| {
+                Some((
+                    *garden_name,
+                    gardens[*garden_name].glyphs.get(glyph_name.as_str())?,
+                ))
+            }) else {
+                continue;
+            };
+            let mut glyph = glyph.clone();
+            glyph
+                .layers
+                .retain(|layer_name, _| source_names.contains(&*layer_name.source));
+            pending.extend(
+                glyph
+                    .layers
+                    .values()
+                    .flat_map(|layer| layer.components.iter().map(|c| c.name.to_string())),
+            );
+            fontgarden.glyphs.insert(glyph_name.as_str().into(), glyph);
+            borrowed.insert(glyph_name, garden_name.to_string());
+        }
+        ResolvedGarden {
+            fontgarden,
+            borrowed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Component, ExtraFields, Glyph, Layer};
+
+    use super::*;
+
+    #[test]
+    fn resolve_components_across_gardens() {
+        let glyph = |sources: &[&str], components: &[&str]| Glyph {
+            layers: sources
+                .iter()
+                .map(|source| {
+                    let layer = Layer {
+                        x_advance: Some(500.),
+                        components: components
+                            .iter()
+                            .map(|name| Component {
+                                name: (*name).into(),
+                                transformation: Default::default(),
+                                location: None,
+                                extra: ExtraFields::new(),
+                            })
+                            .collect(),
+                        ..Default::default()
+                    };
+                    ((*source).into(), layer)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut sans = Fontgarden::new();
+        sans.glyphs.insert("a".into(), glyph(&["Regular"], &[]));
+        sans.glyphs.insert(
+            "arrow.circled".into(),
+            glyph(&["Regular"], &["arrow", "ring"]),
+        );
+        let mut symbols = Fontgarden::new();
+        symbols
+            .glyphs
+            .insert("arrow".into(), glyph(&["Regular", "Black"], &["arrowhead"]));
+        symbols
+            .glyphs
+            .insert("arrowhead".into(), glyph(&["Regular"], &[]));
+        let mut shapes = Fontgarden::new();
+        shapes
+            .glyphs
+            .insert("ring".into(), glyph(&["Regular"], &[]));
+
+        let dir = tempfile::tempdir().unwrap();
+        for (name, fontgarden) in [("Sans", &sans), ("Symbols", &symbols), ("Shapes", &shapes)] {
+            fontgarden
+                .save(&dir.path().join(format!("{name}.fontgarden")))
+                .unwrap();
+        }
+        std::fs::write(
+            dir.path().join(Workspace::FILENAME),
+            r#"
+            [gardens.Sans]
+            path = "Sans.fontgarden"
+            uses = ["Symbols"]
+
+            [gardens.Symbols]
+            path = "Symbols.fontgarden"
+            uses = ["Shapes"]
+
+            [gardens.Shapes]
+            path = "Shapes.fontgarden"
+            "#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::load(dir.path()).unwrap();
+        let gardens = workspace.load_gardens().unwrap();
+        let resolved = workspace.resolve(&gardens, "Sans");
+        assert_eq!(
+            resolved.borrowed,
+            BTreeMap::from([
+                ("arrow".into(), "Symbols".into()),
+                ("arrowhead".into(), "Symbols".into()),
+                ("ring".into(), "Shapes".into()),
+            ])
+        );
+        assert_eq!(
+            resolved.fontgarden.glyphs["arrow"]
+                .layers
+                .keys()
+                .collect::<Vec<_>>(),
+            [&"Regular".into()]
+        );
+
+        std::fs::write(
+            dir.path().join(Workspace::FILENAME),
+            "[gardens.Sans]\npath = \"Sans.fontgarden\"\nuses = [\"Serif\"]\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            Workspace::load(dir.path()),
+            Err(WorkspaceError::UnknownGarden(_, _))
+        ));
+    }
+}